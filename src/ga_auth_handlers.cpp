@@ -1107,7 +1107,7 @@ namespace sdk {
     {
         if (m_net_params.is_electrum()) {
             // FIXME: Move rust to ga_session interface
-            m_result = { { "transactions", m_session->get_transactions(m_details) } };
+            m_result = m_session->get_transactions(m_details);
             return state_type::done;
         }
 
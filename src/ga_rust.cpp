@@ -503,6 +503,9 @@ namespace sdk {
             currency_query["price_url"] = m_net_params.get_price_url();
             currency_query["fallback_rate"] = amount_json.value("fiat_rate", "");
             currency_query["exchange"] = exchange;
+            if (amount_json.contains("exchange")) {
+                currency_query["exchange_override"] = amount_json["exchange"];
+            }
 
             try {
                 auto xrates = rust_call("exchange_rates", currency_query, m_session)["currencies"];
@@ -67,6 +67,10 @@ namespace sdk {
 
             const auto core_descriptors_p = credentials.find("core_descriptors");
             const auto slip132_extended_pubkeys_p = credentials.find("slip132_extended_pubkeys");
+            if (credentials.contains("multisig_descriptors")
+                && (core_descriptors_p != credentials.end() || slip132_extended_pubkeys_p != credentials.end())) {
+                throw user_error("'multisig_descriptors' cannot be used with other watch-only credentials");
+            }
             if (core_descriptors_p != credentials.end()) {
                 if (slip132_extended_pubkeys_p != credentials.end()) {
                     throw user_error(
@@ -81,6 +85,12 @@ namespace sdk {
                 return { { "slip132_extended_pubkeys", *slip132_extended_pubkeys_p } };
             }
 
+            const auto multisig_descriptors_p = credentials.find("multisig_descriptors");
+            if (multisig_descriptors_p != credentials.end()) {
+                // Watch-only multisig login
+                return { { "multisig_descriptors", *multisig_descriptors_p } };
+            }
+
             throw user_error("Invalid credentials");
         }
 
@@ -110,7 +120,7 @@ namespace sdk {
                     throw user_error("HWW/remote signer and login credentials cannot be used together");
                 }
             } else if (credentials.contains("username") || credentials.contains("slip132_extended_pubkeys")
-                || credentials.contains("core_descriptors")) {
+                || credentials.contains("core_descriptors") || credentials.contains("multisig_descriptors")) {
                 ret = WATCH_ONLY_DEVICE_JSON;
             } else if (credentials.contains("seed")) {
                 ret = SOFTWARE_DEVICE_JSON;
@@ -1,4 +1,9 @@
+use std::str::FromStr;
+
 use super::BEScript;
+use crate::error::Error;
+use crate::model::ValidateAddressResult;
+use crate::NetworkId;
 
 #[derive(Debug)]
 pub enum BEAddress {
@@ -7,6 +12,42 @@ pub enum BEAddress {
 }
 
 impl BEAddress {
+    /// Parse an address, failing if it does not belong to the network `id`
+    pub fn from_str_with_network(s: &str, id: NetworkId) -> Result<Self, Error> {
+        match id {
+            NetworkId::Bitcoin(network) => {
+                let address = bitcoin::Address::from_str(s)?;
+                // Testnet and regtest share the base58 prefixes
+                if address.network != network
+                    && !(address.network == bitcoin::Network::Testnet
+                        && network == bitcoin::Network::Regtest)
+                {
+                    return Err(Error::MismatchingNetwork);
+                }
+                Ok(BEAddress::Bitcoin(address))
+            }
+            NetworkId::Elements(network) => {
+                match elements::Address::parse_with_params(s, network.address_params()) {
+                    Ok(address) => Ok(BEAddress::Elements(address)),
+                    Err(_) if elements::Address::from_str(s).is_ok() => {
+                        Err(Error::MismatchingNetwork)
+                    }
+                    Err(_) => Err(Error::InvalidAddress),
+                }
+            }
+        }
+    }
+
+    /// Check whether `s` is an address of the network `id`
+    pub fn validate(s: &str, id: NetworkId) -> ValidateAddressResult {
+        let address = BEAddress::from_str_with_network(s, id).ok();
+        ValidateAddressResult {
+            is_valid: address.is_some(),
+            is_confidential: address.as_ref().and_then(|a| a.elements()).map(|a| a.is_blinded()),
+            address: address.map(|a| a.to_string()),
+        }
+    }
+
     pub fn script_pubkey(&self) -> BEScript {
         match self {
             BEAddress::Bitcoin(addr) => addr.script_pubkey().into(),
@@ -41,3 +82,70 @@ impl ToString for BEAddress {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ElementsNetwork;
+    use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+    #[test]
+    fn test_validate_address() {
+        let mainnet = NetworkId::Bitcoin(bitcoin::Network::Bitcoin);
+        let testnet = NetworkId::Bitcoin(bitcoin::Network::Testnet);
+        let regtest = NetworkId::Bitcoin(bitcoin::Network::Regtest);
+        let liquid = NetworkId::Elements(ElementsNetwork::Liquid);
+        let elements_regtest = NetworkId::Elements(ElementsNetwork::ElementsRegtest);
+
+        let secp = Secp256k1::new();
+        let key = |b| PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[b; 32]).unwrap());
+        let params = &elements::AddressParams::LIQUID;
+        let pk = bitcoin::PublicKey::new(key(1));
+        let confidential = elements::Address::p2wpkh(&pk, Some(key(2)), params).to_string();
+        let unconfidential = elements::Address::p2wpkh(&pk, None, params).to_string();
+
+        let p2wpkh = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+        let cases = [
+            // p2pkh
+            ("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", mainnet, true),
+            ("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn", testnet, true),
+            ("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn", regtest, true),
+            ("mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn", mainnet, false),
+            // p2wpkh
+            (p2wpkh, mainnet, true),
+            ("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", testnet, true),
+            ("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx", mainnet, false),
+            // p2wsh
+            ("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3", mainnet, true),
+            ("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3", testnet, false),
+            // p2tr
+            ("bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr", mainnet, true),
+            // bad checksum
+            ("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdr", mainnet, false),
+            ("", mainnet, false),
+            (&confidential, mainnet, false),
+            // liquid
+            (&confidential, liquid, true),
+            (&unconfidential, liquid, true),
+            (&confidential, elements_regtest, false),
+            (p2wpkh, liquid, false),
+        ];
+        for (address, id, is_valid) in cases {
+            let result = BEAddress::validate(address, id);
+            assert_eq!(result.is_valid, is_valid, "{} {:?}", address, id);
+            assert_eq!(result.address.as_deref(), is_valid.then_some(address), "{}", address);
+        }
+
+        assert_eq!(BEAddress::validate(&confidential, liquid).is_confidential, Some(true));
+        assert_eq!(BEAddress::validate(&unconfidential, liquid).is_confidential, Some(false));
+        assert_eq!(BEAddress::validate(p2wpkh, mainnet).is_confidential, None);
+        assert!(matches!(
+            BEAddress::from_str_with_network(p2wpkh, testnet),
+            Err(Error::MismatchingNetwork)
+        ));
+
+        // The canonical form of bech32 addresses is lowercase
+        let result = BEAddress::validate(&p2wpkh.to_uppercase(), mainnet);
+        assert_eq!(result.address.as_deref(), Some(p2wpkh));
+    }
+}
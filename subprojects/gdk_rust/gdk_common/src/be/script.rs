@@ -1,8 +1,15 @@
 use crate::error::Error;
 use crate::NetworkId;
+use bitcoin::consensus::encode::VarInt;
 use bitcoin::hashes::hex::{FromHex, ToHex};
 use serde::{Deserialize, Serialize};
 
+/// The dust relay fee rate of bitcoin core and elements, in satoshi per 1000 vbytes
+const DUST_RELAY_FEE: u64 = 3000;
+
+// asset, value and nonce commitments of a confidential output
+const CONFIDENTIAL_COMMITMENTS_SIZE: usize = 33 + 33 + 33;
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 pub enum BEScript {
     Bitcoin(bitcoin::Script),
@@ -42,6 +49,33 @@ impl BEScript {
             Self::Elements(script) => Some(script),
         }
     }
+
+    /// The value below which an output paying to this script is dust, computed as bitcoin core
+    /// does: the cost of creating and later spending the output at the dust relay fee rate.
+    ///
+    /// Elements outputs are assumed confidential, the commitments replacing the explicit amount
+    /// make them larger and so raise their dust threshold. As in elements, the rangeproof and
+    /// surjection proof in the output witness are not counted.
+    pub fn dust_value(&self) -> u64 {
+        let (len, is_witness, amount_size) = match self {
+            Self::Bitcoin(script) => (script.len(), script.is_witness_program(), 8),
+            Self::Elements(script) => {
+                (script.len(), script.is_witness_program(), CONFIDENTIAL_COMMITMENTS_SIZE)
+            }
+        };
+        let output_size = amount_size + VarInt(len as u64).len() + len;
+        // outpoint (36), script sig length (1), sequence (4) and a p2pkh script sig (107),
+        // whose witness counterpart is discounted
+        let input_size = 36
+            + 1
+            + 4
+            + if is_witness {
+                107 / 4
+            } else {
+                107
+            };
+        (output_size + input_size) as u64 * DUST_RELAY_FEE / 1000
+    }
 }
 
 impl Default for BEScript {
@@ -153,3 +187,29 @@ impl From<&elements::Script> for BEScript {
         script.clone().into()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::Hash;
+
+    #[test]
+    fn test_dust_value() {
+        let hash = bitcoin::hashes::hash160::Hash::all_zeros();
+        let p2pkh = bitcoin::Script::new_p2pkh(&bitcoin::PubkeyHash::from_hash(hash));
+        let p2sh = bitcoin::Script::new_p2sh(&bitcoin::ScriptHash::from_hash(hash));
+        let p2wpkh = bitcoin::Script::new_v0_p2wpkh(&bitcoin::WPubkeyHash::from_hash(hash));
+
+        // the values of bitcoin core
+        assert_eq!(BEScript::from(&p2pkh).dust_value(), 546);
+        assert_eq!(BEScript::from(&p2sh).dust_value(), 540);
+        assert_eq!(BEScript::from(&p2wpkh).dust_value(), 294);
+
+        for script in [p2pkh, p2sh, p2wpkh] {
+            let bitcoin_dust = BEScript::from(&script).dust_value();
+            let liquid_dust = BEScript::from(script.into_elements()).dust_value();
+            assert!(liquid_dust > bitcoin_dust);
+            assert_eq!(liquid_dust, bitcoin_dust + (99 - 8) * 3);
+        }
+    }
+}
@@ -43,9 +43,29 @@ impl BESigHashType {
         }
     }
 
+    pub fn is_all(&self) -> bool {
+        matches!(
+            self,
+            BESigHashType::Bitcoin(BitcoinSigHashType::All)
+                | BESigHashType::Elements(ElementsSigHashType::All)
+        )
+    }
+
+    /// Whether the signature commits only to the output with the same index as the input
+    pub fn is_single(&self) -> bool {
+        matches!(
+            self,
+            BESigHashType::Bitcoin(BitcoinSigHashType::Single)
+                | BESigHashType::Bitcoin(BitcoinSigHashType::SinglePlusAnyoneCanPay)
+                | BESigHashType::Elements(ElementsSigHashType::Single)
+                | BESigHashType::Elements(ElementsSigHashType::SinglePlusAnyoneCanPay)
+        )
+    }
+
     fn is_allowed(&self) -> Result<(), Error> {
         match self {
             BESigHashType::Bitcoin(BitcoinSigHashType::All)
+            | BESigHashType::Bitcoin(BitcoinSigHashType::SinglePlusAnyoneCanPay)
             | BESigHashType::Elements(ElementsSigHashType::All)
             | BESigHashType::Elements(ElementsSigHashType::SinglePlusAnyoneCanPay) => Ok(()),
             _ => Err(Error::UnsupportedSigHash),
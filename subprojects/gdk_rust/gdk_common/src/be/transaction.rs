@@ -1,16 +1,22 @@
 use crate::be::*;
 use crate::error::Error;
-use crate::model::{Balances, TransactionType};
+use crate::model::{
+    Balances, ComputeFeeRateParams, ComputeFeeRateResult, EstimateTxSizeParams,
+    EstimateTxSizeResult, ScriptTypeCount, TransactionType,
+};
 use crate::scripts::{p2pkh_script, ScriptType};
-use crate::NetworkId;
+use crate::util::weight_to_vsize;
+use crate::{ElementsNetwork, NetworkId};
 use bitcoin::blockdata::script::Instruction;
 use bitcoin::blockdata::transaction::EcdsaSighashType as BitcoinSigHashType;
 use bitcoin::consensus::encode::deserialize as btc_des;
 use bitcoin::consensus::encode::serialize as btc_ser;
 use bitcoin::hashes::hex::{FromHex, ToHex};
 use bitcoin::hashes::Hash;
+use bitcoin::secp256k1::XOnlyPublicKey;
 use bitcoin::secp256k1::{self, ecdsa::Signature, Message, Secp256k1};
-use bitcoin::util::sighash::SighashCache;
+use bitcoin::util::schnorr::{SchnorrSig, TapTweak};
+use bitcoin::util::sighash::{Prevouts, SighashCache};
 use bitcoin::{PackedLockTime, PublicKey, Sequence};
 use elements::confidential;
 use elements::confidential::{Asset, Value};
@@ -22,16 +28,29 @@ use rand::seq::SliceRandom;
 use rand::thread_rng;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::iter;
 use std::ops::{Deref, DerefMut};
 use std::str::FromStr;
 
 pub const DUST_VALUE: u64 = 546;
 
+/// The maximum data size of an OP_RETURN output relayed by default by bitcoin core
+pub const MAX_OP_RETURN_SIZE: usize = 80;
+
 // 52-bit rangeproof size
 const DEFAULT_RANGEPROOF_SIZE: usize = 4174;
 // 3-input ASP size
 const DEFAULT_SURJECTIONPROOF_SIZE: usize = 135;
 
+/// The fee a transaction being built should pay
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeeTarget {
+    /// A fee rate in satoshi/vbyte, applied to the estimated size of the final transaction
+    Rate(f64),
+    /// An exact fee in satoshi
+    Absolute(u64),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Hash)]
 pub enum BETransaction {
     Bitcoin(bitcoin::Transaction),
@@ -102,6 +121,13 @@ impl BETransaction {
         }
     }
 
+    pub fn set_lock_time(&mut self, lock_time: u32) {
+        match self {
+            Self::Bitcoin(tx) => tx.lock_time = PackedLockTime(lock_time),
+            Self::Elements(tx) => tx.lock_time = lock_time,
+        }
+    }
+
     pub fn previous_outputs(&self) -> Vec<BEOutPoint> {
         match self {
             Self::Bitcoin(tx) => {
@@ -325,6 +351,20 @@ impl BETransaction {
         Ok(())
     }
 
+    /// Add a zero value, provably unspendable output carrying `data`, errors on Liquid
+    pub fn add_op_return_output(&mut self, data: &[u8]) -> Result<(), Error> {
+        match self {
+            BETransaction::Bitcoin(tx) => tx.output.push(bitcoin::TxOut {
+                script_pubkey: bitcoin::Script::new_op_return(data),
+                value: 0,
+            }),
+            BETransaction::Elements(_) => {
+                return Err(Error::Generic("op_return outputs are not supported in liquid".into()))
+            }
+        }
+        Ok(())
+    }
+
     pub fn scramble(&mut self) {
         let mut rng = thread_rng();
         match self {
@@ -339,36 +379,40 @@ impl BETransaction {
         }
     }
 
-    /// estimates the fee of the final transaction given the `fee_rate`
+    /// estimates the virtual size of the final transaction,
     /// called when the tx is being built and miss things like signatures and changes outputs.
-    pub fn estimated_fee(&self, fee_rate: f64, more_changes: u8, script_type: ScriptType) -> u64 {
+    pub fn estimated_vbytes(&self, more_changes: u8, script_type: ScriptType) -> f64 {
+        self.estimated_weight(more_changes, script_type) as f64 / 4.0
+    }
+
+    /// estimates the weight of the signed transaction, with `more_changes` outputs still to be added
+    pub fn estimated_weight(&self, more_changes: u8, script_type: ScriptType) -> usize {
+        self.mocked_weight(
+            &vec![script_type; self.input_len()],
+            &vec![script_type; more_changes as usize],
+        )
+    }
+
+    /// the weight of the signed transaction, with inputs of `input_types` and `more_outputs`
+    /// outputs still to be added
+    fn mocked_weight(&self, input_types: &[ScriptType], more_outputs: &[ScriptType]) -> usize {
         let dummy_tx = self.clone();
         match dummy_tx {
             BETransaction::Bitcoin(mut tx) => {
-                for input in tx.input.iter_mut() {
+                for (input, script_type) in tx.input.iter_mut().zip(input_types) {
                     input.witness = script_type.mock_witness();
                     input.script_sig = script_type.mock_script_sig().into();
                 }
-                for _ in 0..more_changes {
+                for script_type in more_outputs {
                     tx.output.push(bitcoin::TxOut {
                         value: 0,
                         script_pubkey: script_type.mock_script_pubkey().into(),
                     })
                 }
-                let vbytes = tx.weight() as f64 / 4.0;
-                let fee_val = (vbytes * fee_rate * 1.02) as u64; // increasing estimated fee by 2% to stay over relay fee TODO improve fee estimation and lower this
-                info!(
-                    "DUMMYTX inputs:{} outputs:{} num_changes:{} vbytes:{} fee_val:{}",
-                    tx.input.len(),
-                    tx.output.len(),
-                    more_changes,
-                    vbytes,
-                    fee_val
-                );
-                fee_val
+                tx.weight()
             }
             BETransaction::Elements(mut tx) => {
-                for input in tx.input.iter_mut() {
+                for (input, script_type) in tx.input.iter_mut().zip(input_types) {
                     let mut tx_wit = TxInWitness::default();
                     tx_wit.script_witness = script_type.mock_witness().to_vec();
                     input.witness = tx_wit;
@@ -377,7 +421,7 @@ impl BETransaction {
                 let mock_asset = confidential::Asset::Confidential(mock_asset());
                 let mock_value = confidential::Value::Confidential(mock_value());
                 let mock_nonce = confidential::Nonce::Confidential(mock_pubkey());
-                for _ in 0..more_changes {
+                for script_type in more_outputs {
                     let new_out = elements::TxOut {
                         asset: mock_asset,
                         value: mock_value,
@@ -395,21 +439,46 @@ impl BETransaction {
                     0,
                     elements::issuance::AssetId::from_slice(&[0u8; 32]).unwrap(),
                 )); // mockup for the explicit fee output
-                let vbytes = (tx.weight() + proofs_size) as f64 / 4.0;
-                let fee_val = (vbytes * fee_rate * 1.03) as u64; // increasing estimated fee by 3% to stay over relay fee, TODO improve fee estimation and lower this
-                info!(
-                    "DUMMYTX inputs:{} outputs:{} num_changes:{} vbytes:{} fee_val:{}",
-                    tx.input.len(),
-                    tx.output.len(),
-                    more_changes,
-                    vbytes,
-                    fee_val
-                );
-                fee_val
+                tx.weight() + proofs_size
             }
         }
     }
 
+    /// estimates the fee of the final transaction given the `fee_rate`
+    /// called when the tx is being built and miss things like signatures and changes outputs.
+    pub fn estimated_fee(&self, fee_rate: f64, more_changes: u8, script_type: ScriptType) -> u64 {
+        let vbytes = self.estimated_vbytes(more_changes, script_type);
+        // increasing estimated fee by 2% (3% for elements) to stay over relay fee
+        // TODO improve fee estimation and lower this
+        let increase = match self {
+            BETransaction::Bitcoin(_) => 1.02,
+            BETransaction::Elements(_) => 1.03,
+        };
+        let fee_val = (vbytes * fee_rate * increase) as u64;
+        info!(
+            "DUMMYTX inputs:{} outputs:{} num_changes:{} vbytes:{} fee_val:{}",
+            self.input_len(),
+            self.output_len(),
+            more_changes,
+            vbytes,
+            fee_val
+        );
+        fee_val
+    }
+
+    /// the fee of the final transaction given the `fee_target`
+    pub fn target_fee(
+        &self,
+        fee_target: FeeTarget,
+        more_changes: u8,
+        script_type: ScriptType,
+    ) -> u64 {
+        match fee_target {
+            FeeTarget::Rate(fee_rate) => self.estimated_fee(fee_rate, more_changes, script_type),
+            FeeTarget::Absolute(fee) => fee,
+        }
+    }
+
     pub fn estimated_changes(
         &self,
         send_all: bool,
@@ -440,7 +509,7 @@ impl BETransaction {
     /// for elements could contain more than 1 element, 1 for each asset, with the policy asset last
     pub fn needs(
         &self,
-        fee_rate: f64,
+        fee_target: FeeTarget,
         no_change: bool,
         policy_asset: Option<elements::issuance::AssetId>,
         all_txs: &BETransactions,
@@ -451,8 +520,8 @@ impl BETransaction {
             Self::Bitcoin(tx) => {
                 let sum_inputs = sum_inputs(tx, all_txs);
                 let sum_outputs: u64 = tx.output.iter().map(|o| o.value).sum();
-                let estimated_fee = self.estimated_fee(
-                    fee_rate,
+                let estimated_fee = self.target_fee(
+                    fee_target,
                     self.estimated_changes(no_change, all_txs, unblinded),
                     script_type,
                 ); // send all does not create change
@@ -489,8 +558,8 @@ impl BETransaction {
                     *inputs.entry(asset).or_insert(0) += value;
                 }
 
-                let estimated_fee = self.estimated_fee(
-                    fee_rate,
+                let estimated_fee = self.target_fee(
+                    fee_target,
                     self.estimated_changes(no_change, all_txs, unblinded),
                     script_type,
                 );
@@ -518,9 +587,12 @@ impl BETransaction {
 
     /// return a Vector with changes of this transaction
     /// requires inputs are greater than outputs for earch asset
+    /// `dust_value` is the dust threshold of the change output, below which the change of the
+    /// bitcoin or policy asset is left to the fee
     pub fn changes(
         &self,
         estimated_fee: u64,
+        dust_value: u64,
         policy_asset: Option<elements::issuance::AssetId>,
         all_txs: &BETransactions,
         unblinded: &HashMap<elements::OutPoint, elements::TxOutSecrets>,
@@ -530,7 +602,7 @@ impl BETransaction {
                 let sum_inputs = sum_inputs(tx, all_txs);
                 let sum_outputs: u64 = tx.output.iter().map(|o| o.value).sum();
                 let change_value = sum_inputs - sum_outputs - estimated_fee;
-                if change_value > DUST_VALUE {
+                if change_value > dust_value {
                     vec![AssetValue::new_bitcoin(change_value)]
                 } else {
                     vec![]
@@ -569,7 +641,7 @@ impl BETransaction {
                         // from a purely privacy perspective could make sense to always create the change output in liquid, so min change = 0
                         // however elements core use the dust anyway for 2 reasons: rebasing from core and economical considerations
                         sum -= estimated_fee;
-                        if sum > DUST_VALUE {
+                        if sum > dust_value {
                             // we apply dust rules for liquid bitcoin as elements do
                             result.push(AssetValue::new(*asset, sum));
                         }
@@ -677,6 +749,35 @@ impl BETransaction {
         }
     }
 
+    /// Fee paid by the transaction, given the values of the outputs it spends.
+    ///
+    /// For elements `input_values` are ignored and the fee is the sum of the explicit fee outputs.
+    pub fn fee_from_input_values(&self, input_values: &[u64]) -> Result<u64, Error> {
+        match self {
+            Self::Bitcoin(tx) => {
+                if input_values.len() != tx.input.len() {
+                    return Err(Error::Generic(format!(
+                        "expected {} input values, got {}",
+                        tx.input.len(),
+                        input_values.len()
+                    )));
+                }
+                let sum_inputs: u64 = input_values.iter().sum();
+                let sum_outputs: u64 = tx.output.iter().map(|o| o.value).sum();
+                sum_inputs
+                    .checked_sub(sum_outputs)
+                    .ok_or_else(|| Error::Generic("unexpected tx balance".into()))
+            }
+            Self::Elements(tx) => {
+                let fee_outputs: Vec<_> = tx.output.iter().filter(|o| o.is_fee()).collect();
+                if fee_outputs.is_empty() {
+                    return Err(Error::Generic("missing fee output".into()));
+                }
+                Ok(fee_outputs.iter().filter_map(|o| o.value.explicit()).sum())
+            }
+        }
+    }
+
     pub fn rbf_optin(&self) -> bool {
         match self {
             Self::Bitcoin(tx) => tx.input.iter().any(|e| e.sequence < Sequence(0xffff_fffe)),
@@ -684,6 +785,14 @@ impl BETransaction {
         }
     }
 
+    /// Whether the transaction has witness data, i.e. it would be serialized in segwit format
+    pub fn has_witness(&self) -> bool {
+        match self {
+            Self::Bitcoin(tx) => tx.input.iter().any(|i| !i.witness.is_empty()),
+            Self::Elements(tx) => tx.has_witness(),
+        }
+    }
+
     pub fn is_redeposit(
         &self,
         all_scripts: &HashMap<BEScript, DerivationPath>,
@@ -846,14 +955,15 @@ impl BETransaction {
     /// can be managed using gdk-rust. Implemented for Bitcoin only.
     ///
     /// The `hashcache` argument should be initialized as None for every tx and
-    /// reused for its inputs.
+    /// reused for its inputs. `prevouts` are the outputs spent by all the tx inputs,
+    /// as taproot signatures commit to all of them.
     pub fn verify_input_sig<'a>(
         &'a self,
         secp: &Secp256k1<impl secp256k1::Verification>,
         hashcache: &mut Option<SighashCache<&'a bitcoin::Transaction>>,
         inv: usize,
         public_key: &PublicKey,
-        value: u64,
+        prevouts: &[bitcoin::TxOut],
         script_type: ScriptType,
     ) -> Result<(), Error> {
         let tx = if let BETransaction::Bitcoin(tx) = self {
@@ -862,6 +972,22 @@ impl BETransaction {
             // Signature verification is currently only used on Bitcoin
             unimplemented!();
         };
+        if script_type == ScriptType::P2tr {
+            let sig = tx.input[inv].witness.to_vec().first().cloned();
+            let sig = SchnorrSig::from_slice(&sig.ok_or(Error::InputValidationFailed)?)
+                .map_err(|_| Error::InputValidationFailed)?;
+            let hashcache = hashcache.get_or_insert_with(|| SighashCache::new(tx));
+            let hash = hashcache.taproot_key_spend_signature_hash(
+                inv,
+                &Prevouts::All(prevouts),
+                sig.hash_ty,
+            )?;
+            let message = Message::from_slice(&hash.into_inner()[..]).unwrap();
+            let (output_key, _) = XOnlyPublicKey::from(public_key.inner).tap_tweak(secp, None);
+            secp.verify_schnorr(&sig.sig, &message, &output_key.to_inner())?;
+            return Ok(());
+        }
+        let value = prevouts.get(inv).ok_or(Error::InputValidationFailed)?.value;
         let mut sig = match script_type {
             ScriptType::P2wpkh | ScriptType::P2shP2wpkh => {
                 tx.input[inv].witness.to_vec().get(0).cloned().ok_or(Error::InputValidationFailed)
@@ -870,6 +996,9 @@ impl BETransaction {
                 Some(Ok(Instruction::PushBytes(sig))) => Ok(sig.to_vec()),
                 _ => Err(Error::InputValidationFailed),
             },
+            ScriptType::P2tr => unreachable!(),
+            // Multisig accounts can't sign yet
+            ScriptType::P2wsh => Err(Error::InputValidationFailed),
         }?;
 
         let sighash = sig.pop().ok_or_else(|| Error::InputValidationFailed)?;
@@ -1030,6 +1159,61 @@ impl BETransactions {
     }
 }
 
+/// Compute fee and fee rate of a transaction without access to a wallet
+pub fn compute_fee_rate(params: &ComputeFeeRateParams) -> Result<ComputeFeeRateResult, Error> {
+    let (tx, input_values) = match &params.input_values {
+        Some(values) => (
+            BETransaction::from_hex(
+                &params.transaction,
+                NetworkId::Bitcoin(bitcoin::Network::Bitcoin),
+            )?,
+            values.as_slice(),
+        ),
+        None => (
+            BETransaction::from_hex(
+                &params.transaction,
+                NetworkId::Elements(ElementsNetwork::Liquid),
+            )?,
+            &[][..],
+        ),
+    };
+    let fee = tx.fee_from_input_values(input_values)?;
+    let entry = BETransactionEntry::from(tx);
+    Ok(ComputeFeeRateResult {
+        fee,
+        vsize: weight_to_vsize(entry.weight),
+        fee_rate: entry.fee_rate(fee),
+    })
+}
+
+/// Estimate the size of a signed transaction from the script types of its inputs and outputs,
+/// without selecting any utxo
+pub fn estimate_tx_size(params: &EstimateTxSizeParams) -> EstimateTxSizeResult {
+    let expand = |counts: &[ScriptTypeCount]| -> Vec<ScriptType> {
+        counts.iter().flat_map(|c| iter::repeat(c.script_type).take(c.count as usize)).collect()
+    };
+    let (input_types, output_types) = (expand(&params.inputs), expand(&params.outputs));
+
+    let tx = if params.is_liquid {
+        let mut tx = BETransaction::new(NetworkId::Elements(ElementsNetwork::Liquid));
+        for _ in 0..input_types.len() {
+            tx.add_input(BEOutPoint::Elements(elements::OutPoint::default()));
+        }
+        tx
+    } else {
+        let mut tx = BETransaction::new(NetworkId::Bitcoin(bitcoin::Network::Bitcoin));
+        for _ in 0..input_types.len() {
+            tx.add_input(BEOutPoint::Bitcoin(bitcoin::OutPoint::default()));
+        }
+        tx
+    };
+    let weight = tx.mocked_weight(&input_types, &output_types);
+    EstimateTxSizeResult {
+        vsize: weight_to_vsize(weight),
+        weight,
+    }
+}
+
 impl From<BETransaction> for BETransactionEntry {
     fn from(tx: BETransaction) -> Self {
         let size = tx.serialize().len();
@@ -1043,7 +1227,7 @@ impl From<BETransaction> for BETransactionEntry {
 }
 
 //TODO remove this, `fn needs` could return BTreeMap<String, u64> instead
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct AssetValue {
     pub asset: Option<elements::issuance::AssetId>,
     pub satoshi: u64,
@@ -1063,3 +1247,209 @@ impl AssetValue {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compute_fee_rate() {
+        let mut tx = BETransaction::new(NetworkId::Bitcoin(bitcoin::Network::Regtest));
+        tx.add_input(BEOutPoint::Bitcoin(bitcoin::OutPoint::default()));
+        if let BETransaction::Bitcoin(tx) = &mut tx {
+            tx.output.push(bitcoin::TxOut {
+                value: 9_000,
+                script_pubkey: bitcoin::Script::new(),
+            });
+        }
+        let transaction = tx.serialize().to_hex();
+
+        let mut params = ComputeFeeRateParams {
+            transaction: transaction.clone(),
+            input_values: Some(vec![10_000]),
+        };
+        let result = compute_fee_rate(&params).unwrap();
+        assert_eq!(result.fee, 1_000);
+        assert_eq!(result.vsize, weight_to_vsize(tx.get_weight()));
+        assert_eq!(result.fee_rate, 1_000 * 4_000 / tx.get_weight() as u64);
+
+        params.input_values = Some(vec![]);
+        assert!(compute_fee_rate(&params).is_err());
+        params.input_values = Some(vec![8_000]);
+        assert!(compute_fee_rate(&params).is_err());
+
+        let mut tx = BETransaction::new(NetworkId::Elements(ElementsNetwork::ElementsRegtest));
+        tx.add_input(BEOutPoint::Elements(elements::OutPoint::default()));
+        if let BETransaction::Elements(tx) = &mut tx {
+            tx.output.push(elements::TxOut::new_fee(500, elements::AssetId::default()));
+        }
+        let params = ComputeFeeRateParams {
+            transaction: tx.serialize().to_hex(),
+            input_values: None,
+        };
+        let result = compute_fee_rate(&params).unwrap();
+        assert_eq!(result.fee, 500);
+        assert_eq!(result.vsize, weight_to_vsize(tx.get_weight()));
+    }
+
+    #[test]
+    fn test_add_op_return_output() {
+        let mut tx = BETransaction::new(NetworkId::Bitcoin(bitcoin::Network::Regtest));
+        tx.add_op_return_output(&[0xab; MAX_OP_RETURN_SIZE]).unwrap();
+        if let BETransaction::Bitcoin(tx) = &tx {
+            let script = &tx.output[0].script_pubkey;
+            assert!(script.is_provably_unspendable());
+            // OP_RETURN OP_PUSHDATA1 <len> <data>, the standard 83 bytes
+            assert_eq!(script.len(), MAX_OP_RETURN_SIZE + 3);
+            assert_eq!(tx.output[0].value, 0);
+        } else {
+            unreachable!();
+        }
+
+        let mut tx = BETransaction::new(NetworkId::Elements(ElementsNetwork::ElementsRegtest));
+        assert!(tx.add_op_return_output(&[0xab]).is_err());
+    }
+
+    #[test]
+    fn test_estimate_tx_size() {
+        let params: EstimateTxSizeParams = serde_json::from_value(serde_json::json!({
+            "inputs": [{ "script_type": "p2wpkh", "count": 1 }],
+            "outputs": [{ "script_type": "p2wpkh", "count": 2 }],
+        }))
+        .unwrap();
+        // 113 non witness bytes, 110 witness bytes
+        let result = estimate_tx_size(&params);
+        assert_eq!(
+            result,
+            EstimateTxSizeResult {
+                weight: 562,
+                vsize: 141
+            }
+        );
+
+        // matches the estimate used when creating transactions
+        let mut tx = BETransaction::new(NetworkId::Bitcoin(bitcoin::Network::Regtest));
+        tx.add_input(BEOutPoint::Bitcoin(bitcoin::OutPoint::default()));
+        tx.add_input(BEOutPoint::Bitcoin(bitcoin::OutPoint::default()));
+        let params = EstimateTxSizeParams {
+            inputs: vec![ScriptTypeCount {
+                script_type: ScriptType::P2shP2wpkh,
+                count: 2,
+            }],
+            outputs: vec![ScriptTypeCount {
+                script_type: ScriptType::P2shP2wpkh,
+                count: 1,
+            }],
+            is_liquid: false,
+        };
+        assert_eq!(
+            estimate_tx_size(&params).weight,
+            tx.estimated_weight(1, ScriptType::P2shP2wpkh)
+        );
+
+        // a legacy input is bigger than a segwit one
+        let mut mixed = params.clone();
+        mixed.inputs[0].count = 1;
+        mixed.inputs.push(ScriptTypeCount {
+            script_type: ScriptType::P2pkh,
+            count: 1,
+        });
+        assert!(estimate_tx_size(&mixed).vsize > estimate_tx_size(&params).vsize);
+
+        let mut tx = BETransaction::new(NetworkId::Elements(ElementsNetwork::Liquid));
+        tx.add_input(BEOutPoint::Elements(elements::OutPoint::default()));
+        tx.add_input(BEOutPoint::Elements(elements::OutPoint::default()));
+        let liquid = EstimateTxSizeParams {
+            is_liquid: true,
+            ..params
+        };
+        assert_eq!(
+            estimate_tx_size(&liquid).weight,
+            tx.estimated_weight(1, ScriptType::P2shP2wpkh)
+        );
+
+        assert_eq!(estimate_tx_size(&EstimateTxSizeParams::default()).weight, 40);
+    }
+
+    #[test]
+    fn test_multi_asset_needs_and_changes() {
+        let policy_asset = elements::AssetId::from_slice(&[1; 32]).unwrap();
+        let token = elements::AssetId::from_slice(&[2; 32]).unwrap();
+        let explicit_out = |asset, value| elements::TxOut {
+            asset: Asset::Explicit(asset),
+            value: Value::Explicit(value),
+            ..Default::default()
+        };
+
+        // a previous transaction funding the wallet with both assets
+        let prev_values = [(policy_asset, 100_000), (token, 500), (token, 300)];
+        let prev_tx = elements::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: prev_values.iter().map(|(a, v)| explicit_out(*a, *v)).collect(),
+        };
+        let mut unblinded = HashMap::new();
+        for (vout, (asset, value)) in prev_values.iter().enumerate() {
+            let outpoint = elements::OutPoint::new(prev_tx.txid(), vout as u32);
+            let secrets = elements::TxOutSecrets::new(
+                *asset,
+                elements::confidential::AssetBlindingFactor::zero(),
+                *value,
+                elements::confidential::ValueBlindingFactor::zero(),
+            );
+            unblinded.insert(outpoint, secrets);
+        }
+        let mut all_txs = BETransactions::default();
+        all_txs.insert(
+            BETxid::Elements(prev_tx.txid()),
+            BETransactionEntry {
+                tx: BETransaction::Elements(prev_tx.clone()),
+                size: 0,
+                weight: 0,
+            },
+        );
+
+        // send both assets, the fee is paid in the policy asset
+        let fee = 1_000;
+        let mut tx = BETransaction::new(NetworkId::Elements(ElementsNetwork::ElementsRegtest));
+        if let BETransaction::Elements(tx) = &mut tx {
+            tx.output.push(explicit_out(policy_asset, 10_000));
+            tx.output.push(explicit_out(token, 600));
+        }
+        let needs = |tx: &BETransaction| {
+            tx.needs(
+                FeeTarget::Absolute(fee),
+                false,
+                Some(policy_asset),
+                &all_txs,
+                &unblinded,
+                ScriptType::P2wpkh,
+            )
+        };
+        let needs_before = needs(&tx);
+        assert_eq!(needs_before.len(), 2);
+        assert_eq!(needs_before[0], AssetValue::new(token, 600));
+        // the policy asset is last
+        assert_eq!(needs_before[1], AssetValue::new(policy_asset, 10_000 + fee));
+
+        let add_input = |tx: &mut BETransaction, vout| {
+            tx.add_input(BEOutPoint::Elements(elements::OutPoint::new(prev_tx.txid(), vout)));
+        };
+        add_input(&mut tx, 0);
+        add_input(&mut tx, 1);
+        assert_eq!(needs(&tx), vec![AssetValue::new(token, 100)]);
+        add_input(&mut tx, 2);
+        assert!(needs(&tx).is_empty());
+
+        let mut changes = tx.changes(fee, DUST_VALUE, Some(policy_asset), &all_txs, &unblinded);
+        changes.sort_by_key(|c| c.satoshi);
+        assert_eq!(
+            changes,
+            vec![
+                AssetValue::new(token, 200),
+                AssetValue::new(policy_asset, 100_000 - 10_000 - fee)
+            ]
+        );
+    }
+}
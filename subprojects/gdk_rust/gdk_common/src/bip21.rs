@@ -0,0 +1,138 @@
+use std::str::FromStr;
+
+use bitcoin::{Amount, Denomination};
+
+use crate::be::BEAddress;
+use crate::error::Error;
+use crate::model::{AddressAmount, ParsedUri};
+use crate::NetworkId;
+
+/// Parse a BIP21 URI, such as `bitcoin:<address>?amount=0.001&label=shop`, checking that the
+/// address belongs to the network `id`.
+///
+/// For Liquid the `assetid` parameter is also parsed. The amount is assumed to have 8 decimals
+/// regardless of the asset, as the asset precision is unknown here.
+pub fn parse_uri(uri: &str, id: NetworkId) -> Result<ParsedUri, Error> {
+    let invalid = || Error::InvalidUri(uri.to_string());
+    let (scheme, rest) = uri.split_once(':').ok_or_else(invalid)?;
+    let schemes: &[&str] = match id {
+        NetworkId::Bitcoin(_) => &["bitcoin"],
+        NetworkId::Elements(_) => &["liquidnetwork", "liquidtestnet"],
+    };
+    if !schemes.iter().any(|s| s.eq_ignore_ascii_case(scheme)) {
+        return Err(invalid());
+    }
+    let (address, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let address = BEAddress::from_str_with_network(address, id)?;
+
+    let mut parsed = ParsedUri {
+        addressee: AddressAmount {
+            address: address.to_string(),
+            ..Default::default()
+        },
+        amount: None,
+        label: None,
+        message: None,
+    };
+    for (key, value) in url::form_urlencoded::parse(query.as_bytes()) {
+        match (key.as_ref(), id) {
+            ("amount", _) => {
+                let amount = Amount::from_str_in(&value, Denomination::Bitcoin)
+                    .map_err(|_| invalid())?
+                    .to_sat();
+                parsed.addressee.satoshi = amount;
+                parsed.amount = Some(amount);
+            }
+            ("label", _) => parsed.label = Some(value.into_owned()),
+            ("message", _) => parsed.message = Some(value.into_owned()),
+            ("assetid", NetworkId::Elements(_)) => {
+                let asset_id =
+                    elements::issuance::AssetId::from_str(&value).map_err(|_| invalid())?;
+                parsed.addressee.asset_id = Some(asset_id.to_string());
+            }
+            // Unknown required parameters must be rejected
+            (key, _) if key.starts_with("req-") => return Err(invalid()),
+            _ => (),
+        }
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ElementsNetwork;
+    use bitcoin::secp256k1::{PublicKey, Secp256k1, SecretKey};
+
+    #[test]
+    fn test_parse_uri_bitcoin() {
+        let mainnet = NetworkId::Bitcoin(bitcoin::Network::Bitcoin);
+        let testnet = NetworkId::Bitcoin(bitcoin::Network::Testnet);
+        let address = "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq";
+
+        let uri = format!(
+            "bitcoin:{}?amount=0.1&label=Luke-Jr&message=Donation%20for%20project%20xyz",
+            address
+        );
+        let parsed = parse_uri(&uri, mainnet).unwrap();
+        assert_eq!(parsed.addressee.address, address);
+        // 0.1 is not representable as a float
+        assert_eq!(parsed.addressee.satoshi, 10_000_000);
+        assert_eq!(parsed.amount, Some(10_000_000));
+        assert_eq!(parsed.label.as_deref(), Some("Luke-Jr"));
+        assert_eq!(parsed.message.as_deref(), Some("Donation for project xyz"));
+        assert_eq!(parsed.addressee.asset_id, None);
+
+        let parsed = parse_uri(&format!("BITCOIN:{}", address), mainnet).unwrap();
+        assert_eq!((parsed.amount, parsed.label), (None, None));
+        assert_eq!(
+            parse_uri(&format!("bitcoin:{}?amount=20.3", address), mainnet).unwrap().amount,
+            Some(2_030_000_000)
+        );
+
+        let testnet_address = "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx";
+        let uri = format!("bitcoin:{}?amount=0.00000001", testnet_address);
+        assert_eq!(parse_uri(&uri, testnet).unwrap().amount, Some(1));
+        assert!(matches!(parse_uri(&uri, mainnet), Err(Error::MismatchingNetwork)));
+
+        for invalid in [
+            format!("bitcoin:{}?amount=0.000000001", address),
+            format!("bitcoin:{}?amount=-1", address),
+            format!("bitcoin:{}?req-somethingyoudontunderstand=50", address),
+            format!("liquidnetwork:{}", address),
+            address.to_string(),
+        ] {
+            assert!(
+                matches!(parse_uri(&invalid, mainnet), Err(Error::InvalidUri(_))),
+                "{}",
+                invalid
+            );
+        }
+        assert!(parse_uri("bitcoin:notanaddress", mainnet).is_err());
+    }
+
+    #[test]
+    fn test_parse_uri_liquid() {
+        let liquid = NetworkId::Elements(ElementsNetwork::Liquid);
+        let secp = Secp256k1::new();
+        let key = |b| PublicKey::from_secret_key(&secp, &SecretKey::from_slice(&[b; 32]).unwrap());
+        let pk = bitcoin::PublicKey::new(key(1));
+        let address =
+            elements::Address::p2wpkh(&pk, Some(key(2)), &elements::AddressParams::LIQUID)
+                .to_string();
+        let asset_id = "6f0279e9ed041c3d710a9f57d0c02928416460c4b722ae3457a11eec381c526d";
+
+        let uri = format!("liquidnetwork:{}?amount=0.00012&assetid={}", address, asset_id);
+        let parsed = parse_uri(&uri, liquid).unwrap();
+        assert_eq!(parsed.addressee.address, address);
+        assert_eq!(parsed.addressee.satoshi, 12_000);
+        assert_eq!(parsed.addressee.asset_id.as_deref(), Some(asset_id));
+
+        assert!(matches!(
+            parse_uri(&format!("liquidnetwork:{}?assetid=xyz", address), liquid),
+            Err(Error::InvalidUri(_))
+        ));
+        let regtest = NetworkId::Elements(ElementsNetwork::ElementsRegtest);
+        assert!(matches!(parse_uri(&uri, regtest), Err(Error::MismatchingNetwork)));
+    }
+}
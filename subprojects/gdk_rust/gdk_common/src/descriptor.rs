@@ -1,7 +1,11 @@
 use crate::error::Error;
 use crate::scripts::ScriptType;
-use bitcoin::util::bip32::{ChildNumber, ExtendedPubKey, Fingerprint};
-use miniscript::descriptor::{Descriptor, DescriptorPublicKey, ShInner};
+use bitcoin::blockdata::opcodes::all::OP_CHECKMULTISIG;
+use bitcoin::blockdata::script::Builder;
+use bitcoin::util::bip32::{ChildNumber, DerivationPath, ExtendedPubKey, Fingerprint};
+use bitcoin::Script;
+use miniscript::descriptor::{Descriptor, DescriptorPublicKey, ShInner, Wildcard, WshInner};
+use miniscript::Terminal;
 
 /// Make sure the key origin is in the expected format
 /// and return the bip32 account number
@@ -77,10 +81,149 @@ pub fn parse_single_sig_descriptor(
                 return check_xpub_consitency(ScriptType::P2pkh, descriptorxkey.xkey, n, *f);
             }
         }
+    } else if let Descriptor::Tr(tr) = desc {
+        // Only key path spends are supported
+        if tr.taptree().is_none() {
+            if let DescriptorPublicKey::XPub(descriptorxkey) = tr.internal_key() {
+                if let Some((f, p)) = &descriptorxkey.origin {
+                    let n = match_key_origin(&p.clone().into(), 86, coin_type)?;
+                    return check_xpub_consitency(ScriptType::P2tr, descriptorxkey.xkey, n, *f);
+                }
+            }
+        }
     }
     Err(Error::UnsupportedDescriptor)
 }
 
+/// A cosigner of a multisig account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cosigner {
+    pub fingerprint: Fingerprint,
+    /// The derivation path from the cosigner master key to `xpub`
+    pub path: DerivationPath,
+    pub xpub: ExtendedPubKey,
+}
+
+/// A multisig account described by a `wsh(multi(...))` or `wsh(sortedmulti(...))` descriptor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultisigDescriptor {
+    /// The number of signatures required
+    pub threshold: usize,
+    /// The cosigners, in the order of the descriptor
+    pub cosigners: Vec<Cosigner>,
+    /// Whether the keys are sorted in the scripts, as in `sortedmulti`
+    pub sorted: bool,
+    /// The bip32 account shared by the cosigners key origins
+    pub bip32_account: u32,
+}
+
+impl MultisigDescriptor {
+    /// The witness script at `index` of the external or internal chain
+    pub fn witness_script(&self, is_internal: bool, index: u32) -> Result<Script, Error> {
+        let path = [ChildNumber::from(is_internal as u32), ChildNumber::from(index)];
+        let mut keys = self
+            .cosigners
+            .iter()
+            .map(|c| Ok(c.xpub.derive_pub(&crate::EC, &path)?.to_pub()))
+            .collect::<Result<Vec<_>, Error>>()?;
+        if self.sorted {
+            keys.sort_by_key(|k| k.to_bytes());
+        }
+        let mut builder = Builder::new().push_int(self.threshold as i64);
+        for key in keys.iter() {
+            builder = builder.push_key(key);
+        }
+        Ok(builder.push_int(keys.len() as i64).push_opcode(OP_CHECKMULTISIG).into_script())
+    }
+
+    /// The descriptor of the external or internal chain, with checksum
+    pub fn descriptor(&self, is_internal: bool) -> Result<String, Error> {
+        let keys = self
+            .cosigners
+            .iter()
+            .map(|c| {
+                let path = &c.path.to_string()[2..];
+                format!("[{}/{}]{}/{}/*", c.fingerprint, path, c.xpub, is_internal as u32)
+            })
+            .collect::<Vec<_>>();
+        let fragment = if self.sorted {
+            "sortedmulti"
+        } else {
+            "multi"
+        };
+        let desc = format!("wsh({}({},{}))", fragment, self.threshold, keys.join(","));
+        let (desc, _) = Descriptor::parse_descriptor(&crate::EC, &desc)
+            .map_err(|_| Error::UnsupportedDescriptor)?;
+        Ok(desc.to_string())
+    }
+}
+
+/// Parse a `wsh(multi(...))` or `wsh(sortedmulti(...))` descriptor of the external or internal
+/// chain. Every key must have a BIP48 key origin, `48'/coin_type'/account'/2'`, with the same
+/// account.
+pub fn parse_multisig_descriptor(s: &str, coin_type: u32) -> Result<MultisigDescriptor, Error> {
+    let (desc, _) =
+        Descriptor::parse_descriptor(&crate::EC, s).map_err(|_| Error::UnsupportedDescriptor)?;
+    let wsh = match desc {
+        Descriptor::Wsh(wsh) => wsh,
+        _ => return Err(Error::UnsupportedDescriptor),
+    };
+    let (threshold, keys, sorted) = match wsh.as_inner() {
+        WshInner::SortedMulti(smv) => (smv.k, smv.pks.clone(), true),
+        WshInner::Ms(ms) => match &ms.node {
+            Terminal::Multi(k, pks) => (*k, pks.clone(), false),
+            _ => return Err(Error::UnsupportedDescriptor),
+        },
+    };
+
+    let mut bip32_account = None;
+    let mut chain = None;
+    let mut cosigners = vec![];
+    for key in keys {
+        let xkey = match key {
+            DescriptorPublicKey::XPub(xkey) if xkey.wildcard == Wildcard::Unhardened => xkey,
+            _ => return Err(Error::UnsupportedDescriptor),
+        };
+        let (fingerprint, path) = xkey.origin.ok_or(Error::UnsupportedDescriptor)?;
+        let account = match path.as_ref() {
+            [ChildNumber::Hardened {
+                index: 48,
+            }, ChildNumber::Hardened {
+                index: c,
+            }, ChildNumber::Hardened {
+                index: n,
+            }, ChildNumber::Hardened {
+                index: 2,
+            }] if *c == coin_type => *n,
+            _ => return Err(Error::UnsupportedDescriptor),
+        };
+        // All the keys must be on the same account and chain
+        let key_chain = match xkey.derivation_path.as_ref() {
+            [ChildNumber::Normal {
+                index,
+            }] if *index < 2 => *index,
+            _ => return Err(Error::UnsupportedDescriptor),
+        };
+        if *bip32_account.get_or_insert(account) != account
+            || *chain.get_or_insert(key_chain) != key_chain
+        {
+            return Err(Error::UnsupportedDescriptor);
+        }
+        cosigners.push(Cosigner {
+            fingerprint,
+            path,
+            xpub: xkey.xkey,
+        });
+    }
+
+    Ok(MultisigDescriptor {
+        threshold,
+        cosigners,
+        sorted,
+        bip32_account: bip32_account.ok_or(Error::UnsupportedDescriptor)?,
+    })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -96,6 +239,9 @@ mod test {
         let p2wpkh_1 = format!("wpkh([00000000/84'/1'/1']{}/0/*)", tpub_1);
         let p2wpkh_inc = format!("wpkh([00000000/84'/1'/0']{}/0/*)", tpub_1);
         let p2pkh = format!("pkh([00000000/44'/1'/0']{}/0/*)", tpub);
+        let p2tr = format!("tr([00000000/86'/1'/0']{}/0/*)", tpub);
+        let p2tr_incorrect_key_origin = format!("tr([00000000/84'/1'/0']{}/0/*)", tpub);
+        let p2tr_script_path = format!("tr([00000000/86'/1'/0']{}/0/*,pk({}/1/*))", tpub, tpub);
         let shmulti = format!("sh(multi(2,{}/0/*,{}/1/*))", tpub, tpub);
         let shp2wkh_no_wildcard = format!("sh(wpkh([00000000/49'/1'/0']{}/0))", tpub);
         let shp2wkh_no_key_origin = format!("sh(wpkh({}/0/*))", tpub);
@@ -128,6 +274,11 @@ mod test {
         assert_eq!(t, ScriptType::P2pkh);
         assert_eq!(bip32_account, 0);
         assert_eq!(f, Fingerprint::default());
+        let (t, p2tr_xpub, bip32_account, f) =
+            parse_single_sig_descriptor(&p2tr, coin_type).unwrap();
+        assert_eq!(t, ScriptType::P2tr);
+        assert_eq!(bip32_account, 0);
+        assert_eq!(f, Fingerprint::default());
 
         // Invalid cases
         let err_str = Error::UnsupportedDescriptor.to_string();
@@ -140,6 +291,8 @@ mod test {
         assert_eq!(f((&p2wpkh_inc, coin_type)), err_str);
         assert_eq!(f((&p2wpkh_incorrect_key_origin1, coin_type)), err_str);
         assert_eq!(f((&p2wpkh_incorrect_key_origin2, coin_type)), err_str);
+        assert_eq!(f((&p2tr_incorrect_key_origin, coin_type)), err_str);
+        assert_eq!(f((&p2tr_script_path, coin_type)), err_str);
 
         // Note that external and internal descriptors yield to the same xpub
         assert_eq!(shp2wpkh_xpub_external.to_string(), tpub);
@@ -147,5 +300,75 @@ mod test {
         assert_eq!(p2wpkh_xpub.to_string(), tpub);
         assert_eq!(p2wpkh_xpub_1.to_string(), tpub_1);
         assert_eq!(p2pkh_xpub.to_string(), tpub);
+        assert_eq!(p2tr_xpub.to_string(), tpub);
+    }
+
+    #[test]
+    fn test_multisig_descriptor() {
+        let coin_type = 1;
+        let tpubs = [
+            "tpubDC2Q4xK4XH72J7Lkp6kAvY2Q5x4cxrKgrevkZKC2FwWZ9A9qA5eY6kvv6QDHb6iJtByzoC5J8KZZ29T45CxFz2Gh6m6PQoFF3DqukrRGtj5",
+            "tpubDC2Q4xK4XH72LKPujd1d7X8YzuwWAemRQhcYpNqduZzpvqvR3DP3bEUJWELoPG8EEsmvQzYZ3Pw81oYrcwnJ5rmVRvm2zdyT2h7mMNJArtJ",
+            "tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp",
+        ];
+        let key = |i: usize, fingerprint: &str, chain: u32| {
+            format!("[{}/48'/1'/0'/2']{}/{}/*", fingerprint, tpubs[i], chain)
+        };
+        let multi = format!(
+            "wsh(multi(2,{},{},{}))",
+            key(0, "00000001", 0),
+            key(1, "00000002", 0),
+            key(2, "00000003", 0)
+        );
+
+        let parsed = parse_multisig_descriptor(&multi, coin_type).unwrap();
+        assert_eq!(parsed.threshold, 2);
+        assert_eq!(parsed.bip32_account, 0);
+        assert!(!parsed.sorted);
+        assert_eq!(parsed.cosigners.len(), 3);
+        assert_eq!(parsed.cosigners[1].xpub.to_string(), tpubs[1]);
+        assert_eq!(parsed.cosigners[2].fingerprint.to_string(), "00000003");
+
+        // The descriptors round trip, with a checksum
+        let external = parsed.descriptor(false).unwrap();
+        assert!(external.starts_with(&multi));
+        assert!(external.contains('#'));
+        assert_eq!(parse_multisig_descriptor(&external, coin_type).unwrap(), parsed);
+        let internal = parsed.descriptor(true).unwrap();
+        assert_eq!(parse_multisig_descriptor(&internal, coin_type).unwrap(), parsed);
+
+        // The witness script matches the one of the descriptor
+        let (desc, _) = Descriptor::parse_descriptor(&crate::EC, &external).unwrap();
+        let expected = desc.at_derivation_index(5).explicit_script().unwrap();
+        assert_eq!(parsed.witness_script(false, 5).unwrap(), expected);
+        let (desc, _) = Descriptor::parse_descriptor(&crate::EC, &internal).unwrap();
+        let expected = desc.at_derivation_index(5).explicit_script().unwrap();
+        assert_eq!(parsed.witness_script(true, 5).unwrap(), expected);
+
+        let sorted = multi.replace("multi(", "sortedmulti(");
+        let parsed = parse_multisig_descriptor(&sorted, coin_type).unwrap();
+        assert!(parsed.sorted);
+        let (desc, _) = Descriptor::parse_descriptor(&crate::EC, &sorted).unwrap();
+        let expected = desc.at_derivation_index(7).explicit_script().unwrap();
+        assert_eq!(parsed.witness_script(false, 7).unwrap(), expected);
+
+        // Invalid cases
+        let err_str = Error::UnsupportedDescriptor.to_string();
+        let f = |s: &str| parse_multisig_descriptor(s, coin_type).unwrap_err().to_string();
+        let mixed_chains = format!(
+            "wsh(multi(2,{},{},{}))",
+            key(0, "00000001", 0),
+            key(1, "00000002", 1),
+            key(2, "00000003", 0)
+        );
+        assert_eq!(f(&mixed_chains), err_str);
+        let no_origin = format!("wsh(multi(1,{}/0/*))", tpubs[0]);
+        assert_eq!(f(&no_origin), err_str);
+        let wrong_purpose = format!("wsh(multi(1,[00000001/84'/1'/0']{}/0/*))", tpubs[0]);
+        assert_eq!(f(&wrong_purpose), err_str);
+        let mainnet_coin = format!("wsh(multi(1,{}))", key(0, "00000001", 0));
+        assert_eq!(parse_multisig_descriptor(&mainnet_coin, 0).unwrap_err().to_string(), err_str);
+        let single_sig = format!("wpkh([00000000/84'/1'/0']{}/0/*)", tpubs[0]);
+        assert_eq!(f(&single_sig), err_str);
     }
 }
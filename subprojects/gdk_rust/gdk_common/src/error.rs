@@ -4,6 +4,9 @@ pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
+    #[error(transparent)]
+    Base64(#[from] base64::DecodeError),
+
     #[error(transparent)]
     BtcAddressError(#[from] bitcoin::util::address::Error),
 
@@ -37,6 +40,9 @@ pub enum Error {
     #[error(transparent)]
     ElementsEncodingError(#[from] elements::encode::Error),
 
+    #[error(transparent)]
+    ElementsPset(#[from] elements::pset::Error),
+
     #[error(transparent)]
     FromSliceError(#[from] std::array::TryFromSliceError),
 
@@ -52,6 +58,9 @@ pub enum Error {
     #[error("Invalid SLIP132 version")]
     InvalidSlip132Version,
 
+    #[error("Invalid URI: {0}")]
+    InvalidUri(String),
+
     #[error("Invalid URL: {0}")]
     InvalidUrl(String),
 
@@ -16,19 +16,33 @@ pub type ExchangeRatesCache = Arc<Mutex<HashMap<Pair, (std::time::SystemTime, f6
 pub trait ExchangeRatesCacher {
     fn xr_cache(&self) -> ExchangeRatesCache;
 
-    /// Returns the exchange rate of `pair` if it's cached, `None` otherwise.
+    /// The time used to timestamp and expire cache entries.
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    /// Returns the exchange rate of `pair` if it's cached and was fetched
+    /// less than `cache_limit` ago, `None` otherwise.
     fn get_cached_rate(&self, pair: &Pair, cache_limit: Duration) -> Option<f64> {
         let cache = self.xr_cache();
         let cache = &*cache.lock().unwrap();
         let &(time_fetched, rate) = cache.get(pair)?;
-        (time_fetched + cache_limit > SystemTime::now()).then(|| rate)
+        (time_fetched + cache_limit > self.now()).then(|| rate)
+    }
+
+    /// Returns the last fetched exchange rate of `pair`, however stale.
+    fn get_last_rate(&self, pair: &Pair) -> Option<f64> {
+        let cache = self.xr_cache();
+        let cache = &*cache.lock().unwrap();
+        cache.get(pair).map(|&(_, rate)| rate)
     }
 
     /// Caches `ticker` for future queries.
     fn cache_ticker(&mut self, ticker: Ticker) {
+        let now = self.now();
         let cache = self.xr_cache();
         let cache = &mut *cache.lock().unwrap();
-        cache.insert(ticker.pair, (SystemTime::now(), ticker.rate));
+        cache.insert(ticker.pair, (now, ticker.rate));
     }
 }
 
@@ -1,4 +1,5 @@
 pub mod be;
+pub mod bip21;
 pub mod descriptor;
 pub mod error;
 pub mod exchange_rates;
@@ -7,11 +8,13 @@ pub mod model;
 pub mod network;
 pub mod notification;
 pub mod password;
+pub mod pset;
 pub mod scripts;
 pub mod session;
 pub mod slip132;
 pub mod state;
 pub mod store;
+pub mod unit;
 pub mod util;
 pub mod wally;
 pub mod aes {
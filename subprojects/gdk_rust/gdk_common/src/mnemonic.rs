@@ -1,5 +1,14 @@
 use std::fmt;
 
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::error::Error;
+use crate::wally::{bip39_mnemonic_from_entropy, bip39_mnemonic_validate};
+
+/// The supported number of words of generated mnemonics
+pub const MNEMONIC_WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
 // This doesn't do validation, but we could?
 #[derive(Eq, Clone, PartialEq)]
 pub struct Mnemonic(String);
@@ -38,6 +47,22 @@ impl From<String> for Mnemonic {
     }
 }
 
+/// Generate a mnemonic of `word_count` words from the English wordlist, using entropy from the OS
+pub fn generate_mnemonic(word_count: usize) -> Result<String, Error> {
+    if !MNEMONIC_WORD_COUNTS.contains(&word_count) {
+        return Err(Error::InputValidationFailed);
+    }
+    // Every 3 words encode 32 bits of entropy and 1 bit of checksum
+    let mut entropy = vec![0u8; word_count / 3 * 4];
+    OsRng.fill_bytes(&mut entropy);
+    Ok(bip39_mnemonic_from_entropy(&entropy))
+}
+
+/// Whether all the words are in the English wordlist and the checksum is valid
+pub fn validate_mnemonic(mnemonic: &str) -> bool {
+    bip39_mnemonic_validate(mnemonic)
+}
+
 #[test]
 fn mnemonic_show_redacted() {
     let mnemonic = Mnemonic("secret sauce".into());
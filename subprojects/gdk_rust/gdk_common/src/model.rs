@@ -1,5 +1,7 @@
 use crate::be::{BEOutPoint, BEScript, BESigHashType, BETransaction, BETransactionEntry, BETxid};
-use crate::descriptor::parse_single_sig_descriptor;
+use crate::descriptor::{
+    parse_multisig_descriptor, parse_single_sig_descriptor, MultisigDescriptor,
+};
 use crate::slip132::{decode_from_slip132_string, extract_bip32_account};
 use crate::util::{is_confidential_txoutsecrets, now, weight_to_vsize};
 use crate::NetworkId;
@@ -7,9 +9,10 @@ use crate::NetworkParameters;
 use bitcoin::Network;
 use elements::confidential;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use crate::error::Error;
+use crate::exchange_rates::Currency;
 use crate::scripts::ScriptType;
 use crate::wally::MasterBlindingKey;
 use bitcoin::blockdata::transaction::EcdsaSighashType as BitcoinSigHashType;
@@ -28,6 +31,89 @@ pub struct InitParam {
 
     #[serde(rename = "registrydir")]
     pub registry_dir: String,
+
+    /// How log records are formatted, ignored on Android where logcat is used
+    #[serde(default)]
+    pub log_format: LogFormat,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human readable lines such as `02.345 INFO - message`
+    #[default]
+    Text,
+
+    /// A single-line JSON object per record with `ts`, `level`, `target` and `message` fields
+    Json,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetLogLevelParam {
+    /// The new maximum level, in the same form as `InitParam::log_level`
+    pub log_level: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GenerateMnemonicParams {
+    /// One of 12, 15, 18, 21 or 24
+    pub word_count: usize,
+}
+
+/// A new mnemonic, which can be used as the `mnemonic` of `Credentials`
+#[derive(Serialize, Deserialize)]
+pub struct GenerateMnemonicResult {
+    pub mnemonic: String,
+}
+
+#[derive(Deserialize)]
+pub struct ValidateMnemonicParams {
+    pub mnemonic: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidateMnemonicResult {
+    /// Whether all the words are in the English wordlist and the checksum is valid
+    pub valid: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CombinePsetParams {
+    /// Base64 PSETs of the same unsigned transaction, e.g. signed by different parties
+    pub psets: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CombinePsetResult {
+    /// The base64 PSET with the fields of all the combined ones
+    pub pset: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FinalizePsetParams {
+    /// A base64 PSET
+    pub pset: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FinalizePsetResult {
+    /// The hex of the final transaction, only if all the inputs are signed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<String>,
+    /// The indexes of the inputs missing a signature
+    pub missing_signatures: Vec<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetVersionResult {
+    /// The version of the gdk_rust crate
+    pub version: String,
+
+    /// The git commit gdk_rust was built from, or "unknown" if it was not available at build time
+    pub git_commit: String,
+
+    /// The cargo features gdk_rust was compiled with
+    pub features: Vec<String>,
 }
 
 pub type Balances = HashMap<String, i64>;
@@ -95,9 +181,48 @@ impl ExchangeRateOk {
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct AddressAmount {
     pub address: String, // could be bitcoin or elements
+    #[serde(with = "crate::util::amount")]
     pub satoshi: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub asset_id: Option<String>,
+    /// Hex data to embed in a provably unspendable output, in place of `address`.
+    /// The output has zero value, so `satoshi` must be 0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub op_return: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ValidateAddressOpt {
+    pub address: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ValidateAddressResult {
+    pub is_valid: bool,
+    /// Liquid only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_confidential: Option<bool>,
+    /// The canonical encoding of the address, if valid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ParseUriOpt {
+    pub uri: String,
+}
+
+/// A BIP21 URI, with the addressee ready to be used in [`CreateTransaction`]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ParsedUri {
+    pub addressee: AddressAmount,
+    /// The requested amount in satoshi
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
 }
 
 impl AddressAmount {
@@ -120,6 +245,10 @@ pub enum UtxoStrategy {
 
     /// Uses all and only the utxos specified by the caller
     Manual,
+
+    /// Search for utxos covering the addressees amounts and fees without needing a change,
+    /// wasting at most the fee cost of a change output, otherwise behave as `Default`
+    BranchAndBound,
 }
 
 impl Default for UtxoStrategy {
@@ -128,11 +257,18 @@ impl Default for UtxoStrategy {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateTransaction {
     #[serde(default)]
     pub addressees: Vec<AddressAmount>,
     pub fee_rate: Option<u64>, // in satoshi/kbyte
+    /// Raise a `fee_rate` below the minimum relay fee rate to the minimum instead of failing
+    #[serde(default)]
+    pub clamp_fee_rate: bool,
+    /// The exact fee to pay, mutually exclusive with `fee_rate`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_absolute: Option<u64>,
     pub subaccount: u32,
     #[serde(default)]
     pub send_all: bool,
@@ -146,18 +282,157 @@ pub struct CreateTransaction {
     /// Minimum number of confirmations for coin selection
     #[serde(default)]
     pub num_confs: u32,
+    /// Allow spending our own unconfirmed change regardless of `num_confs`
+    ///
+    /// Unconfirmed outputs received from others are still subject to `num_confs`.
+    #[serde(default)]
+    pub allow_unconfirmed: bool,
     #[serde(default)]
     pub confidential_utxos_only: bool,
     #[serde(default)]
     pub utxo_strategy: UtxoStrategy,
+    /// Only run coin selection and fee computation, returning a [`TransactionEstimate`]
+    #[serde(default)]
+    pub estimate_only: bool,
+    /// Set the locktime to the current tip height to discourage fee sniping
+    #[serde(default = "default_true")]
+    pub set_locktime_to_tip: bool,
+    /// Set the locktime to this unix time in seconds, so that the transaction can only be mined
+    /// after it. Takes precedence over `set_locktime_to_tip`, and cannot be used to replace a
+    /// transaction.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locktime_datetime: Option<u64>,
+    /// Create a transaction without witness data, only spending p2pkh outputs and with a p2pkh
+    /// change, requires a p2pkh subaccount
+    #[serde(default)]
+    pub force_legacy: bool,
+    /// Outputs removed from `utxos` before coin selection
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude_utxos: Vec<ExcludedUtxo>,
 }
 
+fn default_true() -> bool {
+    true
+}
+
+impl Default for CreateTransaction {
+    fn default() -> Self {
+        CreateTransaction {
+            addressees: vec![],
+            fee_rate: None,
+            clamp_fee_rate: false,
+            fee_absolute: None,
+            subaccount: 0,
+            send_all: false,
+            previous_transaction: None,
+            memo: None,
+            utxos: CreateTxUtxos::default(),
+            num_confs: 0,
+            allow_unconfirmed: false,
+            confidential_utxos_only: false,
+            utxo_strategy: UtxoStrategy::default(),
+            estimate_only: false,
+            set_locktime_to_tip: true,
+            locktime_datetime: None,
+            force_legacy: false,
+            exclude_utxos: vec![],
+        }
+    }
+}
+
+/// Replace an unconfirmed transaction with one paying a higher fee rate
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BumpFeeOpt {
+    pub subaccount: u32,
+    pub txid: String,
+    /// The new fee rate in satoshi/kbyte
+    pub fee_rate: u64,
+    /// Raise a `fee_rate` below the minimum relay fee rate to the minimum instead of failing
+    #[serde(default)]
+    pub clamp_fee_rate: bool,
+}
+
+/// Spend an output of an unconfirmed transaction to raise its effective fee rate (CPFP)
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CpfpOpt {
+    pub subaccount: u32,
+    /// The unconfirmed parent transaction
+    pub txid: String,
+    /// The output of the parent to spend, if not set the largest one owned by the wallet is used
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vout: Option<u32>,
+    /// The target fee rate of parent and child together in satoshi/kbyte
+    pub fee_rate: u64,
+}
+
+/// Sweep the funds controlled by a private key into a subaccount
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CreateSweepOpt {
+    pub subaccount: u32,
+    /// The private key in WIF format
+    pub private_key: String,
+    /// The fee rate in satoshi/kbyte, if not set the minimum relay fee rate is used
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_rate: Option<u64>,
+}
+
+/// The maximum number of transactions returned by a single get_transactions call
+pub const MAX_TRANSACTIONS_COUNT: usize = 500;
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct GetTransactionsOpt {
+    /// An offset past the last transaction returns an empty page
     pub first: usize,
+    /// Clamped to [`MAX_TRANSACTIONS_COUNT`]
     pub count: usize,
     pub subaccount: u32,
     pub num_confs: Option<u32>,
+    #[serde(default)]
+    pub sort: TxSort,
+    /// Include the balance after each transaction. Requires `sort` to be "oldest", since the
+    /// balance is accumulated from the first transaction of the subaccount.
+    #[serde(default)]
+    pub running_balance: bool,
+    /// Only include transactions with `created_at_ts` at or after this time, in microseconds
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_ts: Option<u64>,
+    /// Only include transactions with `created_at_ts` before this time, in microseconds
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_ts: Option<u64>,
+    /// Only include transactions of this type
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_type: Option<TransactionType>,
+    /// Include the registry metadata of the assets of each transaction, Liquid only
+    #[serde(default)]
+    pub with_asset_metadata: bool,
+}
+
+impl GetTransactionsOpt {
+    pub fn is_filtered(&self) -> bool {
+        self.start_ts.is_some() || self.end_ts.is_some() || self.tx_type.is_some()
+    }
+
+    /// Whether a transaction created at `created_at_ts` with type `type_` passes the filters
+    pub fn matches(&self, created_at_ts: u64, type_: &TransactionType) -> bool {
+        self.start_ts.map_or(true, |start| created_at_ts >= start)
+            && self.end_ts.map_or(true, |end| created_at_ts < end)
+            && self.tx_type.as_ref().map_or(true, |t| t == type_)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TxSort {
+    #[default]
+    Newest,
+    Oldest,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -166,6 +441,153 @@ pub struct GetBalanceOpt {
     pub num_confs: u32,
     #[serde(rename = "confidential")]
     pub confidential_utxos_only: Option<bool>,
+    /// Return only the balance of the policy asset, L-BTC on Liquid
+    #[serde(default)]
+    pub policy_asset_only: bool,
+}
+
+/// The output type whose dust threshold is requested, either the one of `address` or the change
+/// output type of `subaccount`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GetDustThresholdOpt {
+    #[serde(default)]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub subaccount: Option<u32>,
+    /// On Liquid, the asset of the output, the policy asset if missing
+    #[serde(default)]
+    pub asset_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DustThreshold {
+    /// Outputs of this value or less are rejected as dust, change outputs are added to the fee
+    pub dust_threshold: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GetBalanceAtHeightOpt {
+    pub subaccount: u32,
+    /// Only transactions confirmed at or below this height are counted
+    pub height: u32,
+}
+
+/// The balance of the policy asset, returned by `get_balance` with `policy_asset_only`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolicyAssetBalance {
+    pub satoshi: u64,
+}
+
+/// The balance of an asset split by whether the outputs holding it are blinded
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct BalanceDetail {
+    /// The amount in outputs with blinded asset and value
+    pub confidential_satoshi: u64,
+    /// The amount in outputs with explicit asset or value, always the whole balance on Bitcoin
+    pub unconfidential_satoshi: u64,
+}
+
+pub type BalancesDetailed = HashMap<String, BalanceDetail>;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SetElectrumTlsOpt {
+    /// Connect to the electrum server with TLS
+    pub tls: bool,
+    /// Validate the server certificate against its domain, only used with TLS. Defaults to true
+    #[serde(default = "default_true")]
+    pub validate_domain: bool,
+}
+
+impl Default for SetElectrumTlsOpt {
+    fn default() -> Self {
+        SetElectrumTlsOpt {
+            tls: false,
+            validate_domain: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GetWalletSummaryOpt {
+    /// Include the hidden subaccounts in the summary
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+/// The balances of the wallet summed over its subaccounts
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct WalletSummary {
+    /// The balance including unconfirmed transactions
+    #[serde(with = "crate::util::amount::map")]
+    pub total: Balances,
+    /// The balance of confirmed transactions only
+    #[serde(with = "crate::util::amount::map")]
+    pub confirmed: Balances,
+    /// The difference between `total` and `confirmed`, negative for unconfirmed spends
+    #[serde(with = "crate::util::amount::map")]
+    pub unconfirmed: Balances,
+    /// The number of subaccounts summed
+    pub account_count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GetPortfolioValueOpt {
+    pub currency: Currency,
+    /// Include the hidden subaccounts, as in `get_wallet_summary`
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+/// The value of the wallet bitcoin (or L-BTC) balance in fiat
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortfolioValue {
+    /// The bitcoin balance summed over the subaccounts, including unconfirmed transactions
+    pub total_satoshi: u64,
+    /// `total_satoshi` converted at `rate`, empty if no rate has been fetched yet
+    pub total_fiat: String,
+    /// The last fetched exchange rate for `currency`, empty if none
+    pub rate: String,
+    pub currency: String,
+    /// The Liquid assets with a balance that are not priced and so not included in the total
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_assets: Vec<String>,
+}
+
+impl PortfolioValue {
+    pub fn new(
+        total_satoshi: u64,
+        rate: Option<f64>,
+        currency: Currency,
+        excluded_assets: Vec<String>,
+    ) -> Self {
+        let fiat = |rate: f64| total_satoshi as f64 / 100_000_000.0 * rate;
+        PortfolioValue {
+            total_satoshi,
+            total_fiat: rate.map(|r| format!("{:.2}", fiat(r))).unwrap_or_default(),
+            rate: rate.map(|r| format!("{:.8}", r)).unwrap_or_default(),
+            currency: currency.to_string(),
+            excluded_assets,
+        }
+    }
+}
+
+/// Convert an amount between units, see `crate::unit::AmountUnit` for the supported ones
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ConvertAmountOpt {
+    pub value: String,
+    pub unit: String,
+    pub to_unit: String,
+    /// The fiat currency, required if `unit` or `to_unit` is `fiat`
+    #[serde(default)]
+    pub currency: Option<Currency>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConvertAmountResult {
+    /// The amount in `to_unit`
+    pub value: String,
+    #[serde(with = "crate::util::amount")]
+    pub satoshi: u64,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -175,6 +597,100 @@ pub struct GetUnspentOpt {
     #[serde(rename = "confidential")]
     pub confidential_utxos_only: Option<bool>,
     pub all_coins: Option<bool>, // unused
+    /// Include the outputs frozen with `set_unspent_output_frozen`
+    pub include_frozen: Option<bool>,
+    /// Return a page of outputs starting from this index, the `last_pointer` of the previous
+    /// page. Paged outputs are sorted by block height, unconfirmed last, then by outpoint.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first: Option<u32>,
+    /// The maximum number of outputs of a page
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
+
+impl GetUnspentOpt {
+    pub fn is_paged(&self) -> bool {
+        self.first.is_some() || self.count.is_some()
+    }
+}
+
+/// Freeze or unfreeze an unspent output, frozen outputs are never selected automatically
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SetUnspentOutputFrozenOpt {
+    pub subaccount: u32,
+    pub txhash: String,
+    pub pt_idx: u32,
+    pub frozen: bool,
+}
+
+/// A wallet transaction broadcast by the session which is not in the wallet history yet, or
+/// whose broadcast was interrupted
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PendingTransaction {
+    pub subaccount: u32,
+    pub txhash: String,
+    #[serde(rename = "transaction")]
+    pub hex: String,
+    /// The outputs spent by the transaction, which are not selected automatically until the
+    /// transaction is in the wallet history or discarded
+    pub used_utxos: Vec<CreateTxUtxo>,
+    pub created_at_ts: u64, // in microseconds
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DiscardPendingTransactionOpt {
+    pub txhash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GetSpendIndexOpt {
+    pub subaccount: u32,
+}
+
+/// A wallet state previously persisted by the caller, see `diff_since`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DiffSinceOpt {
+    /// The tip height when the snapshot was taken
+    pub tip_height: u32,
+    /// The number of transactions of each subaccount when the snapshot was taken, subaccounts
+    /// missing are considered new
+    #[serde(default)]
+    pub tx_count_per_subaccount: HashMap<u32, usize>,
+    /// The transactions of each subaccount that were unconfirmed when the snapshot was taken,
+    /// their balance is already part of the snapshot
+    #[serde(default)]
+    pub unconfirmed_txids_per_subaccount: HashMap<u32, Vec<String>>,
+}
+
+/// The changes of a subaccount since a snapshot
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SubaccountDiff {
+    pub subaccount: u32,
+    /// The current number of transactions, to persist in the next snapshot
+    pub tx_count: usize,
+    /// The transactions currently unconfirmed, to persist in the next snapshot
+    pub unconfirmed_txids: Vec<String>,
+    /// Whether transactions have been dropped or reorged out since the snapshot, so that
+    /// applying the diff is not enough and the subaccount should be reloaded
+    pub reload_required: bool,
+    /// The transactions confirmed after the snapshot tip or still unconfirmed, oldest first,
+    /// excluding the ones already unconfirmed at the snapshot that are still unconfirmed
+    pub transactions: Vec<TxListItem>,
+    /// The wallet outputs spent by `transactions` that were not unconfirmed at the snapshot,
+    /// as `txid:vout`
+    pub spent_utxos: Vec<String>,
+    /// The balance change due to `transactions` that were not unconfirmed at the snapshot
+    #[serde(with = "crate::util::amount::map")]
+    pub balance_delta: Balances,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WalletDiff {
+    /// The current tip height, to persist in the next snapshot
+    pub tip_height: u32,
+    pub subaccounts: Vec<SubaccountDiff>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -193,6 +709,13 @@ pub struct GetMasterBlindingKeyResult {
     pub master_blinding_key: Option<MasterBlindingKey>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetMasterFingerprintResult {
+    /// The BIP32 fingerprint of the master key, as the 8 hex chars used in the key origin of
+    /// descriptors, such as `[d34db33f/84'/0'/0']`
+    pub master_fingerprint: Fingerprint,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SetMasterBlindingKeyOpt {
     /// Master blinding key, when encoded in json is an hex of 128 chars
@@ -202,8 +725,10 @@ pub struct SetMasterBlindingKeyOpt {
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct GetAddressOpt {
     pub subaccount: u32,
-    pub address_type: Option<String>, // unused
-    pub is_internal: Option<bool>,    // true = get an internal change address
+    /// The requested address type, it must be the one of the subaccount. If None, the default of
+    /// the subaccount, of the network, or the type of the subaccount is used, in this order
+    pub address_type: Option<String>,
+    pub is_internal: Option<bool>, // true = get an internal change address
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -218,6 +743,15 @@ pub struct CreateAccountOpt {
     pub is_already_created: bool,
     #[serde(skip_deserializing, skip_serializing)]
     pub allow_gaps: bool,
+    /// Derivation path of the account root, if None the standard path for the subaccount is used
+    ///
+    /// A non-standard path is only kept in the local store, it is not found again when the
+    /// wallet is restored from its mnemonic, see `ExportedAccount::base_path`.
+    #[serde(default)]
+    pub base_path: Option<Vec<ChildNumber>>,
+    /// The cosigners of a watch-only multisig account
+    #[serde(skip)]
+    pub multisig: Option<MultisigDescriptor>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -225,6 +759,15 @@ pub struct DiscoverAccountOpt {
     #[serde(rename = "type")]
     pub script_type: ScriptType,
     pub xpub: ExtendedPubKey,
+    /// Overrides the session gap limit for this discovery
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gap_limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ComputeAccountNumParams {
+    pub bip32_account: u32,
+    pub script_type: ScriptType,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -237,10 +780,44 @@ pub struct GetAccountPathResult {
     pub path: Vec<ChildNumber>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetAccountDescriptorsOpt {
+    pub subaccount: u32,
+}
+
+/// The output descriptors of an account, with key origin and checksum, as accepted by
+/// Bitcoin Core `importdescriptors`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountDescriptors {
+    /// The descriptor of the external chain, `.../0/*`
+    pub receive: String,
+    /// The descriptor of the internal chain, `.../1/*`
+    pub change: String,
+}
+
+/// The data needed to set up a watch-only companion of an account
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedAccount {
+    pub subaccount: u32,
+    #[serde(flatten)]
+    pub descriptors: AccountDescriptors,
+    /// The account extended public key
+    pub xpub: String,
+    /// The derivation path of the account root, to be backed up if it was given at creation
+    /// since such paths are not recovered when restoring the wallet
+    pub base_path: Vec<ChildNumber>,
+    /// The account extended public key with the SLIP-132 version of its script type, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slip132_extended_pubkey: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GetNextAccountOpt {
     #[serde(rename = "type")]
     pub script_type: ScriptType,
+    /// Derivation path the account will be created at, checked to be unused
+    #[serde(default)]
+    pub base_path: Option<Vec<ChildNumber>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -273,6 +850,108 @@ pub struct SPVCommonParams {
     pub encryption_key: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ComputeFeeRateParams {
+    /// The hex of the transaction
+    pub transaction: String,
+
+    /// The values of the outputs spent by the transaction inputs, in input order.
+    ///
+    /// Required for Bitcoin, if None the transaction is parsed as a Liquid transaction and the
+    /// fee is read from its explicit fee output.
+    #[serde(default)]
+    pub input_values: Option<Vec<u64>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ComputeFeeRateResult {
+    pub fee: u64,
+    pub vsize: usize,
+    /// Fee rate in satoshi per 1000 vbytes
+    pub fee_rate: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct EstimateTxSizeParams {
+    #[serde(default)]
+    pub inputs: Vec<ScriptTypeCount>,
+    #[serde(default)]
+    pub outputs: Vec<ScriptTypeCount>,
+    /// Whether the transaction is a Liquid one, with confidential outputs and an explicit fee
+    #[serde(default)]
+    pub is_liquid: bool,
+}
+
+/// A number of inputs or outputs of the same script type
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScriptTypeCount {
+    pub script_type: ScriptType,
+    pub count: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EstimateTxSizeResult {
+    pub vsize: usize,
+    pub weight: usize,
+}
+
+/// The result of a `create_transaction` call with `estimate_only` set
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransactionEstimate {
+    pub fee: u64,
+    /// Estimated virtual size of the signed transaction
+    pub vsize: usize,
+    /// Estimated weight of the signed transaction
+    pub weight: usize,
+    pub used_utxos: Vec<UnspentOutput>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PreviewCoinSelectionOpt {
+    pub subaccount: u32,
+    #[serde(with = "crate::util::amount")]
+    pub satoshi: u64,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_id: Option<String>,
+    /// In satoshi/kbyte, the minimum relay fee rate if not set
+    #[serde(default)]
+    pub fee_rate: Option<u64>,
+    #[serde(default)]
+    pub utxo_strategy: UtxoStrategy,
+}
+
+/// The utxos coin selection would spend to send an amount
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CoinSelectionPreview {
+    pub used_utxos: Vec<UnspentOutput>,
+    /// The sum of the used utxos of the sent asset
+    pub total_in: u64,
+    pub fee: u64,
+    /// The change of the sent asset
+    pub change: u64,
+}
+
+/// An unconfirmed transaction and the unconfirmed transactions it depends on
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Ancestors {
+    /// The unconfirmed ancestors, each one listed before its own ancestors
+    pub ancestors: Vec<AncestorTransaction>,
+    /// The fee of the transaction and all its ancestors
+    pub package_fee: u64,
+    /// The vsize of the transaction and all its ancestors
+    pub package_vsize: usize,
+    /// The effective fee rate of the package, in satoshi/kbyte
+    pub package_fee_rate: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AncestorTransaction {
+    pub txhash: String,
+    pub fee: u64,
+    pub vsize: usize,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct SPVVerifyTxParams {
     #[serde(flatten)]
@@ -316,6 +995,14 @@ pub enum SPVVerifyTxResult {
     Disabled,
 }
 
+/// A transaction created, signed and broadcast by `send_funds`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SendFundsResult {
+    pub txid: String,
+    /// The signed transaction hex
+    pub transaction: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TransactionMeta {
     #[serde(flatten)]
@@ -330,6 +1017,7 @@ pub struct TransactionMeta {
     pub error: String,
     pub addressees_read_only: bool,
     pub is_sweep: bool,
+    #[serde(with = "crate::util::amount::map")]
     pub satoshi: Balances,
     pub fee: u64,
     pub network: Option<Network>,
@@ -352,6 +1040,20 @@ pub struct TransactionMeta {
     #[serde(rename = "transaction_locktime")]
     pub lock_time: u32,
     pub transaction_outputs: Vec<TransactionOutput>,
+    /// Whether some of the inputs are unconfirmed, i.e. the transaction depends on unconfirmed ones
+    #[serde(default)]
+    pub used_unconfirmed_utxos: bool,
+    /// The fee rate in satoshi/kbyte, computed on the estimated size of the signed transaction
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effective_fee_rate: Option<u64>,
+    /// Whether the transaction has witness data, for created transactions whether it will have
+    /// once signed
+    #[serde(default)]
+    pub has_witness: bool,
+    /// For created transactions, the index of the change output, the first one if there are
+    /// several, as on Liquid where there is a change per asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_index: Option<u32>,
 }
 
 impl From<BETransaction> for TransactionMeta {
@@ -361,6 +1063,7 @@ impl From<BETransaction> for TransactionMeta {
         let timestamp = now();
         let rbf_optin = transaction.rbf_optin();
         let weight = transaction.get_weight();
+        let has_witness = transaction.has_witness();
 
         TransactionMeta {
             create_transaction: None,
@@ -385,6 +1088,10 @@ impl From<BETransaction> for TransactionMeta {
             version: transaction.version(),
             lock_time: transaction.lock_time(),
             transaction_outputs: vec![],
+            used_unconfirmed_utxos: false,
+            effective_fee_rate: None,
+            has_witness,
+            change_index: None,
         }
     }
 }
@@ -440,15 +1147,19 @@ pub struct TransactionOutput {
     pub is_change: bool, // Same as is_internal
     pub pointer: u32,    // child_number in bip32 terminology
     pub user_path: Vec<ChildNumber>,
+    /// `user_path` in BIP32 notation, empty if the output does not belong to the account
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub derivation_path: String,
 
     pub pt_idx: u32, // vout
     #[serde(rename = "script")]
     pub script_pubkey: String,
+    #[serde(with = "crate::util::amount")]
     pub satoshi: u64,
 }
 
 /// Input and output element for get_transactions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct GetTxInOut {
     /// The address of the input or output.
     ///
@@ -497,6 +1208,7 @@ pub struct GetTxInOut {
     /// The amount associated to the element.
     ///
     /// For liquid is 0 if the amount cannot be unblinded.
+    #[serde(with = "crate::util::amount")]
     pub satoshi: u64,
 
     /// Multisig field, always 0.
@@ -562,7 +1274,7 @@ pub struct GetTxInOut {
 ///
 /// Note that the follwing types might be inaccurate for complex
 /// transactions such as coinjoins or involving multiple (sub)accounts.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum TransactionType {
     Unknown,
@@ -598,7 +1310,11 @@ pub struct TxListItem {
     pub type_: TransactionType,
     pub memo: String,
     pub txhash: String,
+    #[serde(with = "crate::util::amount::map")]
     pub satoshi: Balances,
+    /// The balance after this transaction, only if requested
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", with = "crate::util::amount::map")]
+    pub running_balance: Balances,
     pub rbf_optin: bool,
     pub can_cpfp: bool,
     pub can_rbf: bool,
@@ -611,6 +1327,22 @@ pub struct TxListItem {
     pub transaction_size: usize,
     pub transaction_vsize: usize,
     pub transaction_weight: usize,
+    /// The txid of the transaction that replaced this one, spending one of its inputs.
+    /// A replaced transaction does not affect the wallet balance and utxos.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<String>,
+    /// The registry metadata of the assets in `satoshi`, `inputs` and `outputs`, by asset id.
+    /// Only if requested with `with_asset_metadata`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset_metadata: Option<HashMap<String, AssetMetadata>>,
+}
+
+/// The fields are null if the asset is not in the registry
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct AssetMetadata {
+    pub ticker: Option<String>,
+    pub name: Option<String>,
+    pub precision: Option<u8>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -668,6 +1400,18 @@ pub struct Credentials {
 pub enum WatchOnlyCredentials {
     Slip132ExtendedPubkeys(Vec<String>),
     CoreDescriptors(Vec<String>),
+    /// `wsh(multi(...))` or `wsh(sortedmulti(...))` descriptors of multisig accounts
+    MultisigDescriptors(Vec<String>),
+}
+
+/// The data needed to login with a watch-only session
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WatchOnlyBundle {
+    /// The fingerprint of the master extended public key
+    pub master_fingerprint: Fingerprint,
+
+    /// Credentials to use in `login_wo`
+    pub credentials: WatchOnlyCredentials,
 }
 
 /// An intermediate struct to hold account data
@@ -676,6 +1420,7 @@ pub struct AccountData {
     pub account_num: u32,
     pub xpub: ExtendedPubKey,
     pub master_xpub_fingerprint: Option<Fingerprint>,
+    pub multisig: Option<MultisigDescriptor>,
 }
 
 fn from_slip132_extended_pubkey(s: &str, expected_is_mainnet: bool) -> Result<AccountData, Error> {
@@ -685,12 +1430,13 @@ fn from_slip132_extended_pubkey(s: &str, expected_is_mainnet: bool) -> Result<Ac
     }
 
     let bip32_account = extract_bip32_account(&xpub)?;
-    let account_num = bip32_account * 16 + script_type.num();
+    let account_num = script_type.account_num(bip32_account).ok_or(Error::InputValidationFailed)?;
 
     Ok(AccountData {
         account_num,
         xpub,
         master_xpub_fingerprint: None,
+        multisig: None,
     })
 }
 
@@ -710,12 +1456,42 @@ fn from_descriptor(s: &str, expected_is_mainnet: bool) -> Result<AccountData, Er
         return Err(Error::MismatchingNetwork);
     }
 
-    let account_num = bip32_account * 16 + script_type.num();
+    let account_num = script_type.account_num(bip32_account).ok_or(Error::InputValidationFailed)?;
 
     Ok(AccountData {
         account_num,
         xpub,
         master_xpub_fingerprint: Some(master_xpub_fingerprint),
+        multisig: None,
+    })
+}
+
+fn from_multisig_descriptor(s: &str, expected_is_mainnet: bool) -> Result<AccountData, Error> {
+    let coin_type = if expected_is_mainnet {
+        0
+    } else {
+        1
+    };
+    let multisig = parse_multisig_descriptor(s, coin_type)?;
+    // Use the first cosigner xpub as the account xpub
+    let xpub = multisig.cosigners[0].xpub;
+    let is_mainnet = match xpub.network {
+        Network::Bitcoin => true,
+        _ => false,
+    };
+    if is_mainnet != expected_is_mainnet {
+        return Err(Error::MismatchingNetwork);
+    }
+
+    let account_num = ScriptType::P2wsh
+        .account_num(multisig.bip32_account)
+        .ok_or(Error::InputValidationFailed)?;
+
+    Ok(AccountData {
+        account_num,
+        xpub,
+        master_xpub_fingerprint: None,
+        multisig: Some(multisig),
     })
 }
 
@@ -742,16 +1518,32 @@ impl WatchOnlyCredentials {
             WatchOnlyCredentials::Slip132ExtendedPubkeys(keys) => {
                 keys.iter().map(|k| from_slip132_extended_pubkey(&k, is_mainnet)).collect()
             }
-            WatchOnlyCredentials::CoreDescriptors(descriptors) => {
-                descriptors.iter().map(|d| from_descriptor(&d, is_mainnet)).collect()
+            WatchOnlyCredentials::CoreDescriptors(descriptors) => descriptors
+                .iter()
+                .map(|d| {
+                    // Single sig descriptors are never wsh
+                    if d.starts_with("wsh(") {
+                        from_multisig_descriptor(d, is_mainnet)
+                    } else {
+                        from_descriptor(d, is_mainnet)
+                    }
+                })
+                .collect(),
+            WatchOnlyCredentials::MultisigDescriptors(descriptors) => {
+                descriptors.iter().map(|d| from_multisig_descriptor(d, is_mainnet)).collect()
             }
         };
         // Handle duplicates
-        let mut m = HashMap::<u32, ExtendedPubKey>::new();
+        let mut m = HashMap::<u32, AccountData>::new();
         let mut master_xpub_fingerprint = None;
-        for a in r? {
-            if let Some(old) = m.insert(a.account_num, a.xpub.clone()) {
-                if old != a.xpub {
+        let accounts = r?;
+        // Single sig accounts share a master key, multisig ones don't, so they can't be mixed
+        if accounts.iter().any(|a| a.multisig.is_some() != accounts[0].multisig.is_some()) {
+            return Err(Error::MismatchingDescriptor);
+        }
+        for a in accounts {
+            if let Some(old) = m.insert(a.account_num, a.clone()) {
+                if old.xpub != a.xpub || old.multisig != a.multisig {
                     return Err(Error::MismatchingXpub);
                 }
             };
@@ -768,11 +1560,10 @@ impl WatchOnlyCredentials {
             }
         }
         let v = m
-            .iter()
-            .map(|(k, v)| AccountData {
-                account_num: *k,
-                xpub: *v,
+            .into_values()
+            .map(|a| AccountData {
                 master_xpub_fingerprint: master_xpub_fingerprint.clone(),
+                ..a
             })
             .collect();
         let master_xpub_fingerprint = master_xpub_fingerprint.unwrap_or_default();
@@ -802,7 +1593,68 @@ pub struct AddressPointer {
 // This one is simple enough to derive a serializer
 #[derive(Serialize, Debug, Clone, Deserialize)]
 pub struct FeeEstimate(pub u64);
-pub struct TxsResult(pub Vec<TxListItem>);
+
+/// The confirmation targets, in blocks, of `FeeEstimatesByTarget`
+pub const FEE_ESTIMATE_TARGETS: [u32; 5] = [1, 3, 6, 12, 24];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeeEstimatesByTarget {
+    /// The minimum relay fee in satoshi per 1000 bytes
+    pub relay_fee: u64,
+    /// Fee rate in satoshi per 1000 bytes for each confirmation target in blocks
+    pub targets: BTreeMap<u32, u64>,
+}
+
+impl FeeEstimatesByTarget {
+    /// Build from the legacy estimates, where the first element is the relay fee and the element
+    /// at index `n` the estimate to confirm in `n` blocks.
+    ///
+    /// A target never has a higher fee rate than a nearer one, since a transaction confirming
+    /// within `n` blocks also confirms within any later target.
+    pub fn from_estimates(estimates: &[FeeEstimate]) -> Result<Self, Error> {
+        let relay_fee = estimates
+            .first()
+            .ok_or_else(|| Error::Generic("Expected at least one feerate".into()))?
+            .0;
+        let mut targets = BTreeMap::new();
+        let mut lowest = u64::MAX;
+        for block in 1..=FEE_ESTIMATE_TARGETS[FEE_ESTIMATE_TARGETS.len() - 1] {
+            let fee = estimates.get(block as usize).map_or(relay_fee, |e| e.0);
+            lowest = lowest.min(fee);
+            if FEE_ESTIMATE_TARGETS.contains(&block) {
+                targets.insert(block, lowest);
+            }
+        }
+        Ok(FeeEstimatesByTarget {
+            relay_fee,
+            targets,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FeeRateAtPercentileOpt {
+    /// From 0 to 100, the share of the mempool weight paying at most the returned fee rate
+    pub percentile: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeeRateAtPercentile {
+    /// Fee rate in satoshi per vbyte
+    pub fee_rate: f64,
+    /// False if the mempool fee histogram was not available and the fee rate comes from the
+    /// fee estimates
+    pub from_histogram: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TxsResult {
+    pub transactions: Vec<TxListItem>,
+    /// Whether the requested count has been clamped and further transactions are available
+    pub truncated: bool,
+}
+
+pub const DEFAULT_MATURE_NUM_BLOCKS: u32 = 6;
 
 /// Change to the model of Settings and Pricing structs could break old versions.
 /// You can't remove fields, change fields type and if you add a new field, it must be Option<T>
@@ -813,6 +1665,9 @@ pub struct Settings {
     pub altimeout: u32,
     pub pricing: Pricing,
     pub sound: bool,
+    /// Confirmations needed for an utxo to be in the `mature` confirmation bucket,
+    /// `DEFAULT_MATURE_NUM_BLOCKS` if not set
+    pub mature_num_blocks: Option<u32>,
 }
 
 impl Settings {
@@ -835,6 +1690,21 @@ impl Settings {
         if let Some(sound) = json.get("sound").and_then(|v| v.as_bool()) {
             self.sound = sound;
         }
+        if let Some(mature_num_blocks) = json.get("mature_num_blocks").and_then(|v| v.as_u64()) {
+            self.mature_num_blocks = Some(mature_num_blocks as u32);
+        }
+    }
+
+    /// The confirmation bucket of an utxo with `confirmations` confirmations:
+    /// `unconfirmed`, `immature` or `mature`
+    pub fn confirmation_bucket(&self, confirmations: u32) -> String {
+        let mature_num_blocks = self.mature_num_blocks.unwrap_or(DEFAULT_MATURE_NUM_BLOCKS);
+        match confirmations {
+            0 => "unconfirmed",
+            c if c < mature_num_blocks => "immature",
+            _ => "mature",
+        }
+        .to_string()
     }
 }
 
@@ -842,6 +1712,10 @@ impl Settings {
 pub struct AccountSettings {
     pub name: String,
     pub hidden: bool,
+    /// The address type returned by `get_receive_address` when none is requested, it takes
+    /// precedence over the network default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_address_type: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -849,6 +1723,28 @@ pub struct UpdateAccountOpt {
     pub subaccount: u32,
     pub name: Option<String>,
     pub hidden: Option<bool>,
+    /// Set the default address type of the subaccount, an empty string removes it
+    pub default_address_type: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RescanSubaccountOpt {
+    pub subaccount: u32,
+    /// Transactions confirmed at or above this height are fetched again from the server
+    pub from_height: u32,
+}
+
+/// Set the memos of many transactions at once
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SetTransactionMemosOpt {
+    /// The memos by txid
+    pub memos: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SetTransactionMemosResult {
+    /// The txids that are not wallet transactions, whose memos have not been set
+    pub unknown_txids: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -876,6 +1772,7 @@ impl Default for Settings {
             altimeout: 5,
             pricing,
             sound: true,
+            mature_num_blocks: None,
         }
     }
 }
@@ -928,6 +1825,20 @@ impl CreateTxUtxo {
     }
 }
 
+/// An output that must not be spent by a transaction, see `CreateTransaction::exclude_utxos`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExcludedUtxo {
+    pub txhash: String,
+    pub pt_idx: u32,
+}
+
+impl ExcludedUtxo {
+    pub fn outpoint(&self, id: NetworkId) -> Result<BEOutPoint, Error> {
+        let betxid = BETxid::from_hex(&self.txhash, id)?;
+        Ok(BEOutPoint::new(betxid, self.pt_idx))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Txo {
     pub outpoint: BEOutPoint,
@@ -968,21 +1879,97 @@ impl Txo {
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct UnblindTransactionOpt {
+    /// The hex of the transaction to unblind, takes precedence over `txid`
+    #[serde(default)]
+    pub transaction: Option<String>,
+    /// The txid of a wallet transaction to unblind
+    #[serde(default)]
+    pub txid: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnblindedOutput {
+    pub pt_idx: u32,
+    /// False if the output could not be unblinded with the master blinding key, in which case
+    /// the remaining fields are missing
+    pub unblinded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub satoshi: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_blinder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_blinder: Option<String>,
+}
+
+impl From<Option<elements::TxOutSecrets>> for UnblindedOutput {
+    fn from(secrets: Option<elements::TxOutSecrets>) -> Self {
+        UnblindedOutput {
+            pt_idx: 0,
+            unblinded: secrets.is_some(),
+            asset_id: secrets.as_ref().map(|s| s.asset.to_hex()),
+            satoshi: secrets.as_ref().map(|s| s.value),
+            asset_blinder: secrets.as_ref().map(|s| s.asset_bf.to_hex()),
+            amount_blinder: secrets.as_ref().map(|s| s.value_bf.to_hex()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnblindedTransaction {
+    pub txhash: String,
+    pub outputs: Vec<UnblindedOutput>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetOutputProofsOpt {
+    pub txid: String,
+    pub vout: u32,
+}
+
+/// The proofs of a Liquid output, empty if the output is explicit
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputProofs {
+    /// The hex serialized range proof of the value commitment
+    pub rangeproof: String,
+    /// The hex serialized surjection proof of the asset commitment
+    pub surjectionproof: String,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GetUnspentOutputs(pub HashMap<String, Vec<UnspentOutput>>);
 
+/// A page of unspent outputs, see `GetUnspentOpt::first`
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnspentOutputsPage {
+    pub unspent_outputs: GetUnspentOutputs,
+
+    /// The index of the first output of the next page.
+    ///
+    /// None if all outputs have been fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_pointer: Option<u32>,
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UnspentOutput {
     pub address_type: String,
     pub block_height: u32,
     pub pointer: u32,
     pub pt_idx: u32,
+    #[serde(with = "crate::util::amount")]
     pub satoshi: u64,
     pub subaccount: u32,
     pub txhash: String,
     /// `true` iff belongs to internal chain, i.e. is change
     pub is_internal: bool,
     pub user_path: Vec<ChildNumber>,
+    /// `user_path` in BIP32 notation, e.g. `m/84'/0'/0'/0/5`, for hardware wallets to display
+    #[serde(default)]
+    pub derivation_path: String,
     #[serde(skip)]
     pub scriptpubkey: BEScript,
     /// This can be Some only when this describes an input
@@ -998,6 +1985,9 @@ pub struct UnspentOutput {
     #[serde(default)]
     #[serde(skip_serializing)]
     pub skip_signing: bool,
+    /// `unconfirmed`, `immature` or `mature`, see `Settings::confirmation_bucket`
+    #[serde(default)]
+    pub confirmation_bucket: String,
 
     // liquid fields
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -1057,6 +2047,7 @@ impl TryFrom<Txo> for UnspentOutput {
             script_code: txo.script_code.to_hex(),
             subaccount: txo.subaccount,
             address_type: txo.script_type.to_string(),
+            derivation_path: DerivationPath::from(txo.user_path.clone()).to_string(),
             user_path: txo.user_path,
             is_internal,
             pointer,
@@ -1064,6 +2055,7 @@ impl TryFrom<Txo> for UnspentOutput {
             sequence: txo.sequence,
             sighash: None,
             skip_signing: false,
+            confirmation_bucket: String::new(),
             is_blinded,
             is_confidential,
             asset_id,
@@ -1102,6 +2094,17 @@ pub fn parse_path(path: &DerivationPath) -> Result<(bool, u32), Error> {
     Ok((is_internal, address_pointer))
 }
 
+// Input of get_transaction_details, which also accepts just the txhash
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GetTransactionDetailsOpt {
+    pub txhash: String,
+
+    /// Also return the addresses of the outputs spent by the transaction, see
+    /// `TransactionDetails::input_addresses`
+    #[serde(default)]
+    pub fetch_input_addresses: bool,
+}
+
 // Output of get_transaction_details
 #[derive(Serialize, Debug, Clone)]
 pub struct TransactionDetails {
@@ -1113,6 +2116,14 @@ pub struct TransactionDetails {
     pub transaction_size: usize,
     pub transaction_vsize: usize,
     pub transaction_weight: usize,
+
+    /// The address of the output spent by each input, empty if it has no address (e.g. coinbase
+    /// inputs or non standard scripts). For Liquid these are unconfidential addresses.
+    ///
+    /// Only returned if `fetch_input_addresses` is set. The previous transactions not belonging
+    /// to the wallet are downloaded, and then cached.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input_addresses: Option<Vec<String>>,
 }
 
 // Output of get_scriptpubkey_data
@@ -1135,11 +2146,12 @@ impl From<&BETransactionEntry> for TransactionDetails {
             transaction_size: tx_entry.size,
             transaction_vsize: weight_to_vsize(tx_entry.weight),
             transaction_weight: tx_entry.weight,
+            input_addresses: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetPreviousAddressesOpt {
     /// The subaccount to get the addresses for.
     pub subaccount: u32,
@@ -1157,6 +2169,24 @@ pub struct GetPreviousAddressesOpt {
     ///
     /// This is needed for pagination.
     pub count: u32,
+
+    /// Whether to compute `tx_count`, which requires scanning the wallet transactions.
+    ///
+    /// If false, `tx_count` is omitted for every address.
+    #[serde(default = "default_true")]
+    pub with_tx_count: bool,
+}
+
+impl Default for GetPreviousAddressesOpt {
+    fn default() -> Self {
+        GetPreviousAddressesOpt {
+            subaccount: 0,
+            last_pointer: None,
+            is_internal: false,
+            count: 0,
+            with_tx_count: true,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
@@ -1180,7 +2210,11 @@ pub struct PreviousAddress {
 
     /// The number of transactions where either an input or an output has a script pubkey matching
     /// this address.
-    pub tx_count: u32,
+    ///
+    /// None if `GetPreviousAddressesOpt::with_tx_count` is false.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_count: Option<u32>,
 
     // Liquid fields, None if Bitcoin
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -1218,11 +2252,232 @@ pub struct AddressDataResult {
     pub user_path: Vec<ChildNumber>,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SpendIndexEntry {
+    /// The spent wallet output, as `txid:vout`.
+    pub outpoint: String,
+
+    /// The txid of the transaction spending the output.
+    pub spent_in: String,
+
+    /// The height of the spending transaction, 0 if unconfirmed.
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VerifyStoreResult {
+    /// Whether no issues were found.
+    pub ok: bool,
+
+    /// A description of the inconsistencies found in the store.
+    pub issues: Vec<String>,
+}
+
+/// A transaction to convert to a PSBT, a `TransactionMeta` deserializes into this too
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TransactionToPsbtOpt {
+    #[serde(rename = "transaction")]
+    pub hex: String,
+    /// The wallet outputs spent by the transaction, their `user_path` is used as key origin
+    #[serde(default)]
+    pub used_utxos: Vec<UnspentOutput>,
+    /// The subaccount of the transaction, if not set the one of the first of `used_utxos`
+    #[serde(default)]
+    pub subaccount: Option<u32>,
+}
+
+/// An unsigned PSBT to be completed by an external signer
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreatePsbtResult {
+    /// The base64 encoded PSBT
+    pub psbt: String,
+}
+
+/// What the electrum server in use supports
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ServerCapabilities {
+    /// The highest electrum protocol version supported by the server
+    pub protocol_version: String,
+    /// The server software and version
+    pub server_version: String,
+    /// Whether taproot subaccounts can be synced with the server
+    pub supports_taproot: bool,
+    /// The height below which the server pruned the history, None if not pruned
+    pub pruning: Option<u32>,
+    /// The hash of the genesis block of the server chain
+    pub genesis_hash: String,
+}
+
+/// The on-disk footprint of the store of the wallet
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StoreInfo {
+    /// The directory containing the store and cache files
+    pub path: String,
+    /// The total size of the store and cache files
+    pub size_bytes: u64,
+    /// The number of wallet transactions in the cache
+    pub tx_count: u32,
+    /// Unix time in seconds of the last write of the store or cache, 0 if never written
+    pub last_modified: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PingServerResult {
+    /// The round trip time of a `server.ping` request over a new connection, excluding the time
+    /// to connect, in milliseconds.
+    pub latency_ms: u64,
+}
+
+/// The electrum server the session is talking to
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    /// The server in use, with a `ssl://` or `tcp://` scheme
+    pub electrum_url: String,
+    pub tls: bool,
+    pub onion: bool,
+    /// Whether the server in use is one of the backups instead of the primary
+    pub backup: bool,
+    /// Whether connections go through the Tor proxy
+    pub use_tor: bool,
+    pub last_network_call_succeeded: bool,
+}
+
 #[cfg(test)]
 mod test {
-    use crate::model::{parse_path, CreateTxUtxos, GetUnspentOutputs};
+    use crate::exchange_rates::Currency;
+    use crate::model::{
+        parse_path, CreateTxUtxos, FeeEstimate, FeeEstimatesByTarget, GetTransactionsOpt,
+        GetUnspentOutputs, PortfolioValue, SetElectrumTlsOpt, Settings, TransactionType,
+    };
+    use crate::scripts::ScriptType;
     use bitcoin::util::bip32::DerivationPath;
 
+    #[test]
+    fn test_account_num() {
+        assert_eq!(ScriptType::P2shP2wpkh.account_num(0), Some(0));
+        assert_eq!(ScriptType::P2wpkh.account_num(0), Some(1));
+        assert_eq!(ScriptType::P2pkh.account_num(3), Some(50));
+        assert_eq!(ScriptType::P2wpkh.account_num(1 << 28), None);
+    }
+
+    #[test]
+    fn test_confirmation_bucket() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.confirmation_bucket(0), "unconfirmed");
+        assert_eq!(settings.confirmation_bucket(5), "immature");
+        assert_eq!(settings.confirmation_bucket(6), "mature");
+
+        settings.update(&serde_json::json!({"mature_num_blocks": 2}));
+        assert_eq!(settings.confirmation_bucket(1), "immature");
+        assert_eq!(settings.confirmation_bucket(2), "mature");
+
+        // Settings stored before the field was added still deserialize
+        let mut json = serde_json::to_value(Settings::default()).unwrap();
+        json.as_object_mut().unwrap().remove("mature_num_blocks");
+        let settings: Settings = serde_json::from_value(json).unwrap();
+        assert_eq!(settings.mature_num_blocks, None);
+    }
+
+    #[test]
+    fn test_fee_estimates_by_target() {
+        let fees = [
+            1000, 10070, 10070, 12000, 3014, 3014, 3014, 2543, 2543, 2543, 2543, 2543, 2543, 1499,
+            1499, 1499, 1499, 1499, 1499, 1499, 1499, 1499, 1499, 1499, 1499,
+        ];
+        let estimates: Vec<_> = fees.iter().map(|f| FeeEstimate(*f)).collect();
+        let by_target = FeeEstimatesByTarget::from_estimates(&estimates).unwrap();
+        assert_eq!(by_target.relay_fee, 1000);
+        let targets: Vec<_> = by_target.targets.iter().map(|(k, v)| (*k, *v)).collect();
+        // the estimate for 3 blocks is higher than the one for 1 block and gets capped
+        assert_eq!(targets, vec![(1, 10070), (3, 10070), (6, 3014), (12, 2543), (24, 1499)]);
+        assert_eq!(
+            serde_json::to_value(&by_target).unwrap()["targets"]["24"],
+            serde_json::json!(1499)
+        );
+
+        // missing estimates fall back to the relay fee
+        let by_target = FeeEstimatesByTarget::from_estimates(&estimates[..2]).unwrap();
+        assert_eq!(by_target.targets[&3], 1000);
+        assert!(FeeEstimatesByTarget::from_estimates(&[]).is_err());
+    }
+
+    #[test]
+    fn test_set_electrum_tls_opt_default() {
+        let opt: SetElectrumTlsOpt = serde_json::from_str(r#"{"tls": true}"#).unwrap();
+        assert!(opt.validate_domain);
+        assert!(SetElectrumTlsOpt::default().validate_domain);
+    }
+
+    #[test]
+    fn test_script_type_from_str() {
+        for script_type in [
+            ScriptType::P2shP2wpkh,
+            ScriptType::P2wpkh,
+            ScriptType::P2pkh,
+            ScriptType::P2tr,
+            ScriptType::P2wsh,
+        ] {
+            assert_eq!(script_type.to_string().parse::<ScriptType>().unwrap(), script_type);
+            let json = serde_json::to_value(script_type).unwrap();
+            assert_eq!(json.as_str().unwrap().parse::<ScriptType>().unwrap(), script_type);
+        }
+        assert!("csv".parse::<ScriptType>().is_err());
+        assert!("".parse::<ScriptType>().is_err());
+    }
+
+    #[test]
+    fn test_watch_only_multisig_accounts() {
+        use super::WatchOnlyCredentials;
+        use bitcoin::util::bip32::{ExtendedPrivKey, ExtendedPubKey};
+        use miniscript::descriptor::Descriptor;
+
+        let path: DerivationPath = "m/48'/1'/2'/2'".parse().unwrap();
+        let keys = (1u8..=3)
+            .map(|i| {
+                let master =
+                    ExtendedPrivKey::new_master(bitcoin::Network::Testnet, &[i; 32]).unwrap();
+                let xprv = master.derive_priv(&crate::EC, &path).unwrap();
+                let xpub = ExtendedPubKey::from_priv(&crate::EC, &xprv);
+                format!("[{}/48'/1'/2'/2']{}", master.fingerprint(&crate::EC), xpub)
+            })
+            .collect::<Vec<_>>();
+        let descriptor = |chain: u32| {
+            let keys = keys.iter().map(|k| format!("{}/{}/*", k, chain)).collect::<Vec<_>>();
+            format!("wsh(sortedmulti(2,{}))", keys.join(","))
+        };
+
+        let credentials = WatchOnlyCredentials::CoreDescriptors(vec![descriptor(0), descriptor(1)]);
+        let (accounts, _) = credentials.accounts(false).unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].account_num, ScriptType::P2wsh.account_num(2).unwrap());
+        let multisig = accounts[0].multisig.as_ref().unwrap();
+        assert_eq!(multisig.threshold, 2);
+        assert_eq!(multisig.cosigners.len(), 3);
+        assert!(multisig.sorted);
+
+        // The first address is the one of the descriptor
+        for (chain, is_internal) in [(0, false), (1, true)] {
+            let (desc, _) = Descriptor::parse_descriptor(&crate::EC, &descriptor(chain)).unwrap();
+            let expected = desc.at_derivation_index(0).address(bitcoin::Network::Regtest).unwrap();
+            let script = multisig.witness_script(is_internal, 0).unwrap();
+            assert_eq!(bitcoin::Address::p2wsh(&script, bitcoin::Network::Regtest), expected);
+            // The descriptor round trips, with a checksum
+            let exported = multisig.descriptor(is_internal).unwrap();
+            assert!(exported.starts_with(&format!("{}#", descriptor(chain))));
+        }
+
+        // Single sig and multisig descriptors can't be mixed
+        let xprv = ExtendedPrivKey::new_master(bitcoin::Network::Testnet, &[4; 32]).unwrap();
+        let path: DerivationPath = "m/84'/1'/0'".parse().unwrap();
+        let xpub =
+            ExtendedPubKey::from_priv(&crate::EC, &xprv.derive_priv(&crate::EC, &path).unwrap());
+        let single_sig = format!("wpkh([{}/84'/1'/0']{}/0/*)", xprv.fingerprint(&crate::EC), xpub);
+        let credentials = WatchOnlyCredentials::CoreDescriptors(vec![single_sig.clone()]);
+        assert_eq!(credentials.accounts(false).unwrap().0.len(), 1);
+        let credentials = WatchOnlyCredentials::CoreDescriptors(vec![single_sig, descriptor(0)]);
+        assert!(matches!(credentials.accounts(false), Err(crate::Error::MismatchingDescriptor)));
+    }
+
     #[test]
     fn test_path() {
         let path_external: DerivationPath = "m/44'/1'/0'/0/0".parse().unwrap();
@@ -1231,10 +2486,59 @@ mod test {
         assert_eq!(parse_path(&path_internal).unwrap(), (true, 0u32));
     }
 
+    #[test]
+    fn test_get_transactions_filters() {
+        let opt: GetTransactionsOpt = serde_json::from_value(serde_json::json!({
+            "first": 0,
+            "count": 10,
+            "subaccount": 0,
+            "num_confs": 0,
+        }))
+        .unwrap();
+        assert!(!opt.is_filtered());
+        assert!(opt.matches(0, &TransactionType::Incoming));
+
+        let opt: GetTransactionsOpt = serde_json::from_value(serde_json::json!({
+            "first": 0,
+            "count": 10,
+            "subaccount": 0,
+            "num_confs": 0,
+            "start_ts": 1_000,
+            "end_ts": 2_000,
+            "tx_type": "outgoing",
+        }))
+        .unwrap();
+        assert!(opt.is_filtered());
+        assert!(opt.matches(1_000, &TransactionType::Outgoing));
+        assert!(opt.matches(1_999, &TransactionType::Outgoing));
+        assert!(!opt.matches(999, &TransactionType::Outgoing));
+        assert!(!opt.matches(2_000, &TransactionType::Outgoing));
+        assert!(!opt.matches(1_500, &TransactionType::Incoming));
+    }
+
     #[test]
     fn test_unspent() {
         let json_str = r#"{"btc": [{"address_type": "p2wsh", "block_height": 1806588, "pointer": 3509, "pt_idx": 1, "satoshi": 3650144, "subaccount": 0, "txhash": "08711d45d4867d7834b133a425da065b252eb6a9b206d57e2bbb226a344c5d13", "is_internal": false, "is_blinded": false, "user_path": [2147483692, 2147483649, 2147483648, 0, 1], "prevout_script": "51", "public_key": "020202020202020202020202020202020202020202020202020202020202020202", "asset_id": ""}, {"address_type": "p2wsh", "block_height": 1835681, "pointer": 3510, "pt_idx": 0, "satoshi": 5589415, "subaccount": 0, "txhash": "fbd00e5b9e8152c04214c72c791a78a65fdbab68b5c6164ff0d8b22a006c5221", "is_internal": false, "is_blinded": false, "user_path": [2147483692, 2147483649, 2147483648, 0, 2], "prevout_script": "51", "public_key": "020202020202020202020202020202020202020202020202020202020202020202", "asset_id": ""}, {"address_type": "p2wsh", "block_height": 1835821, "pointer": 3511, "pt_idx": 0, "satoshi": 568158, "subaccount": 0, "txhash": "e5b358fb8366960130b97794062718d7f4fbe721bf274f47493a19326099b811", "is_internal": false, "is_blinded": false, "user_path": [2147483692, 2147483649, 2147483648, 0, 3], "prevout_script": "51", "public_key": "020202020202020202020202020202020202020202020202020202020202020202", "asset_id": ""}]}"#;
         let _json: GetUnspentOutputs = serde_json::from_str(json_str).unwrap();
         let _json: CreateTxUtxos = serde_json::from_str(json_str).unwrap();
     }
+
+    #[test]
+    fn test_portfolio_value() {
+        let value = PortfolioValue::new(123_456_789, Some(20_000.5), Currency::USD, vec![]);
+        assert_eq!(value.total_fiat, "24691.98");
+        assert_eq!(value.rate, "20000.50000000");
+        assert_eq!(value.currency, "USD");
+        let json = serde_json::to_value(&value).unwrap();
+        assert!(json.get("excluded_assets").is_none());
+
+        // no rate fetched yet
+        let asset = "aa".repeat(32);
+        let value = PortfolioValue::new(1_000, None, Currency::EUR, vec![asset.clone()]);
+        assert_eq!(value.total_satoshi, 1_000);
+        assert_eq!(value.total_fiat, "");
+        assert_eq!(value.rate, "");
+        let json = serde_json::to_value(&value).unwrap();
+        assert_eq!(json["excluded_assets"], serde_json::json!([asset]));
+    }
 }
@@ -2,6 +2,7 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use crate::error::Error;
+use crate::notification::NotificationMode;
 use bitcoin::util::bip32::{ChildNumber, ExtendedPubKey, Fingerprint};
 use bitcoin::{hashes::hex::ToHex, PublicKey};
 use serde::{Deserialize, Serialize};
@@ -25,9 +26,18 @@ pub struct NetworkParameters {
     pub electrum_tls: Option<bool>,
     pub electrum_url: Option<String>,
     pub electrum_onion_url: Option<String>,
+    /// Electrum servers, in the `host:port[:s|t[:noverify]]` form used by `spv_servers`, to
+    /// fail over to in order when the one in use can't be reached
+    pub electrum_backup_urls: Option<Vec<String>>,
     pub validate_domain: Option<bool>,
     pub policy_asset: Option<String>,
     pub sync_interval: Option<u32>,
+    /// Milliseconds to wait before retrying to reach an unreachable electrum server, doubled at
+    /// every failed attempt up to `reconnect_max_delay_ms`. Defaults to 1000.
+    pub reconnect_base_delay_ms: Option<u64>,
+    /// The upper bound of the delay between reconnection attempts, in milliseconds. Defaults to
+    /// 60000.
+    pub reconnect_max_delay_ms: Option<u64>,
     pub spv_enabled: Option<bool>,
     asset_registry_url: Option<String>,
     asset_registry_onion_url: Option<String>,
@@ -43,6 +53,31 @@ pub struct NetworkParameters {
     pub use_tor: Option<bool>,
     pub max_reorg_blocks: Option<u32>,
 
+    /// An HTTP endpoint accepting a raw transaction hex as POST body, such as
+    /// `https://mempool.space/api/tx`, used to broadcast when the electrum server can't be
+    /// reached.
+    ///
+    /// Note that this discloses the transaction, and the IP if no proxy is set, to a third party.
+    pub broadcast_fallback_url: Option<String>,
+
+    /// A second electrum server, in the `host:port[:s|t[:noverify]]` form used by `spv_servers`,
+    /// queried after every new block to cross-check the confirmed unspent outputs returned by the
+    /// primary one. A `verification_mismatch` notification is emitted if they disagree.
+    pub verification_electrum_url: Option<String>,
+
+    /// The number of consecutive unused addresses scanned before assuming there are no more used
+    /// ones, when syncing and discovering subaccounts. Defaults to 20.
+    pub gap_limit: Option<u32>,
+
+    /// The address type returned by `get_receive_address` when none is requested and the
+    /// subaccount has no default of its own. Ignored by subaccounts of a different type.
+    pub default_address_type: Option<String>,
+
+    /// Whether notifications are passed to the native handler, queued to be
+    /// returned by `poll_notifications`, or both
+    #[serde(default)]
+    pub notification_mode: NotificationMode,
+
     /// For electrum sessions is used as root directory for the db cache and for
     /// the headers chain files
     ///
@@ -81,8 +116,30 @@ impl NetworkId {
             _ => None,
         }
     }
+
+    /// The hash of the genesis block, hex encoded as displayed by block explorers.
+    ///
+    /// None for Elements regtest, whose genesis block depends on the chain parameters.
+    pub fn genesis_hash(self: NetworkId) -> Option<String> {
+        match self {
+            NetworkId::Bitcoin(net) => {
+                Some(bitcoin::blockdata::constants::genesis_block(net).block_hash().to_string())
+            }
+            NetworkId::Elements(ElementsNetwork::Liquid) => Some(LIQUID_GENESIS_HASH.to_string()),
+            NetworkId::Elements(ElementsNetwork::LiquidTestnet) => {
+                Some(LIQUID_TESTNET_GENESIS_HASH.to_string())
+            }
+            NetworkId::Elements(ElementsNetwork::ElementsRegtest) => None,
+        }
+    }
 }
 
+const LIQUID_GENESIS_HASH: &str =
+    "1466275836220db2944ca059a3a10ef6fd2ea684b0688d2c379296888a206003";
+
+const LIQUID_TESTNET_GENESIS_HASH: &str =
+    "a771da8e52ee6ad581ed1e9a99825e5b3b7992225534eaa2ae23244fe26ab1c1";
+
 pub const LIQUID_TESTNET: elements::AddressParams = elements::AddressParams {
     p2pkh_prefix: 36,
     p2sh_prefix: 19,
@@ -217,9 +274,23 @@ pub fn build_request_agent(maybe_proxy: Option<&str>) -> Result<ureq::Agent, ure
 
 #[cfg(test)]
 mod tests {
-    use crate::EC;
+    use crate::{ElementsNetwork, NetworkId, EC};
     use bitcoin::util::bip32::{ExtendedPrivKey, ExtendedPubKey};
 
+    #[test]
+    fn test_genesis_hash() {
+        assert_eq!(
+            NetworkId::Bitcoin(bitcoin::Network::Bitcoin).genesis_hash().unwrap(),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(
+            NetworkId::Bitcoin(bitcoin::Network::Regtest).genesis_hash().unwrap(),
+            "0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206"
+        );
+        assert!(NetworkId::Elements(ElementsNetwork::Liquid).genesis_hash().is_some());
+        assert!(NetworkId::Elements(ElementsNetwork::ElementsRegtest).genesis_hash().is_none());
+    }
+
     #[test]
     fn test_wallet_hash_id() {
         let seed = crate::wally::bip39_mnemonic_to_seed(
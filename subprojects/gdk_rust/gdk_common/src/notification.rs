@@ -4,6 +4,11 @@ use crate::{be::BEBlockHash, model::Settings, model::TransactionType, State};
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Maximum number of notifications kept in the queue, older ones are dropped
+const MAX_QUEUED_NOTIFICATIONS: usize = 1000;
 
 pub type NativeType =
     (extern "C" fn(*const libc::c_void, *const libc::c_char), *const libc::c_void);
@@ -11,6 +16,11 @@ pub type NativeType =
 pub struct NativeNotif {
     pub native: Option<NativeType>,
 
+    pub mode: NotificationMode,
+
+    /// Notifications waiting to be returned by `poll_notifications`, used only if `mode` queues
+    queue: Arc<Mutex<VecDeque<Value>>>,
+
     /// With testing feature notifications are simply pushed in the following vec so assertions
     /// could check over it, it's a mutex so that methods signatures doesn't need to be mut
     #[cfg(feature = "testing")]
@@ -18,6 +28,31 @@ pub struct NativeNotif {
 }
 unsafe impl Send for NativeNotif {}
 
+/// How notifications are delivered to the caller, set at session creation
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationMode {
+    /// Notifications are passed to the handler set with `set_native`
+    #[default]
+    Callback,
+
+    /// Notifications are buffered and returned by `poll_notifications`
+    Queue,
+
+    /// Notifications are both passed to the handler and buffered
+    Both,
+}
+
+impl NotificationMode {
+    fn calls_back(self) -> bool {
+        self != NotificationMode::Queue
+    }
+
+    fn queues(self) -> bool {
+        self != NotificationMode::Callback
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Notification {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -29,6 +64,18 @@ pub struct Notification {
     #[serde(skip_serializing_if = "Option::is_none")]
     block: Option<BlockNotification>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verification_mismatch: Option<VerificationMismatchNotification>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    electrum_server: Option<ElectrumServerNotification>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rescan: Option<RescanNotification>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    store_updated: Option<StoreUpdatedNotification>,
+
     event: Kind,
 }
 
@@ -38,6 +85,10 @@ enum Kind {
     Network,
     Transaction,
     Block,
+    VerificationMismatch,
+    ElectrumServer,
+    Rescan,
+    StoreUpdated,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -45,6 +96,9 @@ struct NetworkNotification {
     current_state: State,
     next_state: State,
     wait_ms: u32,
+
+    /// Whether the last call to the server succeeded, the same of `current_state`
+    connected: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -70,6 +124,52 @@ pub struct TransactionNotification {
     pub type_: Option<TransactionType>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct VerificationMismatchNotification {
+    /// The wallet subaccount whose unspent outputs differ.
+    pub subaccount: u32,
+
+    /// Confirmed outputs returned by the verification server but not by the primary one, as
+    /// `txid:vout`.
+    pub missing: Vec<String>,
+
+    /// Confirmed outputs returned by the primary server but not by the verification one, as
+    /// `txid:vout`.
+    pub unexpected: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ElectrumServerNotification {
+    /// The electrum server now in use, as `host:port`.
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RescanNotification {
+    /// The wallet subaccount being rescanned.
+    pub subaccount: u32,
+
+    /// The height the transactions are scanned again from.
+    pub from_height: u32,
+
+    /// The progress of the rescan.
+    pub status: RescanStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RescanStatus {
+    Started,
+    Completed,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StoreUpdatedNotification {
+    /// The transactions whose memo was set.
+    pub memos: Vec<bitcoin::Txid>,
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct BlockNotification {
     /// The height of the block.
@@ -80,6 +180,10 @@ pub struct BlockNotification {
 
     /// The hash of the block prior to this block
     pub previous_hash: bitcoin::BlockHash,
+
+    /// Whether the block replaced the previous tip instead of extending it
+    #[serde(default)]
+    pub reorg: bool,
 }
 
 impl Notification {
@@ -89,18 +193,32 @@ impl Notification {
                 current_state: current,
                 next_state: next,
                 wait_ms: 0,
+                connected: current.into(),
             }),
             transaction: None,
             block: None,
+            verification_mismatch: None,
+            electrum_server: None,
+            rescan: None,
+            store_updated: None,
             event: Kind::Network,
         }
     }
 
+    /// A network notification for the outcome of the last call to the server
+    pub fn new_network_connection(connected: bool) -> Self {
+        Self::new_network(connected.into(), State::Connected)
+    }
+
     pub fn new_transaction(ntf: &TransactionNotification) -> Self {
         Notification {
             network: None,
             transaction: Some(ntf.clone()),
             block: None,
+            verification_mismatch: None,
+            electrum_server: None,
+            rescan: None,
+            store_updated: None,
             event: Kind::Transaction,
         }
     }
@@ -113,12 +231,17 @@ impl Notification {
                 block_height: height,
                 block_hash: hash.into_bitcoin(),
                 previous_hash: prev_hash.into_bitcoin(),
+                reorg: false,
             }),
+            verification_mismatch: None,
+            electrum_server: None,
+            rescan: None,
+            store_updated: None,
             event: Kind::Block,
         }
     }
 
-    pub fn new_block_from_header(height: u32, header: &BEBlockHeader) -> Self {
+    pub fn new_block_from_header(height: u32, header: &BEBlockHeader, reorg: bool) -> Self {
         Notification {
             network: None,
             transaction: None,
@@ -126,10 +249,69 @@ impl Notification {
                 block_height: height,
                 block_hash: header.block_hash().into_bitcoin(),
                 previous_hash: header.prev_block_hash().into_bitcoin(),
+                reorg,
             }),
+            verification_mismatch: None,
+            electrum_server: None,
+            rescan: None,
+            store_updated: None,
             event: Kind::Block,
         }
     }
+
+    pub fn new_verification_mismatch(ntf: &VerificationMismatchNotification) -> Self {
+        Notification {
+            network: None,
+            transaction: None,
+            block: None,
+            verification_mismatch: Some(ntf.clone()),
+            electrum_server: None,
+            rescan: None,
+            store_updated: None,
+            event: Kind::VerificationMismatch,
+        }
+    }
+
+    pub fn new_electrum_server(url: &str) -> Self {
+        Notification {
+            network: None,
+            transaction: None,
+            block: None,
+            verification_mismatch: None,
+            electrum_server: Some(ElectrumServerNotification {
+                url: url.to_string(),
+            }),
+            rescan: None,
+            store_updated: None,
+            event: Kind::ElectrumServer,
+        }
+    }
+
+    pub fn new_rescan(ntf: &RescanNotification) -> Self {
+        Notification {
+            network: None,
+            transaction: None,
+            block: None,
+            verification_mismatch: None,
+            electrum_server: None,
+            rescan: Some(ntf.clone()),
+            store_updated: None,
+            event: Kind::Rescan,
+        }
+    }
+
+    pub fn new_store_updated(ntf: &StoreUpdatedNotification) -> Self {
+        Notification {
+            network: None,
+            transaction: None,
+            block: None,
+            verification_mismatch: None,
+            electrum_server: None,
+            rescan: None,
+            store_updated: Some(ntf.clone()),
+            event: Kind::StoreUpdated,
+        }
+    }
 }
 
 impl NativeNotif {
@@ -137,6 +319,8 @@ impl NativeNotif {
     pub fn new() -> Self {
         NativeNotif {
             native: None,
+            mode: NotificationMode::default(),
+            queue: Arc::new(Mutex::new(VecDeque::new())),
         }
     }
 
@@ -145,13 +329,23 @@ impl NativeNotif {
         let data = serde_json::to_value(data).unwrap();
 
         info!("push notification: {:?}", data);
-        if let Some((handler, self_context)) = self.native.as_ref() {
-            handler(*self_context, make_str(data.to_string()));
-        } else {
-            if !cfg!(feature = "testing") {
-                warn!("no registered handler to receive notification");
+        if self.mode.queues() {
+            let mut queue = self.queue.lock().unwrap();
+            if queue.len() >= MAX_QUEUED_NOTIFICATIONS {
+                queue.pop_front();
+            }
+            queue.push_back(data.clone());
+        }
+        match self.native.as_ref() {
+            Some((handler, self_context)) if self.mode.calls_back() => {
+                handler(*self_context, make_str(data.to_string()));
+            }
+            _ => {
+                if !cfg!(feature = "testing") && !self.mode.queues() {
+                    warn!("no registered handler to receive notification");
+                }
+                self.push(data);
             }
-            self.push(data);
         }
     }
 
@@ -159,6 +353,15 @@ impl NativeNotif {
         self.native = Some(native_type);
     }
 
+    pub fn set_mode(&mut self, mode: NotificationMode) {
+        self.mode = mode;
+    }
+
+    /// Remove and return the queued notifications, oldest first
+    pub fn poll(&self) -> Vec<Value> {
+        self.queue.lock().unwrap().drain(..).collect()
+    }
+
     pub fn block(&self, height: u32, hash: BEBlockHash) {
         let data =
             json!({"block":{"block_height":height,"block_hash": hash.to_hex()},"event":"block"});
@@ -169,8 +372,8 @@ impl NativeNotif {
         self.notify(Notification::new_block_from_hashes(height, hash, prev_hash));
     }
 
-    pub fn block_from_header(&self, height: u32, header: &BEBlockHeader) {
-        self.notify(Notification::new_block_from_header(height, &header));
+    pub fn block_from_header(&self, height: u32, header: &BEBlockHeader, reorg: bool) {
+        self.notify(Notification::new_block_from_header(height, &header, reorg));
     }
 
     pub fn settings(&self, settings: &Settings) {
@@ -186,6 +389,26 @@ impl NativeNotif {
         self.notify(Notification::new_network(current, desired));
     }
 
+    pub fn network_connection(&self, connected: bool) {
+        self.notify(Notification::new_network_connection(connected));
+    }
+
+    pub fn verification_mismatch(&self, ntf: &VerificationMismatchNotification) {
+        self.notify(Notification::new_verification_mismatch(ntf));
+    }
+
+    pub fn electrum_server(&self, url: &str) {
+        self.notify(Notification::new_electrum_server(url));
+    }
+
+    pub fn rescan(&self, ntf: &RescanNotification) {
+        self.notify(Notification::new_rescan(ntf));
+    }
+
+    pub fn store_updated(&self, ntf: &StoreUpdatedNotification) {
+        self.notify(Notification::new_store_updated(ntf));
+    }
+
     #[cfg(not(feature = "testing"))]
     pub fn push(&self, _value: Value) {
         //does nothing in non testing mode
@@ -197,6 +420,8 @@ impl NativeNotif {
     pub fn new() -> Self {
         NativeNotif {
             native: None,
+            mode: NotificationMode::default(),
+            queue: Arc::new(Mutex::new(VecDeque::new())),
             testing: std::sync::Arc::new(std::sync::Mutex::new(vec![])),
         }
     }
@@ -226,9 +451,13 @@ mod test {
 
     #[test]
     fn test_network_json() {
-        let expected = json!({"network":{"wait_ms": 0, "current_state": "connected", "next_state": "connected"},"event":"network"});
+        let expected = json!({"network":{"wait_ms": 0, "current_state": "connected", "next_state": "connected", "connected": true},"event":"network"});
         let obj = Notification::new_network(State::Connected, State::Connected);
         assert_eq!(expected, serde_json::to_value(&obj).unwrap());
+
+        let expected = json!({"network":{"wait_ms": 0, "current_state": "disconnected", "next_state": "connected", "connected": false},"event":"network"});
+        let obj = Notification::new_network_connection(false);
+        assert_eq!(expected, serde_json::to_value(&obj).unwrap());
     }
 
     #[test]
@@ -244,13 +473,76 @@ mod test {
         assert_eq!(expected, serde_json::to_value(&obj).unwrap());
     }
 
+    #[test]
+    fn test_verification_mismatch_json() {
+        let outpoint = format!("{}:0", bitcoin::Txid::all_zeros());
+        let expected = json!({"event":"verification_mismatch","verification_mismatch":{"subaccount":1,"missing":[outpoint],"unexpected":[]}});
+        let obj = Notification::new_verification_mismatch(&VerificationMismatchNotification {
+            subaccount: 1,
+            missing: vec![outpoint.clone()],
+            unexpected: vec![],
+        });
+        assert_eq!(expected, serde_json::to_value(&obj).unwrap());
+    }
+
+    #[test]
+    fn test_electrum_server_json() {
+        let expected =
+            json!({"event":"electrum_server","electrum_server":{"url":"localhost:50001"}});
+        let obj = Notification::new_electrum_server("localhost:50001");
+        assert_eq!(expected, serde_json::to_value(&obj).unwrap());
+    }
+
+    #[test]
+    fn test_rescan_json() {
+        let expected = json!({"event":"rescan","rescan":{"subaccount":1,"from_height":100,"status":"started"}});
+        let obj = Notification::new_rescan(&RescanNotification {
+            subaccount: 1,
+            from_height: 100,
+            status: RescanStatus::Started,
+        });
+        assert_eq!(expected, serde_json::to_value(&obj).unwrap());
+    }
+
+    #[test]
+    fn test_store_updated_json() {
+        let txid = "11".repeat(32);
+        let expected = json!({"event":"store_updated","store_updated":{"memos":[txid]}});
+        let obj = Notification::new_store_updated(&StoreUpdatedNotification {
+            memos: vec![txid.parse().unwrap()],
+        });
+        assert_eq!(expected, serde_json::to_value(&obj).unwrap());
+    }
+
+    #[test]
+    fn test_notification_queue() {
+        let mut notif = NativeNotif::new();
+        notif.network(State::Connected, State::Connected);
+        assert!(notif.poll().is_empty());
+
+        notif.set_mode(NotificationMode::Queue);
+        notif.network(State::Connected, State::Connected);
+        notif.settings(&Settings::default());
+        let polled = notif.poll();
+        assert_eq!(polled.len(), 2);
+        assert_eq!(polled[0]["event"], "network");
+        assert_eq!(polled[1]["event"], "settings");
+        assert!(notif.poll().is_empty());
+
+        for _ in 0..MAX_QUEUED_NOTIFICATIONS + 1 {
+            notif.network(State::Connected, State::Connected);
+        }
+        assert_eq!(notif.poll().len(), MAX_QUEUED_NOTIFICATIONS);
+    }
+
     #[test]
     fn test_block_json() {
-        let expected = json!({"block_height":0,"block_hash":"0000000000000000000000000000000000000000000000000000000000000000","previous_hash":"0000000000000000000000000000000000000000000000000000000000000000"});
+        let expected = json!({"block_height":0,"block_hash":"0000000000000000000000000000000000000000000000000000000000000000","previous_hash":"0000000000000000000000000000000000000000000000000000000000000000","reorg":false});
         let obj = BlockNotification {
             block_height: 0,
             block_hash: BlockHash::all_zeros(),
             previous_hash: BlockHash::all_zeros(),
+            reorg: false,
         };
         assert_eq!(expected, serde_json::to_value(&obj).unwrap());
     }
@@ -0,0 +1,259 @@
+use bitcoin::secp256k1::{ecdsa::Signature, Message};
+use bitcoin::{Address, Network, PublicKey};
+use elements::encode::{deserialize, serialize};
+use elements::hashes::Hash;
+use elements::pset::{Input, PartiallySignedTransaction};
+use elements::script::Builder;
+use elements::sighash::SigHashCache;
+use elements::{EcdsaSigHashType, Transaction, TxOut};
+
+use crate::be::BEScriptConvert;
+use crate::error::Error;
+use crate::model::FinalizePsetResult;
+use crate::scripts::{p2pkh_script, p2shwpkh_script, p2shwpkh_script_sig};
+
+pub fn pset_from_base64(pset: &str) -> Result<PartiallySignedTransaction, Error> {
+    Ok(deserialize(&base64::decode(pset)?)?)
+}
+
+pub fn pset_to_base64(pset: &PartiallySignedTransaction) -> String {
+    base64::encode(serialize(pset))
+}
+
+/// Merge the fields of PSETs of the same unsigned transaction, such as the signatures of the
+/// inputs added by different signers.
+///
+/// Errors if the PSETs are for different transactions or have conflicting global data.
+pub fn combine_psets(psets: &[String]) -> Result<String, Error> {
+    let mut psets = psets.iter().map(|pset| pset_from_base64(pset));
+    let mut combined = psets.next().ok_or(Error::InputValidationFailed)??;
+    for pset in psets {
+        combined.merge(pset?)?;
+    }
+    Ok(pset_to_base64(&combined))
+}
+
+/// Finalize the inputs of a PSET and extract the transaction if all the inputs are signed,
+/// otherwise return the inputs missing a signature.
+///
+/// Inputs that are already final are left as they are and their scripts are not verified, the
+/// others must be single-sig inputs (p2wpkh, p2sh-p2wpkh or p2pkh) with a valid signature of the
+/// key of the output they spend, invalid signatures are reported as missing.
+pub fn finalize_pset(pset: &str) -> Result<FinalizePsetResult, Error> {
+    let mut pset = pset_from_base64(pset)?;
+    let unsigned_tx = pset.extract_tx()?;
+    let mut missing_signatures = vec![];
+    for (i, input) in pset.inputs_mut().iter_mut().enumerate() {
+        // empty final fields are set by PSETs created from unsigned transactions
+        let is_final = input.final_script_sig.as_ref().map_or(false, |s| !s.is_empty())
+            || input.final_script_witness.as_ref().map_or(false, |w| !w.is_empty());
+        if is_final {
+            continue;
+        }
+        if !finalize_input(input, i, &unsigned_tx)? {
+            missing_signatures.push(i as u32);
+        }
+    }
+    let transaction = if missing_signatures.is_empty() {
+        Some(elements::encode::serialize_hex(&pset.extract_tx()?))
+    } else {
+        None
+    };
+    Ok(FinalizePsetResult {
+        transaction,
+        missing_signatures,
+    })
+}
+
+/// Set the final script sig and witness of `input`, returns `false` if it's not validly signed
+/// yet
+fn finalize_input(input: &mut Input, index: usize, tx: &Transaction) -> Result<bool, Error> {
+    let prevout = match (&input.witness_utxo, &input.non_witness_utxo) {
+        (Some(txout), _) => txout.clone(),
+        (None, Some(tx)) => tx
+            .output
+            .get(input.previous_output_index as usize)
+            .ok_or(Error::InputValidationFailed)?
+            .clone(),
+        (None, None) => {
+            return Err(Error::Generic(format!("input {} has no previous output", index)))
+        }
+    };
+    let script_pubkey = prevout.script_pubkey.clone();
+    if !(script_pubkey.is_v0_p2wpkh() || script_pubkey.is_p2sh() || script_pubkey.is_p2pkh()) {
+        return Err(Error::Generic(format!("input {} is not a single-sig input", index)));
+    }
+    // single-sig inputs are signed by the key matching the script pubkey
+    let is_key_of = |pk: &PublicKey| {
+        if script_pubkey.is_v0_p2wpkh() {
+            Address::p2wpkh(pk, Network::Regtest).map(|a| a.script_pubkey().into_elements())
+                == Ok(script_pubkey.clone())
+        } else if script_pubkey.is_p2sh() {
+            p2shwpkh_script(pk).into_elements() == script_pubkey
+        } else if script_pubkey.is_p2pkh() {
+            p2pkh_script(pk).into_elements() == script_pubkey
+        } else {
+            false
+        }
+    };
+    let (pk, sig) = match input
+        .partial_sigs
+        .iter()
+        .find(|(pk, sig)| is_key_of(pk) && is_valid_signature(tx, index, &prevout, pk, sig))
+    {
+        Some((pk, sig)) => (*pk, sig.clone()),
+        None => return Ok(false),
+    };
+
+    if script_pubkey.is_p2pkh() {
+        let script_sig = Builder::new().push_slice(&sig).push_slice(&pk.to_bytes()).into_script();
+        input.final_script_sig = Some(script_sig);
+    } else {
+        if script_pubkey.is_p2sh() {
+            input.final_script_sig = Some(p2shwpkh_script_sig(&pk).into_elements());
+        }
+        input.final_script_witness = Some(vec![sig, pk.to_bytes()]);
+    }
+    input.partial_sigs.clear();
+    input.redeem_script = None;
+    Ok(true)
+}
+
+/// Whether `sig` is a signature of `pk` for the input `index` of `tx` spending the single-sig
+/// output `prevout`
+fn is_valid_signature(
+    tx: &Transaction,
+    index: usize,
+    prevout: &TxOut,
+    pk: &PublicKey,
+    sig: &[u8],
+) -> bool {
+    let (sighash_type, der) = match sig.split_last() {
+        Some((sighash_type, der)) => (EcdsaSigHashType::from_u32(*sighash_type as u32), der),
+        None => return false,
+    };
+    let signature = match Signature::from_der(der) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+    let hash = if prevout.script_pubkey.is_p2pkh() {
+        SigHashCache::new(tx).legacy_sighash(index, &prevout.script_pubkey, sighash_type)
+    } else {
+        let script_code = p2pkh_script(pk).into_elements();
+        SigHashCache::new(tx).segwitv0_sighash(index, &script_code, prevout.value, sighash_type)
+    };
+    Message::from_slice(&hash.into_inner()[..])
+        .map_or(false, |message| crate::EC.verify_ecdsa(&message, &signature, &pk.inner).is_ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use bitcoin::hashes::hex::FromHex;
+    use bitcoin::secp256k1::{Message, SecretKey};
+    use elements::{confidential, AssetId, OutPoint, TxIn};
+
+    fn txout(script_pubkey: elements::Script, value: u64) -> TxOut {
+        TxOut {
+            asset: confidential::Asset::Explicit(AssetId::default()),
+            value: confidential::Value::Explicit(value),
+            script_pubkey,
+            ..Default::default()
+        }
+    }
+
+    fn sign(pset: &mut PartiallySignedTransaction, index: usize, key: &SecretKey) {
+        let pk = PublicKey::new(key.public_key(&crate::EC));
+        let tx = pset.extract_tx().unwrap();
+        let prevout = pset.inputs()[index].witness_utxo.clone().unwrap();
+        let script_code = p2pkh_script(&pk).into_elements();
+        let hash = SigHashCache::new(&tx).segwitv0_sighash(
+            index,
+            &script_code,
+            prevout.value,
+            EcdsaSigHashType::All,
+        );
+        let message = Message::from_slice(&hash.into_inner()[..]).unwrap();
+        let mut sig = crate::EC.sign_ecdsa(&message, key).serialize_der().to_vec();
+        sig.push(EcdsaSigHashType::All as u8);
+        pset.inputs_mut()[index].partial_sigs.insert(pk, sig);
+    }
+
+    #[test]
+    fn test_combine_and_finalize() {
+        let keys = [[1u8; 32], [2u8; 32]].map(|k| SecretKey::from_slice(&k).unwrap());
+        let pks = keys.map(|k| PublicKey::new(k.public_key(&crate::EC)));
+        let prevouts = [
+            txout(
+                Address::p2wpkh(&pks[0], Network::Regtest).unwrap().script_pubkey().into_elements(),
+                5_000,
+            ),
+            txout(p2shwpkh_script(&pks[1]).into_elements(), 6_000),
+        ];
+        let tx = Transaction {
+            version: 2,
+            lock_time: 0,
+            input: (0..2)
+                .map(|vout| TxIn {
+                    previous_output: OutPoint::new(elements::Txid::all_zeros(), vout),
+                    ..Default::default()
+                })
+                .collect(),
+            output: vec![
+                txout(elements::Script::new(), 10_000),
+                TxOut::new_fee(1_000, AssetId::default()),
+            ],
+        };
+        let mut pset = PartiallySignedTransaction::from_tx(tx);
+        for (input, prevout) in pset.inputs_mut().iter_mut().zip(prevouts) {
+            input.witness_utxo = Some(prevout);
+        }
+        pset.inputs_mut()[1].redeem_script = Some(
+            Address::p2wpkh(&pks[1], Network::Regtest).unwrap().script_pubkey().into_elements(),
+        );
+
+        // each party signs its own input
+        let mut pset0 = pset.clone();
+        sign(&mut pset0, 0, &keys[0]);
+        let mut pset1 = pset.clone();
+        sign(&mut pset1, 1, &keys[1]);
+        let pset0 = pset_to_base64(&pset0);
+        let pset1 = pset_to_base64(&pset1);
+
+        let result = finalize_pset(&pset0).unwrap();
+        assert_eq!(result.missing_signatures, vec![1]);
+        assert!(result.transaction.is_none());
+
+        let combined = combine_psets(&[pset0.clone(), pset1]).unwrap();
+        let result = finalize_pset(&combined).unwrap();
+        assert!(result.missing_signatures.is_empty());
+        let tx: Transaction =
+            deserialize(&Vec::<u8>::from_hex(&result.transaction.unwrap()).unwrap()).unwrap();
+        assert_eq!(tx.input[0].witness.script_witness[1], pks[0].to_bytes());
+        assert!(tx.input[0].script_sig.is_empty());
+        assert_eq!(tx.input[1].witness.script_witness[1], pks[1].to_bytes());
+        assert_eq!(tx.input[1].script_sig, p2shwpkh_script_sig(&pks[1]).into_elements());
+
+        // a signature of another key does not satisfy the input
+        let mut wrong = pset.clone();
+        sign(&mut wrong, 0, &keys[1]);
+        let result = finalize_pset(&pset_to_base64(&wrong)).unwrap();
+        assert_eq!(result.missing_signatures, vec![0, 1]);
+
+        // neither does a signature of the right key over a different sighash
+        let mut tampered = pset.clone();
+        sign(&mut tampered, 0, &keys[0]);
+        for sig in tampered.inputs_mut()[0].partial_sigs.values_mut() {
+            *sig.last_mut().unwrap() = EcdsaSigHashType::None as u8;
+        }
+        let result = finalize_pset(&pset_to_base64(&tampered)).unwrap();
+        assert_eq!(result.missing_signatures, vec![0, 1]);
+
+        // PSETs of different transactions can't be combined
+        let mut other = pset.clone();
+        other.inputs_mut()[0].previous_output_index = 7;
+        assert!(combine_psets(&[pset0.clone(), pset_to_base64(&other)]).is_err());
+        assert!(combine_psets(&[]).is_err());
+        assert!(combine_psets(&[pset0, "notbase64!".into()]).is_err());
+    }
+}
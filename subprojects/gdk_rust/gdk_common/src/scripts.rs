@@ -6,6 +6,9 @@ use bitcoin::hashes::Hash;
 use bitcoin::{Address, Network, PublicKey, Script, Witness};
 
 use std::fmt;
+use std::str::FromStr;
+
+use crate::error::Error;
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ScriptType {
@@ -15,9 +18,15 @@ pub enum ScriptType {
     P2wpkh = 1,
     #[serde(rename = "p2pkh")]
     P2pkh = 2,
+    #[serde(rename = "p2tr")]
+    P2tr = 3,
+    /// Multisig accounts, whose scripts depend on the keys of all the cosigners
+    #[serde(rename = "p2wsh")]
+    P2wsh = 4,
 }
 
-const TYPES: [ScriptType; 3] = [ScriptType::P2shP2wpkh, ScriptType::P2wpkh, ScriptType::P2pkh];
+const TYPES: [ScriptType; 4] =
+    [ScriptType::P2shP2wpkh, ScriptType::P2wpkh, ScriptType::P2pkh, ScriptType::P2tr];
 
 impl fmt::Display for ScriptType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -25,10 +34,28 @@ impl fmt::Display for ScriptType {
             Self::P2shP2wpkh => write!(f, "p2sh-p2wpkh"),
             Self::P2wpkh => write!(f, "p2wpkh"),
             Self::P2pkh => write!(f, "p2pkh"),
+            Self::P2tr => write!(f, "p2tr"),
+            Self::P2wsh => write!(f, "p2wsh"),
         }
     }
 }
 
+impl FromStr for ScriptType {
+    type Err = Error;
+
+    /// Parse the names used for `address_type`, the same of `Display`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "p2sh-p2wpkh" => Self::P2shP2wpkh,
+            "p2wpkh" => Self::P2wpkh,
+            "p2pkh" => Self::P2pkh,
+            "p2tr" => Self::P2tr,
+            "p2wsh" => Self::P2wsh,
+            _ => return Err(Error::InputValidationFailed),
+        })
+    }
+}
+
 impl ScriptType {
     /// An integer associated to the scipt/account type to compute the GDK pointer (account_num)
     pub fn num(&self) -> u32 {
@@ -36,8 +63,20 @@ impl ScriptType {
             Self::P2shP2wpkh => 0,
             Self::P2wpkh => 1,
             Self::P2pkh => 2,
+            Self::P2tr => 3,
+            Self::P2wsh => 4,
         }
     }
+
+    /// The GDK pointer (account_num) of the account at `bip32_account` for this script type.
+    ///
+    /// Account numbers are `bip32_account * 16 + num()`: the lowest 4 bits identify the script
+    /// type and the remaining ones the bip32 account index in the path
+    /// `m / purpose' / coin_type' / bip32_account'`.
+    /// Returns None if the result overflows a `u32`.
+    pub fn account_num(&self, bip32_account: u32) -> Option<u32> {
+        bip32_account.checked_mul(16)?.checked_add(self.num())
+    }
 }
 
 // The following scripts are always using regtest network,
@@ -69,7 +108,10 @@ impl ScriptType {
     }
 
     pub fn is_segwit(self) -> bool {
-        matches!(self, ScriptType::P2wpkh | ScriptType::P2shP2wpkh)
+        matches!(
+            self,
+            ScriptType::P2wpkh | ScriptType::P2shP2wpkh | ScriptType::P2tr | ScriptType::P2wsh
+        )
     }
 
     /// Returns a mock witness with the expected size
@@ -77,8 +119,12 @@ impl ScriptType {
         Witness::from_vec(match self {
             // signature (72) + compressed public key (33)
             ScriptType::P2wpkh | ScriptType::P2shP2wpkh => vec![vec![0u8; 72], vec![0u8; 33]],
+            // key path spend with the default sighash: schnorr signature (64)
+            ScriptType::P2tr => vec![vec![0u8; 64]],
             // empty for non-witness inputs
             ScriptType::P2pkh => vec![],
+            // 2-of-3 multisig: empty push (1), 2 signatures (72), witness script (105)
+            ScriptType::P2wsh => vec![vec![], vec![0u8; 72], vec![0u8; 72], vec![0u8; 105]],
         })
     }
 
@@ -86,7 +132,7 @@ impl ScriptType {
     pub fn mock_script_sig(self) -> Vec<u8> {
         match self {
             // empty for native segwit
-            ScriptType::P2wpkh => vec![],
+            ScriptType::P2wpkh | ScriptType::P2tr | ScriptType::P2wsh => vec![],
             // OP_PUSHBYTES <22 bytes>
             ScriptType::P2shP2wpkh => vec![0u8; 23],
             // OP_PUSHBYTES <72 bytes sig> OP_PUSHBYTES <33 bytes compressed key>
@@ -103,6 +149,10 @@ impl ScriptType {
             ScriptType::P2shP2wpkh => vec![0u8; 23],
             // OP_DUP OP_HASH160 OP_PUSHBYTES <20 bytes hash> OP_EQUALVERIFY OP_CHECKSIG
             ScriptType::P2pkh => vec![0u8; 25],
+            // OP_1 OP_PUSHBYTES <32 bytes output key>
+            ScriptType::P2tr => vec![0u8; 34],
+            // OP_0 OP_PUSHBYTES <32 bytes hash>
+            ScriptType::P2wsh => vec![0u8; 34],
         }
     }
 }
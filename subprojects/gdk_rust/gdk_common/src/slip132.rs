@@ -12,15 +12,18 @@ const VERSION_TPUB: [u8; 4] = [0x04, 0x35, 0x87, 0xcf]; // testnet p2pkh
 const VERSION_UPUB: [u8; 4] = [0x04, 0x4a, 0x52, 0x62]; // testnet p2sh-p2wpkh
 const VERSION_VPUB: [u8; 4] = [0x04, 0x5f, 0x1c, 0xf6]; // testnet p2wpkh
 
-pub fn slip132_version(is_mainnet: bool, script_type: ScriptType) -> [u8; 4] {
-    match (is_mainnet, script_type) {
+/// SLIP132 does not define a version for taproot, which is returned as None, and multisig
+/// accounts are not described by a single extended public key
+pub fn slip132_version(is_mainnet: bool, script_type: ScriptType) -> Option<[u8; 4]> {
+    Some(match (is_mainnet, script_type) {
         (true, ScriptType::P2pkh) => VERSION_XPUB,
         (true, ScriptType::P2shP2wpkh) => VERSION_YPUB,
         (true, ScriptType::P2wpkh) => VERSION_ZPUB,
         (false, ScriptType::P2pkh) => VERSION_TPUB,
         (false, ScriptType::P2shP2wpkh) => VERSION_UPUB,
         (false, ScriptType::P2wpkh) => VERSION_VPUB,
-    }
+        (_, ScriptType::P2tr | ScriptType::P2wsh) => return None,
+    })
 }
 
 fn decode_slip132_version(bytes: &[u8; 4]) -> Result<(bool, ScriptType), Error> {
@@ -94,10 +97,11 @@ mod test {
             } else {
                 assert_eq!(prefix, "tpub");
             }
-            assert_eq!(slip132_version(is_mainnet, script_type), version);
+            assert_eq!(slip132_version(is_mainnet, script_type), Some(version));
             assert_eq!(extract_bip32_account(&xpub).unwrap(), n)
         }
 
+        assert_eq!(slip132_version(true, ScriptType::P2tr), None);
         assert!(decode_from_slip132_string("foobar").is_err());
 
         let tpub_err = "tpubDC2Q4xJvBca46ZxTdaQEB1pT6j9fuPG5HnrP5chgWPFh1EjfaCt8f5v6b68M5D7xBBF4Md2MCFi2KBDYPLHy6QhBLuifUTPRSDnMWDYUWAy";
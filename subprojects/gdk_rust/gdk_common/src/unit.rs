@@ -0,0 +1,127 @@
+use std::str::FromStr;
+
+use bitcoin::{Amount, Denomination};
+
+use crate::error::Error;
+
+/// A unit an amount can be expressed in, the bitcoin units of `Settings::unit` or fiat.
+///
+/// Bitcoin amounts are converted through integer satoshis, so they never lose precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmountUnit {
+    Sat,
+    Bits,
+    MilliBtc,
+    Btc,
+    Fiat,
+}
+
+impl FromStr for AmountUnit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        match s.to_ascii_lowercase().as_str() {
+            "sat" | "sats" | "satoshi" => Ok(AmountUnit::Sat),
+            "bits" | "ubtc" | "\u{b5}btc" => Ok(AmountUnit::Bits),
+            "mbtc" => Ok(AmountUnit::MilliBtc),
+            "btc" => Ok(AmountUnit::Btc),
+            "fiat" => Ok(AmountUnit::Fiat),
+            _ => Err(Error::Generic(format!("unknown unit: {}", s))),
+        }
+    }
+}
+
+impl AmountUnit {
+    fn denomination(self) -> Option<Denomination> {
+        match self {
+            AmountUnit::Sat => Some(Denomination::Satoshi),
+            AmountUnit::Bits => Some(Denomination::Bit),
+            AmountUnit::MilliBtc => Some(Denomination::MilliBitcoin),
+            AmountUnit::Btc => Some(Denomination::Bitcoin),
+            AmountUnit::Fiat => None,
+        }
+    }
+}
+
+/// Parse `value` expressed in `unit` as satoshis.
+///
+/// `rate` is the price of a bitcoin in fiat and is needed only for `AmountUnit::Fiat`, the
+/// result is then rounded to the nearest satoshi.
+pub fn to_satoshi(value: &str, unit: AmountUnit, rate: Option<f64>) -> Result<u64, Error> {
+    let invalid = || Error::Generic(format!("invalid amount: {}", value));
+    match unit.denomination() {
+        Some(denom) => Ok(Amount::from_str_in(value, denom).map_err(|_| invalid())?.to_sat()),
+        None => {
+            let rate = fiat_rate(rate)?;
+            let fiat = value.parse::<f64>().map_err(|_| invalid())?;
+            if !fiat.is_finite() || fiat < 0.0 {
+                return Err(invalid());
+            }
+            Ok((fiat / rate * 100_000_000.0).round() as u64)
+        }
+    }
+}
+
+/// Express `satoshi` in `unit`, without trailing zeros for the bitcoin units and with 2
+/// decimals for fiat.
+pub fn from_satoshi(satoshi: u64, unit: AmountUnit, rate: Option<f64>) -> Result<String, Error> {
+    match unit.denomination() {
+        Some(denom) => Ok(Amount::from_sat(satoshi).to_string_in(denom)),
+        None => {
+            let rate = fiat_rate(rate)?;
+            Ok(format!("{:.2}", satoshi as f64 / 100_000_000.0 * rate))
+        }
+    }
+}
+
+fn fiat_rate(rate: Option<f64>) -> Result<f64, Error> {
+    match rate {
+        Some(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        _ => Err(Error::Generic("no exchange rate available for fiat conversion".into())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unit_from_str() {
+        assert_eq!("BTC".parse::<AmountUnit>().unwrap(), AmountUnit::Btc);
+        assert_eq!("mBTC".parse::<AmountUnit>().unwrap(), AmountUnit::MilliBtc);
+        assert_eq!("bits".parse::<AmountUnit>().unwrap(), AmountUnit::Bits);
+        assert_eq!("sats".parse::<AmountUnit>().unwrap(), AmountUnit::Sat);
+        assert_eq!("fiat".parse::<AmountUnit>().unwrap(), AmountUnit::Fiat);
+        assert!("nBTC".parse::<AmountUnit>().is_err());
+    }
+
+    #[test]
+    fn test_sat_btc_round_trip() {
+        for satoshi in [0, 1, 10, 123_456_789, 2_100_000_000_000_000] {
+            let btc = from_satoshi(satoshi, AmountUnit::Btc, None).unwrap();
+            assert_eq!(to_satoshi(&btc, AmountUnit::Btc, None).unwrap(), satoshi);
+        }
+        assert_eq!(from_satoshi(123_456_789, AmountUnit::Btc, None).unwrap(), "1.23456789");
+        assert_eq!(to_satoshi("0.1", AmountUnit::Btc, None).unwrap(), 10_000_000);
+        assert_eq!(from_satoshi(123_456_789, AmountUnit::MilliBtc, None).unwrap(), "1234.56789");
+        assert_eq!(from_satoshi(123_456_789, AmountUnit::Bits, None).unwrap(), "1234567.89");
+        assert_eq!(to_satoshi("1.5", AmountUnit::Bits, None).unwrap(), 150);
+
+        // more decimals than a satoshi
+        assert!(to_satoshi("0.000000001", AmountUnit::Btc, None).is_err());
+        assert!(to_satoshi("1.5", AmountUnit::Sat, None).is_err());
+        assert!(to_satoshi("-1", AmountUnit::Btc, None).is_err());
+    }
+
+    #[test]
+    fn test_fiat() {
+        let rate = Some(20_000.5);
+        assert_eq!(from_satoshi(123_456_789, AmountUnit::Fiat, rate).unwrap(), "24691.98");
+        assert_eq!(to_satoshi("20000.50", AmountUnit::Fiat, rate).unwrap(), 100_000_000);
+        assert_eq!(to_satoshi("1", AmountUnit::Fiat, rate).unwrap(), 5_000);
+
+        assert!(from_satoshi(1, AmountUnit::Fiat, None).is_err());
+        assert!(to_satoshi("1", AmountUnit::Fiat, Some(0.0)).is_err());
+        assert!(to_satoshi("-1", AmountUnit::Fiat, rate).is_err());
+    }
+}
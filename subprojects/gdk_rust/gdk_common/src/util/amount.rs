@@ -0,0 +1,138 @@
+//! Serialization of satoshi amounts.
+//!
+//! Amounts are serialized as JSON numbers by default. Since large Liquid asset amounts may exceed
+//! the integer precision of some consumers (e.g. javascript `Number`), amounts serialized within
+//! [`with_satoshi_as_string`] are strings instead. Deserialization always accepts both.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+thread_local! {
+    static SATOSHI_AS_STRING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Restores the previous setting of the thread when dropped
+struct Restore(bool);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        SATOSHI_AS_STRING.with(|s| s.set(self.0));
+    }
+}
+
+/// Call `f` serializing the satoshi amounts as strings if `enabled`
+///
+/// The setting only applies to the current thread until `f` returns, so that other callers,
+/// such as the C++ layer reading amounts as numbers, are not affected.
+pub fn with_satoshi_as_string<T>(enabled: bool, f: impl FnOnce() -> T) -> T {
+    let _restore = Restore(SATOSHI_AS_STRING.with(|s| s.replace(enabled)));
+    f()
+}
+
+fn satoshi_as_string() -> bool {
+    SATOSHI_AS_STRING.with(Cell::get)
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString<T> {
+    Number(T),
+    String(String),
+}
+
+impl<T: FromStr> NumberOrString<T> {
+    fn into_inner<E: serde::de::Error>(self) -> Result<T, E> {
+        match self {
+            NumberOrString::Number(n) => Ok(n),
+            NumberOrString::String(s) => {
+                s.parse().map_err(|_| E::custom(format!("invalid amount: {}", s)))
+            }
+        }
+    }
+}
+
+/// To be used as `#[serde(with = "crate::util::amount")]` on a single amount
+pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize + Display,
+    S: Serializer,
+{
+    if satoshi_as_string() {
+        serializer.collect_str(value)
+    } else {
+        value.serialize(serializer)
+    }
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Deserialize<'de> + FromStr,
+    D: Deserializer<'de>,
+{
+    NumberOrString::<T>::deserialize(deserializer)?.into_inner()
+}
+
+/// To be used as `#[serde(with = "crate::util::amount::map")]` on a map of amounts, e.g. `Balances`
+pub mod map {
+    use super::*;
+
+    pub fn serialize<T, S>(map: &HashMap<String, T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize + Display,
+        S: Serializer,
+    {
+        if satoshi_as_string() {
+            serializer.collect_map(map.iter().map(|(k, v)| (k, v.to_string())))
+        } else {
+            map.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<HashMap<String, T>, D::Error>
+    where
+        T: Deserialize<'de> + FromStr,
+        D: Deserializer<'de>,
+    {
+        HashMap::<String, NumberOrString<T>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(k, v)| Ok((k, v.into_inner()?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Amounts {
+        #[serde(with = "super")]
+        satoshi: u64,
+        #[serde(with = "super::map")]
+        balances: HashMap<String, i64>,
+    }
+
+    #[test]
+    fn test_amount_serialization() {
+        let amounts = Amounts {
+            satoshi: u64::MAX,
+            balances: vec![("btc".to_string(), -1)].into_iter().collect(),
+        };
+
+        let json = serde_json::to_string(&amounts).unwrap();
+        assert_eq!(json, r#"{"satoshi":18446744073709551615,"balances":{"btc":-1}}"#);
+        assert_eq!(serde_json::from_str::<Amounts>(&json).unwrap(), amounts);
+
+        let json = with_satoshi_as_string(true, || serde_json::to_string(&amounts)).unwrap();
+        assert_eq!(json, r#"{"satoshi":"18446744073709551615","balances":{"btc":"-1"}}"#);
+        assert_eq!(serde_json::from_str::<Amounts>(&json).unwrap(), amounts);
+        assert!(!satoshi_as_string());
+
+        let invalid = r#"{"satoshi":"1.5","balances":{}}"#;
+        assert!(serde_json::from_str::<Amounts>(invalid).is_err());
+    }
+}
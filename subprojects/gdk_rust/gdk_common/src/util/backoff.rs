@@ -0,0 +1,38 @@
+use rand::{thread_rng, Rng};
+use std::time::Duration;
+
+/// Exponential backoff with jitter between attempts to reach a remote server
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    attempts: u32,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Backoff {
+            base,
+            max: max.max(base),
+            attempts: 0,
+        }
+    }
+
+    /// The delay before the next attempt
+    ///
+    /// The delay doubles at every call, starting from the base one and capped to the max one,
+    /// then a random jitter puts it in the upper half of that value, so that clients
+    /// disconnected at the same time don't retry in lockstep.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.base.saturating_mul(2u32.saturating_pow(self.attempts)).min(self.max);
+        self.attempts = self.attempts.saturating_add(1);
+        let half = delay / 2;
+        let jitter = thread_rng().gen_range(0..=(delay - half).as_millis() as u64);
+        half + Duration::from_millis(jitter)
+    }
+
+    /// Restart from the base delay, to be called once connected
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+    }
+}
@@ -1,8 +1,11 @@
 use std::convert::TryFrom;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+pub mod amount;
+mod backoff;
 mod strser;
 
+pub use backoff::Backoff;
 pub use strser::StringSerialized;
 
 pub fn is_confidential_txoutsecrets(txoutsecrets: &elements::TxOutSecrets) -> bool {
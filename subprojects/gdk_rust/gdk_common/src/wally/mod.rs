@@ -59,7 +59,7 @@ impl fmt::Debug for MasterBlindingKey {
 const BIP39_SEED_BYTES: usize = 64;
 
 /// Validate a BIP-39 mnemonic.
-fn bip39_mnemonic_validate(mnemonic: &str) -> bool {
+pub fn bip39_mnemonic_validate(mnemonic: &str) -> bool {
     let c_mnemonic = make_str(mnemonic);
     let ret = unsafe {
         let ret = ffi::bip39_mnemonic_validate(ptr::null(), c_mnemonic);
@@ -3,14 +3,17 @@ use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::str::FromStr;
 
-use gdk_common::bitcoin::util::sighash::SighashCache;
+use gdk_common::bitcoin::util::psbt::PartiallySignedTransaction;
+use gdk_common::bitcoin::util::schnorr::TapTweak;
+use gdk_common::bitcoin::util::sighash::{Prevouts, SighashCache};
+use gdk_common::bitcoin::SchnorrSighashType;
 use gdk_common::electrum_client::ScriptStatus;
 use gdk_common::log::{info, warn};
 
 use gdk_common::bitcoin::blockdata::script;
 use gdk_common::bitcoin::hashes::hex::{FromHex, ToHex};
 use gdk_common::bitcoin::hashes::Hash;
-use gdk_common::bitcoin::secp256k1::{self, Message};
+use gdk_common::bitcoin::secp256k1::{self, KeyPair, Message};
 use gdk_common::bitcoin::util::address::Payload;
 use gdk_common::bitcoin::util::bip32::{
     ChildNumber, DerivationPath, ExtendedPrivKey, ExtendedPubKey, Fingerprint,
@@ -21,14 +24,17 @@ use gdk_common::{bitcoin, elements, rand};
 
 use gdk_common::be::{
     BEAddress, BEOutPoint, BEScript, BEScriptConvert, BESigHashType, BETransaction, BETxid,
-    DUST_VALUE,
+    FeeTarget, MAX_OP_RETURN_SIZE,
 };
+use gdk_common::descriptor::MultisigDescriptor;
 use gdk_common::error::fn_err;
 use gdk_common::model::{
-    parse_path, AccountInfo, AddressAmount, AddressDataResult, AddressPointer, CreateTransaction,
-    GetPreviousAddressesOpt, GetTransactionsOpt, GetTxInOut, PreviousAddress, PreviousAddresses,
-    SPVVerifyTxResult, TransactionMeta, TransactionOutput, TxListItem, Txo, UnspentOutput,
-    UpdateAccountOpt, UtxoStrategy,
+    parse_path, AccountDescriptors, AccountInfo, AddressAmount, AddressDataResult, AddressPointer,
+    Balances, CreateTransaction, ExportedAccount, GetPreviousAddressesOpt, GetTransactionsOpt,
+    GetTxInOut, PreviousAddress, PreviousAddresses, SPVVerifyTxResult, SpendIndexEntry,
+    SubaccountDiff, TransactionEstimate, TransactionMeta, TransactionOutput, TransactionType,
+    TxListItem, TxSort, Txo, TxsResult, UnspentOutput, UpdateAccountOpt, UtxoStrategy,
+    MAX_TRANSACTIONS_COUNT,
 };
 use gdk_common::scripts::{p2pkh_script, p2shwpkh_script_sig, ScriptType};
 use gdk_common::slip132::slip132_version;
@@ -40,13 +46,16 @@ use gdk_common::{ElementsNetwork, NetworkId, NetworkParameters};
 
 use crate::error::Error;
 use crate::interface::ElectrumUrl;
-use crate::store::{RawAccountCache, Store};
-use crate::{ScriptStatuses, GAP_LIMIT};
+use crate::store::{RawAccountCache, Store, StoreMeta};
+use crate::ScriptStatuses;
 
 // The number of account types, including these reserved for future use.
-// Currently only 3 are used: P2SH-P2WPKH, P2WPKH and P2PKH
+// Currently only 5 are used: P2SH-P2WPKH, P2WPKH, P2PKH, P2TR and P2WSH (watch-only multisig)
 const NUM_RESERVED_ACCOUNT_TYPES: u32 = 16;
 
+/// Locktimes below this value are block heights, the others are unix times
+const LOCKTIME_THRESHOLD: u64 = 500_000_000;
+
 #[derive(Clone)]
 pub struct Account {
     account_num: u32,
@@ -67,6 +76,9 @@ pub struct Account {
     master_blinding: Option<MasterBlindingKey>,
 
     path: DerivationPath,
+
+    /// The cosigners of a watch-only multisig account
+    multisig: Option<MultisigDescriptor>,
 }
 
 /// Compare xpub ignoring the fingerprint (which computation might be skipped),
@@ -81,18 +93,36 @@ pub fn xpubs_equivalent(xpub1: &ExtendedPubKey, xpub2: &ExtendedPubKey) -> Resul
     Ok(())
 }
 
+/// The keys an account is derived from, either `master_xprv` or `account_xpub` must be set
+pub struct AccountKeys<'a> {
+    pub master_xprv: &'a Option<ExtendedPrivKey>,
+    pub master_xpub_fingerprint: Fingerprint,
+    pub account_xpub: &'a Option<ExtendedPubKey>,
+    pub master_blinding: Option<MasterBlindingKey>,
+}
+
 impl Account {
     pub fn new(
         network: NetworkParameters,
-        master_xprv: &Option<ExtendedPrivKey>,
-        master_xpub_fingerprint: Fingerprint,
-        account_xpub: &Option<ExtendedPubKey>,
-        master_blinding: Option<MasterBlindingKey>,
+        keys: AccountKeys,
         store: Store,
         account_num: u32,
         discovered: bool,
+        multisig: Option<MultisigDescriptor>,
     ) -> Result<Self, Error> {
+        let AccountKeys {
+            master_xprv,
+            master_xpub_fingerprint,
+            account_xpub,
+            master_blinding,
+        } = keys;
         let (script_type, path) = get_account_derivation(account_num, network.id())?;
+        if (script_type == ScriptType::P2wsh) != multisig.is_some() {
+            return Err(Error::Generic(
+                "multisig subaccounts can only be created from watch-only descriptors".into(),
+            ));
+        }
+        let path = store.read()?.get_account_base_path(account_num).cloned().unwrap_or(path);
 
         let (xprv, xpub) = if let Some(master_xprv) = master_xprv {
             let xprv = master_xprv.derive_priv(&crate::EC, &path)?;
@@ -129,6 +159,7 @@ impl Account {
             store,
             master_blinding,
             path,
+            multisig,
         })
     }
 
@@ -140,34 +171,53 @@ impl Account {
         self.script_type
     }
 
+    /// The derivation path from the master key to the account xpub
+    pub fn path(&self) -> &DerivationPath {
+        &self.path
+    }
+
+    /// Whether this is a watch-only multisig account
+    pub fn is_multisig(&self) -> bool {
+        self.multisig.is_some()
+    }
+
     fn descriptor(&self, is_internal: bool) -> Result<String, Error> {
-        let internal_idx = if is_internal {
-            1
-        } else {
-            0
-        };
-        let (prefix, suffix) = match self.script_type {
-            ScriptType::P2shP2wpkh => ("sh(wpkh", ")"),
-            ScriptType::P2wpkh => ("wpkh", ""),
-            ScriptType::P2pkh => ("pkh", ""),
-        };
-        let (_, path) = get_account_derivation(self.account_num, self.network.id())?;
-        let path = &path.to_string()[2..];
-        let parent_fingerprint = self.master_xpub_fingerprint.to_string();
-        let key_origin = format!("[{}/{}]", parent_fingerprint, path);
-        let desc = format!("{}({}{}/{}/*){}", prefix, key_origin, self.xpub, internal_idx, suffix);
-        let (desc, _) =
-            gdk_common::miniscript::descriptor::Descriptor::parse_descriptor(&crate::EC, &desc)?;
-        Ok(desc.to_string())
+        if let Some(multisig) = &self.multisig {
+            return Ok(multisig.descriptor(is_internal)?);
+        }
+        account_descriptor(
+            self.script_type,
+            &self.path,
+            self.master_xpub_fingerprint,
+            &self.xpub,
+            is_internal,
+        )
+    }
+
+    pub fn descriptors(&self) -> Result<AccountDescriptors, Error> {
+        Ok(AccountDescriptors {
+            receive: self.descriptor(false)?,
+            change: self.descriptor(true)?,
+        })
+    }
+
+    pub fn export(&self) -> Result<ExportedAccount, Error> {
+        Ok(ExportedAccount {
+            subaccount: self.account_num,
+            descriptors: self.descriptors()?,
+            xpub: self.xpub.to_string(),
+            base_path: self.path.clone().into(),
+            slip132_extended_pubkey: self.slip132_extended_pubkey(),
+        })
     }
 
     fn slip132_extended_pubkey(&self) -> Option<String> {
         if self.network.liquid {
             None
         } else {
+            let version = slip132_version(self.network.mainnet, self.script_type)?;
             let mut xpub_bytes = self.xpub.encode();
-            xpub_bytes[0..4]
-                .copy_from_slice(&slip132_version(self.network.mainnet, self.script_type));
+            xpub_bytes[0..4].copy_from_slice(&version);
             Some(bitcoin::util::base58::check_encode_slice(&xpub_bytes))
         }
     }
@@ -182,6 +232,15 @@ impl Account {
         self.path.extend(account_path)
     }
 
+    /// The inverse of `get_full_path`, fails if `full_path` is not a path of this account
+    fn account_path(&self, full_path: &DerivationPath) -> Result<DerivationPath, Error> {
+        full_path
+            .as_ref()
+            .strip_prefix(self.path.as_ref())
+            .map(DerivationPath::from)
+            .ok_or_else(|| Error::Generic(format!("{} is not a path of this account", full_path)))
+    }
+
     pub fn info(&self) -> Result<AccountInfo, Error> {
         let settings = self.store.read()?.get_account_settings(self.account_num).cloned();
 
@@ -208,10 +267,36 @@ impl Account {
         if let Some(hidden) = opt.hidden {
             settings.hidden = hidden;
         }
+        if let Some(address_type) = opt.default_address_type {
+            settings.default_address_type = if address_type.is_empty() {
+                None
+            } else if self.supports_address_type(&address_type) {
+                Some(address_type)
+            } else {
+                return Err(Error::UnsupportedAddressType(address_type, self.account_num));
+            };
+        }
         store_write.set_account_settings(self.account_num, settings)?;
         Ok(true)
     }
 
+    /// Whether the account derives addresses of the given type, such as `p2wpkh`
+    pub fn supports_address_type(&self, address_type: &str) -> bool {
+        address_type.parse().ok() == Some(self.script_type)
+    }
+
+    /// The address type used when none is requested: the account default if set, then the
+    /// network default if the account supports it, otherwise the account script type
+    pub fn default_address_type(&self) -> Result<String, Error> {
+        let store = self.store.read()?;
+        let account_default = store
+            .get_account_settings(self.account_num)
+            .and_then(|s| s.default_address_type.clone());
+        let network_default =
+            self.network.default_address_type.clone().filter(|t| self.supports_address_type(t));
+        Ok(account_default.or(network_default).unwrap_or_else(|| self.script_type.to_string()))
+    }
+
     pub fn set_name(&self, name: &str) -> Result<bool, Error> {
         self.set_settings(UpdateAccountOpt {
             name: Some(name.into()),
@@ -220,6 +305,16 @@ impl Account {
     }
 
     pub fn derive_address(&self, is_internal: bool, index: u32) -> Result<BEAddress, Error> {
+        if let Some(multisig) = &self.multisig {
+            let network = match self.network.id() {
+                NetworkId::Bitcoin(network) => network,
+                NetworkId::Elements(_) => {
+                    unreachable!("multisig accounts are not created on Liquid")
+                }
+            };
+            let script = multisig.witness_script(is_internal, index)?;
+            return Ok(BEAddress::Bitcoin(bitcoin::Address::p2wsh(&script, network)));
+        }
         derive_address(
             &self.chains[is_internal as usize],
             index,
@@ -229,6 +324,13 @@ impl Account {
         )
     }
 
+    /// The next change address, without advancing the internal index
+    pub fn next_change_address(&self) -> Result<BEAddress, Error> {
+        let store = self.store.read()?;
+        let internal = store.account_cache(self.account_num)?.indexes.internal;
+        self.derive_address(true, internal + 1)
+    }
+
     pub fn get_next_address(&self, is_internal: bool) -> Result<AddressPointer, Error> {
         let store = &mut self.store.write()?;
         let acc_store = store.account_cache_mut(self.account_num)?;
@@ -307,7 +409,11 @@ impl Account {
                 None => None,
                 Some(_pubkey) => Some(script_pubkey.to_hex()),
             };
-            let tx_count = acc_store.all_txs.tx_count(&script_pubkey);
+            let tx_count = if opt.with_tx_count {
+                Some(acc_store.all_txs.tx_count(&script_pubkey))
+            } else {
+                None
+            };
             previous_addresses.push(PreviousAddress {
                 address: address.to_string(),
                 address_type: self.script_type.to_string(),
@@ -333,7 +439,7 @@ impl Account {
         })
     }
 
-    pub fn list_tx(&self, opt: &GetTransactionsOpt) -> Result<Vec<TxListItem>, Error> {
+    pub fn list_tx(&self, opt: &GetTransactionsOpt) -> Result<TxsResult, Error> {
         let store = self.store.read()?;
         let acc_store = store.account_cache(self.account_num)?;
 
@@ -341,9 +447,12 @@ impl Account {
         let num_confs = opt.num_confs.unwrap_or(0);
 
         let mut txs = vec![];
+        // replaced transactions are not in the wallet anymore, but they are kept in the list
+        let replaced = acc_store.replaced.keys().map(|txid| (txid, &None));
         let mut my_txids: Vec<(&BETxid, &Option<u32>)> = acc_store
             .heights
             .iter()
+            .chain(replaced)
             .filter(|(_, height)| {
                 num_confs <= height.map_or(0, |height| (tip_height + 1).saturating_sub(height))
             })
@@ -356,47 +465,223 @@ impl Account {
             }
         });
 
-        for (tx_id, height) in my_txids.iter().skip(opt.first).take(opt.count) {
-            let txe = acc_store
-                .all_txs
-                .get(*tx_id)
-                .ok_or_else(fn_err(&format!("list_tx no tx {}", tx_id)))?;
-            let tx = &txe.tx;
+        if opt.sort == TxSort::Oldest {
+            my_txids.reverse();
+        } else if opt.running_balance {
+            return Err(Error::Generic("running_balance requires sort \"oldest\"".into()));
+        }
 
-            let timestamp = height
-                .map(|h| store.cache.headers.get(&h))
-                .flatten()
-                .map(|h| 1_000_000u64.saturating_mul(h.time() as u64))
-                .unwrap_or_else(now); // in microseconds
+        // filter before paging, so that pages are counted on the matching transactions only
+        if opt.is_filtered() {
+            if opt.running_balance {
+                return Err(Error::Generic("running_balance cannot be filtered".into()));
+            }
+            let mut filtered = Vec::with_capacity(my_txids.len());
+            for (tx_id, height) in my_txids {
+                let txe = acc_store
+                    .all_txs
+                    .get(tx_id)
+                    .ok_or_else(fn_err(&format!("list_tx no tx {}", tx_id)))?;
+                let satoshi = txe.tx.my_balance_changes(
+                    &acc_store.all_txs,
+                    &acc_store.paths,
+                    &acc_store.unblinded,
+                );
+                let type_ = tx_type(&txe.tx, &satoshi, acc_store);
+                if opt.matches(tx_timestamp(&store, *height), &type_) {
+                    filtered.push((tx_id, height));
+                }
+            }
+            my_txids = filtered;
+        }
+
+        let (count, truncated) = clamp_txs_count(my_txids.len(), opt.first, opt.count);
+        let mut running_balance = Balances::new();
+        if opt.running_balance {
+            for (tx_id, _) in my_txids.iter().take(opt.first) {
+                if acc_store.replaced.contains_key(tx_id) {
+                    continue;
+                }
+                let txe = acc_store
+                    .all_txs
+                    .get(tx_id)
+                    .ok_or_else(fn_err(&format!("list_tx no tx {}", tx_id)))?;
+                let satoshi = txe.tx.my_balance_changes(
+                    &acc_store.all_txs,
+                    &acc_store.paths,
+                    &acc_store.unblinded,
+                );
+                add_balances(&mut running_balance, &satoshi);
+            }
+        }
+        for (tx_id, height) in my_txids.iter().skip(opt.first).take(count) {
+            let mut tx = self.tx_list_item(&store, tx_id, **height)?;
+            if opt.running_balance && tx.replaced_by.is_none() {
+                add_balances(&mut running_balance, &tx.satoshi);
+                tx.running_balance = running_balance.clone();
+            }
+            txs.push(tx);
+        }
+        info!("list_tx {:?}", txs.iter().map(|e| &e.txhash).collect::<Vec<&String>>());
 
-            let memo = store.get_memo(tx_id).cloned().unwrap_or("".to_string());
+        Ok(TxsResult {
+            transactions: txs,
+            truncated,
+        })
+    }
 
-            let fee = tx.fee(
+    /// The balance of the account once every transaction confirmed at or below `height` was
+    /// applied, computed from the stored history only
+    pub fn balance_at_height(&self, height: u32) -> Result<Balances, Error> {
+        let store = self.store.read()?;
+        let acc_store = store.account_cache(self.account_num)?;
+        let mut balance = Balances::new();
+        for (tx_id, tx_height) in acc_store.heights.iter() {
+            if !matches!(tx_height, Some(h) if *h <= height) {
+                continue;
+            }
+            let txe = acc_store
+                .all_txs
+                .get(tx_id)
+                .ok_or_else(fn_err(&format!("balance_at_height no tx {}", tx_id)))?;
+            let satoshi = txe.tx.my_balance_changes(
                 &acc_store.all_txs,
+                &acc_store.paths,
                 &acc_store.unblinded,
-                &self.network.policy_asset_id().ok(),
-            )?;
+            );
+            add_balances(&mut balance, &satoshi);
+        }
+        Ok(balance)
+    }
+
+    /// Returns the list item of a single transaction of this account.
+    pub fn get_tx_item(&self, txid: &BETxid) -> Result<TxListItem, Error> {
+        let store = self.store.read()?;
+        let acc_store = store.account_cache(self.account_num)?;
+        let height = match acc_store.heights.get(txid) {
+            Some(height) => *height,
+            None if acc_store.replaced.contains_key(txid) => None,
+            None => return Err(Error::TxNotFound(*txid)),
+        };
+        self.tx_list_item(&store, txid, height)
+    }
 
-            let fee_rate = txe.fee_rate(fee);
+    /// The changes since a snapshot taken at `tip_height`, when the account had `tx_count`
+    /// transactions of which `unconfirmed` were unconfirmed, or since the account creation if
+    /// `tx_count` is `None`
+    pub fn diff_since(
+        &self,
+        tip_height: u32,
+        tx_count: Option<usize>,
+        unconfirmed: &HashSet<BETxid>,
+    ) -> Result<SubaccountDiff, Error> {
+        let spend_index = self.spend_index()?;
 
-            let satoshi =
-                tx.my_balance_changes(&acc_store.all_txs, &acc_store.paths, &acc_store.unblinded);
+        let store = self.store.read()?;
+        let acc_store = store.account_cache(self.account_num)?;
+        let current_count = acc_store.heights.len();
 
-            let is_redeposit = tx.is_redeposit(&acc_store.paths, &acc_store.all_txs);
-            let type_ = tx.type_(&satoshi, is_redeposit);
-            let user_signed = type_.user_signed();
+        // Transactions unconfirmed at the snapshot are already accounted for, they are returned
+        // again only once confirmed and without contributing to the balance change
+        let mut changed_txids: Vec<(&BETxid, &Option<u32>)> = acc_store
+            .heights
+            .iter()
+            .filter(|(txid, height)| match (tx_count, height) {
+                (None, _) => true,
+                (Some(_), None) => !unconfirmed.contains(txid),
+                (Some(_), Some(h)) => *h > tip_height,
+            })
+            .collect();
+        changed_txids.sort_by_key(|(txid, height)| (height.unwrap_or(u32::MAX), **txid));
+
+        let mut transactions = vec![];
+        let mut balance_delta = Balances::new();
+        let mut new_txids = HashSet::new();
+        for (tx_id, height) in changed_txids {
+            let tx = self.tx_list_item(&store, tx_id, *height)?;
+            if !unconfirmed.contains(tx_id) {
+                add_balances(&mut balance_delta, &tx.satoshi);
+                new_txids.insert(tx.txhash.clone());
+            }
+            transactions.push(tx);
+        }
 
-            let spv_verified = if self.network.spv_enabled.unwrap_or(false) {
-                store.spv_verification_status(self.num(), tx_id)
-            } else {
-                SPVVerifyTxResult::Disabled
-            };
+        let spent_utxos = spend_index
+            .into_iter()
+            .filter(|entry| new_txids.contains(&entry.spent_in))
+            .map(|entry| entry.outpoint)
+            .collect();
+
+        let unconfirmed_txids = acc_store
+            .heights
+            .iter()
+            .filter(|(_, height)| height.is_none())
+            .map(|(txid, _)| txid.to_hex())
+            .collect();
+
+        // The transactions that were confirmed at the snapshot must all still be confirmed at or
+        // below its tip, and the unconfirmed ones must still be there, otherwise a reorg or a
+        // replacement happened, even if the overall count did not go down
+        let reload_required = tx_count.map_or(false, |count| {
+            let confirmed_before = acc_store
+                .heights
+                .values()
+                .filter(|height| height.map_or(false, |h| h <= tip_height))
+                .count();
+            confirmed_before + unconfirmed.len() != count
+                || unconfirmed.iter().any(|txid| !acc_store.heights.contains_key(txid))
+        });
+
+        Ok(SubaccountDiff {
+            subaccount: self.account_num,
+            tx_count: current_count,
+            unconfirmed_txids,
+            reload_required,
+            transactions,
+            spent_utxos,
+            balance_delta,
+        })
+    }
+
+    fn tx_list_item(
+        &self,
+        store: &StoreMeta,
+        tx_id: &BETxid,
+        height: Option<u32>,
+    ) -> Result<TxListItem, Error> {
+        let acc_store = store.account_cache(self.account_num)?;
+
+        let txe =
+            acc_store.all_txs.get(tx_id).ok_or_else(fn_err(&format!("list_tx no tx {}", tx_id)))?;
+        let tx = &txe.tx;
 
-            let rbf_optin = tx.rbf_optin();
-            let can_rbf = height.is_none() && rbf_optin && user_signed;
+        let timestamp = tx_timestamp(store, height);
 
-            let inputs = tx
-                .previous_outputs()
+        let memo = store.get_memo(tx_id).cloned().unwrap_or("".to_string());
+
+        let fee =
+            tx.fee(&acc_store.all_txs, &acc_store.unblinded, &self.network.policy_asset_id().ok())?;
+
+        let fee_rate = txe.fee_rate(fee);
+
+        let satoshi =
+            tx.my_balance_changes(&acc_store.all_txs, &acc_store.paths, &acc_store.unblinded);
+
+        let type_ = tx_type(tx, &satoshi, acc_store);
+        let user_signed = type_.user_signed();
+
+        let spv_verified = if self.network.spv_enabled.unwrap_or(false) {
+            store.spv_verification_status(self.num(), tx_id)
+        } else {
+            SPVVerifyTxResult::Disabled
+        };
+
+        let rbf_optin = tx.rbf_optin();
+        let replaced_by = acc_store.replaced.get(tx_id).map(ToString::to_string);
+        let can_rbf = height.is_none() && rbf_optin && user_signed && replaced_by.is_none();
+
+        let inputs =
+            tx.previous_outputs()
                 .iter()
                 .enumerate()
                 .map(|(vin, beoutpoint)| {
@@ -511,7 +796,8 @@ impl Account {
                 })
                 .collect::<Result<Vec<GetTxInOut>, Error>>()?;
 
-            let outputs = (0..tx.output_len() as u32)
+        let outputs =
+            (0..tx.output_len() as u32)
                 .map(|vout| {
                     let (is_relevant, is_internal, pointer) = {
                         match acc_store.paths.get(&tx.output_script(vout)) {
@@ -593,29 +879,28 @@ impl Account {
                 })
                 .collect::<Result<Vec<GetTxInOut>, Error>>()?;
 
-            txs.push(TxListItem {
-                block_height: height.unwrap_or(0),
-                created_at_ts: timestamp,
-                type_,
-                memo,
-                txhash: tx_id.to_string(),
-                satoshi,
-                rbf_optin,
-                can_cpfp: false,
-                can_rbf,
-                spv_verified: spv_verified.to_string(),
-                fee,
-                fee_rate,
-                inputs,
-                outputs,
-                transaction_size: txe.size,
-                transaction_vsize: weight_to_vsize(txe.weight),
-                transaction_weight: txe.weight,
-            });
-        }
-        info!("list_tx {:?}", txs.iter().map(|e| &e.txhash).collect::<Vec<&String>>());
-
-        Ok(txs)
+        Ok(TxListItem {
+            block_height: height.unwrap_or(0),
+            created_at_ts: timestamp,
+            type_,
+            memo,
+            txhash: tx_id.to_string(),
+            satoshi,
+            running_balance: Balances::new(),
+            rbf_optin,
+            can_cpfp: false,
+            can_rbf,
+            spv_verified: spv_verified.to_string(),
+            fee,
+            fee_rate,
+            inputs,
+            outputs,
+            transaction_size: txe.size,
+            transaction_vsize: weight_to_vsize(txe.weight),
+            transaction_weight: txe.weight,
+            replaced_by,
+            asset_metadata: None,
+        })
     }
 
     pub fn public_key(&self, path: &DerivationPath) -> PublicKey {
@@ -652,9 +937,11 @@ impl Account {
                     pt_idx: vout,
                     script_pubkey: script_pubkey.to_hex(),
                     user_path: vec![],
+                    derivation_path: String::new(),
                 },
                 Some(account_path) => {
                     let (is_internal, pointer) = parse_path(&account_path)?;
+                    let full_path = self.get_full_path(&account_path);
                     TransactionOutput {
                         address,
                         satoshi,
@@ -666,7 +953,8 @@ impl Account {
                         pointer,
                         pt_idx: vout,
                         script_pubkey: script_pubkey.to_hex(),
-                        user_path: self.get_full_path(&account_path).into(),
+                        derivation_path: full_path.to_string(),
+                        user_path: full_path.into(),
                     }
                 }
             });
@@ -736,10 +1024,14 @@ impl Account {
     }
 
     pub fn unspents(&self) -> Result<HashSet<BEOutPoint>, Error> {
+        let store_read = self.store.read()?;
+        Ok(Self::unspents_in(store_read.account_cache(self.account_num)?))
+    }
+
+    /// The unspent outputs of the account cache, for callers already holding the store lock
+    pub fn unspents_in(acc_store: &RawAccountCache) -> HashSet<BEOutPoint> {
         let mut relevant_outputs = HashSet::new();
         let mut inputs = HashSet::new();
-        let store_read = self.store.read()?;
-        let acc_store = store_read.account_cache(self.account_num)?;
         for (txid, txe) in acc_store.all_txs.iter() {
             if !acc_store.heights.contains_key(&txid) {
                 // transaction has been replaced or dropped out of mempool
@@ -760,7 +1052,75 @@ impl Account {
                 }
             }
         }
-        Ok(relevant_outputs.difference(&inputs).cloned().collect())
+        relevant_outputs.difference(&inputs).cloned().collect()
+    }
+
+    /// Return the scripts of the account and the confirmed outputs on them that the store holds
+    /// as unspent, including Liquid outputs that cannot be unblinded.
+    pub fn confirmed_unspents(&self) -> Result<(Vec<BEScript>, HashSet<BEOutPoint>), Error> {
+        let mut relevant_outputs = HashSet::new();
+        let mut inputs = HashSet::new();
+        let store_read = self.store.read()?;
+        let acc_store = store_read.account_cache(self.account_num)?;
+        for (txid, txe) in acc_store.all_txs.iter() {
+            let confirmed = match acc_store.heights.get(txid) {
+                Some(height) => height.is_some(),
+                // transaction has been replaced or dropped out of mempool
+                None => continue,
+            };
+            inputs.extend(txe.tx.previous_outputs());
+            if !confirmed {
+                continue;
+            }
+            for vout in 0..(txe.tx.output_len() as u32) {
+                let script_pubkey = txe.tx.output_script(vout);
+                if !script_pubkey.is_empty() && acc_store.paths.contains_key(&script_pubkey) {
+                    relevant_outputs.insert(txe.tx.outpoint(vout));
+                }
+            }
+        }
+        let scripts = acc_store.paths.keys().cloned().collect();
+        Ok((scripts, relevant_outputs.difference(&inputs).cloned().collect()))
+    }
+
+    /// Return the wallet outputs that have been spent, with the transaction spending them.
+    ///
+    /// Entries are sorted by the height of the spending transaction, unconfirmed ones last.
+    pub fn spend_index(&self) -> Result<Vec<SpendIndexEntry>, Error> {
+        let store_read = self.store.read()?;
+        let acc_store = store_read.account_cache(self.account_num)?;
+        let mut relevant_outputs = HashSet::new();
+        for (txid, txe) in acc_store.all_txs.iter() {
+            if !acc_store.heights.contains_key(txid) {
+                // transaction has been replaced or dropped out of mempool
+                continue;
+            }
+            for vout in 0..(txe.tx.output_len() as u32) {
+                let script_pubkey = txe.tx.output_script(vout);
+                if !script_pubkey.is_empty() && acc_store.paths.contains_key(&script_pubkey) {
+                    relevant_outputs.insert(txe.tx.outpoint(vout));
+                }
+            }
+        }
+
+        let mut entries = vec![];
+        for (txid, height) in acc_store.heights.iter() {
+            let txe = match acc_store.all_txs.get(txid) {
+                Some(txe) => txe,
+                None => continue,
+            };
+            for outpoint in txe.tx.previous_outputs() {
+                if relevant_outputs.contains(&outpoint) {
+                    entries.push(SpendIndexEntry {
+                        outpoint: format!("{}:{}", outpoint.txid().to_hex(), outpoint.vout()),
+                        spent_in: txid.to_hex(),
+                        height: height.unwrap_or(0),
+                    });
+                }
+            }
+        }
+        entries.sort_by_key(|e| (e.height == 0, e.height, e.spent_in.clone(), e.outpoint.clone()));
+        Ok(entries)
     }
 
     pub fn has_transactions(&self) -> Result<bool, Error> {
@@ -776,6 +1136,98 @@ impl Account {
         create_tx(self, request)
     }
 
+    pub fn estimate_tx(
+        &self,
+        request: &mut CreateTransaction,
+    ) -> Result<TransactionEstimate, Error> {
+        if request.subaccount != self.account_num {
+            return Err(Error::InvalidSubaccount(request.subaccount));
+        }
+        estimate_tx(self, request)
+    }
+
+    /// Create an unsigned PSBT from the transaction `tx_hex`, adding the previous outputs
+    /// and the key origins of the wallet inputs and change outputs, so that it can be signed
+    /// by an external signer. Does not require the private keys.
+    ///
+    /// The key origin of an input spending one of `used_utxos` is taken from its `user_path`,
+    /// the ones of the other inputs are looked up from the previous output script.
+    pub fn create_psbt(&self, tx_hex: &str, used_utxos: &[UnspentOutput]) -> Result<String, Error> {
+        let tx = match BETransaction::from_hex(tx_hex, self.network.id())? {
+            BETransaction::Bitcoin(tx) => tx,
+            BETransaction::Elements(_) => {
+                return Err(Error::Generic("PSBT creation is not supported on Liquid".into()))
+            }
+        };
+        let store_read = self.store.read()?;
+        let acc_store = store_read.account_cache(self.account_num)?;
+
+        let mut psbt = PartiallySignedTransaction::from_unsigned_tx(tx)?;
+        for (txin, input) in psbt.unsigned_tx.input.iter().zip(psbt.inputs.iter_mut()) {
+            let prevout = txin.previous_output;
+            // Inputs not belonging to the wallet are left to other signers
+            let prev_tx = match acc_store.get_bitcoin_tx(&prevout.txid) {
+                Ok(prev_tx) => prev_tx,
+                Err(_) => continue,
+            };
+            let out = match prev_tx.output.get(prevout.vout as usize) {
+                Some(out) => out.clone(),
+                None => continue,
+            };
+            let used_utxo = used_utxos
+                .iter()
+                .find(|u| u.txhash == prevout.txid.to_hex() && u.pt_idx == prevout.vout);
+            let path = match used_utxo {
+                Some(utxo) => self.account_path(&utxo.user_path.clone().into())?,
+                None => match acc_store.paths.get(&out.script_pubkey.clone().into_be()) {
+                    Some(path) => path.clone(),
+                    None => continue,
+                },
+            };
+            let public_key = self.xpub.derive_pub(&crate::EC, &path)?.to_pub();
+            let origin = (self.master_xpub_fingerprint, self.get_full_path(&path));
+            if self.script_type == ScriptType::P2tr {
+                let internal_key = public_key.inner.into();
+                input.tap_internal_key = Some(internal_key);
+                input.tap_key_origins.insert(internal_key, (vec![], origin));
+            } else {
+                input.bip32_derivation.insert(public_key.inner, origin);
+                // Signers may need the whole previous transaction for segwit v0 too
+                input.non_witness_utxo = Some(prev_tx);
+            }
+            if self.script_type == ScriptType::P2shP2wpkh {
+                input.redeem_script = Some(bitcoin::Script::new_v0_p2wpkh(
+                    &public_key.wpubkey_hash().expect("compressed key"),
+                ));
+            }
+            if self.script_type.is_segwit() {
+                input.witness_utxo = Some(out);
+            }
+        }
+        for (txout, output) in psbt.unsigned_tx.output.iter().zip(psbt.outputs.iter_mut()) {
+            let path = match acc_store.paths.get(&txout.script_pubkey.clone().into_be()) {
+                Some(path) => path,
+                None => continue,
+            };
+            let public_key = self.xpub.derive_pub(&crate::EC, path)?.to_pub();
+            let origin = (self.master_xpub_fingerprint, self.get_full_path(path));
+            if self.script_type == ScriptType::P2tr {
+                let internal_key = public_key.inner.into();
+                output.tap_internal_key = Some(internal_key);
+                output.tap_key_origins.insert(internal_key, (vec![], origin));
+            } else {
+                output.bip32_derivation.insert(public_key.inner, origin);
+            }
+            if self.script_type == ScriptType::P2shP2wpkh {
+                output.redeem_script = Some(bitcoin::Script::new_v0_p2wpkh(
+                    &public_key.wpubkey_hash().expect("compressed key"),
+                ));
+            }
+        }
+
+        Ok(base64::encode(bitcoin::consensus::encode::serialize(&psbt)))
+    }
+
     // TODO when we can serialize psbt
     //pub fn sign(&self, psbt: PartiallySignedTransaction) -> Result<PartiallySignedTransaction, Error> { Err(Error::Generic("NotImplemented".to_string())) }
     pub fn sign(&self, request: &TransactionMeta) -> Result<TransactionMeta, Error> {
@@ -789,12 +1241,13 @@ impl Account {
         let store_read = self.store.read()?;
         let acc_store = store_read.account_cache(self.account_num)?;
 
-        let sighashes = request
-            .used_utxos
-            .iter()
-            .map(|u| u.sighash())
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|_| Error::InvalidSigHash)?;
+        let sighashes =
+            request.used_utxos.iter().map(|u| u.sighash()).collect::<Result<Vec<_>, _>>().map_err(
+                |e| match e {
+                    gdk_common::Error::UnsupportedSigHash => Error::UnsupportedSigHash,
+                    _ => Error::InvalidSigHash,
+                },
+            )?;
         if sighashes.len() != be_tx.input_len() {
             return Err(Error::Generic("Mismatching used_utxos and transaction".into()));
         }
@@ -803,29 +1256,57 @@ impl Account {
             BETransaction::Bitcoin(tx) => {
                 let mut out_tx = tx.clone();
 
+                // Taproot signatures commit to the outputs spent by all the inputs
+                let prevouts = if self.script_type == ScriptType::P2tr {
+                    tx.input
+                        .iter()
+                        .map(|i| {
+                            let prev_tx = acc_store.get_bitcoin_tx(&i.previous_output.txid)?;
+                            prev_tx
+                                .output
+                                .get(i.previous_output.vout as usize)
+                                .cloned()
+                                .ok_or_else(|| output_not_found(i.previous_output.into()))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?
+                } else {
+                    vec![]
+                };
+
                 for i in 0..tx.input.len() {
                     if request.used_utxos[i].skip_signing {
                         continue;
                     }
+                    check_input_sighash(&sighashes[i], i, tx.output.len(), self.script_type)?;
                     let prev_output = tx.input[i].previous_output;
                     info!("input#{} prev_output:{:?}", i, prev_output);
                     let prev_tx = acc_store.get_bitcoin_tx(&prev_output.txid)?;
-                    let out = prev_tx.output[prev_output.vout as usize].clone();
+                    let out = prev_tx
+                        .output
+                        .get(prev_output.vout as usize)
+                        .cloned()
+                        .ok_or_else(|| output_not_found(prev_output.into()))?;
                     let derivation_path = acc_store.get_path(&out.script_pubkey.into())?;
                     info!(
                         "input#{} prev_output:{:?} derivation_path:{:?}",
                         i, prev_output, derivation_path
                     );
 
-                    let (script_sig, witness) = internal_sign_bitcoin(
-                        &tx,
-                        i,
-                        &xprv,
-                        &derivation_path,
-                        out.value,
-                        self.script_type,
-                        &sighashes[i],
-                    )?;
+                    let (script_sig, witness) = if self.script_type == ScriptType::P2tr {
+                        // SIGHASH_ALL is the only allowed sighash, which is SIGHASH_DEFAULT for taproot
+                        // (checked by check_input_sighash)
+                        internal_sign_taproot(&tx, i, &xprv, derivation_path, &prevouts)?
+                    } else {
+                        internal_sign_bitcoin(
+                            &tx,
+                            i,
+                            &xprv,
+                            derivation_path,
+                            out.value,
+                            self.script_type,
+                            &sighashes[i],
+                        )?
+                    };
 
                     out_tx.input[i].script_sig = script_sig;
                     out_tx.input[i].witness = Witness::from_vec(witness);
@@ -846,17 +1327,22 @@ impl Account {
                     if request.used_utxos[i].skip_signing {
                         continue;
                     }
+                    check_input_sighash(&sighashes[i], i, tx.output.len(), self.script_type)?;
                     let prev_output = tx.input[i].previous_output;
                     info!("input#{} prev_output:{:?}", i, prev_output);
                     let prev_tx = acc_store.get_liquid_tx(&prev_output.txid)?;
-                    let out = prev_tx.output[prev_output.vout as usize].clone();
+                    let out = prev_tx
+                        .output
+                        .get(prev_output.vout as usize)
+                        .cloned()
+                        .ok_or_else(|| output_not_found(prev_output.into()))?;
                     let derivation_path = acc_store.get_path(&out.script_pubkey.into())?;
 
                     let (script_sig, witness) = internal_sign_elements(
                         &tx,
                         i,
                         &xprv,
-                        &derivation_path,
+                        derivation_path,
                         out.value,
                         self.script_type,
                         &sighashes[i],
@@ -883,6 +1369,7 @@ impl Account {
         betx.fee = request.fee;
         betx.create_transaction = request.create_transaction.clone();
         betx.used_utxos = request.used_utxos.clone();
+        betx.change_index = request.change_index;
 
         drop(acc_store);
         drop(store_read);
@@ -977,27 +1464,35 @@ impl Account {
                 continue;
             }
             let mut hashcache = None;
-            for (vin, outpoint) in tx.previous_outputs().iter().enumerate() {
-                let script = acc_store
-                    .all_txs
-                    .get_previous_output_script_pubkey(outpoint)
-                    .expect("prevout to be indexed");
+            let previous_outputs = tx.previous_outputs();
+            let prevouts: Vec<_> = previous_outputs
+                .iter()
+                .map(|outpoint| bitcoin::TxOut {
+                    script_pubkey: acc_store
+                        .all_txs
+                        .get_previous_output_script_pubkey(outpoint)
+                        .expect("prevout to be indexed")
+                        .into_bitcoin(),
+                    value: acc_store
+                        .all_txs
+                        .get_previous_output_value(outpoint, &acc_store.unblinded)
+                        .expect("prevout to have known value"),
+                })
+                .collect();
+            for (vin, prevout) in prevouts.iter().enumerate() {
+                let script = prevout.script_pubkey.clone().into_be();
                 let public_key = match acc_store.paths.get(&script) {
                     Some(path) => self.xpub.derive_pub(&crate::EC, path)?,
                     // We only need to check wallet-owned inputs
                     None => continue,
                 }
                 .to_pub();
-                let value = acc_store
-                    .all_txs
-                    .get_previous_output_value(&outpoint, &acc_store.unblinded)
-                    .expect("own prevout to have known value");
                 if let Err(err) = tx.verify_input_sig(
                     &crate::EC,
                     &mut hashcache,
                     vin,
                     &public_key,
-                    value,
+                    &prevouts,
                     self.script_type,
                 ) {
                     warn!("tx {} verification failed: {:?}", txid, err);
@@ -1028,6 +1523,69 @@ where
     hash_arr.into()
 }
 
+/// Find the transactions replaced by a conflicting one, i.e. spending one of the same outpoints.
+///
+/// `new_txs` are the transactions in the latest history returned by the server with their fee,
+/// among the ones spending the same outpoint the one with the greater fee is kept (the server
+/// can't be relied upon to detect unconfirmed conflicts). `existing_txs` are the wallet
+/// transactions already in the cache, these are replaced by any new transaction they conflict
+/// with, since for sure it happened later.
+///
+/// Returns a map from the replaced txids to the txids replacing them.
+pub(crate) fn find_replaced_txs<'a, Txs>(
+    new_txs: &HashMap<BETxid, (BETransaction, u64)>,
+    existing_txs: Txs,
+) -> HashMap<BETxid, BETxid>
+where
+    Txs: IntoIterator<Item = (BETxid, &'a BETransaction)>,
+{
+    // for every outpoint spent by the new transactions, the one with the greater fee, ties are
+    // broken by txid so that the result does not depend on the iteration order
+    let mut outpoint_spender: HashMap<BEOutPoint, (u64, BETxid)> = HashMap::new();
+    for (txid, (tx, fee)) in new_txs.iter() {
+        for outpoint in tx.previous_outputs() {
+            let spender = outpoint_spender.entry(outpoint).or_insert((*fee, *txid));
+            *spender = (*spender).max((*fee, *txid));
+        }
+    }
+
+    let replaced_by = |txid: &BETxid, tx: &BETransaction| {
+        tx.previous_outputs()
+            .iter()
+            .filter_map(|outpoint| outpoint_spender.get(outpoint))
+            .map(|(_, spender)| *spender)
+            .find(|spender| spender != txid)
+    };
+
+    let mut replaced = HashMap::new();
+    for (txid, (tx, _)) in new_txs.iter() {
+        if let Some(spender) = replaced_by(txid, tx) {
+            replaced.insert(*txid, spender);
+        }
+    }
+    for (txid, tx) in existing_txs {
+        if new_txs.contains_key(&txid) {
+            continue;
+        }
+        if let Some(spender) = replaced_by(&txid, tx) {
+            replaced.insert(txid, spender);
+        }
+    }
+    replaced
+}
+
+/// Error if `path` is the root path of an account other than `account_num`
+pub fn check_account_path_unused(
+    accounts: &HashMap<u32, Account>,
+    account_num: u32,
+    path: &DerivationPath,
+) -> Result<(), Error> {
+    match accounts.values().find(|a| a.path() == path && a.num() != account_num) {
+        Some(account) => Err(Error::AccountPathCollision(path.clone(), account.num())),
+        None => Ok(()),
+    }
+}
+
 /// Return the last (if any) and next account numbers for the given script type
 pub fn get_last_next_account_nums(
     existing: HashSet<u32>,
@@ -1043,14 +1601,45 @@ pub fn get_last_next_account_nums(
     (last_account, next_account)
 }
 
-pub fn get_account_script_purpose(account_num: u32) -> Result<(ScriptType, u32), Error> {
-    Ok(match account_num % NUM_RESERVED_ACCOUNT_TYPES {
-        0 => (ScriptType::P2shP2wpkh, 49),
-        1 => (ScriptType::P2wpkh, 84),
-        2 => (ScriptType::P2pkh, 44),
-        _ => return Err(Error::InvalidSubaccount(account_num)),
-    })
-}
+fn account_descriptor(
+    script_type: ScriptType,
+    path: &DerivationPath,
+    master_xpub_fingerprint: Fingerprint,
+    xpub: &ExtendedPubKey,
+    is_internal: bool,
+) -> Result<String, Error> {
+    let internal_idx = if is_internal {
+        1
+    } else {
+        0
+    };
+    let (prefix, suffix) = match script_type {
+        ScriptType::P2shP2wpkh => ("sh(wpkh", ")"),
+        ScriptType::P2wpkh => ("wpkh", ""),
+        ScriptType::P2pkh => ("pkh", ""),
+        ScriptType::P2tr => ("tr", ""),
+        ScriptType::P2wsh => {
+            return Err(Error::Generic("multisig descriptors require the cosigners keys".into()))
+        }
+    };
+    let path = &path.to_string()[2..];
+    let key_origin = format!("[{}/{}]", master_xpub_fingerprint, path);
+    let desc = format!("{}({}{}/{}/*){}", prefix, key_origin, xpub, internal_idx, suffix);
+    let (desc, _) =
+        gdk_common::miniscript::descriptor::Descriptor::parse_descriptor(&crate::EC, &desc)?;
+    Ok(desc.to_string())
+}
+
+pub fn get_account_script_purpose(account_num: u32) -> Result<(ScriptType, u32), Error> {
+    Ok(match account_num % NUM_RESERVED_ACCOUNT_TYPES {
+        0 => (ScriptType::P2shP2wpkh, 49),
+        1 => (ScriptType::P2wpkh, 84),
+        2 => (ScriptType::P2pkh, 44),
+        3 => (ScriptType::P2tr, 86),
+        4 => (ScriptType::P2wsh, 48),
+        _ => return Err(Error::InvalidSubaccount(account_num)),
+    })
+}
 
 pub fn get_account_derivation(
     account_num: u32,
@@ -1058,11 +1647,22 @@ pub fn get_account_derivation(
 ) -> Result<(ScriptType, DerivationPath), Error> {
     let coin_type = get_coin_type(network_id);
     let (script_type, purpose) = get_account_script_purpose(account_num)?;
+    if script_type == ScriptType::P2tr && matches!(network_id, NetworkId::Elements(_)) {
+        return Err(Error::UnsupportedLiquidTaproot);
+    }
     let bip32_account_num = account_num / NUM_RESERVED_ACCOUNT_TYPES;
 
     // BIP44: m / purpose' / coin_type' / account' / change / address_index
+    // BIP48: m / purpose' / coin_type' / account' / script_type' / change / address_index
+    let script_type_suffix = if script_type == ScriptType::P2wsh {
+        "/2'"
+    } else {
+        ""
+    };
     let path: DerivationPath =
-        format!("m/{}'/{}'/{}'", purpose, coin_type, bip32_account_num).parse().unwrap();
+        format!("m/{}'/{}'/{}'{}", purpose, coin_type, bip32_account_num, script_type_suffix)
+            .parse()
+            .unwrap();
 
     info!("derivation path for account {}: {}", account_num, path);
 
@@ -1122,6 +1722,8 @@ fn bitcoin_address(
         ScriptType::P2shP2wpkh => Address::p2shwpkh(public_key, net).expect("no compressed keys"),
         ScriptType::P2wpkh => Address::p2wpkh(public_key, net).expect("no compressed keys"),
         ScriptType::P2pkh => Address::p2pkh(public_key, net),
+        ScriptType::P2tr => Address::p2tr(&crate::EC, public_key.inner.into(), None, net),
+        ScriptType::P2wsh => unreachable!("multisig addresses are derived from witness scripts"),
     }
 }
 
@@ -1136,6 +1738,8 @@ fn elements_address(
         ScriptType::P2pkh => elements::Address::p2pkh(public_key, None, addr_params),
         ScriptType::P2shP2wpkh => elements::Address::p2shwpkh(public_key, None, addr_params),
         ScriptType::P2wpkh => elements::Address::p2wpkh(public_key, None, addr_params),
+        ScriptType::P2tr => unreachable!("taproot accounts are not created on Liquid"),
+        ScriptType::P2wsh => unreachable!("multisig accounts are not created on Liquid"),
     };
     let script_pubkey = address.script_pubkey();
     let blinding_prv = asset_blinding_key_to_ec_private_key(master_blinding_key, &script_pubkey);
@@ -1143,19 +1747,51 @@ fn elements_address(
     address.to_confidential(blinding_pub)
 }
 
+/// The time of the block confirming a transaction at `height`, or the current time if it is
+/// unconfirmed, in microseconds
+fn tx_timestamp(store: &StoreMeta, height: Option<u32>) -> u64 {
+    height
+        .and_then(|h| store.cache.headers.get(&h))
+        .map(|h| 1_000_000u64.saturating_mul(h.time() as u64))
+        .unwrap_or_else(now)
+}
+
+fn tx_type(tx: &BETransaction, satoshi: &Balances, acc_store: &RawAccountCache) -> TransactionType {
+    let is_redeposit = tx.is_redeposit(&acc_store.paths, &acc_store.all_txs);
+    tx.type_(satoshi, is_redeposit)
+}
+
+fn add_balances(total: &mut Balances, changes: &Balances) {
+    for (asset, value) in changes {
+        *total.entry(asset.clone()).or_insert(0) += value;
+    }
+}
+
+/// Clamp the number of transactions to list to [`MAX_TRANSACTIONS_COUNT`], returning the
+/// clamped count and whether more than that would have been returned
+fn clamp_txs_count(num_txs: usize, first: usize, count: usize) -> (usize, bool) {
+    let clamped = count.min(MAX_TRANSACTIONS_COUNT);
+    (clamped, num_txs.saturating_sub(first).min(count) > clamped)
+}
+
 pub fn discover_account(
     electrum_url: &ElectrumUrl,
     proxy: Option<&str>,
     account_xpub: &ExtendedPubKey,
     script_type: ScriptType,
+    gap_limit: u32,
 ) -> Result<bool, Error> {
     use gdk_common::electrum_client::ElectrumApi;
 
+    if script_type == ScriptType::P2wsh {
+        return Err(Error::Generic("multisig subaccounts cannot be discovered".into()));
+    }
+
     // build our own client so that the subscriptions are dropped at the end
     let client = electrum_url.build_client(proxy, None)?;
 
     let external_xpub = account_xpub.ckd_pub(&crate::EC, 0.into())?;
-    for index in 0..GAP_LIMIT {
+    for index in 0..gap_limit {
         let child_key = external_xpub.ckd_pub(&crate::EC, index.into())?;
         // Every network has the same scriptpubkey
         let script = bitcoin_address(&child_key.to_pub(), script_type, bitcoin::Network::Bitcoin)
@@ -1169,31 +1805,307 @@ pub fn discover_account(
     Ok(false)
 }
 
-#[allow(clippy::cognitive_complexity)]
+/// An output controlled by a private key being swept
+#[derive(Debug, Clone)]
+pub struct SweepUtxo {
+    pub outpoint: bitcoin::OutPoint,
+    pub value: u64,
+    pub script_type: ScriptType,
+}
+
+/// The scripts of the outputs that can be swept with `private_key`
+///
+/// Segwit scripts are only considered for compressed keys.
+pub fn sweep_scripts(
+    private_key: &bitcoin::PrivateKey,
+    network: bitcoin::Network,
+) -> Vec<(ScriptType, bitcoin::Script)> {
+    let script_types: &[ScriptType] = if private_key.compressed {
+        &[ScriptType::P2pkh, ScriptType::P2wpkh, ScriptType::P2shP2wpkh]
+    } else {
+        &[ScriptType::P2pkh]
+    };
+    let public_key = private_key.public_key(&crate::EC);
+    script_types
+        .iter()
+        .map(|t| (*t, bitcoin_address(&public_key, *t, network).script_pubkey()))
+        .collect()
+}
+
+/// Create a transaction sending all the `utxos` to `script_pubkey`, signed with the
+/// `private_key` controlling them, returning it with the fee paid
+pub fn create_sweep_tx(
+    private_key: &bitcoin::PrivateKey,
+    utxos: &[SweepUtxo],
+    script_pubkey: bitcoin::Script,
+    fee_rate: u64,
+) -> Result<(bitcoin::Transaction, u64), Error> {
+    let total: u64 = utxos.iter().map(|u| u.value).sum();
+    let mut tx = bitcoin::Transaction {
+        version: 2,
+        lock_time: bitcoin::PackedLockTime(0),
+        input: utxos
+            .iter()
+            .map(|u| bitcoin::TxIn {
+                previous_output: u.outpoint,
+                sequence: bitcoin::Sequence(0xffff_fffd),
+                ..Default::default()
+            })
+            .collect(),
+        output: vec![bitcoin::TxOut {
+            value: total,
+            script_pubkey,
+        }],
+    };
+    let dust = BEScript::from(&tx.output[0].script_pubkey).dust_value();
+    let sign = |tx: &mut bitcoin::Transaction| -> Result<(), Error> {
+        let unsigned = tx.clone();
+        for (i, utxo) in utxos.iter().enumerate() {
+            let (script_sig, witness) = sign_bitcoin_input(
+                &unsigned,
+                i,
+                private_key,
+                utxo.value,
+                utxo.script_type,
+                bitcoin::EcdsaSighashType::All,
+            )?;
+            tx.input[i].script_sig = script_sig;
+            tx.input[i].witness = Witness::from_vec(witness);
+        }
+        Ok(())
+    };
+
+    // Sign once to get the size, allowing each signature to be 1 byte longer in the final tx
+    sign(&mut tx)?;
+    let weight = tx.weight() + 4 * tx.input.len();
+    let fee = (weight_to_vsize(weight) as u64 * fee_rate + 999) / 1000;
+    if total <= fee + dust {
+        return Err(Error::InsufficientFunds {
+            asset: None,
+            shortfall: fee + dust + 1 - total,
+        });
+    }
+    tx.output[0].value = total - fee;
+    sign(&mut tx)?;
+    Ok((tx, fee))
+}
+
 pub fn create_tx(
     account: &Account,
     request: &mut CreateTransaction,
 ) -> Result<TransactionMeta, Error> {
     info!("create_tx {:?}", request);
 
+    let network = &account.network;
+    let store_read = account.store.read()?;
+    let acc_store = store_read.account_cache(account.num())?;
+    let tip_height = store_read.cache.tip_height();
+
+    let BuiltTx {
+        mut tx,
+        fee: fee_val,
+        effective_fee_rate,
+        changes,
+    } = build_tx(account, request, acc_store, tip_height, store_read.min_fee_rate(), false)?;
+
+    let policy_asset = network.policy_asset_id().ok();
+    tx.add_fee_if_elements(fee_val, &policy_asset)?;
+
+    info!("created tx fee {:?}", fee_val);
+
+    let mut satoshi =
+        tx.my_balance_changes(&acc_store.all_txs, &acc_store.paths, &acc_store.unblinded);
+
+    for (_, v) in satoshi.iter_mut() {
+        *v = v.abs();
+    }
+
+    let used_utxos = account.used_utxos(&tx, acc_store)?;
+    let tx_outputs = account.tx_outputs(&tx, acc_store)?;
+    let mut created_tx = TransactionMeta::new(
+        tx,
+        None,
+        None,
+        satoshi,
+        fee_val,
+        network.id().get_bitcoin_network().unwrap_or(bitcoin::Network::Bitcoin),
+        "outgoing".to_string(),
+        request.clone(),
+        SPVVerifyTxResult::InProgress,
+    );
+    created_tx.effective_fee_rate = Some(effective_fee_rate);
+    // Liquid transactions always have output witnesses, while in Bitcoin the inputs of the
+    // subaccount are signed with witnesses unless it's p2pkh
+    created_tx.has_witness = network.liquid || account.script_type().is_segwit();
+    created_tx.used_unconfirmed_utxos = used_utxos.iter().any(|u| u.block_height == 0);
+    created_tx.used_utxos = used_utxos;
+    // Outputs are listed in the final order, so the index holds whatever sorting was applied
+    if changes > 0 {
+        created_tx.change_index = tx_outputs.iter().find(|o| o.is_change).map(|o| o.pt_idx);
+    }
+    created_tx.transaction_outputs = tx_outputs;
+    created_tx.changes_used = Some(changes as u32);
+    created_tx.addressees_read_only = request.previous_transaction.is_some();
+    info!("returning: {:?}", created_tx);
+
+    Ok(created_tx)
+}
+
+/// Run coin selection and fee computation for `request` without creating the transaction
+pub fn estimate_tx(
+    account: &Account,
+    request: &mut CreateTransaction,
+) -> Result<TransactionEstimate, Error> {
+    info!("estimate_tx {:?}", request);
+
+    let store_read = account.store.read()?;
+    let acc_store = store_read.account_cache(account.num())?;
+    let tip_height = store_read.cache.tip_height();
+
+    let built = build_tx(account, request, acc_store, tip_height, store_read.min_fee_rate(), true)?;
+    let weight = built.tx.estimated_weight(0, account.script_type);
+
+    Ok(TransactionEstimate {
+        fee: built.fee,
+        vsize: weight_to_vsize(weight),
+        weight,
+        used_utxos: account.used_utxos(&built.tx, acc_store)?,
+    })
+}
+
+/// The fee a child of size `child_vsize` must pay for the package with its parent to reach
+/// `fee_rate` (in satoshi/kbyte), given the parent already pays `parent_fee`
+pub fn cpfp_child_fee(
+    parent_fee: u64,
+    parent_vsize: usize,
+    child_vsize: usize,
+    fee_rate: u64,
+) -> u64 {
+    let package_fee = (fee_rate * (parent_vsize + child_vsize) as u64 + 999) / 1000;
+    package_fee.saturating_sub(parent_fee)
+}
+
+/// A transaction with inputs and change outputs selected, but without the explicit fee output
+struct BuiltTx {
+    tx: BETransaction,
+    fee: u64,
+    /// fee rate in satoshi/kbyte, computed on the estimated size of the signed tx
+    effective_fee_rate: u64,
+    changes: usize,
+}
+
+/// The value at or below which an output of `asset` paying to `script` is rejected as dust
+///
+/// On Liquid only the policy asset is subject to the dust rules, as in elements, other assets
+/// have no threshold.
+pub fn dust_threshold(network: &NetworkParameters, script: &BEScript, asset: Option<&str>) -> u64 {
+    match network.id() {
+        NetworkId::Elements(_) if asset.is_some() && asset != network.policy_asset.as_deref() => 0,
+        _ => script.dust_value(),
+    }
+}
+
+/// The locktime for a new transaction to discourage fee sniping, following Bitcoin Core:
+/// the tip height, occasionally moved back by up to 99 blocks so that transactions which were
+/// delayed (e.g. for privacy) do not stand out.
+fn anti_fee_sniping_lock_time<R: rand::Rng>(tip_height: u32, rng: &mut R) -> u32 {
+    if rng.gen_range(0..10) == 0 {
+        tip_height.saturating_sub(rng.gen_range(0..100))
+    } else {
+        tip_height
+    }
+}
+
+/// The fee rate to use for `fee_rate`, the minimum relay fee rate if not set. Fee rates below the
+/// minimum are raised to it if `clamp`, otherwise they are rejected.
+fn fee_rate_floor(fee_rate: Option<u64>, min_fee_rate: u64, clamp: bool) -> Result<u64, Error> {
+    match fee_rate {
+        None => Ok(min_fee_rate),
+        Some(fee_rate) if fee_rate >= min_fee_rate => Ok(fee_rate),
+        Some(fee_rate) if clamp => {
+            info!("fee_rate {} clamped to the minimum {}", fee_rate, min_fee_rate);
+            Ok(min_fee_rate)
+        }
+        Some(_) => Err(Error::FeeRateBelowMinimum(min_fee_rate)),
+    }
+}
+
+#[allow(clippy::cognitive_complexity)]
+fn build_tx(
+    account: &Account,
+    request: &mut CreateTransaction,
+    acc_store: &RawAccountCache,
+    tip_height: u32,
+    min_fee_rate: u64,
+    estimate: bool,
+) -> Result<BuiltTx, Error> {
     let network = &account.network;
 
-    let default_min_fee_rate = match network.id() {
-        NetworkId::Bitcoin(_) => 1000,
-        NetworkId::Elements(_) => 100,
+    let fee_target = match request.fee_absolute {
+        Some(_) if request.fee_rate.is_some() => return Err(Error::InvalidFeeOptions),
+        Some(fee) => {
+            info!("target fee {} satoshi", fee);
+            FeeTarget::Absolute(fee)
+        }
+        None => {
+            let fee_rate_sat_kb =
+                fee_rate_floor(request.fee_rate, min_fee_rate, request.clamp_fee_rate)?;
+            request.fee_rate = Some(fee_rate_sat_kb);
+
+            // convert from satoshi/kbyte to satoshi/byte
+            let fee_rate = (fee_rate_sat_kb as f64) / 1000.0;
+            info!("target fee_rate {:?} satoshi/byte", fee_rate);
+            FeeTarget::Rate(fee_rate)
+        }
     };
-    let fee_rate_sat_kb = request.fee_rate.get_or_insert(default_min_fee_rate);
-    if *fee_rate_sat_kb < default_min_fee_rate {
-        return Err(Error::FeeRateBelowMinimum(default_min_fee_rate));
+
+    if account.is_multisig() {
+        return Err(Error::Generic("multisig subaccounts are watch-only".into()));
     }
 
-    // convert from satoshi/kbyte to satoshi/byte
-    let fee_rate = (*fee_rate_sat_kb as f64) / 1000.0;
-    info!("target fee_rate {:?} satoshi/byte", fee_rate);
+    // Subaccounts have a single script type, so requiring a p2pkh one restricts both coin
+    // selection and change to p2pkh
+    if request.force_legacy {
+        if network.liquid {
+            return Err(Error::Generic("legacy transactions are not supported in liquid".into()));
+        }
+        if account.script_type() != ScriptType::P2pkh {
+            return Err(Error::Generic("legacy transactions require a p2pkh subaccount".into()));
+        }
+    }
+
+    // Inputs always have a non final sequence, so the locktime is enforced
+    if let Some(time) = request.locktime_datetime {
+        if request.previous_transaction.is_some() {
+            return Err(Error::InvalidReplacementRequest);
+        }
+        if !(LOCKTIME_THRESHOLD..=u32::MAX as u64).contains(&time) {
+            return Err(Error::InvalidLockTimeDatetime(time));
+        }
+    }
 
     // TODO put checks into CreateTransaction::validate
     // eagerly check for address validity
+    if request.addressees.iter().filter(|a| a.op_return.is_some()).count() > 1 {
+        return Err(Error::MultipleOpReturn);
+    }
     for addressee in request.addressees.iter() {
+        if let Some(data) = &addressee.op_return {
+            if network.liquid {
+                return Err(Error::Generic("op_return outputs are not supported in liquid".into()));
+            }
+            if !addressee.address.is_empty() {
+                return Err(Error::InvalidAddress);
+            }
+            if addressee.satoshi != 0 {
+                return Err(Error::InvalidAmount);
+            }
+            let len = Vec::<u8>::from_hex(data)?.len();
+            if len > MAX_OP_RETURN_SIZE {
+                return Err(Error::OpReturnTooLarge(len));
+            }
+            continue;
+        }
         match network.id() {
             NetworkId::Bitcoin(network) => {
                 if let Ok(address) = bitcoin::Address::from_str(&addressee.address) {
@@ -1253,16 +2165,13 @@ pub fn create_tx(
     }
 
     let send_all = request.send_all;
-    if !send_all && request.addressees.iter().any(|a| a.satoshi == 0) {
+    if !send_all && request.addressees.iter().any(|a| a.satoshi == 0 && a.op_return.is_none()) {
         return Err(Error::InvalidAmount);
     }
 
     let mut template_tx = None;
     let mut change_addresses = vec![];
 
-    let store_read = account.store.read()?;
-    let acc_store = store_read.account_cache(account.num())?;
-
     // When a previous transaction is replaced, use it as a template for the new transaction
     if let Some(ref prev_txitem) = request.previous_transaction {
         if send_all || network.liquid {
@@ -1292,12 +2201,23 @@ pub fn create_tx(
                 .output
                 .iter()
                 .filter_map(|o| {
+                    if o.script_pubkey.is_op_return() {
+                        let data = match o.script_pubkey.instructions().nth(1) {
+                            Some(Ok(script::Instruction::PushBytes(data))) => data.to_hex(),
+                            _ => String::new(),
+                        };
+                        return Some(AddressAmount {
+                            op_return: Some(data),
+                            ..Default::default()
+                        });
+                    }
                     Some(AddressAmount {
                         address: bitcoin::Address::from_script(&o.script_pubkey, net)
                             .ok()?
                             .to_string(),
                         satoshi: o.value,
                         asset_id: None,
+                        op_return: None,
                     })
                 })
                 .collect();
@@ -1314,22 +2234,20 @@ pub fn create_tx(
             return Err(Error::EmptyAddressees);
         }
 
-        if !send_all && request.addressees.iter().any(|a| a.satoshi == 0) {
+        if !send_all && request.addressees.iter().any(|a| a.satoshi == 0 && a.op_return.is_none()) {
             return Err(Error::InvalidAmount);
         }
 
         if !send_all {
-            for address_amount in request.addressees.iter() {
-                if address_amount.satoshi <= DUST_VALUE {
-                    match network.id() {
-                        NetworkId::Bitcoin(_) => return Err(Error::InvalidAmount),
-                        NetworkId::Elements(_) => {
-                            if address_amount.asset_id == network.policy_asset {
-                                // we apply dust rules for liquid bitcoin as elements do
-                                return Err(Error::InvalidAmount);
-                            }
-                        }
-                    }
+            for address_amount in request.addressees.iter().filter(|a| a.op_return.is_none()) {
+                // for liquid bitcoin the threshold is the one of a confidential output
+                let address =
+                    BEAddress::from_str_with_network(&address_amount.address, network.id())?;
+                let asset = address_amount.asset_id.as_deref();
+                if address_amount.satoshi
+                    <= dust_threshold(network, &address.script_pubkey(), asset)
+                {
+                    return Err(Error::InvalidAmount);
                 }
             }
         }
@@ -1346,12 +2264,22 @@ pub fn create_tx(
     for (_, outpoints) in request.utxos.iter() {
         for o in outpoints {
             let outpoint = o.outpoint(id)?;
-            // TODO: check that the outpoint is not confirmed
             // TODO: check that outpoints are unique
             let utxo = account.txo(&outpoint, acc_store)?;
             if request.confidential_utxos_only && !utxo.is_confidential() {
                 continue;
             }
+            let confirmations = match utxo.height {
+                None | Some(0) => 0,
+                Some(h) => (tip_height + 1).saturating_sub(h),
+            };
+            if confirmations < request.num_confs {
+                // Our own change can be spent while unconfirmed only if explicitly allowed
+                let (is_internal, _) = parse_path(&utxo.user_path.clone().into())?;
+                if !(request.allow_unconfirmed && is_internal) {
+                    continue;
+                }
+            }
             utxos.push(utxo);
         }
     }
@@ -1361,7 +2289,7 @@ pub fn create_tx(
         // send_all works by creating a dummy tx with all utxos, estimate the fee and set the
         // sending amount to `total_amount_utxos - estimated_fee`
         info!("send_all calculating total_amount");
-        if request.addressees.len() != 1 {
+        if request.addressees.len() != 1 || request.addressees[0].op_return.is_some() {
             return Err(Error::SendAll);
         }
         let asset = request.addressees[0].asset_id();
@@ -1377,9 +2305,19 @@ pub fn create_tx(
             dummy_tx
                 .add_output(&out.address, out.satoshi, out.asset_id(), network.id())
                 .map_err(|_| Error::InvalidAddress)?;
-            // estimating 2 satoshi more as estimating less would later result in InsufficientFunds
-            let estimated_fee = dummy_tx.estimated_fee(fee_rate, 0, account.script_type) + 2;
-            total_amount_utxos.checked_sub(estimated_fee).ok_or_else(|| Error::InsufficientFunds)?
+            let estimated_fee = match fee_target {
+                // estimating 2 satoshi more as estimating less would later result in InsufficientFunds
+                FeeTarget::Rate(fee_rate) => {
+                    dummy_tx.estimated_fee(fee_rate, 0, account.script_type) + 2
+                }
+                FeeTarget::Absolute(fee) => fee,
+            };
+            total_amount_utxos.checked_sub(estimated_fee).ok_or_else(|| {
+                Error::InsufficientFunds {
+                    asset,
+                    shortfall: estimated_fee - total_amount_utxos,
+                }
+            })?
         } else {
             total_amount_utxos
         };
@@ -1399,7 +2337,17 @@ pub fn create_tx(
     let mut tx = template_tx.map_or_else(
         || -> Result<_, Error> {
             let mut new_tx = BETransaction::new(network.id());
+            if let Some(time) = request.locktime_datetime {
+                new_tx.set_lock_time(time as u32);
+            } else if request.set_locktime_to_tip {
+                new_tx
+                    .set_lock_time(anti_fee_sniping_lock_time(tip_height, &mut rand::thread_rng()));
+            }
             for out in request.addressees.iter() {
+                if let Some(data) = &out.op_return {
+                    new_tx.add_op_return_output(&Vec::<u8>::from_hex(data)?)?;
+                    continue;
+                }
                 new_tx
                     .add_output(&out.address, out.satoshi, out.asset_id(), network.id())
                     .map_err(|_| Error::InvalidAddress)?;
@@ -1410,13 +2358,24 @@ pub fn create_tx(
     )?;
 
     // STEP 2) add utxos until tx outputs are covered (including fees) or fail
+    let changeless = request.utxo_strategy == UtxoStrategy::BranchAndBound
+        && !send_all
+        && add_changeless_inputs(
+            &mut tx,
+            &utxos,
+            fee_target,
+            network.policy_asset_id().ok(),
+            acc_store,
+            account.script_type,
+        );
+    let no_change = send_all || changeless;
     match request.utxo_strategy {
-        UtxoStrategy::Default => {
+        UtxoStrategy::Default | UtxoStrategy::BranchAndBound => {
             let mut used_utxo: HashSet<BEOutPoint> = HashSet::new();
             loop {
                 let mut needs = tx.needs(
-                    fee_rate,
-                    send_all,
+                    fee_target,
+                    no_change,
                     network.policy_asset_id().ok(),
                     &acc_store.all_txs,
                     &acc_store.unblinded,
@@ -1439,7 +2398,10 @@ pub fn create_tx(
 
                 // sort by biggest utxo, random maybe another option, but it should be deterministically random (purely random breaks send_all algorithm)
                 asset_utxos.sort_by(|a, b| a.satoshi.cmp(&b.satoshi));
-                let utxo = asset_utxos.pop().ok_or(Error::InsufficientFunds)?;
+                let utxo = asset_utxos.pop().ok_or(Error::InsufficientFunds {
+                    asset: current_need.asset,
+                    shortfall: current_need.satoshi,
+                })?;
 
                 match network.id() {
                     NetworkId::Bitcoin(_) => {
@@ -1468,37 +2430,55 @@ pub fn create_tx(
                 tx.add_input(utxo.outpoint.clone());
             }
             let needs = tx.needs(
-                fee_rate,
+                fee_target,
                 send_all,
                 network.policy_asset_id().ok(),
                 &acc_store.all_txs,
                 &acc_store.unblinded,
                 account.script_type,
             );
-            if !needs.is_empty() {
-                return Err(Error::InsufficientFunds);
+            // "policy asset" is last, report the first missing asset
+            if let Some(need) = needs.into_iter().next() {
+                return Err(Error::InsufficientFunds {
+                    asset: need.asset,
+                    shortfall: need.satoshi,
+                });
             }
         }
     }
 
-    // STEP 3) adding change(s)
-    let estimated_fee = tx.estimated_fee(
-        fee_rate,
-        tx.estimated_changes(send_all, &acc_store.all_txs, &acc_store.unblinded),
+    // STEP 3) adding change(s), the excess of a changeless selection is left to the fee
+    let estimated_fee = tx.target_fee(
+        fee_target,
+        tx.estimated_changes(no_change, &acc_store.all_txs, &acc_store.unblinded),
         account.script_type,
     );
-    let changes = tx.changes(
-        estimated_fee,
-        network.policy_asset_id().ok(),
-        &acc_store.all_txs,
-        &acc_store.unblinded,
-    ); // Vec<Change> asset, value
+    // Estimates only need the size of the change outputs, so they pay to the first change
+    // address as a placeholder instead of the next unused ones
+    let change_address_at = |i: u32| -> Result<BEAddress, Error> {
+        let index = if estimate {
+            0
+        } else {
+            acc_store.indexes.internal + i + 1
+        };
+        account.derive_address(true, index)
+    };
+    let changes = if changeless {
+        vec![]
+    } else {
+        // change below the threshold of the change output type is added to the fee
+        let dust_value = change_address_at(0)?.script_pubkey().dust_value();
+        tx.changes(
+            estimated_fee,
+            dust_value,
+            network.policy_asset_id().ok(),
+            &acc_store.all_txs,
+            &acc_store.unblinded,
+        )
+    }; // Vec<Change> asset, value
     for (i, change) in changes.iter().enumerate() {
         let change_address = change_addresses.pop().map_or_else(
-            || -> Result<_, Error> {
-                let change_index = acc_store.indexes.internal + i as u32 + 1;
-                Ok(account.derive_address(true, change_index)?.to_string())
-            },
+            || -> Result<_, Error> { Ok(change_address_at(i as u32)?.to_string()) },
             Ok,
         )?;
         info!(
@@ -1514,37 +2494,190 @@ pub fn create_tx(
     let policy_asset = network.policy_asset_id().ok();
     // recompute exact fee_val from built tx
     let fee_val = tx.fee(&acc_store.all_txs, &acc_store.unblinded, &policy_asset)?;
-    tx.add_fee_if_elements(fee_val, &policy_asset)?;
 
-    info!("created tx fee {:?}", fee_val);
+    // fee rate in satoshi/kbyte, computed on the estimated size of the signed tx
+    let effective_fee_rate =
+        (fee_val as f64 * 1000.0 / tx.estimated_vbytes(0, account.script_type)) as u64;
+    if let FeeTarget::Absolute(fee) = fee_target {
+        if fee_val != fee {
+            // the change would have been dust, thus it has been added to the fee
+            return Err(Error::FeeAbsoluteNotMatched(fee_val));
+        }
+        if effective_fee_rate < min_fee_rate {
+            return Err(Error::FeeRateBelowMinimum(min_fee_rate));
+        }
+    }
+    info!("effective fee_rate {} satoshi/kbyte", effective_fee_rate);
 
-    let mut satoshi =
-        tx.my_balance_changes(&acc_store.all_txs, &acc_store.paths, &acc_store.unblinded);
+    Ok(BuiltTx {
+        tx,
+        fee: fee_val,
+        effective_fee_rate,
+        changes: changes.len(),
+    })
+}
 
-    for (_, v) in satoshi.iter_mut() {
-        *v = v.abs();
+/// Add to `tx` the utxos of a selection that covers its outputs and fee without a change output,
+/// wasting at most the fee cost of the change output, returns whether such selection was found
+///
+/// Only policy asset needs are considered, and utxos sharing a script are selected together in
+/// Bitcoin, as the default selection does.
+fn add_changeless_inputs(
+    tx: &mut BETransaction,
+    utxos: &[Txo],
+    fee_target: FeeTarget,
+    policy_asset: Option<elements::issuance::AssetId>,
+    acc_store: &RawAccountCache,
+    script_type: ScriptType,
+) -> bool {
+    let (all_txs, unblinded) = (&acc_store.all_txs, &acc_store.unblinded);
+    let needs = tx.needs(fee_target, true, policy_asset, all_txs, unblinded, script_type);
+    let target = match &needs[..] {
+        [need] if need.asset == policy_asset => need.satoshi,
+        _ => return false,
+    };
+
+    let mut groups: Vec<Vec<&Txo>> = vec![];
+    for utxo in utxos.iter().filter(|u| u.asset_id() == policy_asset) {
+        let same_script = match tx {
+            BETransaction::Bitcoin(_) => {
+                groups.iter_mut().find(|g| g[0].script_pubkey == utxo.script_pubkey)
+            }
+            BETransaction::Elements(_) => None,
+        };
+        match same_script {
+            Some(group) => group.push(utxo),
+            None => groups.push(vec![utxo]),
+        }
     }
+    let first = match groups.first() {
+        Some(group) => group[0],
+        None => return false,
+    };
 
-    let used_utxos = account.used_utxos(&tx, acc_store)?;
-    let tx_outputs = account.tx_outputs(&tx, acc_store)?;
-    let mut created_tx = TransactionMeta::new(
-        tx,
-        None,
-        None,
-        satoshi,
-        fee_val,
-        network.id().get_bitcoin_network().unwrap_or(bitcoin::Network::Bitcoin),
-        "outgoing".to_string(),
-        request.clone(),
-        SPVVerifyTxResult::InProgress,
-    );
-    created_tx.used_utxos = used_utxos;
-    created_tx.transaction_outputs = tx_outputs;
-    created_tx.changes_used = Some(changes.len() as u32);
-    created_tx.addressees_read_only = request.previous_transaction.is_some();
-    info!("returning: {:?}", created_tx);
+    // the fee cost of adding an input or the change output, all inputs have the same size
+    let base_fee = tx.target_fee(fee_target, 0, script_type);
+    let change_cost = tx.target_fee(fee_target, 1, script_type) - base_fee;
+    let mut one_more = tx.clone();
+    one_more.add_input(first.outpoint.clone());
+    let input_cost = one_more.target_fee(fee_target, 0, script_type) - base_fee;
+
+    let effective_values: Vec<u64> = groups
+        .iter()
+        .map(|g| {
+            let value: u64 = g.iter().map(|u| u.satoshi).sum();
+            value.saturating_sub(input_cost * g.len() as u64)
+        })
+        .collect();
+    let selected = match branch_and_bound(&effective_values, target, change_cost) {
+        Some(selected) => selected,
+        None => return false,
+    };
 
-    Ok(created_tx)
+    // the fee is not exactly linear in the number of inputs, double check the selection
+    let mut candidate = tx.clone();
+    for utxo in selected.iter().flat_map(|i| groups[*i].iter()) {
+        candidate.add_input(utxo.outpoint.clone());
+    }
+    if !candidate.needs(fee_target, true, policy_asset, all_txs, unblinded, script_type).is_empty()
+    {
+        return false;
+    }
+    info!("changeless selection of {} inputs", candidate.input_len());
+    *tx = candidate;
+    true
+}
+
+/// Search the subset of `values` with sum in `[target, target + window]`, returning the indexes
+/// of the one closest to `target`, if found within a bounded number of tries
+fn branch_and_bound(values: &[u64], target: u64, window: u64) -> Option<Vec<usize>> {
+    struct Search<'a> {
+        /// (index, value) sorted by descending value
+        values: &'a [(usize, u64)],
+        /// the sum of the values from each position on
+        remaining: Vec<u64>,
+        target: u64,
+        window: u64,
+        tries: usize,
+        best: Option<(u64, Vec<usize>)>,
+    }
+
+    impl Search<'_> {
+        fn search(&mut self, depth: usize, sum: u64, selected: &mut Vec<usize>) {
+            if self.tries == 0 || self.best.as_ref().map_or(false, |(waste, _)| *waste == 0) {
+                return;
+            }
+            self.tries -= 1;
+            if sum > self.target + self.window {
+                return;
+            }
+            if sum >= self.target {
+                // adding more values would only increase the waste
+                let waste = sum - self.target;
+                if self.best.as_ref().map_or(true, |(best, _)| waste < *best) {
+                    self.best = Some((waste, selected.clone()));
+                }
+                return;
+            }
+            if depth == self.values.len() || sum + self.remaining[depth] < self.target {
+                return;
+            }
+            let (index, value) = self.values[depth];
+            selected.push(index);
+            self.search(depth + 1, sum + value, selected);
+            selected.pop();
+            self.search(depth + 1, sum, selected);
+        }
+    }
+
+    const MAX_TRIES: usize = 100_000;
+
+    let mut sorted: Vec<(usize, u64)> =
+        values.iter().copied().enumerate().filter(|(_, v)| *v > 0).collect();
+    sorted.sort_by_key(|(_, value)| std::cmp::Reverse(*value));
+    let mut remaining = vec![0; sorted.len() + 1];
+    for i in (0..sorted.len()).rev() {
+        remaining[i] = remaining[i + 1] + sorted[i].1;
+    }
+
+    let mut search = Search {
+        values: &sorted,
+        remaining,
+        target,
+        window,
+        tries: MAX_TRIES,
+        best: None,
+    };
+    search.search(0, 0, &mut vec![]);
+    search.best.map(|(_, selected)| selected)
+}
+
+/// Error if `sighash` can't be used to sign input `index` of a transaction with `num_outputs`
+/// The error for an input spending an output missing from its previous transaction
+fn output_not_found(outpoint: BEOutPoint) -> Error {
+    Error::UnspentOutputNotFound(outpoint.txid(), outpoint.vout())
+}
+
+fn check_input_sighash(
+    sighash: &BESigHashType,
+    index: usize,
+    num_outputs: usize,
+    script_type: ScriptType,
+) -> Result<(), Error> {
+    if script_type == ScriptType::P2tr && !sighash.is_all() {
+        return Err(Error::UnsupportedInputSigHash(
+            index,
+            "taproot inputs can only be signed with SIGHASH_ALL",
+        ));
+    }
+    if sighash.is_single() && index >= num_outputs {
+        // the signature would commit to nothing on Bitcoin and it's invalid on Liquid
+        return Err(Error::UnsupportedInputSigHash(
+            index,
+            "SIGHASH_SINGLE requires an output with the same index as the input",
+        ));
+    }
+    Ok(())
 }
 
 fn internal_sign_bitcoin(
@@ -1557,11 +2690,21 @@ fn internal_sign_bitcoin(
     sighash: &BESigHashType,
 ) -> Result<(bitcoin::Script, Vec<Vec<u8>>), Error> {
     let xprv = xprv.derive_priv(&crate::EC, &path).unwrap();
-    let private_key = &xprv.to_priv();
+    let sighash = sighash.into_bitcoin()?;
+    sign_bitcoin_input(tx, input_index, &xprv.to_priv(), value, script_type, sighash)
+}
+
+fn sign_bitcoin_input(
+    tx: &bitcoin::Transaction,
+    input_index: usize,
+    private_key: &bitcoin::PrivateKey,
+    value: u64,
+    script_type: ScriptType,
+    sighash: bitcoin::EcdsaSighashType,
+) -> Result<(bitcoin::Script, Vec<Vec<u8>>), Error> {
     let public_key = &PublicKey::from_private_key(&crate::EC, private_key);
     let script_code = p2pkh_script(public_key);
 
-    let sighash = sighash.into_bitcoin()?;
     let hash = if script_type.is_segwit() {
         SighashCache::new(tx).segwit_signature_hash(input_index, &script_code, value, sighash)?
     } else {
@@ -1577,6 +2720,35 @@ fn internal_sign_bitcoin(
     Ok(prepare_input(&public_key, signature, script_type))
 }
 
+/// Sign a taproot key path spend, `prevouts` are the outputs spent by all the tx inputs
+fn internal_sign_taproot(
+    tx: &bitcoin::Transaction,
+    input_index: usize,
+    xprv: &ExtendedPrivKey,
+    path: &DerivationPath,
+    prevouts: &[bitcoin::TxOut],
+) -> Result<(bitcoin::Script, Vec<Vec<u8>>), Error> {
+    let xprv = xprv.derive_priv(&crate::EC, &path).unwrap();
+    let private_key = &xprv.to_priv();
+    let public_key = &PublicKey::from_private_key(&crate::EC, private_key);
+
+    let hash = SighashCache::new(tx).taproot_key_spend_signature_hash(
+        input_index,
+        &Prevouts::All(prevouts),
+        SchnorrSighashType::Default,
+    )?;
+    let message = Message::from_slice(&hash.into_inner()[..]).unwrap();
+    let keypair =
+        KeyPair::from_secret_key(&crate::EC, &private_key.inner).tap_tweak(&crate::EC, None);
+    let signature = crate::EC.sign_schnorr_with_aux_rand(
+        &message,
+        &keypair.to_inner(),
+        &rand::random::<[u8; 32]>(),
+    );
+
+    Ok(prepare_input(public_key, signature.as_ref().to_vec(), ScriptType::P2tr))
+}
+
 fn internal_sign_elements(
     tx: &elements::Transaction,
     input_index: usize,
@@ -1622,6 +2794,8 @@ fn prepare_input(
     match script_type {
         ScriptType::P2shP2wpkh => (p2shwpkh_script_sig(public_key), vec![signature, pk]),
         ScriptType::P2wpkh => (bitcoin::Script::new(), vec![signature, pk]),
+        ScriptType::P2tr => (bitcoin::Script::new(), vec![signature]),
+        ScriptType::P2wsh => unreachable!("multisig inputs are not signed"),
         ScriptType::P2pkh => (
             script::Builder::new()
                 .push_slice(signature.as_slice())
@@ -1713,20 +2887,370 @@ mod test {
         test_derivation(0, ScriptType::P2shP2wpkh, "m/49'/1'/0'");
         test_derivation(1, ScriptType::P2wpkh, "m/84'/1'/0'");
         test_derivation(2, ScriptType::P2pkh, "m/44'/1'/0'");
+        test_derivation(3, ScriptType::P2tr, "m/86'/1'/0'");
+        test_derivation(4, ScriptType::P2wsh, "m/48'/1'/0'/2'");
 
         // reserved for future use, currently rejected
-        for n in 3..=15 {
+        for n in 5..=15 {
             test_derivation_fails(n);
         }
 
         test_derivation(16, ScriptType::P2shP2wpkh, "m/49'/1'/1'");
         test_derivation(17, ScriptType::P2wpkh, "m/84'/1'/1'");
         test_derivation(18, ScriptType::P2pkh, "m/44'/1'/1'");
-        test_derivation_fails(19);
+        test_derivation(19, ScriptType::P2tr, "m/86'/1'/1'");
+        test_derivation(20, ScriptType::P2wsh, "m/48'/1'/1'/2'");
+        test_derivation_fails(21);
 
         test_derivation(160, ScriptType::P2shP2wpkh, "m/49'/1'/10'");
         test_derivation(161, ScriptType::P2wpkh, "m/84'/1'/10'");
         test_derivation(162, ScriptType::P2pkh, "m/44'/1'/10'");
+        test_derivation(163, ScriptType::P2tr, "m/86'/1'/10'");
+
+        let liquid = NetworkId::Elements(ElementsNetwork::Liquid);
+        assert!(matches!(get_account_derivation(3, liquid), Err(Error::UnsupportedLiquidTaproot)));
+    }
+
+    #[test]
+    fn test_account_descriptor_checksum() {
+        use gdk_common::descriptor::parse_single_sig_descriptor;
+        use gdk_common::miniscript::descriptor::Descriptor;
+
+        // From the Bitcoin Core descriptors documentation
+        let xpub = ExtendedPubKey::from_str("xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL").unwrap();
+        let fingerprint = Fingerprint::from_str("d34db33f").unwrap();
+        let path = DerivationPath::from_str("m/44'/0'/0'").unwrap();
+        let change =
+            account_descriptor(ScriptType::P2pkh, &path, fingerprint, &xpub, true).unwrap();
+        assert_eq!(change, "pkh([d34db33f/44'/0'/0']xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL/1/*)#ml40v0wf");
+
+        let master = ExtendedPrivKey::new_master(bitcoin::Network::Bitcoin, &[1; 32]).unwrap();
+        let fingerprint = master.fingerprint(&crate::EC);
+        let network_id = NetworkId::Bitcoin(bitcoin::Network::Bitcoin);
+        for (account_num, script_type) in
+            [(0, ScriptType::P2shP2wpkh), (17, ScriptType::P2wpkh), (2, ScriptType::P2pkh)]
+        {
+            let (_, path) = get_account_derivation(account_num, network_id).unwrap();
+            let xprv = master.derive_priv(&crate::EC, &path).unwrap();
+            let xpub = ExtendedPubKey::from_priv(&crate::EC, &xprv);
+            let key_origin = format!("[{}/{}]", fingerprint, &path.to_string()[2..]);
+            for (is_internal, chain) in [(false, "/0/*"), (true, "/1/*")] {
+                let desc = account_descriptor(script_type, &path, fingerprint, &xpub, is_internal)
+                    .unwrap();
+                let (body, checksum) = desc.split_once('#').unwrap();
+                assert!(body.contains(&key_origin), "{}", desc);
+                assert!(body.contains(chain), "{}", desc);
+                assert_eq!(checksum.len(), 8);
+
+                // The parser validates the checksum and gives back the same account
+                let (parsed_type, parsed_xpub, bip32_account, parsed_fingerprint) =
+                    parse_single_sig_descriptor(&desc, 0).unwrap();
+                assert_eq!(parsed_type, script_type);
+                assert_eq!(parsed_xpub, xpub);
+                assert_eq!(script_type.account_num(bip32_account), Some(account_num));
+                assert_eq!(parsed_fingerprint, fingerprint);
+
+                // A corrupted checksum is rejected
+                let last = if checksum.ends_with('q') {
+                    'p'
+                } else {
+                    'q'
+                };
+                let corrupted = format!("{}#{}{}", body, &checksum[..7], last);
+                assert!(Descriptor::parse_descriptor(&crate::EC, &corrupted).is_err());
+                assert!(parse_single_sig_descriptor(&corrupted, 0).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_p2tr() {
+        // BIP86 test vectors
+        // seed of the mnemonic "abandon abandon ... about"
+        let seed = Vec::<u8>::from_hex("5eb00bbddcf069084889a8ab9155568165f5c453ccb85e70811aaed6f6da5fc19a5ac40b389cd370d086206dec8aa6c43daea6690f20ad3d8d48b2d2ce9e38e4").unwrap();
+        let master = ExtendedPrivKey::new_master(bitcoin::Network::Bitcoin, &seed).unwrap();
+        let path = DerivationPath::from_str("m/86'/0'/0'").unwrap();
+        let xpub =
+            ExtendedPubKey::from_priv(&crate::EC, &master.derive_priv(&crate::EC, &path).unwrap());
+        let network_id = NetworkId::Bitcoin(bitcoin::Network::Bitcoin);
+        let address = |is_internal: bool, index| {
+            let chain = xpub.ckd_pub(&crate::EC, (is_internal as u32).into()).unwrap();
+            derive_address(&chain, index, ScriptType::P2tr, network_id, None).unwrap().to_string()
+        };
+        let first_receive = "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr";
+        assert_eq!(address(false, 0), first_receive);
+        assert_eq!(
+            address(false, 1),
+            "bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh"
+        );
+        assert_eq!(
+            address(true, 0),
+            "bc1p3qkhfews2uk44qtvauqyr2ttdsw7svhkl9nkm9s9c3x4ax5h60wqwruhk7"
+        );
+
+        // Descriptor round trip
+        let fingerprint = master.fingerprint(&crate::EC);
+        assert_eq!(fingerprint.to_string(), "73c5da0a");
+        assert_eq!(get_account_derivation(3, network_id).unwrap().1, path);
+        let desc = account_descriptor(ScriptType::P2tr, &path, fingerprint, &xpub, false).unwrap();
+        assert!(desc.starts_with("tr([73c5da0a/86'/0'/0']"), "{}", desc);
+        let (script_type, parsed_xpub, bip32_account, parsed_fingerprint) =
+            gdk_common::descriptor::parse_single_sig_descriptor(&desc, 0).unwrap();
+        assert_eq!(script_type, ScriptType::P2tr);
+        assert_eq!(parsed_xpub, xpub);
+        assert_eq!(script_type.account_num(bip32_account), Some(3));
+        assert_eq!(parsed_fingerprint, fingerprint);
+
+        let (desc, _) =
+            gdk_common::miniscript::descriptor::Descriptor::parse_descriptor(&crate::EC, &desc)
+                .unwrap();
+        let derived = desc.at_derivation_index(0).address(bitcoin::Network::Bitcoin).unwrap();
+        assert_eq!(derived.to_string(), first_receive);
+
+        // Sign a key path spend and verify it
+        let prevouts: Vec<_> = (0..2)
+            .map(|index| bitcoin::TxOut {
+                value: 10_000 + index as u64,
+                script_pubkey: derive_address(&xpub, index, ScriptType::P2tr, network_id, None)
+                    .unwrap()
+                    .script_pubkey()
+                    .into_bitcoin(),
+            })
+            .collect();
+        let mut tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: bitcoin::PackedLockTime(0),
+            input: (0..2)
+                .map(|vout| bitcoin::TxIn {
+                    previous_output: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), vout),
+                    ..Default::default()
+                })
+                .collect(),
+            output: vec![],
+        };
+        let account_xprv = master.derive_priv(&crate::EC, &path).unwrap();
+        let input_path = DerivationPath::from_str("m/1").unwrap();
+        let (script_sig, witness) =
+            internal_sign_taproot(&tx, 1, &account_xprv, &input_path, &prevouts).unwrap();
+        assert!(script_sig.is_empty());
+        assert_eq!(witness.len(), 1);
+        assert_eq!(witness[0].len(), 64);
+        tx.input[1].witness = Witness::from_vec(witness);
+
+        let public_key = xpub.derive_pub(&crate::EC, &input_path).unwrap().to_pub();
+        let tx = BETransaction::Bitcoin(tx);
+        let verify = |prevouts: &[bitcoin::TxOut]| {
+            tx.verify_input_sig(&crate::EC, &mut None, 1, &public_key, prevouts, ScriptType::P2tr)
+        };
+        verify(&prevouts).unwrap();
+        // the signature commits to the amounts of all the inputs
+        let mut other_prevouts = prevouts.clone();
+        other_prevouts[0].value += 1;
+        assert!(verify(&other_prevouts).is_err());
+    }
+
+    #[test]
+    fn test_sign_single_anyonecanpay() {
+        let master = ExtendedPrivKey::new_master(bitcoin::Network::Regtest, &[1u8; 32]).unwrap();
+        let path = DerivationPath::from_str("m/0/0").unwrap();
+        let public_key =
+            master.derive_priv(&crate::EC, &path).unwrap().to_priv().public_key(&crate::EC);
+        let sighash = BESigHashType::from_u32(0x83, false).unwrap();
+        assert_eq!(
+            sighash,
+            BESigHashType::Bitcoin(bitcoin::EcdsaSighashType::SinglePlusAnyoneCanPay)
+        );
+        let output = |value| bitcoin::TxOut {
+            value,
+            script_pubkey: bitcoin::Script::new(),
+        };
+        let input = |vout| bitcoin::TxIn {
+            previous_output: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), vout),
+            ..Default::default()
+        };
+        let tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: bitcoin::PackedLockTime(0),
+            input: vec![input(0), input(1)],
+            output: vec![output(1_000), output(2_000)],
+        };
+        let value = 5_000;
+        let prevouts = vec![output(0), output(value)];
+
+        for script_type in [ScriptType::P2wpkh, ScriptType::P2shP2wpkh, ScriptType::P2pkh] {
+            check_input_sighash(&sighash, 1, tx.output.len(), script_type).unwrap();
+            let (script_sig, witness) =
+                internal_sign_bitcoin(&tx, 1, &master, &path, value, script_type, &sighash)
+                    .unwrap();
+            let mut signed = tx.clone();
+            signed.input[1].script_sig = script_sig;
+            signed.input[1].witness = Witness::from_vec(witness);
+            let verify = |tx: &bitcoin::Transaction| {
+                BETransaction::Bitcoin(tx.clone()).verify_input_sig(
+                    &crate::EC,
+                    &mut None,
+                    1,
+                    &public_key,
+                    &prevouts,
+                    script_type,
+                )
+            };
+            verify(&signed).unwrap();
+
+            // other inputs and outputs can be changed
+            let mut other = signed.clone();
+            other.input[0] = input(7);
+            other.input.push(input(8));
+            other.output[0].value += 1;
+            other.output.push(output(3_000));
+            verify(&other).unwrap();
+
+            // the output with the same index can't
+            let mut other = signed.clone();
+            other.output[1].value += 1;
+            assert!(verify(&other).is_err());
+        }
+
+        // no output with the same index
+        assert!(matches!(
+            check_input_sighash(&sighash, 2, 2, ScriptType::P2wpkh),
+            Err(Error::UnsupportedInputSigHash(2, _))
+        ));
+        // taproot is SIGHASH_ALL only
+        assert!(matches!(
+            check_input_sighash(&sighash, 0, 2, ScriptType::P2tr),
+            Err(Error::UnsupportedInputSigHash(0, _))
+        ));
+        let all = BESigHashType::from_u32(0x01, false).unwrap();
+        check_input_sighash(&all, 0, 2, ScriptType::P2tr).unwrap();
+        check_input_sighash(&all, 2, 2, ScriptType::P2wpkh).unwrap();
+        // other sighashes are still rejected
+        assert!(BESigHashType::from_u32(0x03, false).is_err());
+        assert!(BESigHashType::from_u32(0x81, false).is_err());
+    }
+
+    #[test]
+    fn test_create_sweep_tx() {
+        let network = bitcoin::Network::Testnet;
+        let private_key =
+            bitcoin::PrivateKey::from_wif("cVt4o7BGAig1UXywgGSmARhxMdzP5qvQsxKkSsc1XEkw3tDTQFpy")
+                .unwrap();
+        let public_key = private_key.public_key(&crate::EC);
+        let scripts = sweep_scripts(&private_key, network);
+        assert_eq!(scripts.len(), 3);
+        let uncompressed = bitcoin::PrivateKey {
+            compressed: false,
+            ..private_key
+        };
+        assert_eq!(sweep_scripts(&uncompressed, network).len(), 1);
+
+        let utxos: Vec<_> = scripts
+            .iter()
+            .enumerate()
+            .map(|(vout, (script_type, _))| SweepUtxo {
+                outpoint: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), vout as u32),
+                value: 10_000,
+                script_type: *script_type,
+            })
+            .collect();
+        let prevouts: Vec<_> = scripts
+            .iter()
+            .map(|(_, script)| bitcoin::TxOut {
+                value: 10_000,
+                script_pubkey: script.clone(),
+            })
+            .collect();
+        let dest = scripts[0].1.clone();
+        let (tx, fee) = create_sweep_tx(&private_key, &utxos, dest.clone(), 1000).unwrap();
+        assert_eq!(tx.output.len(), 1);
+        assert_eq!(tx.output[0].script_pubkey, dest);
+        assert_eq!(tx.output[0].value + fee, 30_000);
+        assert!(fee >= tx.vsize() as u64 && fee <= tx.vsize() as u64 + 3);
+        assert!(tx.input.iter().all(|i| i.sequence == bitcoin::Sequence(0xffff_fffd)));
+
+        let tx = BETransaction::Bitcoin(tx);
+        for (i, utxo) in utxos.iter().enumerate() {
+            tx.verify_input_sig(&crate::EC, &mut None, i, &public_key, &prevouts, utxo.script_type)
+                .unwrap();
+        }
+
+        let err = create_sweep_tx(&private_key, &utxos[..1], dest, 100_000).unwrap_err();
+        assert!(matches!(err, Error::InsufficientFunds { .. }));
+    }
+
+    #[test]
+    fn test_fee_rate_floor() {
+        assert_eq!(fee_rate_floor(None, 1000, false).unwrap(), 1000);
+        assert_eq!(fee_rate_floor(Some(1000), 1000, false).unwrap(), 1000);
+        assert_eq!(fee_rate_floor(Some(2500), 1000, true).unwrap(), 2500);
+        assert!(matches!(
+            fee_rate_floor(Some(999), 1000, false),
+            Err(Error::FeeRateBelowMinimum(1000))
+        ));
+        assert_eq!(fee_rate_floor(Some(999), 1000, true).unwrap(), 1000);
+        assert_eq!(fee_rate_floor(Some(0), 100, true).unwrap(), 100);
+    }
+
+    #[test]
+    fn test_anti_fee_sniping_lock_time() {
+        let mut rng = rand::thread_rng();
+        let lock_times: Vec<u32> =
+            (0..1000).map(|_| anti_fee_sniping_lock_time(1000, &mut rng)).collect();
+        assert!(lock_times.iter().all(|l| (901..=1000).contains(l)));
+        assert!(lock_times.iter().filter(|&&l| l == 1000).count() > 800);
+        assert_eq!(anti_fee_sniping_lock_time(0, &mut rng), 0);
+
+        let create_tx: CreateTransaction = serde_json::from_str(r#"{"subaccount": 0}"#).unwrap();
+        assert!(create_tx.set_locktime_to_tip);
+        assert!(CreateTransaction::default().set_locktime_to_tip);
+    }
+
+    #[test]
+    fn test_clamp_txs_count() {
+        assert_eq!(clamp_txs_count(10, 0, 30), (30, false));
+        assert_eq!(clamp_txs_count(10, usize::MAX, usize::MAX), (MAX_TRANSACTIONS_COUNT, false));
+        assert_eq!(clamp_txs_count(600, 0, 600), (MAX_TRANSACTIONS_COUNT, true));
+        assert_eq!(clamp_txs_count(600, 100, 600), (MAX_TRANSACTIONS_COUNT, false));
+        assert_eq!(clamp_txs_count(600, 50, usize::MAX), (MAX_TRANSACTIONS_COUNT, true));
+    }
+
+    #[test]
+    fn test_cpfp_child_fee() {
+        // the parent pays 1 sat/vbyte, the package 5 sat/vbyte
+        assert_eq!(cpfp_child_fee(200, 200, 110, 5000), 1350);
+        assert_eq!(cpfp_child_fee(200, 200, 111, 5000), 1355);
+        // rounding up to not fall below the target
+        assert_eq!(cpfp_child_fee(0, 141, 110, 1001), 252);
+        // the parent alone already pays enough
+        assert_eq!(cpfp_child_fee(10_000, 200, 110, 5000), 0);
+        assert_eq!(cpfp_child_fee(1000, 200, 0, 5000), 0);
+    }
+
+    #[test]
+    fn test_branch_and_bound() {
+        let sorted = |selected: Option<Vec<usize>>| {
+            selected.map(|mut s| {
+                s.sort();
+                s
+            })
+        };
+
+        // changeless selections
+        let values = [50_000, 30_000, 20_000, 12_000, 7_000];
+        assert_eq!(sorted(branch_and_bound(&values, 37_000, 0)), Some(vec![1, 4]));
+        assert_eq!(sorted(branch_and_bound(&values, 32_000, 500)), Some(vec![2, 3]));
+        assert_eq!(sorted(branch_and_bound(&values, 38_700, 500)), Some(vec![2, 3, 4]));
+        assert_eq!(sorted(branch_and_bound(&values, 119_000, 0)), Some(vec![0, 1, 2, 3, 4]));
+
+        // waste as little as possible
+        assert_eq!(sorted(branch_and_bound(&[10_300, 10_100, 10_500], 10_000, 400)), Some(vec![1]));
+
+        // fall back to the default selection
+        assert_eq!(branch_and_bound(&values, 1_000, 500), None);
+        assert_eq!(branch_and_bound(&values, 45_000, 500), None);
+        assert_eq!(branch_and_bound(&values, 120_000, 10_000), None);
+        assert_eq!(branch_and_bound(&[], 1_000, 500), None);
+        assert_eq!(branch_and_bound(&[0, 0], 1, 0), None);
     }
 
     #[test]
@@ -1745,6 +3269,50 @@ mod test {
         assert!(xpubs_equivalent(&j, &o).is_err());
     }
 
+    #[test]
+    fn test_find_replaced_txs() {
+        let tx = |vouts: &[u32], value| {
+            BETransaction::Bitcoin(bitcoin::Transaction {
+                version: 2,
+                lock_time: bitcoin::PackedLockTime(0),
+                input: vouts
+                    .iter()
+                    .map(|vout| bitcoin::TxIn {
+                        previous_output: bitcoin::OutPoint::new(bitcoin::Txid::all_zeros(), *vout),
+                        ..Default::default()
+                    })
+                    .collect(),
+                output: vec![bitcoin::TxOut {
+                    value,
+                    script_pubkey: bitcoin::Script::new(),
+                }],
+            })
+        };
+
+        // an RBF pair sharing an input, the replacement paying a greater fee
+        let original = tx(&[0, 1], 9_000);
+        let replacement = tx(&[1], 8_000);
+        let unrelated = tx(&[2], 9_000);
+        let new_txs: HashMap<_, _> =
+            [(&original, 1_000), (&replacement, 2_000), (&unrelated, 1_000)]
+                .iter()
+                .map(|(tx, fee)| (tx.txid(), ((*tx).clone(), *fee)))
+                .collect();
+        let replaced = find_replaced_txs(&new_txs, []);
+        assert_eq!(replaced, HashMap::from([(original.txid(), replacement.txid())]));
+
+        // the original already in the cache, the server returning only the replacement
+        let new_txs: HashMap<_, _> =
+            new_txs.into_iter().filter(|(txid, _)| *txid != original.txid()).collect();
+        let existing = [&original, &unrelated].map(|tx| (tx.txid(), tx));
+        let replaced = find_replaced_txs(&new_txs, existing);
+        assert_eq!(replaced, HashMap::from([(original.txid(), replacement.txid())]));
+
+        // no conflicts
+        let replaced = find_replaced_txs(&HashMap::new(), existing);
+        assert!(replaced.is_empty());
+    }
+
     #[test]
     fn test_script_status() {
         // The following test vectors were generated with an electrs server.
@@ -1780,4 +3348,82 @@ mod test {
             assert_eq!(script_status, expected);
         }
     }
+
+    #[test]
+    fn test_dust_threshold() {
+        use gdk_common::bitcoin::hashes::Hash;
+        let hash = bitcoin::WPubkeyHash::all_zeros();
+        let p2wpkh = bitcoin::Script::new_v0_p2wpkh(&hash);
+        let p2pkh = bitcoin::Script::new_p2pkh(&bitcoin::PubkeyHash::from_inner(hash.into_inner()));
+
+        let mut network = NetworkParameters::default();
+        network.development = true;
+        assert_eq!(dust_threshold(&network, &BEScript::from(&p2wpkh), None), 294);
+        assert_eq!(dust_threshold(&network, &BEScript::from(&p2pkh), None), 546);
+
+        network.liquid = true;
+        let policy_asset = "5ac9f65c0efcc4775e0baec4ec03abdde22473cd3cf33c0419ca290e0751b225";
+        network.policy_asset = Some(policy_asset.to_string());
+        let script = BEScript::from(p2wpkh.into_elements());
+        let policy_dust = script.dust_value();
+        assert!(policy_dust > 294);
+        assert_eq!(dust_threshold(&network, &script, None), policy_dust);
+        assert_eq!(dust_threshold(&network, &script, Some(policy_asset)), policy_dust);
+        // other assets are not subject to dust rules
+        assert_eq!(dust_threshold(&network, &script, Some(&"11".repeat(32))), 0);
+    }
+
+    #[test]
+    fn test_previous_addresses_without_tx_count() {
+        use std::sync::{Arc, RwLock};
+
+        let mut network = NetworkParameters::default();
+        network.development = true;
+        let master = ExtendedPrivKey::new_master(bitcoin::Network::Regtest, &[1; 32]).unwrap();
+        let master_xpub = ExtendedPubKey::from_priv(&crate::EC, &master);
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = StoreMeta::new(dir.path(), &master_xpub, network.id()).unwrap();
+        let store = Arc::new(RwLock::new(store));
+        let fingerprint = master.fingerprint(&crate::EC);
+        let keys = AccountKeys {
+            master_xprv: &Some(master),
+            master_xpub_fingerprint: fingerprint,
+            account_xpub: &None,
+            master_blinding: None,
+        };
+        let account = Account::new(network, keys, store.clone(), 0, true, None).unwrap();
+
+        // index a transaction paying to the first address
+        let script_pubkey = account.derive_address(false, 0).unwrap().script_pubkey();
+        let tx = bitcoin::Transaction {
+            version: 2,
+            lock_time: bitcoin::PackedLockTime::ZERO,
+            input: vec![],
+            output: vec![bitcoin::TxOut {
+                value: 1_000,
+                script_pubkey: script_pubkey.into_bitcoin(),
+            }],
+        };
+        let txid = BETxid::Bitcoin(tx.txid());
+        store
+            .write()
+            .unwrap()
+            .account_cache_mut(0)
+            .unwrap()
+            .all_txs
+            .insert(txid, BETransaction::Bitcoin(tx).into());
+
+        let mut opt = GetPreviousAddressesOpt {
+            count: 1,
+            ..Default::default()
+        };
+        assert!(opt.with_tx_count);
+        assert_eq!(account.get_previous_addresses(&opt).unwrap().list[0].tx_count, Some(1));
+
+        // the transaction index is not scanned, the used address has no count rather than 0
+        opt.with_tx_count = false;
+        let address = &account.get_previous_addresses(&opt).unwrap().list[0];
+        assert_eq!(address.tx_count, None);
+        assert!(serde_json::to_value(address).unwrap().get("tx_count").is_none());
+    }
 }
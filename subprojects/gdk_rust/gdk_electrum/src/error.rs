@@ -1,5 +1,6 @@
 use crate::BETxid;
-use gdk_common::bitcoin::util::bip32::ExtendedPubKey;
+use gdk_common::bitcoin::hashes::hex::ToHex;
+use gdk_common::bitcoin::util::bip32::{DerivationPath, ExtendedPubKey};
 use gdk_common::bitcoin::util::sighash;
 use gdk_common::error::Error as CommonError;
 use gdk_common::{bitcoin, electrum_client, elements, ureq};
@@ -76,7 +77,11 @@ pub enum Error {
     JSON(#[from] serde_json::error::Error),
 
     #[error("insufficient funds")]
-    InsufficientFunds,
+    InsufficientFunds {
+        asset: Option<elements::issuance::AssetId>,
+        /// The missing amount in satoshi, fees included
+        shortfall: u64,
+    },
 
     #[error("invalid address")]
     InvalidAddress,
@@ -90,18 +95,36 @@ pub enum Error {
     #[error("Invalid Electrum URL: {0}")]
     InvalidElectrumUrl(String),
 
+    #[error("the requested fee cannot be matched exactly, it would be {0}")]
+    FeeAbsoluteNotMatched(u64),
+
+    #[error("fee_rate and fee_absolute cannot be set together")]
+    InvalidFeeOptions,
+
     #[error("invalid headers")]
     InvalidHeaders,
 
     #[error(transparent)]
     InvalidKeyIvLength(#[from] block_modes::InvalidKeyIvLength),
 
+    #[error("locktime_datetime {0} is not a unix time in seconds after 1985")]
+    InvalidLockTimeDatetime(u64),
+
     #[error("invalid mnemonic")]
     InvalidMnemonic,
 
     #[error("invalid replacement request fields")]
     InvalidReplacementRequest,
 
+    #[error("replacement fee rate must be higher than {0}sat/kb")]
+    InvalidReplacementFeeRate(u64),
+
+    #[error("package fee rate must be higher than {0}sat/kb")]
+    InvalidCpfpFeeRate(u64),
+
+    #[error("{0}:{1} is not an unspent output of the wallet")]
+    InvalidCpfpOutput(BETxid, u32),
+
     #[error("invalid sighash")]
     InvalidSigHash,
 
@@ -114,12 +137,29 @@ pub enum Error {
     #[error(transparent)]
     InvalidStrUtf8(#[from] std::str::Utf8Error),
 
+    #[error("the private key has no funds to sweep")]
+    NoFundsToSweep,
+
     #[error("invalid subaccount {0}")]
     InvalidSubaccount(u32),
 
+    #[error("address type {0} is not supported by subaccount {1}")]
+    UnsupportedAddressType(String, u32),
+
+    #[error("derivation path {0} is already used by subaccount {1}")]
+    AccountPathCollision(DerivationPath, u32),
+
     #[error(transparent)]
     MiniscriptError(#[from] gdk_common::miniscript::Error),
 
+    #[error(
+        "the electrum server is on another network, genesis hash {found} instead of {expected}"
+    )]
+    MismatchingGenesisHash {
+        expected: String,
+        found: String,
+    },
+
     #[error("Xpubs mismatch ({0} vs {1})")]
     MismatchingXpubs(ExtendedPubKey, ExtendedPubKey),
 
@@ -132,6 +172,12 @@ pub enum Error {
     #[error("non confidential address")]
     NonConfidentialAddress,
 
+    #[error("op_return data is {0} bytes, more than the standard limit of 80")]
+    OpReturnTooLarge(usize),
+
+    #[error("a transaction can have at most one op_return output")]
+    MultipleOpReturn,
+
     #[error("Invalid proxy socket: {0}")]
     InvalidProxySocket(String),
 
@@ -145,6 +191,9 @@ pub enum Error {
     #[error(transparent)]
     PsetBlindError(#[from] elements::pset::PsetBlindError),
 
+    #[error(transparent)]
+    Psbt(#[from] bitcoin::util::psbt::Error),
+
     #[error("RW lock is poisoned: {0}")]
     RwLockPoisonError(String),
 
@@ -163,6 +212,13 @@ pub enum Error {
     #[error("sendall error")]
     SendAll,
 
+    #[error("send_funds failed at the {stage} stage: {error}")]
+    SendFundsFailed {
+        /// One of `create`, `sign` or `broadcast`
+        stage: &'static str,
+        error: Box<Error>,
+    },
+
     #[error(transparent)]
     SerdeCborError(#[from] serde_cbor::error::Error),
 
@@ -178,15 +234,33 @@ pub enum Error {
     #[error("Transaction not found ({0})")]
     TxNotFound(BETxid),
 
+    #[error("Transaction {0} is already confirmed")]
+    TxAlreadyConfirmed(BETxid),
+
+    #[error("Transaction {0} has no unspent output of the wallet")]
+    TxNoOwnedOutput(BETxid),
+
+    #[error("Transaction {0} did not opt into RBF")]
+    TxNotReplaceable(BETxid),
+
     #[error(transparent)]
     UnblindError(#[from] elements::UnblindError),
 
     #[error("unknown call")]
     UnknownCall,
 
+    #[error("{0}:{1} is not an unspent output of the wallet")]
+    UnspentOutputNotFound(BETxid, u32),
+
     #[error("unsupported sighash")]
     UnsupportedSigHash,
 
+    #[error("unsupported sighash for input {0}: {1}")]
+    UnsupportedInputSigHash(usize, &'static str),
+
+    #[error("taproot subaccounts are not supported on Liquid")]
+    UnsupportedLiquidTaproot,
+
     #[error(transparent)]
     UreqError(#[from] ureq::Error),
 
@@ -260,17 +334,24 @@ impl Error {
     pub fn to_gdk_code(&self) -> String {
         // Unhandled error codes:
         // id_no_amount_specified
-        // id_invalid_replacement_fee_rate
-        // id_send_all_requires_a_single_output
 
         use super::Error::*;
         match *self {
-            InsufficientFunds => "id_insufficient_funds",
-            InvalidAddress => "id_invalid_address",
+            SendFundsFailed {
+                ref error,
+                ..
+            } => return error.to_gdk_code(),
+            InsufficientFunds {
+                ..
+            } => "id_insufficient_funds",
+            InvalidAddress | BitcoinAddressError(_) | ElementsAddressError(_) => {
+                "id_invalid_address"
+            }
             NonConfidentialAddress => "id_nonconfidential_addresses_not",
             InvalidAmount => "id_invalid_amount",
             InvalidAssetId => "id_invalid_asset_id",
             FeeRateBelowMinimum(_) => "id_fee_rate_is_below_minimum",
+            InvalidReplacementFeeRate(_) => "id_invalid_replacement_fee_rate",
             // An invalid pin attempt. Should trigger an increment to the
             // caller counter as after 3 consecutive wrong guesses the server
             // will delete the corresponding key. Other errors should leave
@@ -280,8 +361,68 @@ impl Error {
             }
             PinClient(_) => "id_connection_failed",
             EmptyAddressees => "id_no_recipients",
+            MismatchingGenesisHash {
+                ..
+            } => "id_wrong_network_server",
+            SendAll => "id_send_all_requires_a_single",
+            NoFundsToSweep => "id_insufficient_funds",
             _ => "id_unknown",
         }
         .to_string()
     }
+
+    /// Additional data about the error, an empty object if there is none.
+    pub fn details(&self) -> serde_json::Value {
+        use super::Error::*;
+        match *self {
+            InsufficientFunds {
+                asset,
+                shortfall,
+            } => json!({
+                "asset_id": asset.map_or_else(|| "btc".to_string(), |a| a.to_hex()),
+                "shortfall": shortfall,
+            }),
+            FeeRateBelowMinimum(min_fee_rate)
+            | InvalidReplacementFeeRate(min_fee_rate)
+            | InvalidCpfpFeeRate(min_fee_rate) => json!({ "min_fee_rate": min_fee_rate }),
+            FeeAbsoluteNotMatched(fee) => json!({ "fee": fee }),
+            SendFundsFailed {
+                stage,
+                ref error,
+            } => {
+                let mut details = error.details();
+                details["stage"] = stage.into();
+                details
+            }
+            _ => json!({}),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_gdk_code() {
+        let insufficient = Error::InsufficientFunds {
+            asset: None,
+            shortfall: 1000,
+        };
+        assert_eq!(insufficient.to_gdk_code(), "id_insufficient_funds");
+        assert_eq!(insufficient.details()["shortfall"], 1000);
+
+        let address_err = bitcoin::Address::from_str("x").unwrap_err();
+        assert_eq!(Error::from(address_err).to_gdk_code(), "id_invalid_address");
+        assert_eq!(Error::SendAll.to_gdk_code(), "id_send_all_requires_a_single");
+
+        let wrapped = Error::SendFundsFailed {
+            stage: "create",
+            error: Box::new(Error::FeeRateBelowMinimum(1000)),
+        };
+        assert_eq!(wrapped.to_gdk_code(), "id_fee_rate_is_below_minimum");
+        assert_eq!(wrapped.details()["stage"], "create");
+        assert_eq!(Error::Generic("x".into()).to_gdk_code(), "id_unknown");
+    }
 }
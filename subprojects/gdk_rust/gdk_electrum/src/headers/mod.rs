@@ -8,6 +8,7 @@ use gdk_common::aes::{Aes256GcmSiv, Key};
 use gdk_common::be::{BETxid, BETxidConvert};
 use gdk_common::bitcoin::hashes::hex::ToHex;
 use gdk_common::bitcoin::hashes::{sha256, sha256d, Hash};
+use gdk_common::bitcoin::BlockHeader;
 use gdk_common::electrum_client;
 use gdk_common::elements;
 use gdk_common::log::{debug, info, warn};
@@ -83,8 +84,72 @@ impl ParamsMethods for SPVCommonParams {
     }
 }
 
+/// Number of concurrent requests used to download headers when not behind a proxy
+const DOWNLOAD_WORKERS: usize = 4;
+
+/// Where block headers are downloaded from, the electrum server except in tests
+pub(crate) trait HeadersSource: Sync {
+    /// At most `count` headers starting at `start_height`, fewer if the tip is reached
+    fn headers(&self, start_height: usize, count: usize) -> Result<Vec<BlockHeader>, Error>;
+}
+
+impl HeadersSource for Client {
+    fn headers(&self, start_height: usize, count: usize) -> Result<Vec<BlockHeader>, Error> {
+        Ok(self.block_headers(start_height, count)?.headers)
+    }
+}
+
+/// Fetch `count` headers starting at `start_height`, split in chunks requested concurrently by
+/// `workers` threads. Headers are returned in order, stopping at the first incomplete chunk.
+pub(crate) fn fetch_headers<S: HeadersSource>(
+    source: &S,
+    start_height: usize,
+    count: usize,
+    workers: usize,
+) -> Result<Vec<BlockHeader>, Error> {
+    if workers <= 1 || count <= 1 {
+        return source.headers(start_height, count);
+    }
+    let chunk_size = (count + workers - 1) / workers;
+    let chunks = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..count)
+            .step_by(chunk_size)
+            .map(|offset| {
+                let len = chunk_size.min(count - offset);
+                let handle = scope.spawn(move || source.headers(start_height + offset, len));
+                (len, handle)
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|(len, handle)| {
+                let chunk = handle
+                    .join()
+                    .map_err(|_| Error::Generic("headers download panicked".into()))?;
+                Ok((len, chunk?))
+            })
+            .collect::<Result<Vec<_>, Error>>()
+    })?;
+
+    let mut headers = Vec::with_capacity(count);
+    for (len, chunk) in chunks {
+        let complete = chunk.len() == len;
+        headers.extend(chunk);
+        if !complete {
+            // The tip has been reached, following chunks are empty or belong to blocks found
+            // in the meantime, which can't be linked
+            break;
+        }
+    }
+    Ok(headers)
+}
+
 /// Download headers and persist locally, needed to verify tx with `spv_verify_tx`.
 ///
+/// Headers are downloaded concurrently unless a proxy is used, in which case requests are kept
+/// sequential to avoid loading the Tor circuit. In both cases the chain linkage is verified in
+/// order before the headers are persisted.
+///
 /// Used to expose SPV functionality through C interface
 pub fn download_headers(
     input: &SPVDownloadHeadersParams,
@@ -99,7 +164,16 @@ pub fn download_headers(
     let client = input.params.build_client()?;
     let mut chain = input.params.headers_chain()?;
     let headers_to_download = input.headers_to_download.unwrap_or(2016);
-    let headers = client.block_headers(chain.height() as usize + 1, headers_to_download)?.headers;
+    let network_params = &input.params.network;
+    let uses_proxy = network_params.proxy.as_ref().map_or(false, |p| !p.trim().is_empty())
+        || network_params.use_tor.unwrap_or(false);
+    let workers = if uses_proxy {
+        1
+    } else {
+        DOWNLOAD_WORKERS
+    };
+    let headers =
+        fetch_headers(&client, chain.height() as usize + 1, headers_to_download, workers)?;
     info!("height:{} downloaded_headers:{}", chain.height(), headers.len());
     let mut reorg_happened = false;
     if let Err(Error::InvalidHeaders) = chain.push(headers) {
@@ -223,7 +297,13 @@ impl VerifiedCache {
                 let cipher = Aes256GcmSiv::new(Key::from_slice(&key_bytes));
                 let set = match VerifiedCache::read_and_decrypt(&mut filepath, &cipher) {
                     Ok(set) => set,
-                    Err(_) => HashSet::new(),
+                    Err(e) => {
+                        // A corrupted cache is discarded and overwritten at the next flush
+                        if filepath.exists() {
+                            warn!("discarding unreadable verified cache {:?}: {:?}", filepath, e);
+                        }
+                        HashSet::new()
+                    }
                 };
                 let store = Some(Store {
                     filepath,
@@ -277,3 +357,86 @@ impl VerifiedCache {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+    use tempfile::TempDir;
+
+    const TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+
+    fn verify_params(state_dir: &TempDir, encryption_key: Option<&str>) -> SPVVerifyTxParams {
+        // No electrum server is listening, any network access fails
+        let closed = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let closed_url = closed.local_addr().unwrap().to_string();
+        drop(closed);
+
+        let mut params = SPVVerifyTxParams {
+            txid: TXID.into(),
+            height: 170,
+            ..Default::default()
+        };
+        params.params.network.state_dir = state_dir.path().to_str().unwrap().into();
+        params.params.network.electrum_url = Some(closed_url);
+        params.params.network.electrum_tls = Some(false);
+        params.params.encryption_key = encryption_key.map(Into::into);
+        params
+    }
+
+    #[test]
+    fn test_verified_cache() {
+        let temp = TempDir::new().unwrap();
+        let network = NetworkId::Bitcoin(gdk_common::bitcoin::Network::Testnet);
+        let txid = BETxid::from_hex(TXID, network).unwrap();
+        let key = Some("key".to_string());
+
+        let mut cache = VerifiedCache::new(&temp, network, &key);
+        assert!(!cache.contains(&txid, 170).unwrap());
+        cache.write(&txid, 170).unwrap();
+
+        // The cache is persisted
+        let cache = VerifiedCache::new(&temp, network, &key);
+        assert!(cache.contains(&txid, 170).unwrap());
+        assert!(!cache.contains(&txid, 171).unwrap());
+
+        // Another key doesn't see the entries
+        let other_key = Some("other key".to_string());
+        let cache = VerifiedCache::new(&temp, network, &other_key);
+        assert!(!cache.contains(&txid, 170).unwrap());
+
+        // Without a key nothing is persisted
+        let mut cache = VerifiedCache::new(&temp, network, &None);
+        cache.write(&txid, 171).unwrap();
+        let cache = VerifiedCache::new(&temp, network, &None);
+        assert!(!cache.contains(&txid, 171).unwrap());
+
+        // A corrupted cache is discarded
+        let filepath = VerifiedCache::new(&temp, network, &key).store.unwrap().filepath;
+        std::fs::write(&filepath, b"corrupted").unwrap();
+        let mut cache = VerifiedCache::new(&temp, network, &key);
+        assert!(!cache.contains(&txid, 170).unwrap());
+        cache.write(&txid, 170).unwrap();
+        let cache = VerifiedCache::new(&temp, network, &key);
+        assert!(cache.contains(&txid, 170).unwrap());
+    }
+
+    #[test]
+    fn test_spv_verify_tx_cached() {
+        let temp = TempDir::new().unwrap();
+        let params = verify_params(&temp, Some("key"));
+
+        // Without a cache the server is contacted, and it can't be reached
+        assert!(spv_verify_tx(&verify_params(&temp, None)).is_err());
+        assert!(spv_verify_tx(&params).is_err());
+
+        // Once verified, the result comes from the cache without network access
+        let txid = BETxid::from_hex(TXID, params.params.network.id()).unwrap();
+        params.params.verified_cache().unwrap().write(&txid, params.height).unwrap();
+        assert!(matches!(spv_verify_tx(&params).unwrap(), SPVVerifyTxResult::Verified));
+        assert!(matches!(spv_verify_tx(&params).unwrap(), SPVVerifyTxResult::Verified));
+
+        // Callers without a key keep being stateless
+        assert!(spv_verify_tx(&verify_params(&temp, None)).is_err());
+    }
+}
@@ -4,9 +4,25 @@ use crate::error::*;
 
 use electrum_client::{Client, ConfigBuilder, Socks5Config};
 use gdk_common::electrum_client;
+use gdk_common::log::warn;
 use gdk_common::network::NETWORK_REQUEST_TIMEOUT;
+use gdk_common::rand::{thread_rng, Rng};
+use gdk_common::util::Backoff;
+use gdk_common::NetworkParameters;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::iter;
 use std::net::ToSocketAddrs;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default delay before retrying to reach an unreachable electrum server
+pub const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Default upper bound of the delay between reconnection attempts
+pub const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum ElectrumUrl {
@@ -14,31 +30,69 @@ pub enum ElectrumUrl {
     Plaintext(String),
 }
 
+/// The socks5 configuration for `proxy`, if any
+///
+/// With `isolate`, random credentials are set: Tor routes connections with different
+/// credentials through different circuits (stream isolation).
+fn socks5_config(proxy: Option<&str>, isolate: bool) -> Result<Option<Socks5Config>, Error> {
+    let proxy = match proxy {
+        Some(proxy) if !proxy.trim().is_empty() => proxy,
+        _ => return Ok(None),
+    };
+    if proxy.replacen("socks5://", "", 1).to_socket_addrs().is_err() {
+        return Err(Error::InvalidProxySocket(proxy.to_string()));
+    }
+    Ok(Some(if isolate {
+        let random = || format!("{:032x}", thread_rng().gen::<u128>());
+        Socks5Config::with_credentials(proxy, random(), random())
+    } else {
+        Socks5Config::new(proxy)
+    }))
+}
+
 impl ElectrumUrl {
     pub fn build_client(&self, proxy: Option<&str>, timeout: Option<u8>) -> Result<Client, Error> {
-        let mut config = ConfigBuilder::new();
+        self.build_client_inner(proxy, timeout, false)
+    }
 
-        // TODO: add support for socks5 credentials?
-        if let Some(proxy) = proxy {
-            if !proxy.trim().is_empty() {
-                if proxy.replacen("socks5://", "", 1).to_socket_addrs().is_err() {
-                    return Err(Error::InvalidProxySocket(proxy.to_string()));
-                }
-                config = config.socks5(Some(Socks5Config::new(proxy)));
-            }
-        }
+    /// A client going through a Tor circuit not shared with the other clients
+    pub fn build_isolated_client(
+        &self,
+        proxy: Option<&str>,
+        timeout: Option<u8>,
+    ) -> Result<Client, Error> {
+        self.build_client_inner(proxy, timeout, true)
+    }
+
+    fn build_client_inner(
+        &self,
+        proxy: Option<&str>,
+        timeout: Option<u8>,
+        isolate: bool,
+    ) -> Result<Client, Error> {
+        let mut config = ConfigBuilder::new().socks5(socks5_config(proxy, isolate)?);
 
         let timeout = timeout.unwrap_or(NETWORK_REQUEST_TIMEOUT.as_secs() as u8);
 
         config = config.timeout(Some(timeout));
 
-        let (url, config) = match self {
-            ElectrumUrl::Tls(url, validate) => {
-                (format!("ssl://{}", url), config.validate_domain(*validate))
-            }
-            ElectrumUrl::Plaintext(url) => (format!("tcp://{}", url), config),
+        let config = match self {
+            ElectrumUrl::Tls(_, validate) => config.validate_domain(*validate),
+            ElectrumUrl::Plaintext(_) => config,
         };
-        Ok(Client::from_config(&url, config.build())?)
+        Ok(Client::from_config(&self.to_uri(), config.build())?)
+    }
+
+    /// The url with the scheme the electrum client connects with
+    pub fn to_uri(&self) -> String {
+        match self {
+            ElectrumUrl::Tls(url, _) => format!("ssl://{}", url),
+            ElectrumUrl::Plaintext(url) => format!("tcp://{}", url),
+        }
+    }
+
+    pub fn is_tls(&self) -> bool {
+        matches!(self, ElectrumUrl::Tls(_, _))
     }
 
     pub fn url(&self) -> &str {
@@ -54,6 +108,157 @@ impl ElectrumUrl {
             ElectrumUrl::Plaintext(url) => url.ends_with(".onion"),
         }
     }
+
+    /// The same server, connected with or without TLS
+    pub fn with_tls(&self, tls: bool, validate_domain: bool) -> ElectrumUrl {
+        let url = self.url().to_string();
+        if tls {
+            ElectrumUrl::Tls(url, validate_domain)
+        } else {
+            ElectrumUrl::Plaintext(url)
+        }
+    }
+}
+
+/// The primary electrum server followed by its backups
+///
+/// Clones share the server in use, so that a failover is seen by every thread.
+#[derive(Clone, Debug)]
+pub struct ElectrumUrls {
+    urls: Vec<ElectrumUrl>,
+    active: Arc<AtomicUsize>,
+}
+
+impl ElectrumUrls {
+    pub fn new(primary: ElectrumUrl, backups: Vec<ElectrumUrl>) -> Self {
+        ElectrumUrls {
+            urls: iter::once(primary).chain(backups).collect(),
+            active: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// The index in `urls` of the server in use
+    fn active_index(&self) -> usize {
+        self.active.load(Ordering::Relaxed) % self.urls.len()
+    }
+
+    /// The server in use
+    pub fn active(&self) -> &ElectrumUrl {
+        &self.urls[self.active_index()]
+    }
+
+    /// Switch to the next server, wrapping around to the primary after the last backup
+    ///
+    /// Returns the new server in use, or `None` if there are no backups.
+    pub fn rotate(&self) -> Option<&ElectrumUrl> {
+        if self.urls.len() < 2 {
+            return None;
+        }
+        self.active.fetch_add(1, Ordering::Relaxed);
+        Some(self.active())
+    }
+
+    pub fn primary(&self) -> &ElectrumUrl {
+        &self.urls[0]
+    }
+
+    /// Replace the primary server, keeping the backups and the server in use
+    ///
+    /// The result does not share the server in use with `self` and its clones.
+    pub fn with_primary(&self, primary: ElectrumUrl) -> Self {
+        let mut urls = self.urls.clone();
+        urls[0] = primary;
+        ElectrumUrls {
+            urls,
+            active: Arc::new(AtomicUsize::new(self.active.load(Ordering::Relaxed))),
+        }
+    }
+
+    /// The number of servers, including the primary
+    pub fn count(&self) -> usize {
+        self.urls.len()
+    }
+
+    pub fn build_client(&self, proxy: Option<&str>, timeout: Option<u8>) -> Result<Client, Error> {
+        self.active().build_client(proxy, timeout)
+    }
+
+    pub fn url(&self) -> &str {
+        self.active().url()
+    }
+
+    /// Whether the server in use is a backup
+    pub fn is_backup(&self) -> bool {
+        self.active_index() > 0
+    }
+}
+
+/// Clients scanning a single subaccount each, over a Tor circuit of their own, so that the scripts
+/// of different subaccounts are not correlated by the circuit they are requested from
+///
+/// Subaccounts without a client of their own are scanned with the shared client.
+#[derive(Default)]
+pub struct AccountClients {
+    clients: HashMap<u32, Client>,
+}
+
+impl AccountClients {
+    /// Build the missing clients of `accounts`, each with distinct socks5 credentials
+    pub fn connect(
+        &mut self,
+        url: &ElectrumUrl,
+        proxy: Option<&str>,
+        accounts: impl IntoIterator<Item = u32>,
+    ) -> Result<(), Error> {
+        for account_num in accounts {
+            if let Entry::Vacant(entry) = self.clients.entry(account_num) {
+                entry.insert(url.build_isolated_client(proxy, None)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// The client scanning `account_num`, `shared` if it has none
+    pub fn get<'a>(&'a self, account_num: u32, shared: &'a Client) -> &'a Client {
+        self.clients.get(&account_num).unwrap_or(shared)
+    }
+
+    /// Drop the clients, for instance after a failover to another server
+    pub fn clear(&mut self) {
+        self.clients.clear();
+    }
+}
+
+/// The backoff between attempts to reach the electrum servers of `network`
+pub fn network_backoff(network: &NetworkParameters) -> Backoff {
+    let base = network.reconnect_base_delay_ms.map_or(RECONNECT_BASE_DELAY, Duration::from_millis);
+    let max = network.reconnect_max_delay_ms.map_or(RECONNECT_MAX_DELAY, Duration::from_millis);
+    Backoff::new(base, max)
+}
+
+/// Call `connect` until it succeeds, passing the delay before every new attempt to `wait`
+///
+/// `wait` returns `true` if the attempts must stop, in which case `None` is returned.
+pub fn retry_with_backoff<T>(
+    backoff: &mut Backoff,
+    mut connect: impl FnMut() -> Result<T, Error>,
+    mut wait: impl FnMut(Duration) -> bool,
+) -> Option<T> {
+    loop {
+        match connect() {
+            Ok(client) => {
+                backoff.reset();
+                return Some(client);
+            }
+            Err(e) => {
+                let delay = backoff.next_delay();
+                warn!("cannot build client {e:?}, retrying in {delay:?}");
+                if wait(delay) {
+                    return None;
+                }
+            }
+        }
+    }
 }
 
 // Parse the standard <host>:<port>:<t|s> string format,
@@ -90,7 +295,9 @@ mod test {
     use gdk_common::bitcoin::{Address, Network, Transaction};
     use gdk_common::bitcoin::{EcdsaSighashType, Script};
     use gdk_common::scripts::p2shwpkh_script_sig;
+    use std::collections::HashSet;
     use std::str::FromStr;
+    use std::sync::atomic::AtomicBool;
 
     use super::*;
 
@@ -201,6 +408,158 @@ mod test {
         assert_eq!(tx.input[0].script_sig, script_sig);
     }
 
+    #[test]
+    fn test_electrum_urls_rotate() {
+        let primary = ElectrumUrl::Plaintext("primary:50001".into());
+        let backup = ElectrumUrl::Tls("backup:50002".into(), true);
+
+        let urls = ElectrumUrls::new(primary.clone(), vec![]);
+        assert!(urls.rotate().is_none());
+        assert_eq!(urls.url(), "primary:50001");
+
+        let urls = ElectrumUrls::new(primary, vec![backup]);
+        let shared = urls.clone();
+        assert_eq!(urls.count(), 2);
+        assert_eq!(urls.url(), "primary:50001");
+        assert!(!urls.is_backup());
+        assert_eq!(urls.active().to_uri(), "tcp://primary:50001");
+        assert_eq!(urls.rotate().unwrap().url(), "backup:50002");
+        assert_eq!(shared.url(), "backup:50002");
+        assert!(shared.is_backup());
+        assert!(shared.active().is_tls());
+        assert_eq!(shared.active().to_uri(), "ssl://backup:50002");
+        assert_eq!(shared.rotate().unwrap().url(), "primary:50001");
+        assert_eq!(urls.url(), "primary:50001");
+    }
+
+    #[test]
+    fn test_electrum_urls_with_primary_tls() {
+        let primary = ElectrumUrl::Plaintext("primary:50001".into());
+        let backup = ElectrumUrl::Plaintext("backup:50002".into());
+        let urls = ElectrumUrls::new(primary, vec![backup]);
+        urls.rotate();
+
+        let tls = urls.primary().with_tls(true, true);
+        assert!(matches!(&tls, ElectrumUrl::Tls(url, true) if url == "primary:50001"));
+        let switched = urls.with_primary(tls);
+        assert!(matches!(switched.primary(), ElectrumUrl::Tls(_, true)));
+        // the backups and the server in use are kept
+        assert_eq!(switched.count(), 2);
+        assert_eq!(switched.url(), "backup:50002");
+        assert!(matches!(switched.active(), ElectrumUrl::Plaintext(_)));
+        // but not shared anymore with the previous urls
+        switched.rotate();
+        assert_eq!(switched.url(), "primary:50001");
+        assert_eq!(urls.url(), "backup:50002");
+
+        let plaintext = switched.primary().with_tls(false, true);
+        assert!(matches!(&plaintext, ElectrumUrl::Plaintext(url) if url == "primary:50001"));
+    }
+
+    #[test]
+    fn test_socks5_isolation() {
+        assert!(socks5_config(None, true).unwrap().is_none());
+        assert!(socks5_config(Some(" "), true).unwrap().is_none());
+        assert!(socks5_config(Some("notaproxy"), true).is_err());
+
+        let shared = socks5_config(Some("socks5://127.0.0.1:9050"), false).unwrap().unwrap();
+        assert_eq!(shared.addr, "127.0.0.1:9050");
+        assert!(shared.credentials.is_none());
+
+        // every subaccount scan gets its own credentials, thus its own Tor circuit
+        let credentials: HashSet<_> = (0..4)
+            .map(|_| {
+                let config = socks5_config(Some("127.0.0.1:9050"), true).unwrap().unwrap();
+                let credentials = config.credentials.unwrap();
+                assert_ne!(credentials.username, credentials.password);
+                credentials.username
+            })
+            .collect();
+        assert_eq!(credentials.len(), 4);
+    }
+
+    /// A transport failing a given number of times before connecting
+    struct FakeTransport {
+        failures: u32,
+        attempts: u32,
+    }
+
+    impl FakeTransport {
+        fn connect(&mut self) -> Result<(), Error> {
+            self.attempts += 1;
+            if self.attempts > self.failures {
+                Ok(())
+            } else {
+                Err(Error::Generic("unreachable".into()))
+            }
+        }
+    }
+
+    #[test]
+    fn test_retry_with_backoff() {
+        let base = Duration::from_millis(100);
+        let max = Duration::from_millis(1000);
+        let mut backoff = Backoff::new(base, max);
+        let mut transport = FakeTransport {
+            failures: 8,
+            attempts: 0,
+        };
+        let mut delays = vec![];
+        let connected = retry_with_backoff(
+            &mut backoff,
+            || transport.connect(),
+            |delay| {
+                delays.push(delay);
+                false
+            },
+        );
+        assert!(connected.is_some());
+        assert_eq!(transport.attempts, 9);
+
+        let caps = [100, 200, 400, 800, 1000, 1000, 1000, 1000].map(Duration::from_millis);
+        assert_eq!(delays.len(), caps.len());
+        for (delay, cap) in delays.iter().zip(caps) {
+            assert!(
+                *delay >= cap / 2 && *delay <= cap,
+                "{:?} not in the range of {:?}",
+                delay,
+                cap
+            );
+        }
+        // Before reaching the cap, the jitter ranges don't overlap
+        assert!(delays[..5].windows(2).all(|w| w[0] <= w[1]));
+
+        // The delay restarts from the base one after connecting
+        assert!(backoff.next_delay() <= base);
+
+        // Attempts stop as soon as the caller doesn't want to sync
+        let user_wants_to_sync = AtomicBool::new(false);
+        let mut transport = FakeTransport {
+            failures: u32::MAX,
+            attempts: 0,
+        };
+        let connected = retry_with_backoff(
+            &mut backoff,
+            || transport.connect(),
+            |_| !user_wants_to_sync.load(Ordering::Relaxed),
+        );
+        assert!(connected.is_none());
+        assert_eq!(transport.attempts, 1);
+    }
+
+    #[test]
+    fn test_backoff_from_network() {
+        let mut network = NetworkParameters::default();
+        let mut backoff = network_backoff(&network);
+        assert!(backoff.next_delay() <= RECONNECT_BASE_DELAY);
+
+        network.reconnect_base_delay_ms = Some(10);
+        network.reconnect_max_delay_ms = Some(15);
+        let mut backoff = network_backoff(&network);
+        backoff.next_delay();
+        assert!(backoff.next_delay() <= Duration::from_millis(15));
+    }
+
     /// Tests that passing an invalid proxy to `ElectrumUrl::build_client()`
     /// immediately results in an error.
     #[test]
@@ -20,11 +20,14 @@ pub mod session;
 pub mod spv;
 
 use crate::account::{
-    discover_account, get_account_derivation, get_account_script_purpose,
-    get_last_next_account_nums, Account,
+    check_account_path_unused, cpfp_child_fee, create_sweep_tx, discover_account,
+    get_account_derivation, get_account_script_purpose, get_last_next_account_nums, sweep_scripts,
+    Account, AccountKeys, SweepUtxo,
 };
 use crate::error::Error;
-use crate::interface::ElectrumUrl;
+use crate::interface::{
+    network_backoff, retry_with_backoff, AccountClients, ElectrumUrl, ElectrumUrls,
+};
 use crate::store::*;
 
 use gdk_common::bitcoin::hashes::hex::{FromHex, ToHex};
@@ -35,6 +38,7 @@ use gdk_common::{bitcoin, elements};
 
 use gdk_common::model::*;
 use gdk_common::network::NetworkParameters;
+use gdk_common::util::weight_to_vsize;
 use gdk_common::wally::{
     self, asset_blinding_key_from_seed, asset_blinding_key_to_ec_private_key, MasterBlindingKey,
 };
@@ -43,8 +47,9 @@ use gdk_common::{be::*, State};
 use gdk_common::electrum_client::{self, ScriptStatus};
 use gdk_common::elements::confidential::{self, Asset, Nonce};
 use gdk_common::error::Error::{BtcEncodingError, ElementsEncodingError};
-use gdk_common::exchange_rates::{Currency, ExchangeRatesCache};
+use gdk_common::exchange_rates::{Currency, ExchangeRatesCache, ExchangeRatesCacher, Pair};
 use gdk_common::network;
+use gdk_common::unit::{self, AmountUnit};
 use gdk_common::NetworkId;
 use gdk_common::EC;
 use std::collections::hash_map::Entry;
@@ -61,17 +66,22 @@ use crate::headers::ChainOrVerifier;
 use crate::spv::SpvCrossValidator;
 use electrum_client::{Client, ElectrumApi};
 use gdk_common::bitcoin::blockdata::constants::DIFFCHANGE_INTERVAL;
-pub use gdk_common::notification::{NativeNotif, Notification, TransactionNotification};
+pub use gdk_common::notification::{
+    NativeNotif, Notification, RescanNotification, RescanStatus, StoreUpdatedNotification,
+    TransactionNotification, VerificationMismatchNotification,
+};
 use gdk_common::rand::seq::SliceRandom;
 use gdk_common::rand::thread_rng;
 use gdk_common::ureq;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hasher;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread::JoinHandle;
 
 const CROSS_VALIDATION_RATE: u8 = 4; // Once every 4 thread loop runs, or roughly 28 seconds
+const FAILOVER_ATTEMPTS: u32 = 3; // Consecutive failed syncer loops before the next server is used
+const NETWORK_DEBOUNCE: Duration = Duration::from_secs(5); // Time a connection state must last to be notified
 pub const GAP_LIMIT: u32 = 20;
 
 type ScriptStatuses = HashMap<bitcoin::Script, ScriptStatus>;
@@ -99,7 +109,11 @@ pub struct ElectrumSession {
     pub proxy: Option<String>,
     pub timeout: Option<u8>,
     pub network: NetworkParameters,
-    pub url: ElectrumUrl,
+    /// The electrum server in use, with the ones to fail over to
+    pub urls: ElectrumUrls,
+
+    /// Server used to cross-check the unspent outputs returned by `url`, if configured
+    pub verification_url: Option<ElectrumUrl>,
 
     /// Accounts of the wallet
     pub accounts: Arc<RwLock<HashMap<u32, Account>>>,
@@ -124,6 +138,9 @@ pub struct ElectrumSession {
     // True if the last call (to the Electrum server) succeeded
     pub last_network_call_succeeded: Arc<AtomicBool>,
 
+    /// The client of the syncer thread, while it is running
+    syncer_client: Arc<Mutex<Option<Arc<Client>>>>,
+
     pub store: Option<Store>,
 
     /// Master xprv of the signer associated to the session
@@ -152,22 +169,38 @@ pub struct ElectrumSession {
 pub struct StateUpdater {
     current: Arc<AtomicBool>,
     notify: NativeNotif,
+
+    /// The connection state last notified
+    notified: bool,
+
+    /// When the connection state first differed from the notified one, if it does
+    changed_since: Option<Instant>,
 }
 
 impl StateUpdater {
-    fn update_if_needed(&self, new_network_call_succeeded: bool) {
-        let last_network_call_succeeded =
-            self.current.swap(new_network_call_succeeded, Ordering::Relaxed);
-        if last_network_call_succeeded != new_network_call_succeeded {
-            // The second parameter should be taken from the state of the threads, but the current state could
-            // be changed only if threads are running so we use the constant `State::Connected`
-            let state: State = new_network_call_succeeded.into();
-            self.notify.network(state, State::Connected);
+    fn update_if_needed(&mut self, new_network_call_succeeded: bool) {
+        self.update_at(new_network_call_succeeded, Instant::now())
+    }
+
+    /// Record the outcome of a network call made at `now`, notifying the new connection state
+    /// once it's the outcome of the calls of the last `NETWORK_DEBOUNCE`, so that a connection
+    /// flapping briefly doesn't emit a notification for every change.
+    fn update_at(&mut self, new_network_call_succeeded: bool, now: Instant) {
+        self.current.store(new_network_call_succeeded, Ordering::Relaxed);
+        if new_network_call_succeeded == self.notified {
+            self.changed_since = None;
+            return;
+        }
+        let changed_since = *self.changed_since.get_or_insert(now);
+        if now.duration_since(changed_since) >= NETWORK_DEBOUNCE {
+            self.notified = new_network_call_succeeded;
+            self.changed_since = None;
+            self.notify.network_connection(new_network_call_succeeded);
         }
     }
 }
 
-fn socksify(proxy: Option<&str>) -> Option<String> {
+pub fn socksify(proxy: Option<&str>) -> Option<String> {
     const SOCKS5: &str = "socks5://";
     if let Some(proxy) = proxy {
         let trimmed = proxy.trim();
@@ -183,6 +216,24 @@ fn socksify(proxy: Option<&str>) -> Option<String> {
     }
 }
 
+/// Fetch the server features, failing if the server genesis hash is not the one of `network_id`
+fn server_features_checked(
+    client: &Client,
+    network_id: NetworkId,
+) -> Result<electrum_client::ServerFeaturesRes, Error> {
+    let features = client.server_features()?;
+    if let Some(expected) = network_id.genesis_hash() {
+        let found = features.genesis_hash.to_hex();
+        if found != expected {
+            return Err(Error::MismatchingGenesisHash {
+                expected,
+                found,
+            });
+        }
+    }
+    Ok(features)
+}
+
 fn try_get_fee_estimates(client: &Client) -> Result<Vec<FeeEstimate>, Error> {
     let relay_fee = (client.relay_fee()? * 100_000_000.0) as u64;
     let blocks: Vec<usize> = (1..25).collect();
@@ -197,6 +248,39 @@ fn try_get_fee_estimates(client: &Client) -> Result<Vec<FeeEstimate>, Error> {
     Ok(estimates)
 }
 
+/// The mempool fee histogram, pairs of fee rate in sat/vbyte and vsize of the transactions paying
+/// it, from the highest fee rate
+fn try_get_fee_histogram(client: &Client) -> Result<Vec<(f64, f64)>, Error> {
+    let histogram = client.raw_call("mempool.get_fee_histogram", vec![])?;
+    Ok(serde_json::from_value(histogram)?)
+}
+
+/// The fee rate such that `percentile`% of the mempool vsize pays at most that fee rate,
+/// None if the mempool is empty
+fn fee_rate_at_percentile(histogram: &[(f64, f64)], percentile: f64) -> Option<f64> {
+    let total: f64 = histogram.iter().map(|(_, vsize)| vsize).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let target = total * percentile / 100.0;
+    let mut sorted = histogram.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut cumulative = 0.0;
+    for (fee_rate, vsize) in sorted.iter() {
+        cumulative += vsize;
+        if cumulative >= target {
+            return Some(*fee_rate);
+        }
+    }
+    sorted.last().map(|(fee_rate, _)| *fee_rate)
+}
+
+/// The fee estimate nearest to `percentile`, the 100th being the next block and the 0th 24 blocks
+fn fee_estimate_at_percentile(estimates: &[FeeEstimate], percentile: f64) -> Option<f64> {
+    let blocks = 1 + ((100.0 - percentile) / 100.0 * 23.0).round() as usize;
+    estimates.get(blocks).or_else(|| estimates.last()).map(|e| e.0 as f64 / 1000.0)
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct EncryptWithPinDetails {
     /// The PIN to protect the server-provided encryption key with.
@@ -260,29 +344,50 @@ impl ElectrumSession {
         } else {
             // We can't call start_threads() here because not everything is loaded before login,
             // but we need to emit a network notification, to do so we test the electrum server
-            // with a ping to emit a notification
-            let electrum_url = self.url.clone();
+            // with a ping to emit a notification. If the server can't be pinged, the backups are
+            // tried in order. The ping fetches the server features, so a server on another network
+            // is refused before any wallet data is synced from it.
             let proxy = self.proxy.clone();
-            match electrum_url.build_client(proxy.as_deref(), None) {
-                Ok(client) => match client.ping() {
-                    Ok(_) => {
-                        info!("succesfully pinged electrum server {:?}", electrum_url.url());
-                        self.last_network_call_succeeded.store(true, Ordering::Relaxed);
-                        true
-                    }
+            let mut succeeded = false;
+            for attempt in 0..self.urls.count() {
+                if attempt > 0 {
+                    failover(&self.urls, &self.notify);
+                }
+                let electrum_url = self.urls.active();
+                match electrum_url.build_client(proxy.as_deref(), None) {
+                    Ok(client) => match server_features_checked(&client, self.network.id()) {
+                        Ok(_) => {
+                            info!("succesfully pinged electrum server {:?}", electrum_url.url());
+                            self.last_network_call_succeeded.store(true, Ordering::Relaxed);
+                            succeeded = true;
+                            break;
+                        }
+                        Err(
+                            e @ Error::MismatchingGenesisHash {
+                                ..
+                            },
+                        ) => {
+                            warn!("electrum server {:?}: {}", electrum_url.url(), e);
+                            self.user_wants_to_sync.store(false, Ordering::Relaxed);
+                            return Err(e);
+                        }
+                        Err(e) => {
+                            warn!(
+                                "failed to ping electrum server {:?}: {:?}",
+                                electrum_url.url(),
+                                e
+                            );
+                        }
+                    },
                     Err(e) => {
-                        warn!("failed to ping electrum server {:?}: {:?}", electrum_url.url(), e);
-                        false
+                        warn!("build client failed {:?}", e);
                     }
-                },
-                Err(e) => {
-                    warn!("build client failed {:?}", e);
-                    false
                 }
             }
+            succeeded
         };
 
-        self.notify.network(last_network_call_succeeded.into(), State::Connected);
+        self.notify.network_connection(last_network_call_succeeded);
         Ok(())
     }
 
@@ -305,6 +410,43 @@ impl ElectrumSession {
         Ok(())
     }
 
+    /// Switch the primary electrum server between TLS and plaintext, keeping host and port.
+    ///
+    /// If connected, the background threads are restarted on the new connection, which is pinged
+    /// to emit a network notification.
+    pub fn set_electrum_tls(&mut self, opt: &SetElectrumTlsOpt) -> Result<(), Error> {
+        let primary = self.urls.primary();
+        if opt.tls && primary.is_onion() {
+            return Err(Error::Generic("TLS is not supported for onion servers".into()));
+        }
+        let urls = self.urls.with_primary(primary.with_tls(opt.tls, opt.validate_domain));
+        info!("switching electrum server to {:?}", urls.primary());
+
+        let was_syncing = self.user_wants_to_sync.swap(false, Ordering::Relaxed);
+        if was_syncing {
+            self.join_threads();
+        }
+        self.urls = urls;
+        self.network.electrum_tls = Some(opt.tls);
+        self.network.validate_domain = Some(opt.validate_domain);
+
+        let succeeded = self
+            .urls
+            .build_client(self.proxy.as_deref(), self.timeout)
+            .and_then(|client| client.ping().map_err(Error::ClientError))
+            .is_ok();
+        self.last_network_call_succeeded.store(succeeded, Ordering::Relaxed);
+        if was_syncing {
+            self.user_wants_to_sync.store(true, Ordering::Relaxed);
+            if self.master_xpub.is_some() {
+                self.start_threads()?;
+            }
+        }
+        // the server changed even if not syncing, let the caller know if it's reachable
+        self.notify.network_connection(succeeded);
+        Ok(())
+    }
+
     fn inner_decrypt_with_pin(&self, details: &DecryptWithPinDetails) -> Result<Vec<u8>, Error> {
         let agent = self.build_request_agent()?;
 
@@ -362,6 +504,11 @@ impl ElectrumSession {
         Ok(())
     }
 
+    /// The location, size and transaction count of the loaded store, also for watch-only sessions
+    pub fn get_store_info(&self) -> Result<StoreInfo, Error> {
+        self.store()?.read()?.info()
+    }
+
     /// Remove the persisted cache and store
     ///
     /// The actual file removal will happen when the session will be dropped.
@@ -388,6 +535,18 @@ impl ElectrumSession {
         })
     }
 
+    /// Return the fingerprint of the master key, to reference this wallet as a multisig cosigner
+    ///
+    /// For watch-only sessions it's the one of the descriptors used to login.
+    pub fn get_master_fingerprint(&self) -> Result<GetMasterFingerprintResult, Error> {
+        if self.master_xpub.is_none() {
+            return Err(Error::WalletNotInitialized);
+        }
+        Ok(GetMasterFingerprintResult {
+            master_fingerprint: self.master_xpub_fingerprint,
+        })
+    }
+
     pub fn store(&self) -> Result<Store, Error> {
         Ok(self.store.as_ref().ok_or_else(|| Error::StoreNotLoaded)?.clone())
     }
@@ -413,6 +572,8 @@ impl ElectrumSession {
                 discovered: false,
                 is_already_created: true,
                 allow_gaps: true,
+                base_path: None,
+                multisig: account.multisig,
             })?;
         }
 
@@ -420,6 +581,22 @@ impl ElectrumSession {
         self.get_wallet_hash_id()
     }
 
+    /// Export the descriptors of all the accounts, the inverse of `login_wo`
+    pub fn export_watch_only_bundle(&self) -> Result<WatchOnlyBundle, Error> {
+        if self.network.liquid {
+            return Err(Error::Generic("Watch-only export not implemented for Liquid".into()));
+        }
+
+        let mut descriptors = vec![];
+        for account in self.get_accounts()? {
+            descriptors.extend(account.info()?.core_descriptors);
+        }
+        Ok(WatchOnlyBundle {
+            master_fingerprint: self.master_xpub_fingerprint,
+            credentials: WatchOnlyCredentials::CoreDescriptors(descriptors),
+        })
+    }
+
     pub fn login(&mut self, credentials: Credentials) -> Result<LoginData, Error> {
         info!(
             "login {:?} last network call succeeded {:?}",
@@ -466,6 +643,8 @@ impl ElectrumSession {
                 discovered: false,
                 is_already_created: true,
                 allow_gaps: false,
+                base_path: None,
+                multisig: None,
             })?;
         }
 
@@ -477,12 +656,18 @@ impl ElectrumSession {
         while let Some(handle) = self.handles.pop() {
             handle.join().expect("Couldn't join on the associated thread");
         }
+        if let Ok(mut syncer_client) = self.syncer_client.lock() {
+            *syncer_client = None;
+        }
     }
 
     pub fn state_updater(&self) -> Result<StateUpdater, Error> {
         Ok(StateUpdater {
             current: self.last_network_call_succeeded.clone(),
             notify: self.notify.clone(),
+            // the current state has been notified on connect
+            notified: self.last_network_call_succeeded.load(Ordering::Relaxed),
+            changed_since: None,
         })
     }
 
@@ -520,11 +705,11 @@ impl ElectrumSession {
 
         info!(
             "building client, url {}, proxy {}",
-            self.url.url(),
+            self.urls.url(),
             self.proxy.as_ref().unwrap_or(&"".to_string())
         );
 
-        if let Ok(fee_client) = self.url.build_client(self.proxy.as_deref(), None) {
+        if let Ok(fee_client) = self.urls.build_client(self.proxy.as_deref(), None) {
             info!("building built end");
             let fee_store = self.store()?;
             thread::spawn(move || {
@@ -561,7 +746,7 @@ impl ElectrumSession {
                 cross_validator,
             };
 
-            let headers_url = self.url.clone();
+            let headers_url = self.urls.clone();
             let proxy = self.proxy.clone();
             let notify_blocks = self.notify.clone();
             let chunk_size = DIFFCHANGE_INTERVAL as usize;
@@ -573,7 +758,7 @@ impl ElectrumSession {
                 let mut round = 0u8;
 
                 'outer: loop {
-                    if wait_or_close(&user_wants_to_sync, 7) {
+                    if wait_or_close(&user_wants_to_sync, Duration::from_secs(7)) {
                         info!("closing headers thread");
                         break;
                     }
@@ -673,14 +858,19 @@ impl ElectrumSession {
 
         let user_wants_to_sync = self.user_wants_to_sync.clone();
         let notify = self.notify.clone();
-        let url = self.url.clone();
+        let urls = self.urls.clone();
+        let verification_url = self.verification_url.clone();
         let proxy = self.proxy.clone();
+        let isolate_scans = self.isolate_scans();
+        let syncer_client = self.syncer_client.clone();
+        // Delays between attempts to reach an unreachable server
+        let mut backoff = network_backoff(&self.network);
 
         // Only the syncer thread is responsible to send network notification due for the state
         // of the electrum server. This is to avoid intermittent connect/disconnect if one endpoint
         // works while another don't. Once we categorize the disconnection by endpoint we can
         // monitor state of every network call.
-        let state_updater = self.state_updater()?;
+        let mut state_updater = self.state_updater()?;
         let first_sync = self.first_sync.clone();
 
         let syncer_tipper_handle = thread::spawn(move || {
@@ -697,27 +887,49 @@ impl ElectrumSession {
             // So we store the last statuses for each script in this map.
             let mut last_statuses = ScriptStatuses::new();
 
-            let mut client = loop {
-                // In theory this loop is superfluous, because the client is created at the
-                // beginning of the next loop before being used, however, rust compiler thinks
-                // it could be not initialized so we need to initialize it.
-                match url.build_client(proxy.as_deref(), None) {
-                    Ok(new_client) => break new_client,
-                    Err(_) => {
-                        if wait_or_close(&user_wants_to_sync, sync_interval) {
-                            // The thread needs to stop when `user_wants_to_sync` is false.
-                            // below this is done by just breaking from the main loop,
-                            // but here we are out of the loop so we return.
-                            // (If you start the threads without connection you are stuck in this
-                            // loop so it must be handled)
-                            info!(
-                                "closing syncer & tipper thread by breaking build client attempts"
-                            );
-                            return;
+            // On Tor, every subaccount is scanned over a circuit of its own
+            let mut account_clients = AccountClients::default();
+
+            // Consecutive loops without a working connection, used to fail over to the backup
+            // electrum servers
+            let mut failed_attempts = 0;
+
+            // In theory this retry is superfluous, because the client is created at the
+            // beginning of the next loop before being used, however, rust compiler thinks
+            // it could be not initialized so we need to initialize it.
+            let client = retry_with_backoff(
+                &mut backoff,
+                || {
+                    let result = urls.build_client(proxy.as_deref(), None);
+                    if result.is_err() {
+                        failed_attempts += 1;
+                        if failed_attempts >= FAILOVER_ATTEMPTS {
+                            failed_attempts = 0;
+                            failover(&urls, &notify);
                         }
                     }
-                };
+                    result
+                },
+                |delay| wait_or_close(&user_wants_to_sync, delay),
+            );
+            let mut client = match client {
+                Some(client) => Arc::new(client),
+                None => {
+                    // The thread needs to stop when `user_wants_to_sync` is false.
+                    // below this is done by just breaking from the main loop,
+                    // but here we are out of the loop so we return.
+                    // (If you start the threads without connection you are stuck in this
+                    // loop so it must be handled)
+                    info!("closing syncer & tipper thread by breaking build client attempts");
+                    return;
+                }
             };
+            *syncer_client.lock().unwrap() = Some(client.clone());
+
+            // Client for the verification server, built on first use, and the tip height at
+            // which the unspent outputs have last been cross-checked.
+            let mut verifier = None;
+            let mut last_verified_height = None;
 
             let mut avoid_first_wait = true;
             loop {
@@ -726,19 +938,37 @@ impl ElectrumSession {
 
                 if avoid_first_wait {
                     avoid_first_wait = false;
-                } else if wait_or_close(&user_wants_to_sync, sync_interval) {
-                    info!("closing syncer & tipper thread");
-                    break;
+                } else {
+                    // Back off while the server can't be reached, to avoid hammering the network
+                    let delay = if is_connected {
+                        Duration::from_secs(sync_interval.into())
+                    } else {
+                        backoff.next_delay()
+                    };
+                    if wait_or_close(&user_wants_to_sync, delay) {
+                        info!("closing syncer & tipper thread");
+                        break;
+                    }
                 }
 
                 if !is_connected {
-                    match url.build_client(proxy.as_deref(), None) {
-                        Ok(new_client) => client = new_client,
+                    failed_attempts += 1;
+                    if failed_attempts >= FAILOVER_ATTEMPTS {
+                        failed_attempts = 0;
+                        failover(&urls, &notify);
+                    }
+                    match urls.build_client(proxy.as_deref(), None) {
+                        Ok(new_client) => client = Arc::new(new_client),
                         Err(e) => {
                             warn!("cannot build client {e:?}");
                             continue;
                         }
                     };
+                    account_clients.clear();
+                    *syncer_client.lock().unwrap() = Some(client.clone());
+                } else {
+                    failed_attempts = 0;
+                    backoff.reset();
                 }
 
                 let tip_before_sync = match tipper.server_tip(&client) {
@@ -756,7 +986,24 @@ impl ElectrumSession {
                     }
                 };
 
-                match syncer.sync(&client, &mut last_statuses, &user_wants_to_sync) {
+                if isolate_scans {
+                    let account_nums: Vec<u32> =
+                        syncer.accounts.read().unwrap().keys().cloned().collect();
+                    let active = urls.active();
+                    if let Err(e) = account_clients.connect(active, proxy.as_deref(), account_nums)
+                    {
+                        state_updater.update_if_needed(false);
+                        warn!("cannot build subaccount clients {e:?}");
+                        continue;
+                    }
+                }
+
+                match syncer.sync(
+                    &client,
+                    &account_clients,
+                    &mut last_statuses,
+                    &user_wants_to_sync,
+                ) {
                     Ok(tx_ntfs) => {
                         state_updater.update_if_needed(true);
                         // Skip sending transaction notifications if it's the
@@ -796,15 +1043,42 @@ impl ElectrumSession {
                     // consistency.
                     continue;
                 }
-                if let Ok(Some((height, header))) =
-                    tipper.update_cache_if_needed(tip_after_sync.0, tip_after_sync.1)
-                {
-                    notify.block_from_header(height, &header);
+                if let Err(e) = tipper.update_tip(tip_after_sync.0, tip_after_sync.1, &notify) {
+                    warn!("cannot update the tip {:?}", e);
                 }
                 while let Some(ntf) = txs_to_notify.pop() {
                     info!("New tx notification: {}", ntf.txid);
                     notify.updated_txs(&ntf);
                 }
+
+                let height = tip_after_sync.0;
+                match verification_url.as_ref() {
+                    Some(verification_url) if last_verified_height != Some(height) => {
+                        if verifier.is_none() {
+                            match verification_url.build_client(proxy.as_deref(), None) {
+                                Ok(new_client) => verifier = Some(new_client),
+                                Err(e) => warn!("cannot build verification client {e:?}"),
+                            }
+                        }
+                        if let Some(client) = verifier.as_ref() {
+                            match syncer.verify_utxos(client, height) {
+                                Ok(Some(mismatches)) => {
+                                    last_verified_height = Some(height);
+                                    for ntf in mismatches {
+                                        warn!("verification mismatch {ntf:?}");
+                                        notify.verification_mismatch(&ntf);
+                                    }
+                                }
+                                Ok(None) => {}
+                                Err(e) => {
+                                    warn!("error verifying utxos {e:?}");
+                                    verifier = None;
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
             }
         });
         self.handles.push(syncer_tipper_handle);
@@ -822,8 +1096,15 @@ impl ElectrumSession {
 
     pub fn get_receive_address(&self, opt: &GetAddressOpt) -> Result<AddressPointer, Error> {
         debug!("get_receive_address {:?}", opt);
-        let address =
-            self.get_account(opt.subaccount)?.get_next_address(opt.is_internal.unwrap_or(false))?;
+        let account = self.get_account(opt.subaccount)?;
+        let address_type = match opt.address_type.as_deref().filter(|t| !t.is_empty()) {
+            Some(address_type) => address_type.to_string(),
+            None => account.default_address_type()?,
+        };
+        if !account.supports_address_type(&address_type) {
+            return Err(Error::UnsupportedAddressType(address_type, opt.subaccount));
+        }
+        let address = account.get_next_address(opt.is_internal.unwrap_or(false))?;
         debug!("get_address {:?}", address);
         Ok(address)
     }
@@ -872,16 +1153,42 @@ impl ElectrumSession {
         self.get_account(account_num)?.info()
     }
 
+    pub fn get_account_descriptors(
+        &self,
+        opt: &GetAccountDescriptorsOpt,
+    ) -> Result<AccountDescriptors, Error> {
+        self.get_account(opt.subaccount)?.descriptors()
+    }
+
+    /// Export the descriptors and the extended public key of an account, to set up a watch-only
+    /// companion with `login_wo`
+    pub fn export_account(&self, opt: &GetAccountDescriptorsOpt) -> Result<ExportedAccount, Error> {
+        if self.network.liquid {
+            return Err(Error::Generic("Watch-only export not implemented for Liquid".into()));
+        }
+        self.get_account(opt.subaccount)?.export()
+    }
+
     pub fn get_subaccount_root_path(
         &mut self,
         opt: GetAccountPathOpt,
     ) -> Result<GetAccountPathResult, Error> {
-        let (_, path) = get_account_derivation(opt.subaccount, self.network.id())?;
         Ok(GetAccountPathResult {
-            path: path.into(),
+            path: self.account_root_path(opt.subaccount)?.into(),
         })
     }
 
+    /// The derivation path of the account root, the one given at creation if non standard
+    fn account_root_path(&self, account_num: u32) -> Result<DerivationPath, Error> {
+        let (_, path) = get_account_derivation(account_num, self.network.id())?;
+        if let Some(store) = self.store.as_ref() {
+            if let Some(base_path) = store.read()?.get_account_base_path(account_num) {
+                return Ok(base_path.clone());
+            }
+        }
+        Ok(path)
+    }
+
     pub fn create_subaccount(&mut self, opt: CreateAccountOpt) -> Result<AccountInfo, Error> {
         let master_xprv = self.master_xprv.clone();
         let store = self.store()?.clone();
@@ -909,18 +1216,31 @@ impl ElectrumSession {
             }
         }
 
+        let path = match &opt.base_path {
+            Some(base_path) => DerivationPath::from(base_path.clone()),
+            None => self.account_root_path(opt.subaccount)?,
+        };
+        check_account_path_unused(&accounts, opt.subaccount, &path)?;
+
         let account = match accounts.entry(opt.subaccount) {
             Entry::Occupied(entry) => entry.into_mut(),
             Entry::Vacant(entry) => {
+                if let Some(base_path) = opt.base_path {
+                    store.write()?.insert_account_base_path(opt.subaccount, base_path.into())?;
+                }
+                let keys = AccountKeys {
+                    master_xprv: &master_xprv,
+                    master_xpub_fingerprint: self.master_xpub_fingerprint,
+                    account_xpub: &opt.xpub,
+                    master_blinding,
+                };
                 let account = entry.insert(Account::new(
                     network,
-                    &master_xprv,
-                    self.master_xpub_fingerprint,
-                    &opt.xpub, // account xpub
-                    master_blinding,
+                    keys,
                     store,
                     opt.subaccount,
                     opt.discovered,
+                    opt.multisig,
                 )?);
                 if !opt.name.is_empty() {
                     account.set_name(&opt.name)?;
@@ -932,17 +1252,73 @@ impl ElectrumSession {
     }
 
     pub fn discover_subaccount(&self, opt: DiscoverAccountOpt) -> Result<bool, Error> {
-        discover_account(&self.url, self.proxy.as_deref(), &opt.xpub, opt.script_type)
+        let gap_limit = opt.gap_limit.or(self.network.gap_limit).unwrap_or(GAP_LIMIT);
+        discover_account(
+            self.urls.active(),
+            self.proxy.as_deref(),
+            &opt.xpub,
+            opt.script_type,
+            gap_limit,
+        )
     }
 
     pub fn get_next_subaccount(&self, opt: GetNextAccountOpt) -> Result<u32, Error> {
-        let (_, next_account) = get_last_next_account_nums(
-            self.accounts.read()?.keys().copied().collect(),
-            opt.script_type,
-        );
+        let accounts = self.accounts.read()?;
+        let (_, next_account) =
+            get_last_next_account_nums(accounts.keys().copied().collect(), opt.script_type);
+        if let Some(base_path) = opt.base_path {
+            check_account_path_unused(&accounts, next_account, &base_path.into())?;
+        }
         Ok(next_account)
     }
 
+    /// The electrum server in use, which may be a backup after a failover
+    pub fn get_connection_info(&self) -> Result<ConnectionInfo, Error> {
+        let active = self.urls.active();
+        Ok(ConnectionInfo {
+            electrum_url: active.to_uri(),
+            tls: active.is_tls(),
+            onion: active.is_onion(),
+            backup: self.urls.is_backup(),
+            use_tor: self.network.use_tor(),
+            last_network_call_succeeded: self.last_network_call_succeeded.load(Ordering::Relaxed),
+        })
+    }
+
+    /// Measure the round trip time of a `server.ping` request to the electrum server, over the
+    /// connection of the syncer, or a new connection if the session is not syncing.
+    pub fn ping_server(&self) -> Result<PingServerResult, Error> {
+        let syncer_client = self.syncer_client.lock()?.clone();
+        let client = match syncer_client {
+            Some(client) => client,
+            None => Arc::new(self.urls.build_client(self.proxy.as_deref(), self.timeout)?),
+        };
+        let start = Instant::now();
+        client.ping()?;
+        let latency_ms = start.elapsed().as_millis() as u64;
+        info!("pinged electrum server {:?} in {}ms", self.urls.url(), latency_ms);
+        Ok(PingServerResult {
+            latency_ms,
+        })
+    }
+
+    /// Query the features of the electrum server, failing if it is on another network
+    pub fn probe_server_capabilities(&self) -> Result<ServerCapabilities, Error> {
+        let client = self.urls.build_client(self.proxy.as_deref(), self.timeout)?;
+        let features = server_features_checked(&client, self.network.id())?;
+        let genesis_hash = features.genesis_hash.to_hex();
+        // Servers index outputs by script hash, so any script type is indexed, but taproot
+        // subaccounts are not supported on Liquid
+        let supports_taproot = !self.network.liquid;
+        Ok(ServerCapabilities {
+            protocol_version: features.protocol_max,
+            server_version: features.server_version,
+            supports_taproot,
+            pruning: features.pruning.and_then(|p| p.try_into().ok()),
+            genesis_hash,
+        })
+    }
+
     pub fn get_block_height(&self) -> Result<u32, Error> {
         Ok(self.store()?.read()?.cache.tip_height())
     }
@@ -952,6 +1328,7 @@ impl ElectrumSession {
             subaccount: opt.subaccount,
             name: Some(opt.new_name),
             hidden: None,
+            default_address_type: None,
         })
     }
 
@@ -960,6 +1337,7 @@ impl ElectrumSession {
             subaccount: opt.subaccount,
             hidden: Some(opt.hidden),
             name: None,
+            default_address_type: None,
         })
     }
 
@@ -968,8 +1346,15 @@ impl ElectrumSession {
     }
 
     pub fn get_transactions(&self, opt: &GetTransactionsOpt) -> Result<TxsResult, Error> {
-        let txs = self.get_account(opt.subaccount)?.list_tx(opt)?;
-        Ok(TxsResult(txs))
+        let mut txs = self.get_account(opt.subaccount)?.list_tx(opt)?;
+        if opt.with_asset_metadata {
+            if let NetworkId::Elements(network) = self.network.id() {
+                add_asset_metadata(&mut txs.transactions, |asset_id| {
+                    registry_asset_metadata(network.into(), asset_id)
+                });
+            }
+        }
+        Ok(txs)
     }
 
     pub fn get_transaction_hex(&self, txid: &str) -> Result<String, Error> {
@@ -979,11 +1364,154 @@ impl ElectrumSession {
         store.get_tx_entry(&txid).map(|e| e.tx.serialize().to_hex())
     }
 
-    pub fn get_transaction_details(&self, txid: &str) -> Result<TransactionDetails, Error> {
+    /// Unblind the outputs of a Liquid transaction, not necessarily in the wallet, with the
+    /// master blinding key
+    pub fn unblind_transaction(
+        &self,
+        opt: &UnblindTransactionOpt,
+    ) -> Result<UnblindedTransaction, Error> {
+        if !self.network.liquid {
+            return Err(Error::Generic("unblinding is only available on Liquid".into()));
+        }
+        let store = self.store()?;
+        let store = store.read()?;
+        let master_blinding =
+            store.cache.master_blinding.as_ref().ok_or_else(|| Error::MissingMasterBlindingKey)?;
+        let tx = match (&opt.transaction, &opt.txid) {
+            (Some(hex), _) => BETransaction::from_hex(hex, self.network.id())?,
+            (None, Some(txid)) => {
+                store.get_tx_entry(&BETxid::from_hex(txid, self.network.id())?)?.tx.clone()
+            }
+            (None, None) => {
+                return Err(Error::Generic("either transaction or txid is required".into()))
+            }
+        };
+        match tx {
+            BETransaction::Elements(tx) => Ok(unblind_outputs(&tx, master_blinding)),
+            BETransaction::Bitcoin(_) => Err(Error::Generic("not a liquid transaction".into())),
+        }
+    }
+
+    /// The unblinded data of the outputs of a wallet transaction that the wallet could unblind,
+    /// Liquid only
+    pub fn get_transaction_unblinded(&self, txid: &str) -> Result<UnblindedTransaction, Error> {
+        if !self.network.liquid {
+            return Err(Error::Generic("unblinding is only available on Liquid".into()));
+        }
         let txid = BETxid::from_hex(txid, self.network.id())?;
         let store = self.store()?;
         let store = store.read()?;
-        store.get_tx_entry(&txid).map(|e| e.into())
+        match &store.get_tx_entry(&txid)?.tx {
+            BETransaction::Elements(tx) => Ok(wallet_unblinded_outputs(
+                tx,
+                store.cache.accounts.values().map(|acc_store| &acc_store.unblinded),
+            )),
+            BETransaction::Bitcoin(_) => Err(Error::Generic("not a liquid transaction".into())),
+        }
+    }
+
+    /// The range proof and surjection proof of an output of a wallet transaction, Liquid only
+    pub fn get_output_proofs(&self, opt: &GetOutputProofsOpt) -> Result<OutputProofs, Error> {
+        let txid = BETxid::from_hex(&opt.txid, self.network.id())?;
+        let store = self.store()?;
+        let store = store.read()?;
+        let tx = match &store.get_tx_entry(&txid)?.tx {
+            BETransaction::Elements(tx) => tx,
+            BETransaction::Bitcoin(_) => {
+                return Err(Error::Generic("output proofs are only available on Liquid".into()))
+            }
+        };
+        let output = tx.output.get(opt.vout as usize).ok_or_else(|| {
+            Error::Generic(format!("transaction {} has no output {}", opt.txid, opt.vout))
+        })?;
+        Ok(OutputProofs {
+            rangeproof: output
+                .witness
+                .rangeproof
+                .as_ref()
+                .map(|p| p.as_ref().serialize().to_hex())
+                .unwrap_or_default(),
+            surjectionproof: output
+                .witness
+                .surjection_proof
+                .as_ref()
+                .map(|p| p.as_ref().serialize().to_hex())
+                .unwrap_or_default(),
+        })
+    }
+
+    pub fn get_transaction_details(
+        &self,
+        opt: &GetTransactionDetailsOpt,
+    ) -> Result<TransactionDetails, Error> {
+        let txid = BETxid::from_hex(&opt.txhash, self.network.id())?;
+        let store = self.store()?;
+        let (mut details, tx) = {
+            let store = store.read()?;
+            let tx_entry = store.get_tx_entry(&txid)?;
+            (TransactionDetails::from(tx_entry), tx_entry.tx.clone())
+        };
+        if opt.fetch_input_addresses {
+            details.input_addresses = Some(self.input_addresses(&tx)?);
+        }
+        Ok(details)
+    }
+
+    /// The addresses of the outputs spent by `tx`, downloading the previous transactions that
+    /// are not in the cache
+    fn input_addresses(&self, tx: &BETransaction) -> Result<Vec<String>, Error> {
+        let store = self.store()?;
+        let txs_to_download: Vec<bitcoin::Txid> = {
+            let store = store.read()?;
+            tx.previous_output_txids()
+                .into_iter()
+                .filter(|txid| {
+                    store.get_tx_entry(txid).is_err()
+                        && !store.cache.input_prevouts.contains_key(txid)
+                })
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .map(BETxidConvert::into_bitcoin)
+                .collect()
+        };
+        if !txs_to_download.is_empty() {
+            info!("downloading {} previous transactions", txs_to_download.len());
+            let client = self.urls.build_client(self.proxy.as_deref(), self.timeout)?;
+            let txs_bytes_downloaded = client.batch_transaction_get_raw(txs_to_download.iter())?;
+            let mut store = store.write()?;
+            for vec in txs_bytes_downloaded {
+                let prev_tx = BETransaction::deserialize(&vec, self.network.id())?;
+                store.cache.input_prevouts.insert(prev_tx.txid(), prev_tx);
+            }
+        }
+
+        let store = store.read()?;
+        let addresses = tx
+            .previous_outputs()
+            .iter()
+            .map(|outpoint| {
+                let txid = outpoint.txid();
+                let prev_tx = match store.get_tx_entry(&txid) {
+                    Ok(tx_entry) => Some(&tx_entry.tx),
+                    Err(_) => store.cache.input_prevouts.get(&txid),
+                };
+                prev_tx
+                    .and_then(|prev_tx| prev_tx.output_address(outpoint.vout(), self.network.id()))
+                    .unwrap_or_default()
+            })
+            .collect();
+        Ok(addresses)
+    }
+
+    pub fn validate_address(
+        &self,
+        opt: &ValidateAddressOpt,
+    ) -> Result<ValidateAddressResult, Error> {
+        Ok(BEAddress::validate(&opt.address, self.network.id()))
+    }
+
+    pub fn parse_uri(&self, opt: &ParseUriOpt) -> Result<ParsedUri, Error> {
+        Ok(gdk_common::bip21::parse_uri(&opt.uri, self.network.id())?)
     }
 
     pub fn get_scriptpubkey_data(&self, script_pubkey: &str) -> Result<ScriptPubKeyData, Error> {
@@ -1007,15 +1535,22 @@ impl ElectrumSession {
         return Err(Error::ScriptPubkeyNotFound);
     }
 
+    /// The key of the policy asset in the balances, "btc" on bitcoin
+    fn policy_asset(&self) -> Result<String, Error> {
+        match self.network.id() {
+            NetworkId::Bitcoin(_) => Ok("btc".to_string()),
+            NetworkId::Elements(_) => self
+                .network
+                .policy_asset
+                .clone()
+                .ok_or_else(|| Error::Generic("no policy asset".into())),
+        }
+    }
+
     pub fn get_balance(&self, opt: &GetBalanceOpt) -> Result<Balances, Error> {
         let mut result = HashMap::new();
         // bitcoin balance is always set even if 0
-        match self.network.id() {
-            NetworkId::Bitcoin(_) => result.entry("btc".to_string()).or_insert(0),
-            NetworkId::Elements(_) => {
-                result.entry(self.network.policy_asset.as_ref().unwrap().clone()).or_insert(0)
-            }
-        };
+        result.entry(self.policy_asset()?).or_insert(0);
 
         // Compute balance from get_unspent_outputs
         let opt = GetUnspentOpt {
@@ -1023,6 +1558,9 @@ impl ElectrumSession {
             num_confs: Some(opt.num_confs),
             confidential_utxos_only: opt.confidential_utxos_only,
             all_coins: None,
+            // frozen outputs are still part of the balance
+            include_frozen: Some(true),
+            ..Default::default()
         };
         let unspent_outputs = self.get_unspent_outputs(&opt)?;
         for (asset, utxos) in unspent_outputs.0.iter() {
@@ -1033,16 +1571,216 @@ impl ElectrumSession {
         Ok(result)
     }
 
+    pub fn get_dust_threshold(&self, opt: &GetDustThresholdOpt) -> Result<DustThreshold, Error> {
+        let script = match (&opt.address, opt.subaccount) {
+            (Some(address), None) => BEAddress::from_str_with_network(address, self.network.id())
+                .map_err(|_| Error::InvalidAddress)?
+                .script_pubkey(),
+            (None, Some(subaccount)) => {
+                self.get_account(subaccount)?.derive_address(true, 0)?.script_pubkey()
+            }
+            _ => {
+                return Err(Error::Generic(
+                    "get_dust_threshold: either address or subaccount must be set".into(),
+                ))
+            }
+        };
+        Ok(DustThreshold {
+            dust_threshold: account::dust_threshold(
+                &self.network,
+                &script,
+                opt.asset_id.as_deref(),
+            ),
+        })
+    }
+
+    /// The balance of a subaccount as of a past block height, for point-in-time reporting
+    pub fn get_balance_at_height(&self, opt: &GetBalanceAtHeightOpt) -> Result<Balances, Error> {
+        let mut balance = self.get_account(opt.subaccount)?.balance_at_height(opt.height)?;
+        // bitcoin balance is always set even if 0
+        let policy_asset = self.policy_asset()?;
+        balance.entry(policy_asset).or_insert(0);
+        Ok(balance)
+    }
+
+    /// The balance of the policy asset only, so that callers don't need to know its id
+    pub fn get_policy_asset_balance(
+        &self,
+        opt: &GetBalanceOpt,
+    ) -> Result<PolicyAssetBalance, Error> {
+        let policy_asset = self.policy_asset()?;
+        let balances = self.get_balance(opt)?;
+        Ok(PolicyAssetBalance {
+            satoshi: balances.get(&policy_asset).copied().unwrap_or(0) as u64,
+        })
+    }
+
+    /// Like `get_balance`, but with each asset balance split between confidential and
+    /// unconfidential outputs
+    pub fn get_balance_detailed(&self, opt: &GetBalanceOpt) -> Result<BalancesDetailed, Error> {
+        let mut result = BalancesDetailed::new();
+        let default_asset = self.policy_asset()?;
+        result.entry(default_asset).or_default();
+
+        let opt = GetUnspentOpt {
+            subaccount: opt.subaccount,
+            num_confs: Some(opt.num_confs),
+            confidential_utxos_only: opt.confidential_utxos_only,
+            all_coins: None,
+            // frozen outputs are still part of the balance
+            include_frozen: Some(true),
+            ..Default::default()
+        };
+        let unspent_outputs = self.get_unspent_outputs(&opt)?;
+        for (asset, utxos) in unspent_outputs.0.iter() {
+            let detail = result.entry(asset.clone()).or_default();
+            for utxo in utxos {
+                if utxo.is_blinded.unwrap_or(false) {
+                    detail.confidential_satoshi += utxo.satoshi;
+                } else {
+                    detail.unconfidential_satoshi += utxo.satoshi;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// The balances of all the subaccounts summed, hidden ones are skipped unless
+    /// `opt.include_hidden`
+    pub fn get_wallet_summary(&self, opt: &GetWalletSummaryOpt) -> Result<WalletSummary, Error> {
+        let mut summary = WalletSummary::default();
+        for account in self.get_accounts()? {
+            let hidden = self
+                .store()?
+                .read()?
+                .get_account_settings(account.num())
+                .map_or(false, |s| s.hidden);
+            if hidden && !opt.include_hidden {
+                continue;
+            }
+            for (num_confs, balances) in [(0, &mut summary.total), (1, &mut summary.confirmed)] {
+                let opt = GetBalanceOpt {
+                    subaccount: account.num(),
+                    num_confs,
+                    confidential_utxos_only: None,
+                    policy_asset_only: false,
+                };
+                for (asset, satoshi) in self.get_balance(&opt)? {
+                    *balances.entry(asset).or_default() += satoshi;
+                }
+            }
+            summary.account_count += 1;
+        }
+        summary.unconfirmed = summary
+            .total
+            .iter()
+            .map(|(asset, total)| {
+                let confirmed = summary.confirmed.get(asset).copied().unwrap_or_default();
+                (asset.clone(), total - confirmed)
+            })
+            .collect();
+        for (asset, confirmed) in summary.confirmed.iter() {
+            summary.unconfirmed.entry(asset.clone()).or_insert(-confirmed);
+        }
+        Ok(summary)
+    }
+
+    /// The bitcoin balance of the wallet converted with the last fetched exchange rate for
+    /// `opt.currency`. On Liquid L-BTC is counted, the other assets are reported as excluded.
+    pub fn get_portfolio_value(&self, opt: &GetPortfolioValueOpt) -> Result<PortfolioValue, Error> {
+        let summary = self.get_wallet_summary(&GetWalletSummaryOpt {
+            include_hidden: opt.include_hidden,
+        })?;
+        let policy_asset = self.policy_asset()?;
+        let total_satoshi = summary.total.get(&policy_asset).copied().unwrap_or(0).max(0) as u64;
+        let mut excluded_assets: Vec<String> = summary
+            .total
+            .into_iter()
+            .filter(|(asset, satoshi)| *asset != policy_asset && *satoshi > 0)
+            .map(|(asset, _)| asset)
+            .collect();
+        excluded_assets.sort();
+        let rate = self.get_last_rate(&Pair::new(Currency::BTC, opt.currency));
+        Ok(PortfolioValue::new(total_satoshi, rate, opt.currency, excluded_assets))
+    }
+
+    /// Convert an amount between bitcoin units and fiat, using the last fetched exchange rate
+    pub fn convert_amount(&self, opt: &ConvertAmountOpt) -> Result<ConvertAmountResult, Error> {
+        let unit: AmountUnit = opt.unit.parse()?;
+        let to_unit: AmountUnit = opt.to_unit.parse()?;
+        let rate = if unit == AmountUnit::Fiat || to_unit == AmountUnit::Fiat {
+            let currency = opt
+                .currency
+                .ok_or_else(|| Error::Generic("convert_amount: missing currency".into()))?;
+            self.get_last_rate(&Pair::new(Currency::BTC, currency))
+        } else {
+            None
+        };
+        let satoshi = unit::to_satoshi(&opt.value, unit, rate)?;
+        Ok(ConvertAmountResult {
+            value: unit::from_satoshi(satoshi, to_unit, rate)?,
+            satoshi,
+        })
+    }
+
+    /// Set the memo of a wallet transaction, erroring if the transaction is not in the wallet.
     pub fn set_transaction_memo(&self, txid: &str, memo: &str) -> Result<(), Error> {
         let txid = BETxid::from_hex(txid, self.network.id())?;
         if memo.len() > 1024 {
             return Err(Error::Generic("Too long memo (max 1024)".into()));
         }
-        self.store()?.write()?.insert_memo(txid, memo)?;
+        let store = self.store()?;
+        let mut store = store.write()?;
+        if !store.is_wallet_tx(&txid) {
+            return Err(Error::TxNotFound(txid));
+        }
+        store.insert_memo(txid, memo)?;
+        drop(store);
+        self.notify.store_updated(&StoreUpdatedNotification {
+            memos: vec![txid.into_bitcoin()],
+        });
 
         Ok(())
     }
 
+    /// Set the memos of many wallet transactions with a single write of the store.
+    ///
+    /// Memos of transactions that are not in the wallet are skipped, their txids are returned.
+    /// A single store updated notification lists the transactions whose memo was set.
+    pub fn set_transaction_memos(
+        &self,
+        opt: &SetTransactionMemosOpt,
+    ) -> Result<SetTransactionMemosResult, Error> {
+        if opt.memos.values().any(|memo| memo.len() > 1024) {
+            return Err(Error::Generic("Too long memo (max 1024)".into()));
+        }
+        let mut unknown_txids = vec![];
+        let mut memos = vec![];
+        for (txid, memo) in opt.memos.iter() {
+            match BETxid::from_hex(txid, self.network.id()) {
+                Ok(txid) => memos.push((txid, memo.clone())),
+                Err(_) => unknown_txids.push(txid.clone()),
+            }
+        }
+        let updated = memos.iter().map(|(txid, _)| *txid).collect::<Vec<_>>();
+        let unknown = self.store()?.write()?.insert_memos(memos)?;
+        let updated: Vec<_> = updated
+            .into_iter()
+            .filter(|t| !unknown.contains(t))
+            .map(|t| t.into_bitcoin())
+            .collect();
+        if !updated.is_empty() {
+            self.notify.store_updated(&StoreUpdatedNotification {
+                memos: updated,
+            });
+        }
+        unknown_txids.extend(unknown.iter().map(BETxid::to_hex));
+        Ok(SetTransactionMemosResult {
+            unknown_txids,
+        })
+    }
+
     fn remove_recent_spent_utxos(&self, tx_req: &mut CreateTransaction) -> Result<(), Error> {
         let id = self.network.id();
         let recent_spent_utxos = self.recent_spent_utxos.read()?;
@@ -1054,17 +1792,374 @@ impl ElectrumSession {
         Ok(())
     }
 
-    pub fn create_transaction(
-        &mut self,
-        tx_req: &mut CreateTransaction,
-    ) -> Result<TransactionMeta, Error> {
-        info!("electrum create_transaction {:?}", tx_req);
-
-        self.remove_recent_spent_utxos(tx_req)?;
-        self.get_account(tx_req.subaccount)?.create_tx(tx_req)
-    }
-
-    pub fn sign_transaction(&self, create_tx: &TransactionMeta) -> Result<TransactionMeta, Error> {
+    /// Frozen utxos can only be spent selecting them manually
+    fn remove_frozen_utxos(&self, tx_req: &mut CreateTransaction) -> Result<(), Error> {
+        if tx_req.utxo_strategy == UtxoStrategy::Manual {
+            return Ok(());
+        }
+        let id = self.network.id();
+        let store = self.store()?;
+        let store_read = store.read()?;
+        for asset_utxos in tx_req.utxos.values_mut() {
+            asset_utxos.retain(|u| {
+                u.outpoint(id).ok().map(|o| !store_read.is_output_frozen(&o)).unwrap_or(false)
+            });
+        }
+        Ok(())
+    }
+
+    /// Utxos spent by pending transactions can only be spent selecting them manually
+    fn remove_reserved_utxos(&self, tx_req: &mut CreateTransaction) -> Result<(), Error> {
+        if tx_req.utxo_strategy == UtxoStrategy::Manual {
+            return Ok(());
+        }
+        let id = self.network.id();
+        let store = self.store()?;
+        let store_read = store.read()?;
+        for asset_utxos in tx_req.utxos.values_mut() {
+            asset_utxos.retain(|u| {
+                u.outpoint(id).ok().map(|o| !store_read.is_output_reserved(&o)).unwrap_or(false)
+            });
+        }
+        Ok(())
+    }
+
+    /// Utxos listed in `exclude_utxos` are left out of automatic coin selection
+    fn remove_excluded_utxos(&self, tx_req: &mut CreateTransaction) -> Result<(), Error> {
+        if tx_req.utxo_strategy == UtxoStrategy::Manual || tx_req.exclude_utxos.is_empty() {
+            return Ok(());
+        }
+        let id = self.network.id();
+        let excluded = tx_req
+            .exclude_utxos
+            .iter()
+            .map(|e| e.outpoint(id))
+            .collect::<Result<HashSet<_>, _>>()?;
+        for asset_utxos in tx_req.utxos.values_mut() {
+            asset_utxos.retain(|u| u.outpoint(id).map(|o| !excluded.contains(&o)).unwrap_or(false));
+        }
+        Ok(())
+    }
+
+    pub fn set_unspent_output_frozen(&self, opt: &SetUnspentOutputFrozenOpt) -> Result<(), Error> {
+        info!("electrum set_unspent_output_frozen {:?}", opt);
+
+        let outpoint =
+            BEOutPoint::new(BETxid::from_hex(&opt.txhash, self.network.id())?, opt.pt_idx);
+        // frozen outputs that have been spent can still be unfrozen
+        if opt.frozen && !self.get_account(opt.subaccount)?.unspents()?.contains(&outpoint) {
+            return Err(Error::UnspentOutputNotFound(outpoint.txid(), opt.pt_idx));
+        }
+        self.store()?.write()?.set_output_frozen(&outpoint, opt.frozen)
+    }
+
+    pub fn get_frozen_outputs(&self) -> Result<Vec<CreateTxUtxo>, Error> {
+        let store = self.store()?;
+        let store_read = store.read()?;
+        Ok(store_read
+            .frozen_outputs()
+            .into_iter()
+            .map(|o| CreateTxUtxo {
+                txid: o.txid().to_hex(),
+                vout: o.vout(),
+            })
+            .collect())
+    }
+
+    pub fn create_transaction(
+        &mut self,
+        tx_req: &mut CreateTransaction,
+    ) -> Result<TransactionMeta, Error> {
+        info!("electrum create_transaction {:?}", tx_req);
+
+        self.remove_recent_spent_utxos(tx_req)?;
+        self.remove_frozen_utxos(tx_req)?;
+        self.remove_reserved_utxos(tx_req)?;
+        self.remove_excluded_utxos(tx_req)?;
+        self.get_account(tx_req.subaccount)?.create_tx(tx_req)
+    }
+
+    pub fn estimate_transaction(
+        &mut self,
+        tx_req: &mut CreateTransaction,
+    ) -> Result<TransactionEstimate, Error> {
+        info!("electrum estimate_transaction {:?}", tx_req);
+
+        self.remove_recent_spent_utxos(tx_req)?;
+        self.remove_frozen_utxos(tx_req)?;
+        self.remove_reserved_utxos(tx_req)?;
+        self.remove_excluded_utxos(tx_req)?;
+        self.get_account(tx_req.subaccount)?.estimate_tx(tx_req)
+    }
+
+    /// The unspent outputs of a subaccount, as accepted by `create_transaction`
+    fn create_tx_utxos(&self, subaccount: u32) -> Result<CreateTxUtxos, Error> {
+        Ok(self
+            .get_unspent_outputs(&GetUnspentOpt {
+                subaccount,
+                ..Default::default()
+            })?
+            .0
+            .into_iter()
+            .map(|(asset, utxos)| {
+                let utxos = utxos
+                    .into_iter()
+                    .map(|u| CreateTxUtxo {
+                        txid: u.txhash,
+                        vout: u.pt_idx,
+                    })
+                    .collect();
+                (asset, utxos)
+            })
+            .collect())
+    }
+
+    /// The utxos that coin selection would pick to send `opt.satoshi`, without creating the
+    /// transaction or modifying the store
+    pub fn preview_coin_selection(
+        &mut self,
+        opt: &PreviewCoinSelectionOpt,
+    ) -> Result<CoinSelectionPreview, Error> {
+        // Any address of the subaccount works, it's only used to size the output
+        let address = self.get_account(opt.subaccount)?.derive_address(false, 0)?;
+        // on Liquid the policy asset is sent if none is given
+        let asset_id = match &opt.asset_id {
+            None if self.network.liquid => self.network.policy_asset.clone(),
+            asset_id => asset_id.clone(),
+        };
+        let mut create_tx = CreateTransaction {
+            subaccount: opt.subaccount,
+            addressees: vec![AddressAmount {
+                address: address.to_string(),
+                satoshi: opt.satoshi,
+                asset_id: asset_id.clone(),
+                op_return: None,
+            }],
+            fee_rate: opt.fee_rate,
+            utxos: self.create_tx_utxos(opt.subaccount)?,
+            utxo_strategy: opt.utxo_strategy.clone(),
+            ..Default::default()
+        };
+        // only estimate, creating the transaction would derive a change address
+        let tx = self.estimate_transaction(&mut create_tx)?;
+
+        let total_in =
+            tx.used_utxos.iter().filter(|u| u.asset_id == asset_id).map(|u| u.satoshi).sum::<u64>();
+        // the fee is paid in the policy asset
+        let pays_fee = !self.network.liquid || asset_id == self.network.policy_asset;
+        let spent = opt.satoshi
+            + if pays_fee {
+                tx.fee
+            } else {
+                0
+            };
+        Ok(CoinSelectionPreview {
+            total_in,
+            fee: tx.fee,
+            change: total_in.saturating_sub(spent),
+            used_utxos: tx.used_utxos,
+        })
+    }
+
+    /// Create a transaction replacing the unconfirmed `opt.txid` with a higher fee rate
+    ///
+    /// The change output is reduced to pay for the higher fee, confirmed utxos are added if
+    /// that is not enough.
+    pub fn bump_fee(&mut self, opt: &BumpFeeOpt) -> Result<TransactionMeta, Error> {
+        info!("electrum bump_fee {:?}", opt);
+
+        let txid = BETxid::from_hex(&opt.txid, self.network.id())?;
+        let txitem = self.get_account(opt.subaccount)?.get_tx_item(&txid)?;
+        if txitem.block_height != 0 {
+            return Err(Error::TxAlreadyConfirmed(txid));
+        }
+        if !txitem.rbf_optin {
+            return Err(Error::TxNotReplaceable(txid));
+        }
+        let fee_rate = if opt.clamp_fee_rate {
+            opt.fee_rate.max(self.get_min_fee_rate()?)
+        } else {
+            opt.fee_rate
+        };
+        if fee_rate <= txitem.fee_rate {
+            return Err(Error::InvalidReplacementFeeRate(txitem.fee_rate));
+        }
+
+        let mut create_tx = CreateTransaction {
+            subaccount: opt.subaccount,
+            previous_transaction: Some(txitem),
+            fee_rate: Some(fee_rate),
+            utxos: self.create_tx_utxos(opt.subaccount)?,
+            // replacements cannot spend new unconfirmed inputs (BIP125 rule 2)
+            num_confs: 1,
+            ..Default::default()
+        };
+        self.create_transaction(&mut create_tx)
+    }
+
+    /// Create a transaction spending an output of the unconfirmed `opt.txid` back to the wallet,
+    /// paying enough fee for parent and child together to reach `opt.fee_rate`
+    pub fn create_cpfp_transaction(&mut self, opt: &CpfpOpt) -> Result<TransactionMeta, Error> {
+        info!("electrum create_cpfp_transaction {:?}", opt);
+
+        let txid = BETxid::from_hex(&opt.txid, self.network.id())?;
+        let account = self.get_account(opt.subaccount)?;
+        let parent = account.get_tx_item(&txid)?;
+        if parent.block_height != 0 {
+            return Err(Error::TxAlreadyConfirmed(txid));
+        }
+        if opt.fee_rate <= parent.fee_rate {
+            return Err(Error::InvalidCpfpFeeRate(parent.fee_rate));
+        }
+
+        // only policy asset outputs can pay for the fee
+        let asset = self.network.policy_asset_id().ok();
+        let asset_key = asset.map_or_else(|| "btc".to_string(), |a| a.to_hex());
+        let owned = self
+            .get_unspent_outputs(&GetUnspentOpt {
+                subaccount: opt.subaccount,
+                ..Default::default()
+            })?
+            .0
+            .remove(&asset_key)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|u| u.txhash == opt.txid);
+        let output = match opt.vout {
+            Some(vout) => owned
+                .into_iter()
+                .find(|u| u.pt_idx == vout)
+                .ok_or(Error::InvalidCpfpOutput(txid, vout))?,
+            None => owned.max_by_key(|u| u.satoshi).ok_or(Error::TxNoOwnedOutput(txid))?,
+        };
+
+        let mut create_tx = CreateTransaction {
+            subaccount: opt.subaccount,
+            addressees: vec![AddressAmount {
+                address: account.next_change_address()?.to_string(),
+                satoshi: 0,
+                asset_id: asset.map(|a| a.to_hex()),
+                op_return: None,
+            }],
+            send_all: true,
+            utxos: vec![(
+                asset_key.clone(),
+                vec![CreateTxUtxo {
+                    txid: output.txhash.clone(),
+                    vout: output.pt_idx,
+                }],
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        // the child size does not depend on the fee, since it has a single output
+        let child_vsize = account.estimate_tx(&mut create_tx.clone())?.vsize;
+        let fee = cpfp_child_fee(parent.fee, parent.transaction_vsize, child_vsize, opt.fee_rate);
+        info!("cpfp parent fee {} child vsize {} child fee {}", parent.fee, child_vsize, fee);
+
+        create_tx.fee_absolute = Some(fee);
+        self.create_transaction(&mut create_tx)
+    }
+
+    /// The unconfirmed ancestors of an unconfirmed wallet transaction, with the fee rate of the
+    /// package made of the transaction and its ancestors.
+    ///
+    /// Ancestors outside the wallet are downloaded from the electrum server and cached, and their
+    /// confirmation status is taken from the history of their outputs.
+    pub fn get_ancestors(&self, txid: &str) -> Result<Ancestors, Error> {
+        let txid = BETxid::from_hex(txid, self.network.id())?;
+        let store = self.store()?;
+        // the store is only locked to copy data out of it, never across electrum calls
+        let wallet_height = |txid: &BETxid| -> Result<Option<Option<u32>>, Error> {
+            let store = store.read()?;
+            Ok(store.cache.accounts.values().find_map(|a| a.heights.get(txid)).copied())
+        };
+        match wallet_height(&txid)? {
+            None => return Err(Error::TxNotFound(txid)),
+            Some(Some(_)) => return Err(Error::TxAlreadyConfirmed(txid)),
+            Some(None) => (),
+        }
+
+        // only needed for the transactions outside the store
+        let client = self
+            .urls
+            .build_client(self.proxy.as_deref(), self.timeout)
+            .map_err(|e| warn!("cannot build client {:?}", e))
+            .ok();
+        let unreachable = || Error::Generic("cannot reach the electrum server".into());
+        let get_tx = |txid: &BETxid| -> Result<BETransaction, Error> {
+            {
+                let store = store.read()?;
+                if let Ok(entry) = store.get_tx_entry(txid) {
+                    return Ok(entry.tx.clone());
+                }
+                if let Some(tx) = store.cache.input_prevouts.get(txid) {
+                    return Ok(tx.clone());
+                }
+            }
+            let client = client.as_ref().ok_or_else(unreachable)?;
+            let tx = client.transaction_get_raw(&txid.into_bitcoin())?;
+            let tx = BETransaction::deserialize(&tx, self.network.id())?;
+            store.write()?.cache.input_prevouts.insert(*txid, tx.clone());
+            Ok(tx)
+        };
+        let is_unconfirmed = |txid: &BETxid, tx: &BETransaction| -> Result<bool, Error> {
+            if let Some(height) = wallet_height(txid)? {
+                return Ok(height.is_none());
+            }
+            let script = (0..tx.output_len() as u32)
+                .map(|vout| tx.output_script(vout))
+                .find(|script| !script.is_empty())
+                .ok_or_else(|| Error::Generic(format!("{} has no output script", txid)))?;
+            let client = client.as_ref().ok_or_else(unreachable)?;
+            let history = client.script_get_history(&script.into_bitcoin())?;
+            let txid = txid.into_bitcoin();
+            Ok(history.iter().any(|h| h.tx_hash == txid && h.height <= 0))
+        };
+
+        let tx = get_tx(&txid)?;
+        let ancestors = unconfirmed_ancestors(&tx, |txid| {
+            let parent = get_tx(txid)?;
+            Ok(is_unconfirmed(txid, &parent)?.then_some(parent))
+        })?;
+
+        let policy_asset = self.network.policy_asset_id().ok();
+        let fee = |tx: &BETransaction| -> Result<u64, Error> {
+            // liquid transactions have an explicit fee output
+            let mut prevouts = BETransactions::default();
+            if let BETransaction::Bitcoin(_) = tx {
+                for txid in tx.previous_output_txids() {
+                    prevouts.insert(txid, get_tx(&txid)?.into());
+                }
+            }
+            Ok(tx.fee(&prevouts, &HashMap::new(), &policy_asset)?)
+        };
+        let mut package_fee = fee(&tx)?;
+        let mut package_vsize = weight_to_vsize(tx.get_weight());
+        let ancestors = ancestors
+            .iter()
+            .map(|ancestor| {
+                let fee = fee(ancestor)?;
+                let vsize = weight_to_vsize(ancestor.get_weight());
+                package_fee += fee;
+                package_vsize += vsize;
+                Ok(AncestorTransaction {
+                    txhash: ancestor.txid().to_hex(),
+                    fee,
+                    vsize,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(Ancestors {
+            ancestors,
+            package_fee,
+            package_vsize,
+            package_fee_rate: package_fee * 1000 / package_vsize as u64,
+        })
+    }
+
+    pub fn sign_transaction(&self, create_tx: &TransactionMeta) -> Result<TransactionMeta, Error> {
         info!("electrum sign_transaction {:?}", create_tx);
         let account_num = create_tx
             .create_transaction
@@ -1074,6 +2169,163 @@ impl ElectrumSession {
         self.get_account(account_num)?.sign(create_tx)
     }
 
+    /// Create a signed transaction sending all the funds controlled by the WIF private key
+    /// `opt.private_key` to a new address of `opt.subaccount`
+    pub fn create_sweep_transaction(
+        &mut self,
+        opt: &CreateSweepOpt,
+    ) -> Result<TransactionMeta, Error> {
+        // do not log the private key
+        info!("electrum create_sweep_transaction subaccount:{}", opt.subaccount);
+        let network = self
+            .network
+            .id()
+            .get_bitcoin_network()
+            .ok_or_else(|| Error::Generic("sweeping is not supported on Liquid".into()))?;
+        let private_key = bitcoin::PrivateKey::from_wif(&opt.private_key)?;
+        if private_key.network != network
+            && (private_key.network == bitcoin::Network::Bitcoin
+                || network == bitcoin::Network::Bitcoin)
+        {
+            return Err(Error::Common(gdk_common::error::Error::MismatchingNetwork));
+        }
+        let min_fee_rate = self.get_min_fee_rate()?;
+        let fee_rate = opt.fee_rate.unwrap_or(min_fee_rate);
+        if fee_rate < min_fee_rate {
+            return Err(Error::FeeRateBelowMinimum(min_fee_rate));
+        }
+
+        let client = self.urls.build_client(self.proxy.as_deref(), self.timeout)?;
+        let mut utxos = vec![];
+        for (script_type, script) in sweep_scripts(&private_key, network) {
+            for u in client.script_list_unspent(&script)? {
+                utxos.push(SweepUtxo {
+                    outpoint: bitcoin::OutPoint::new(u.tx_hash, u.tx_pos as u32),
+                    value: u.value,
+                    script_type,
+                });
+            }
+        }
+        if utxos.is_empty() {
+            return Err(Error::NoFundsToSweep);
+        }
+
+        let account = self.get_account(opt.subaccount)?;
+        let address = bitcoin::Address::from_str(&account.get_next_address(false)?.address)?;
+        let (tx, fee) = create_sweep_tx(&private_key, &utxos, address.script_pubkey(), fee_rate)?;
+        let value = tx.output[0].value;
+        let mut created_tx = TransactionMeta::new(
+            BETransaction::Bitcoin(tx),
+            None,
+            None,
+            vec![("btc".to_string(), value as i64)].into_iter().collect(),
+            fee,
+            network,
+            "incoming".to_string(),
+            CreateTransaction {
+                subaccount: opt.subaccount,
+                fee_rate: Some(fee_rate),
+                ..Default::default()
+            },
+            SPVVerifyTxResult::InProgress,
+        );
+        created_tx.is_sweep = true;
+        Ok(created_tx)
+    }
+
+    /// Create an unsigned PSBT from a transaction returned by `create_transaction`, which can
+    /// be signed externally, for instance by the signer of a watch-only session
+    pub fn create_psbt(&self, create_tx: &TransactionMeta) -> Result<CreatePsbtResult, Error> {
+        info!("electrum create_psbt {:?}", create_tx);
+        let account_num = create_tx
+            .create_transaction
+            .as_ref()
+            .ok_or_else(|| Error::Generic("Cannot create a PSBT without tx data".into()))?
+            .subaccount;
+        Ok(CreatePsbtResult {
+            psbt: self
+                .get_account(account_num)?
+                .create_psbt(&create_tx.hex, &create_tx.used_utxos)?,
+        })
+    }
+
+    /// Create an unsigned PSBT from a transaction hex and the wallet outputs it spends, which
+    /// can also be the whole transaction returned by `create_transaction`
+    pub fn transaction_to_psbt(
+        &self,
+        opt: &TransactionToPsbtOpt,
+    ) -> Result<CreatePsbtResult, Error> {
+        info!("electrum transaction_to_psbt {:?}", opt);
+        let account_num =
+            opt.subaccount
+                .or_else(|| opt.used_utxos.first().map(|u| u.subaccount))
+                .ok_or_else(|| Error::Generic("transaction_to_psbt: missing subaccount".into()))?;
+        Ok(CreatePsbtResult {
+            psbt: self.get_account(account_num)?.create_psbt(&opt.hex, &opt.used_utxos)?,
+        })
+    }
+
+    /// The transactions broadcast by the session and not in the wallet history yet, oldest first
+    pub fn get_pending_transactions(&self) -> Result<Vec<PendingTransaction>, Error> {
+        Ok(self.store()?.read()?.pending_transactions().into_iter().cloned().collect())
+    }
+
+    /// Forget a pending transaction, making the outputs it spends available to coin selection
+    pub fn discard_pending_transaction(
+        &self,
+        opt: &DiscardPendingTransactionOpt,
+    ) -> Result<(), Error> {
+        info!("electrum discard_pending_transaction {:?}", opt);
+
+        let txid = BETxid::from_hex(&opt.txhash, self.network.id())?;
+        match self.store()?.write()?.remove_pending_transaction(&txid)? {
+            Some(_) => Ok(()),
+            None => Err(Error::TxNotFound(txid)),
+        }
+    }
+
+    /// Record a wallet transaction about to be broadcast, its outputs stay reserved until the
+    /// syncer finds it in the wallet history, or it is discarded if the broadcast was interrupted
+    fn insert_pending_transaction(
+        &self,
+        transaction: &BETransaction,
+        hex: &str,
+    ) -> Result<(), Error> {
+        // broadcasting does not require a loaded store
+        let store = match self.store.as_ref() {
+            Some(store) => store,
+            None => return Ok(()),
+        };
+        let mut store_write = store.write()?;
+        let subaccount = match store_write.spending_account(transaction) {
+            Some(subaccount) => subaccount,
+            None => return Ok(()),
+        };
+        let pending = PendingTransaction {
+            subaccount,
+            txhash: transaction.txid().to_hex(),
+            hex: hex.to_string(),
+            used_utxos: transaction
+                .previous_outputs()
+                .iter()
+                .map(|o| CreateTxUtxo {
+                    txid: o.txid().to_hex(),
+                    vout: o.vout(),
+                })
+                .collect(),
+            created_at_ts: gdk_common::util::now(),
+        };
+        store_write.insert_pending_transaction(transaction.txid(), pending)
+    }
+
+    fn remove_pending_transaction(&self, txid: &BETxid) -> Result<(), Error> {
+        // broadcasting does not require a loaded store
+        if let Some(store) = self.store.as_ref() {
+            store.write()?.remove_pending_transaction(txid)?;
+        }
+        Ok(())
+    }
+
     fn set_recent_spent_utxos(&self, tx: &BETransaction) -> Result<(), Error> {
         let mut recent_spent_utxos = self.recent_spent_utxos.write()?;
         (*recent_spent_utxos).extend(tx.previous_outputs());
@@ -1082,29 +2334,99 @@ impl ElectrumSession {
 
     pub fn send_transaction(&mut self, tx: &TransactionMeta) -> Result<TransactionMeta, Error> {
         info!("electrum send_transaction {:#?}", tx);
-        let client = self.url.build_client(self.proxy.as_deref(), None)?;
         let tx_bytes = Vec::<u8>::from_hex(&tx.hex)?;
-        let txid = client.transaction_broadcast_raw(&tx_bytes)?;
+        let betx = BETransaction::deserialize(&tx_bytes[..], self.network.id())?;
+        self.insert_pending_transaction(&betx, &tx.hex)?;
+        let txid = match self
+            .urls
+            .build_client(self.proxy.as_deref(), None)
+            .and_then(|client| Ok(client.transaction_broadcast_raw(&tx_bytes)?))
+        {
+            Ok(txid) => txid,
+            Err(err) => {
+                self.remove_pending_transaction(&betx.txid())?;
+                return Err(err);
+            }
+        };
         if let Some(memo) = tx.create_transaction.as_ref().and_then(|o| o.memo.as_ref()) {
             self.store()?.write()?.insert_memo(txid.into(), memo)?;
         }
         let mut tx = tx.clone();
         // If sign transaction happens externally txid might not have been updated
         tx.txid = txid.to_string();
-        let betx = BETransaction::deserialize(&tx_bytes[..], self.network.id())?;
         self.set_recent_spent_utxos(&betx)?;
         Ok(tx)
     }
 
+    /// Create, sign and broadcast a transaction in a single call.
+    ///
+    /// On failure the error reports the stage that failed, and the utxos of a transaction that
+    /// has been signed but not broadcast are not left reserved.
+    pub fn send_funds(&mut self, tx_req: &mut CreateTransaction) -> Result<SendFundsResult, Error> {
+        info!("electrum send_funds {:?}", tx_req);
+        let failed = |stage| {
+            move |error| Error::SendFundsFailed {
+                stage,
+                error: Box::new(error),
+            }
+        };
+        let created = self.create_transaction(tx_req).map_err(failed("create"))?;
+        let signed = self.sign_transaction(&created).map_err(failed("sign"))?;
+        let betxid = BETxid::from_hex(&signed.txid, self.network.id())
+            .map_err(|e| failed("sign")(e.into()))?;
+        let txid = self.broadcast_transaction(&signed.hex).map_err(failed("broadcast"))?;
+        if let Some(memo) = tx_req.memo.as_ref() {
+            // the transaction is already broadcast, a memo failure must not be reported as a failed send
+            if let Err(e) =
+                self.store().and_then(|store| Ok(store.write()?.insert_memo(betxid, memo)?))
+            {
+                warn!("can't store the memo of {}: {:?}", txid, e);
+            }
+        }
+        Ok(SendFundsResult {
+            txid,
+            transaction: signed.hex,
+        })
+    }
+
+    /// Broadcast a transaction, if it spends wallet outputs it is pending until the syncer finds
+    /// it in the wallet history
     pub fn broadcast_transaction(&mut self, tx_hex: &str) -> Result<String, Error> {
         let transaction = BETransaction::from_hex(&tx_hex, self.network.id())?;
 
         info!("broadcast_transaction {:#?}", transaction.txid());
-        let client = self.url.build_client(self.proxy.as_deref(), None)?;
+        self.insert_pending_transaction(&transaction, tx_hex)?;
+        match self.broadcast_raw(&transaction, tx_hex) {
+            Ok(txid) => {
+                self.set_recent_spent_utxos(&transaction)?;
+                Ok(txid)
+            }
+            Err(err) => {
+                self.remove_pending_transaction(&transaction.txid())?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Broadcast to the electrum server, or to `broadcast_fallback_url` if it can't be reached
+    fn broadcast_raw(&self, transaction: &BETransaction, tx_hex: &str) -> Result<String, Error> {
         let hex = Vec::<u8>::from_hex(tx_hex)?;
-        let txid = client.transaction_broadcast_raw(&hex)?;
-        self.set_recent_spent_utxos(&transaction)?;
-        Ok(format!("{}", txid))
+        let result = self
+            .urls
+            .build_client(self.proxy.as_deref(), None)
+            .and_then(|client| Ok(client.transaction_broadcast_raw(&hex)?.to_string()));
+        let txid = match (result, &self.network.broadcast_fallback_url) {
+            (Ok(txid), _) => txid,
+            // a transaction rejected by the server would be rejected by the fallback too
+            (Err(err @ Error::ClientError(electrum_client::Error::Protocol(_))), _)
+            | (Err(err), None) => return Err(err),
+            (Err(err), Some(url)) => {
+                warn!("electrum broadcast failed: {}, falling back to {}", err, url);
+                self.build_request_agent()?.post(url).send_string(tx_hex)?;
+                transaction.txid().to_string()
+            }
+        };
+        Ok(txid)
     }
 
     /// The estimates are returned as an array of 25 elements. Each element is
@@ -1118,13 +2440,47 @@ impl ElectrumSession {
             NetworkId::Elements(_) => 100,
         };
         let fee_estimates =
-            try_get_fee_estimates(&self.url.build_client(self.proxy.as_deref(), None)?)
+            try_get_fee_estimates(&self.urls.build_client(self.proxy.as_deref(), None)?)
                 .unwrap_or_else(|_| vec![FeeEstimate(min_fee); 25]);
         self.store()?.write()?.cache.fee_estimates = fee_estimates.clone();
         Ok(fee_estimates)
         //TODO better implement default
     }
 
+    /// The fee estimates keyed by their confirmation target in blocks, see `FEE_ESTIMATE_TARGETS`
+    pub fn get_fee_estimates_by_target(&mut self) -> Result<FeeEstimatesByTarget, Error> {
+        Ok(FeeEstimatesByTarget::from_estimates(&self.get_fee_estimates()?)?)
+    }
+
+    /// The fee rate at a percentile of the mempool fee histogram, falling back to the nearest fee
+    /// estimate if the histogram is not available
+    pub fn fee_rate_at_percentile(
+        &mut self,
+        opt: &FeeRateAtPercentileOpt,
+    ) -> Result<FeeRateAtPercentile, Error> {
+        if !(0.0..=100.0).contains(&opt.percentile) {
+            return Err(Error::Generic("percentile must be between 0 and 100".into()));
+        }
+        let client = self.urls.build_client(self.proxy.as_deref(), None)?;
+        let histogram = try_get_fee_histogram(&client).unwrap_or_else(|e| {
+            warn!("cannot get the fee histogram {:?}", e);
+            vec![]
+        });
+        if let Some(fee_rate) = fee_rate_at_percentile(&histogram, opt.percentile) {
+            return Ok(FeeRateAtPercentile {
+                fee_rate,
+                from_histogram: true,
+            });
+        }
+        let estimates = self.get_fee_estimates()?;
+        let fee_rate = fee_estimate_at_percentile(&estimates, opt.percentile)
+            .ok_or_else(|| Error::Generic("fee estimates not available".into()))?;
+        Ok(FeeRateAtPercentile {
+            fee_rate,
+            from_histogram: false,
+        })
+    }
+
     pub fn get_min_fee_rate(&self) -> Result<u64, Error> {
         Ok(self.store()?.read()?.min_fee_rate())
     }
@@ -1161,33 +2517,152 @@ impl ElectrumSession {
     }
 
     pub fn get_unspent_outputs(&self, opt: &GetUnspentOpt) -> Result<GetUnspentOutputs, Error> {
-        let mut unspent_outputs: HashMap<String, Vec<UnspentOutput>> = HashMap::new();
+        Ok(self.get_unspent_outputs_page(opt)?.unspent_outputs)
+    }
+
+    /// The unspent outputs of `opt.subaccount`, only a page of them if `opt.is_paged()`
+    pub fn get_unspent_outputs_page(
+        &self,
+        opt: &GetUnspentOpt,
+    ) -> Result<UnspentOutputsPage, Error> {
         let account = self.get_account(opt.subaccount)?;
 
         let store = self.store()?;
         let store_read = store.read()?;
         let acc_store = store_read.account_cache(opt.subaccount)?;
         let height = store_read.cache.tip_height();
+        let settings = store_read.get_settings().unwrap_or_default();
+        let confirmations = |utxo_height: Option<u32>| match utxo_height {
+            None | Some(0) => 0,
+            Some(h) => (height + 1).saturating_sub(h),
+        };
 
         let num_confs = opt.num_confs.unwrap_or(0);
         let confidential_utxos_only = opt.confidential_utxos_only.unwrap_or(false);
+        let include_frozen = opt.include_frozen.unwrap_or(false);
 
+        let mut utxos = vec![];
         for outpoint in account.unspents()? {
+            if !include_frozen && store_read.is_output_frozen(&outpoint) {
+                continue;
+            }
             let utxo = account.txo(&outpoint, acc_store)?;
-            let confirmations = match utxo.height {
-                None | Some(0) => 0,
-                Some(h) => (height + 1).saturating_sub(h),
-            };
-            if num_confs > confirmations || (confidential_utxos_only && !utxo.is_confidential()) {
+            if num_confs > confirmations(utxo.height)
+                || (confidential_utxos_only && !utxo.is_confidential())
+            {
                 continue;
             }
+            utxos.push(utxo);
+        }
+
+        let mut last_pointer = None;
+        if opt.is_paged() {
+            utxos.sort_by_key(|u| {
+                let height = u.height.filter(|h| *h > 0).unwrap_or(u32::MAX);
+                (height, u.outpoint.txid(), u.outpoint.vout())
+            });
+            (utxos, last_pointer) = page(utxos, opt.first.unwrap_or(0), opt.count);
+        }
+
+        let mut unspent_outputs: HashMap<String, Vec<UnspentOutput>> = HashMap::new();
+        for utxo in utxos {
             let asset_id = match &utxo.txoutsecrets {
                 None => "btc".to_string(),
                 Some(s) => s.asset.to_hex(),
             };
-            (*unspent_outputs.entry(asset_id).or_insert(vec![])).push(utxo.try_into()?);
+            let confirmation_bucket = settings.confirmation_bucket(confirmations(utxo.height));
+            let mut unspent_output: UnspentOutput = utxo.try_into()?;
+            unspent_output.confirmation_bucket = confirmation_bucket;
+            (*unspent_outputs.entry(asset_id).or_insert(vec![])).push(unspent_output);
+        }
+        Ok(UnspentOutputsPage {
+            unspent_outputs: GetUnspentOutputs(unspent_outputs),
+            last_pointer,
+        })
+    }
+
+    /// Forget the transactions of `opt.subaccount` confirmed at or above `opt.from_height`, or
+    /// unconfirmed, and scan its scripts again from the electrum server.
+    ///
+    /// The rescan runs on its own connection, so it can be called while the session syncs.
+    pub fn rescan_subaccount(&self, opt: &RescanSubaccountOpt) -> Result<(), Error> {
+        info!("electrum rescan_subaccount {:?}", opt);
+        self.get_account(opt.subaccount)?;
+        let notify = |status| {
+            self.notify.rescan(&RescanNotification {
+                subaccount: opt.subaccount,
+                from_height: opt.from_height,
+                status,
+            })
+        };
+        notify(RescanStatus::Started);
+
+        let result = self.rescan(opt);
+        match result {
+            Ok(_) => {
+                notify(RescanStatus::Completed);
+                Ok(())
+            }
+            Err(e) => {
+                notify(RescanStatus::Failed);
+                Err(e)
+            }
+        }
+    }
+
+    fn rescan(&self, opt: &RescanSubaccountOpt) -> Result<(), Error> {
+        let store = self.store()?;
+        store.write()?.clear_account_history(opt.subaccount, opt.from_height)?;
+        let master_blinding = store.read()?.cache.master_blinding.clone();
+        let syncer = Syncer {
+            accounts: self.accounts.clone(),
+            store,
+            master_blinding,
+            network: self.network.clone(),
+            recent_spent_utxos: self.recent_spent_utxos.clone(),
+        };
+        let client = self.urls.build_client(self.proxy.as_deref(), None)?;
+        let mut account_clients = AccountClients::default();
+        if self.isolate_scans() {
+            let account_nums = self.accounts.read()?.keys().cloned().collect::<Vec<_>>();
+            account_clients.connect(self.urls.active(), self.proxy.as_deref(), account_nums)?;
         }
-        Ok(GetUnspentOutputs(unspent_outputs))
+        let user_wants_to_sync = Arc::new(AtomicBool::new(true));
+        syncer.sync(&client, &account_clients, &mut ScriptStatuses::new(), &user_wants_to_sync)?;
+        Ok(())
+    }
+
+    /// Whether subaccounts are scanned over distinct Tor circuits, see `AccountClients`
+    fn isolate_scans(&self) -> bool {
+        self.network.use_tor() && self.proxy.as_deref().map_or(false, |p| !p.trim().is_empty())
+    }
+
+    /// The changes of every subaccount since the snapshot in `opt`
+    pub fn diff_since(&self, opt: &DiffSinceOpt) -> Result<WalletDiff, Error> {
+        let tip_height = self.store()?.read()?.cache.tip_height();
+        let subaccounts = self
+            .get_accounts()?
+            .iter()
+            .map(|account| {
+                let tx_count = opt.tx_count_per_subaccount.get(&account.num()).copied();
+                let unconfirmed = opt
+                    .unconfirmed_txids_per_subaccount
+                    .get(&account.num())
+                    .into_iter()
+                    .flatten()
+                    .map(|txid| BETxid::from_hex(txid, self.network.id()))
+                    .collect::<Result<_, _>>()?;
+                account.diff_since(opt.tip_height, tx_count, &unconfirmed)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(WalletDiff {
+            tip_height,
+            subaccounts,
+        })
+    }
+
+    pub fn get_spend_index(&self, opt: &GetSpendIndexOpt) -> Result<Vec<SpendIndexEntry>, Error> {
+        self.get_account(opt.subaccount)?.spend_index()
     }
 
     pub fn get_address_data(&self, opt: AddressDataRequest) -> Result<AddressDataResult, Error> {
@@ -1204,6 +2679,18 @@ impl ElectrumSession {
             .ok_or(Error::ScriptPubkeyNotFound)
     }
 
+    /// Check the consistency of the local store without modifying it
+    pub fn verify_store(&self) -> Result<VerifyStoreResult, Error> {
+        let store = self.store()?;
+        let store_read = store.read()?;
+        let issues = store_read.consistency_issues();
+
+        Ok(VerifyStoreResult {
+            ok: issues.is_empty(),
+            issues,
+        })
+    }
+
     pub fn export_cache(&mut self) -> Result<RawCache, Error> {
         self.store()?.write()?.export_cache()
     }
@@ -1222,7 +2709,7 @@ impl ElectrumSession {
         let mut hasher = DefaultHasher::new();
         for account in self.get_accounts()? {
             opt.subaccount = account.num();
-            let txs = self.get_transactions(&opt)?.0;
+            let txs = self.get_transactions(&opt)?.transactions;
             for tx in txs.iter() {
                 std::hash::Hash::hash(&tx.txhash, &mut hasher);
             }
@@ -1252,24 +2739,30 @@ impl Tipper {
         let new_header = BEBlockHeader::deserialize(&header.header, self.network.id())?;
         Ok((new_height, new_header))
     }
-    pub fn update_cache_if_needed(
+
+    /// Save the server tip and notify it, if it's different from the saved one
+    pub fn update_tip(
         &self,
         new_height: u32,
         new_header: BEBlockHeader,
-    ) -> Result<Option<(u32, BEBlockHeader)>, Error> {
-        let do_update = match &self.store.read()?.cache.tip_ {
-            None => true,
+        notify: &NativeNotif,
+    ) -> Result<(), Error> {
+        let reorg = match &self.store.read()?.cache.tip_ {
+            None => false,
             Some((current_height, current_header)) => {
-                &new_height != current_height || &new_header != current_header
+                if new_height == *current_height && new_header == *current_header {
+                    return Ok(());
+                }
+                // when more than one block arrived, the new tip cannot be linked to the saved one
+                new_height <= *current_height
+                    || (new_height == current_height + 1
+                        && new_header.prev_block_hash() != current_header.block_hash())
             }
         };
-        if do_update {
-            info!("saving in store new tip {:?}", new_height);
-            self.store.write()?.update_tip(new_height, new_header.clone())?;
-            Ok(Some((new_height, new_header)))
-        } else {
-            Ok(None)
-        }
+        info!("saving in store new tip {:?} reorg {}", new_height, reorg);
+        self.store.write()?.update_tip(new_height, new_header.clone())?;
+        notify.block_from_header(new_height, &new_header, reorg);
+        Ok(())
     }
 }
 
@@ -1402,10 +2895,62 @@ struct DownloadTxResult {
 }
 
 impl Syncer {
+    /// Compare the confirmed unspent outputs of every account with the ones returned by the
+    /// `verifier` server, and return the accounts where they differ.
+    ///
+    /// Return `None` if the verifier is not at `height`, since outputs confirmed in the blocks
+    /// seen only by one of the servers would be reported as mismatches.
+    pub fn verify_utxos(
+        &self,
+        verifier: &Client,
+        height: u32,
+    ) -> Result<Option<Vec<VerificationMismatchNotification>>, Error> {
+        let verifier_height = verifier.block_headers_subscribe_raw()?.height as u32;
+        if verifier_height != height {
+            info!("verification server at height {verifier_height}, expected {height}");
+            return Ok(None);
+        }
+
+        let net = self.network.id();
+        let accounts = self.accounts.read().unwrap();
+        let mut mismatches = vec![];
+        for account in accounts.values() {
+            let (scripts, unspents) = account.confirmed_unspents()?;
+            let scripts: Vec<_> = scripts.into_iter().map(|s| s.into_bitcoin()).collect();
+            let verified: HashSet<BEOutPoint> = verifier
+                .batch_script_list_unspent(scripts.iter())?
+                .into_iter()
+                .flatten()
+                .filter(|u| u.height > 0)
+                .map(|u| BEOutPoint::new(u.tx_hash.into_net(net), u.tx_pos as u32))
+                .collect();
+
+            let to_strings = |outpoints: HashSet<&BEOutPoint>| {
+                let mut v: Vec<_> = outpoints
+                    .into_iter()
+                    .map(|o| format!("{}:{}", o.txid().to_hex(), o.vout()))
+                    .collect();
+                v.sort();
+                v
+            };
+            let missing = to_strings(verified.difference(&unspents).collect());
+            let unexpected = to_strings(unspents.difference(&verified).collect());
+            if !missing.is_empty() || !unexpected.is_empty() {
+                mismatches.push(VerificationMismatchNotification {
+                    subaccount: account.num(),
+                    missing,
+                    unexpected,
+                });
+            }
+        }
+        Ok(Some(mismatches))
+    }
+
     /// Sync the wallet, return the set of updated accounts
     pub fn sync(
         &self,
-        client: &Client,
+        shared_client: &Client,
+        account_clients: &AccountClients,
         last_statuses: &mut ScriptStatuses,
         user_wants_to_sync: &Arc<AtomicBool>,
     ) -> Result<Vec<TransactionNotification>, Error> {
@@ -1414,8 +2959,10 @@ impl Syncer {
 
         let accounts = self.accounts.read().unwrap();
         let mut updated_txs: HashMap<BETxid, TransactionNotification> = HashMap::new();
+        let gap_limit = self.network.gap_limit.unwrap_or(GAP_LIMIT);
 
         for account in accounts.values() {
+            let client = account_clients.get(account.num(), shared_client);
             let mut new_statuses = ScriptStatuses::new();
             let cache_statuses = account.status()?;
             let mut history_txs_id = HashSet::<BETxid>::new();
@@ -1477,7 +3024,7 @@ impl Syncer {
                         None => {
                             // Script never had a tx, initially and neither via updates
                             count_consecutive_empty += 1;
-                            if count_consecutive_empty >= GAP_LIMIT {
+                            if count_consecutive_empty >= gap_limit {
                                 break;
                             } else {
                                 continue;
@@ -1545,11 +3092,8 @@ impl Syncer {
 
                 // # Removing conflicting transactions
                 // We have new transactions, but some of them could conflict (spend same outpoint)
-                // with each other or with the ones in the cache. We can't rely on the server to
-                // detect unconfirmed conflicting transactions.
-
-                // ## Remove new transactions conflicting with each other
-                // keeping the one having the greater fee.
+                // with each other or with the ones in the cache. The replaced ones are removed
+                // from the wallet transactions, but kept aside to be shown as replaced.
 
                 // fetch full transactions and their fee from txids
                 let new_txs_fee: HashMap<BETxid, (BETransaction, u64)> = txid_height
@@ -1561,46 +3105,21 @@ impl Syncer {
                     }
                 ).collect();
 
-                // build a reverse map so that I know which outpoint is spent by more than 1 tx,
-                // meaning the transactions are conflicting
-                let mut outpoints_to_tx: HashMap<BEOutPoint, Vec<BETxid>> = HashMap::new();
-                for (txid, (tx, _)) in new_txs_fee.iter() {
-                    for outpoint in tx.previous_outputs() {
-                        let entry = outpoints_to_tx.entry(outpoint).or_insert(vec![]);
-                        entry.push(*txid);
-                    }
-                }
-
-                // We are going to keep the txids that have no conflicts with other new txs, in case
-                // there are conflicts with another tx, we keep the one with greater fee.
-                txid_height.retain(|txid, _| {
-                    let (tx, fee) =
-                        new_txs_fee.get(txid).expect("new_txs_fee built over txid_height");
-                    for outpoint in tx.previous_outputs() {
-                        let txs_spending_this_outpoint = outpoints_to_tx
-                            .get(&outpoint)
-                            .expect("for sure there is at least current txid");
-                        for other_txid in txs_spending_this_outpoint.iter().filter(|t| *t != txid) {
-                            // if the tx is not found in the new ones, it means it doesn't conflict with the new ones.
-                            if let Some((_, other_fee)) = new_txs_fee.get(other_txid) {
-                                // return false (remove the txid) only if the fee is lower than the other_fee
-                                return fee > other_fee;
-                            }
-                        }
-                    }
-                    true
+                let existing_txs = acc_store.heights.keys().map(|txid| {
+                    let txe = acc_store.all_txs.get(txid).expect("all wallet txs are in cache");
+                    (*txid, &txe.tx)
                 });
-
-                // ## search in existing transactions the ones that use a respent outpoint and
-                // remove them. Here we don't have to bother to check the fee, because we are sure
-                // they happened before.
-                let existing_txids_height: Vec<_> = acc_store.heights.keys().cloned().collect();
-                for txid in existing_txids_height.iter() {
-                    let tx = acc_store.all_txs.get(&txid).expect("all txs must be in cache, the new ones in this loop are already inserted in previous extend").clone();
-                    if tx.tx.previous_outputs().iter().any(|p| outpoints_to_tx.contains_key(p)) {
-                        acc_store.heights.remove(&txid);
-                    }
+                let replaced = account::find_replaced_txs(&new_txs_fee, existing_txs);
+                if !replaced.is_empty() {
+                    info!("replaced txs {:?}", replaced);
+                }
+                txid_height.retain(|txid, _| !replaced.contains_key(txid));
+                for txid in replaced.keys() {
+                    acc_store.heights.remove(txid);
                 }
+                // a tx could be back in the history, e.g. its replacement has been evicted
+                acc_store.replaced.retain(|txid, _| !txid_height.contains_key(txid));
+                acc_store.replaced.extend(replaced);
 
                 acc_store.heights.extend(txid_height.into_iter());
                 acc_store.scripts.extend(scripts.clone().into_iter().map(|(a, b)| (b, a)));
@@ -1650,6 +3169,8 @@ impl Syncer {
                     }
                 }
 
+                // pending transactions now in the wallet history release their utxos
+                store_write.prune_pending_transactions()?;
                 store_write.flush()?;
                 drop(store_write);
 
@@ -1883,13 +3404,172 @@ fn unblind_output(
     }
 }
 
-fn wait_or_close(user_wants_to_sync: &Arc<AtomicBool>, interval: u32) -> bool {
-    for _ in 0..(interval * 2) {
+fn unblind_outputs(
+    tx: &elements::Transaction,
+    master_blinding: &MasterBlindingKey,
+) -> UnblindedTransaction {
+    let txid = tx.txid();
+    let outputs = tx
+        .output
+        .iter()
+        .enumerate()
+        .map(|(vout, output)| {
+            let outpoint = elements::OutPoint::new(txid, vout as u32);
+            let secrets = match unblind_output(output.clone(), master_blinding, Some(outpoint)) {
+                Ok(secrets) => Some(secrets),
+                Err(e) => {
+                    info!("{outpoint} cannot unblind {e}");
+                    None
+                }
+            };
+            UnblindedOutput {
+                pt_idx: vout as u32,
+                ..secrets.into()
+            }
+        })
+        .collect();
+    UnblindedTransaction {
+        txhash: txid.to_hex(),
+        outputs,
+    }
+}
+
+/// Set the metadata of the assets of each transaction, looking up each asset once
+fn add_asset_metadata(txs: &mut [TxListItem], lookup: impl Fn(&str) -> AssetMetadata) {
+    let mut cache = HashMap::<String, AssetMetadata>::new();
+    for tx in txs.iter_mut() {
+        let asset_ids = tx
+            .satoshi
+            .keys()
+            .chain(tx.inputs.iter().chain(tx.outputs.iter()).filter_map(|e| e.asset_id.as_ref()))
+            .cloned()
+            .collect::<HashSet<_>>();
+        let metadata = asset_ids
+            .into_iter()
+            .map(|asset_id| {
+                let metadata =
+                    cache.entry(asset_id.clone()).or_insert_with(|| lookup(&asset_id)).clone();
+                (asset_id, metadata)
+            })
+            .collect();
+        tx.asset_metadata = Some(metadata);
+    }
+}
+
+fn registry_asset_metadata(
+    network: gdk_registry::ElementsNetwork,
+    asset_id: &str,
+) -> AssetMetadata {
+    let params = match elements::AssetId::from_str(asset_id) {
+        Ok(asset_id) => gdk_registry::GetAssetParams {
+            asset_id,
+            config: gdk_registry::Config::with_network(network),
+        },
+        Err(_) => return AssetMetadata::default(),
+    };
+    match gdk_registry::get_asset(params) {
+        Ok(Some(info)) => AssetMetadata {
+            ticker: info.ticker,
+            name: Some(info.name),
+            precision: Some(info.precision),
+        },
+        Ok(None) => AssetMetadata::default(),
+        Err(e) => {
+            warn!("cannot get {} from the registry {:?}", asset_id, e);
+            AssetMetadata::default()
+        }
+    }
+}
+
+/// The unconfirmed ancestors of `tx`, each one listed before its own ancestors.
+///
+/// `get_unconfirmed` returns the transaction `txid` if it's unconfirmed.
+fn unconfirmed_ancestors<F>(
+    tx: &BETransaction,
+    mut get_unconfirmed: F,
+) -> Result<Vec<BETransaction>, Error>
+where
+    F: FnMut(&BETxid) -> Result<Option<BETransaction>, Error>,
+{
+    // depth first, adding a transaction after all its ancestors
+    fn visit<F>(
+        tx: &BETransaction,
+        get_unconfirmed: &mut F,
+        visited: &mut HashSet<BETxid>,
+        ancestors: &mut Vec<BETransaction>,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&BETxid) -> Result<Option<BETransaction>, Error>,
+    {
+        for txid in tx.previous_output_txids() {
+            if !visited.insert(txid) {
+                continue;
+            }
+            if let Some(parent) = get_unconfirmed(&txid)? {
+                visit(&parent, get_unconfirmed, visited, ancestors)?;
+                ancestors.push(parent);
+            }
+        }
+        Ok(())
+    }
+
+    let mut ancestors = vec![];
+    visit(tx, &mut get_unconfirmed, &mut HashSet::new(), &mut ancestors)?;
+    ancestors.reverse();
+    Ok(ancestors)
+}
+
+/// The outputs of `tx` whose secrets are in any of the `unblinded` maps of the accounts
+fn wallet_unblinded_outputs<'a>(
+    tx: &elements::Transaction,
+    unblinded: impl Iterator<Item = &'a HashMap<elements::OutPoint, elements::TxOutSecrets>> + Clone,
+) -> UnblindedTransaction {
+    let txid = tx.txid();
+    let outputs = (0..tx.output.len() as u32)
+        .filter_map(|vout| {
+            let outpoint = elements::OutPoint::new(txid, vout);
+            let secrets = unblinded.clone().find_map(|u| u.get(&outpoint)).cloned()?;
+            Some(UnblindedOutput {
+                pt_idx: vout,
+                ..Some(secrets).into()
+            })
+        })
+        .collect();
+    UnblindedTransaction {
+        txhash: txid.to_hex(),
+        outputs,
+    }
+}
+
+/// Take up to `count` items starting from `first`, returning them with the index of the next
+/// item, if any
+fn page<T>(items: Vec<T>, first: u32, count: Option<u32>) -> (Vec<T>, Option<u32>) {
+    let len = items.len();
+    let first = (first as usize).min(len);
+    let end = count.map_or(len, |c| first.saturating_add(c as usize).min(len));
+    let next = (end < len).then_some(end as u32);
+    (items.into_iter().skip(first).take(end - first).collect(), next)
+}
+
+/// Switch to the next electrum server, if there are backups, and notify it
+fn failover(urls: &ElectrumUrls, notify: &NativeNotif) {
+    if let Some(next) = urls.rotate() {
+        warn!("failing over to electrum server {:?}", next.url());
+        notify.electrum_server(next.url());
+    }
+}
+
+fn wait_or_close(user_wants_to_sync: &Arc<AtomicBool>, interval: Duration) -> bool {
+    let step = Duration::from_millis(500);
+    let mut waited = Duration::ZERO;
+    while waited < interval {
         if !user_wants_to_sync.load(Ordering::Relaxed) {
             // Threads should stop, close
             return true;
         }
-        thread::sleep(Duration::from_millis(500));
+        let sleep = step.min(interval - waited);
+        thread::sleep(sleep);
+        waited += sleep;
     }
     false
 }
@@ -1927,6 +3607,345 @@ impl ElectrumSession {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_tip_notifications() {
+        use bitcoin::hashes::Hash;
+        use gdk_common::notification::NotificationMode;
+
+        let xpub = ExtendedPubKey::from_str("tpubD97UxEEcrMpkE8yG3NQveraWveHzTAJx3KwPsUycx9ABfxRjMtiwfm6BtrY5yhF9yF2eyMg2hyDtGDYXx6gVLBox1m2Mq4u8zB2NXFhUZmm").unwrap();
+        let dir = tempfile::TempDir::new().unwrap();
+        let network = NetworkParameters::default();
+        let tipper = Tipper {
+            store: Arc::new(RwLock::new(StoreMeta::new(dir.path(), &xpub, network.id()).unwrap())),
+            network,
+        };
+        let mut notify = NativeNotif::new();
+        notify.set_mode(NotificationMode::Queue);
+
+        let header = |prev: &BEBlockHeader, nonce| {
+            BEBlockHeader::Bitcoin(bitcoin::BlockHeader {
+                version: 1,
+                prev_blockhash: prev.block_hash().into_bitcoin(),
+                merkle_root: bitcoin::TxMerkleNode::all_zeros(),
+                time: 0,
+                bits: 0,
+                nonce,
+            })
+        };
+        let genesis = BEBlockHeader::Bitcoin(bitcoin::BlockHeader {
+            version: 1,
+            prev_blockhash: bitcoin::BlockHash::all_zeros(),
+            merkle_root: bitcoin::TxMerkleNode::all_zeros(),
+            time: 0,
+            bits: 0,
+            nonce: 0,
+        });
+        let h1 = header(&genesis, 1);
+        let h2 = header(&h1, 2);
+        let h2_fork = header(&h1, 3);
+        let h3_fork = header(&h2_fork, 4);
+        let h4_fork = header(&h3_fork, 5);
+
+        // initial sync, then the same tip is seen again
+        tipper.update_tip(1, h1.clone(), &notify).unwrap();
+        tipper.update_tip(1, h1, &notify).unwrap();
+        tipper.update_tip(2, h2, &notify).unwrap();
+        tipper.update_tip(2, h2_fork.clone(), &notify).unwrap();
+        tipper.update_tip(2, h2_fork, &notify).unwrap();
+        tipper.update_tip(4, h4_fork.clone(), &notify).unwrap();
+        // a block that does not extend the tip
+        tipper.update_tip(5, h3_fork, &notify).unwrap();
+
+        let blocks: Vec<_> = notify.poll().into_iter().map(|n| n["block"].clone()).collect();
+        let summary: Vec<_> = blocks
+            .iter()
+            .map(|b| (b["block_height"].as_u64().unwrap(), b["reorg"].as_bool().unwrap()))
+            .collect();
+        assert_eq!(summary, vec![(1, false), (2, false), (2, true), (4, false), (5, true)]);
+        assert_eq!(blocks[3]["block_hash"], h4_fork.block_hash().into_bitcoin().to_hex());
+        assert_eq!(tipper.store.read().unwrap().cache.tip_height(), 5);
+    }
+
+    #[test]
+    fn test_network_notification_debounce() {
+        use gdk_common::notification::NotificationMode;
+
+        let mut notify = NativeNotif::new();
+        notify.set_mode(NotificationMode::Queue);
+        let mut state_updater = StateUpdater {
+            current: Arc::new(AtomicBool::new(true)),
+            notify: notify.clone(),
+            notified: true,
+            changed_since: None,
+        };
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // a brief disconnection is not notified
+        state_updater.update_at(false, at(0));
+        assert!(!state_updater.current.load(Ordering::Relaxed));
+        state_updater.update_at(true, at(1));
+        assert!(notify.poll().is_empty());
+
+        // a lasting disconnection is notified once
+        for secs in 2..12 {
+            state_updater.update_at(false, at(secs));
+        }
+        // and so is the following reconnection, even if it flaps before settling
+        state_updater.update_at(true, at(12));
+        state_updater.update_at(false, at(13));
+        for secs in 14..30 {
+            state_updater.update_at(true, at(secs));
+        }
+
+        let connected: Vec<_> = notify
+            .poll()
+            .into_iter()
+            .map(|n| n["network"]["connected"].as_bool().unwrap())
+            .collect();
+        assert_eq!(connected, vec![false, true]);
+    }
+
+    #[test]
+    fn test_unconfirmed_ancestors() {
+        let spending = |prevouts: &[&BETransaction], lock_time| {
+            BETransaction::Bitcoin(bitcoin::Transaction {
+                version: 2,
+                lock_time: bitcoin::PackedLockTime(lock_time),
+                input: prevouts
+                    .iter()
+                    .map(|tx| bitcoin::TxIn {
+                        previous_output: bitcoin::OutPoint::new(tx.txid().into_bitcoin(), 0),
+                        ..Default::default()
+                    })
+                    .collect(),
+                output: vec![bitcoin::TxOut::default()],
+            })
+        };
+        let confirmed = spending(&[], 0);
+        let a = spending(&[&confirmed], 1);
+        let b = spending(&[&a], 2);
+        let c = spending(&[&confirmed], 3);
+        // spends a both directly and through b
+        let tx = spending(&[&a, &b, &c, &confirmed], 4);
+
+        let unconfirmed = [a.clone(), b.clone(), c.clone()];
+        let mut lookups = vec![];
+        let ancestors = unconfirmed_ancestors(&tx, |txid| {
+            lookups.push(*txid);
+            Ok(unconfirmed.iter().find(|t| &t.txid() == txid).cloned())
+        })
+        .unwrap();
+        // b depends on a, so it comes first
+        let txids: Vec<_> = ancestors.iter().map(BETransaction::txid).collect();
+        assert_eq!(txids, vec![c.txid(), b.txid(), a.txid()]);
+        // each transaction is looked up once
+        assert_eq!(lookups.len(), 4);
+
+        assert!(unconfirmed_ancestors(&confirmed, |_| unreachable!()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_add_asset_metadata() {
+        let network = gdk_registry::ElementsNetwork::Liquid;
+        let lbtc = gdk_registry::policy_asset_id(network).to_hex();
+        let unknown = "11".repeat(32);
+        let mut txs = vec![
+            TxListItem {
+                satoshi: HashMap::from([(lbtc.clone(), -1_000)]),
+                outputs: vec![GetTxInOut {
+                    asset_id: Some(unknown.clone()),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            TxListItem::default(),
+        ];
+        let lookups = std::cell::Cell::new(0);
+        add_asset_metadata(&mut txs, |asset_id| {
+            lookups.set(lookups.get() + 1);
+            registry_asset_metadata(network, asset_id)
+        });
+
+        let metadata = txs[0].asset_metadata.as_ref().unwrap();
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(
+            metadata[&lbtc],
+            AssetMetadata {
+                ticker: Some("L-BTC".into()),
+                name: Some("btc".into()),
+                precision: Some(8),
+            }
+        );
+        // Missing from the registry
+        assert_eq!(metadata[&unknown], AssetMetadata::default());
+        assert_eq!(lookups.get(), 2);
+        assert_eq!(txs[1].asset_metadata, Some(HashMap::new()));
+    }
+
+    #[test]
+    fn test_fee_rate_at_percentile() {
+        // from the highest fee rate, as returned by the server
+        let histogram = [(50.0, 1_000.0), (20.0, 1_000.0), (10.0, 2_000.0), (1.0, 6_000.0)];
+        assert_eq!(fee_rate_at_percentile(&histogram, 0.0), Some(1.0));
+        assert_eq!(fee_rate_at_percentile(&histogram, 60.0), Some(1.0));
+        assert_eq!(fee_rate_at_percentile(&histogram, 61.0), Some(10.0));
+        assert_eq!(fee_rate_at_percentile(&histogram, 80.0), Some(10.0));
+        assert_eq!(fee_rate_at_percentile(&histogram, 90.0), Some(20.0));
+        assert_eq!(fee_rate_at_percentile(&histogram, 95.0), Some(50.0));
+        assert_eq!(fee_rate_at_percentile(&histogram, 100.0), Some(50.0));
+        assert_eq!(fee_rate_at_percentile(&[], 50.0), None);
+        assert_eq!(fee_rate_at_percentile(&[(5.0, 0.0)], 50.0), None);
+
+        let estimates: Vec<_> = (0..25).map(|i| FeeEstimate(25_000 - i * 1_000)).collect();
+        assert_eq!(fee_estimate_at_percentile(&estimates, 100.0), Some(24.0));
+        assert_eq!(fee_estimate_at_percentile(&estimates, 50.0), Some(12.0));
+        assert_eq!(fee_estimate_at_percentile(&estimates, 0.0), Some(1.0));
+        assert_eq!(fee_estimate_at_percentile(&estimates[..3], 0.0), Some(23.0));
+        assert_eq!(fee_estimate_at_percentile(&[], 0.0), None);
+    }
+
+    #[test]
+    fn test_page() {
+        let items: Vec<u32> = (0..10).collect();
+        for count in 1..=11 {
+            let mut reassembled = vec![];
+            let mut first = 0;
+            loop {
+                let (page, next) = page(items.clone(), first, Some(count));
+                assert!(page.len() <= count as usize);
+                reassembled.extend(page);
+                match next {
+                    Some(next) => first = next,
+                    None => break,
+                }
+            }
+            assert_eq!(reassembled, items);
+        }
+        assert_eq!(page(items.clone(), 3, None), ((3..10).collect(), None));
+        assert_eq!(page(items.clone(), 20, Some(5)), (vec![], None));
+        assert_eq!(page(items, 0, Some(0)), (vec![], Some(0)));
+    }
+
+    #[test]
+    fn test_unblind_outputs() {
+        use elements::confidential::{AssetBlindingFactor, ValueBlindingFactor};
+
+        let master_blinding = MasterBlindingKey([1u8; 64]);
+        let asset = elements::AssetId::from_slice(&[2u8; 32]).unwrap();
+        let params = &elements::AddressParams::ELEMENTS;
+        let pk = bitcoin::PublicKey::from_private_key(
+            &EC,
+            &bitcoin::PrivateKey::from_slice(&[3u8; 32], bitcoin::Network::Regtest).unwrap(),
+        );
+        let address = elements::Address::p2wpkh(&pk, None, params);
+        let blinding_sk =
+            asset_blinding_key_to_ec_private_key(&master_blinding, &address.script_pubkey());
+        let blinding_pk = bitcoin::secp256k1::PublicKey::from_secret_key(&EC, &blinding_sk);
+        let foreign_pk = bitcoin::secp256k1::PublicKey::from_secret_key(
+            &EC,
+            &bitcoin::secp256k1::SecretKey::from_slice(&[4u8; 32]).unwrap(),
+        );
+
+        let spent = [elements::TxOutSecrets::new(
+            asset,
+            AssetBlindingFactor::zero(),
+            100_000,
+            ValueBlindingFactor::zero(),
+        )];
+        let mut rng = thread_rng();
+        let (mine, abf, vbf, _) = elements::TxOut::new_not_last_confidential(
+            &mut rng,
+            &EC,
+            60_000,
+            address.to_confidential(blinding_pk),
+            asset,
+            &spent,
+        )
+        .unwrap();
+        let (foreign, _, _, _) = elements::TxOut::new_not_last_confidential(
+            &mut rng,
+            &EC,
+            39_000,
+            address.to_confidential(foreign_pk),
+            asset,
+            &spent,
+        )
+        .unwrap();
+        let fee = elements::TxOut::new_fee(1_000, asset);
+        let tx = elements::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![mine, foreign, fee],
+        };
+
+        let unblinded = unblind_outputs(&tx, &master_blinding);
+        assert_eq!(unblinded.txhash, tx.txid().to_hex());
+        assert_eq!(
+            unblinded.outputs,
+            vec![
+                UnblindedOutput {
+                    pt_idx: 0,
+                    unblinded: true,
+                    asset_id: Some(asset.to_hex()),
+                    satoshi: Some(60_000),
+                    asset_blinder: Some(abf.to_hex()),
+                    amount_blinder: Some(vbf.to_hex()),
+                },
+                UnblindedOutput {
+                    pt_idx: 1,
+                    ..Default::default()
+                },
+                UnblindedOutput {
+                    pt_idx: 2,
+                    unblinded: true,
+                    asset_id: Some(asset.to_hex()),
+                    satoshi: Some(1_000),
+                    asset_blinder: Some(AssetBlindingFactor::zero().to_hex()),
+                    amount_blinder: Some(ValueBlindingFactor::zero().to_hex()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wallet_unblinded_outputs() {
+        use elements::confidential::{AssetBlindingFactor, ValueBlindingFactor};
+
+        let asset = elements::AssetId::from_slice(&[2u8; 32]).unwrap();
+        let tx = elements::Transaction {
+            version: 2,
+            lock_time: 0,
+            input: vec![],
+            output: vec![elements::TxOut::new_fee(1_000, asset); 3],
+        };
+        let secrets = |value| {
+            elements::TxOutSecrets::new(
+                asset,
+                AssetBlindingFactor::zero(),
+                value,
+                ValueBlindingFactor::zero(),
+            )
+        };
+        let outpoint = |vout| elements::OutPoint::new(tx.txid(), vout);
+        // outputs received by different accounts
+        let account0 = HashMap::from([(outpoint(2), secrets(20))]);
+        let account1 = HashMap::from([(outpoint(0), secrets(10))]);
+
+        let unblinded = wallet_unblinded_outputs(&tx, [&account0, &account1].iter().copied());
+        assert_eq!(unblinded.txhash, tx.txid().to_hex());
+        assert_eq!(
+            unblinded.outputs.iter().map(|o| (o.pt_idx, o.satoshi)).collect::<Vec<_>>(),
+            vec![(0, Some(10)), (2, Some(20))]
+        );
+        assert!(unblinded.outputs.iter().all(|o| o.unblinded));
+        assert_eq!(unblinded.outputs[0].asset_id, Some(asset.to_hex()));
+
+        let unblinded = wallet_unblinded_outputs(&tx, iter::empty());
+        assert!(unblinded.outputs.is_empty());
+    }
+
     #[test]
     fn test_passphrase() {
         // From bip39 passphrase
@@ -1,6 +1,6 @@
 use std::{
     collections::{HashMap, HashSet},
-    sync::{atomic::AtomicBool, Arc, RwLock},
+    sync::{atomic::AtomicBool, Arc, Mutex, RwLock},
 };
 
 use gdk_common::{
@@ -11,11 +11,14 @@ use gdk_common::{
     model::*,
     notification::NativeNotif,
     session::{JsonError, Session},
-    ureq, NetworkParameters,
+    ureq,
+    util::amount,
+    NetworkParameters,
 };
 use serde_json::Value;
 
-use crate::{account::Account, error::Error, interface::ElectrumUrl, socksify, ElectrumSession};
+use crate::interface::{ElectrumUrl, ElectrumUrls};
+use crate::{account::Account, error::Error, socksify, ElectrumSession};
 
 impl ExchangeRatesCacher for ElectrumSession {
     fn xr_cache(&self) -> ExchangeRatesCache {
@@ -25,17 +28,22 @@ impl ExchangeRatesCacher for ElectrumSession {
 
 impl Session for ElectrumSession {
     fn new(network_parameters: NetworkParameters) -> Result<Self, JsonError> {
-        let url = determine_electrum_url(&network_parameters)?;
+        let urls = determine_electrum_urls(&network_parameters)?;
+        let verification_url = determine_verification_url(&network_parameters)?;
+        let mut notify = NativeNotif::new();
+        notify.set_mode(network_parameters.notification_mode);
 
         Ok(Self {
             proxy: socksify(network_parameters.proxy.as_deref()),
             network: network_parameters,
-            url,
+            urls,
+            verification_url,
             accounts: Arc::new(RwLock::new(HashMap::<u32, Account>::new())),
-            notify: NativeNotif::new(),
+            notify,
             handles: vec![],
             user_wants_to_sync: Arc::new(AtomicBool::new(false)),
             last_network_call_succeeded: Arc::new(AtomicBool::new(false)),
+            syncer_client: Arc::new(Mutex::new(None)),
             timeout: None,
             store: None,
             master_xpub: None,
@@ -64,12 +72,17 @@ impl Session for ElectrumSession {
         match method {
             "poll_session" => self.poll_session().to_json(),
 
+            "poll_notifications" => Ok(Value::Array(self.notify.poll())),
+
             "connect" => self.connect(&input).to_json(),
 
             "disconnect" => self.disconnect().to_json(),
 
+            "set_electrum_tls" => self.set_electrum_tls(&serde_json::from_value(input)?).to_json(),
+
             "login" => self.login(serde_json::from_value(input)?).to_json(),
             "login_wo" => self.login_wo(serde_json::from_value(input)?).to_json(),
+            "export_watch_only_bundle" => self.export_watch_only_bundle().to_json(),
             "credentials_from_pin_data" => {
                 self.credentials_from_pin_data(&serde_json::from_value(input)?).to_json()
             }
@@ -78,6 +91,10 @@ impl Session for ElectrumSession {
 
             "get_block_height" => self.get_block_height().to_json(),
 
+            "ping_server" => self.ping_server().to_json(),
+            "get_connection_info" => self.get_connection_info().to_json(),
+            "probe_server_capabilities" => self.probe_server_capabilities().to_json(),
+
             "get_subaccount_nums" => self.get_subaccount_nums().to_json(),
 
             "get_subaccounts" => self.get_subaccounts().to_json(),
@@ -87,6 +104,10 @@ impl Session for ElectrumSession {
             "discover_subaccount" => {
                 self.discover_subaccount(serde_json::from_value(input)?).to_json()
             }
+            "export_account" => self.export_account(&serde_json::from_value(input)?).to_json(),
+            "get_account_descriptors" => {
+                self.get_account_descriptors(&serde_json::from_value(input)?).to_json()
+            }
             "get_subaccount_root_path" => {
                 self.get_subaccount_root_path(serde_json::from_value(input)?).to_json()
             }
@@ -111,25 +132,98 @@ impl Session for ElectrumSession {
                 self.update_subaccount(opt).to_json()
             }
 
-            "get_transactions" => {
-                let opt: GetTransactionsOpt = serde_json::from_value(input)?;
-                self.get_transactions(&opt).map(|x| txs_result_value(&x)).map_err(Into::into)
-            }
+            "get_transactions" => self.get_transactions(&serde_json::from_value(input)?).to_json(),
 
             "get_transaction_hex" => get_transaction_hex(self, &input).to_json(),
-            "get_transaction_details" => self
-                .get_transaction_details(input.as_str().ok_or_else(|| {
-                    Error::Generic("get_transaction_details: input is not a string".into())
-                })?)
-                .to_json(),
-            "get_balance" => self.get_balance(&serde_json::from_value(input)?).to_json(),
+            "get_transaction_details" => {
+                let opt = match input {
+                    Value::String(txhash) => GetTransactionDetailsOpt {
+                        txhash,
+                        ..Default::default()
+                    },
+                    Value::Object(_) => serde_json::from_value(input)?,
+                    _ => {
+                        return Err(Error::Generic(
+                            "get_transaction_details: input is not a string nor an object".into(),
+                        )
+                        .into())
+                    }
+                };
+                self.get_transaction_details(&opt).to_json()
+            }
+            "get_balance" => {
+                let opt: GetBalanceOpt = serde_json::from_value(input)?;
+                if opt.policy_asset_only {
+                    return self.get_policy_asset_balance(&opt).to_json();
+                }
+                let balances = self.get_balance(&opt)?;
+                Ok(amount::map::serialize(&balances, serde_json::value::Serializer)?)
+            }
+            "get_dust_threshold" => {
+                self.get_dust_threshold(&serde_json::from_value(input)?).to_json()
+            }
+            "get_balance_at_height" => {
+                let balances = self.get_balance_at_height(&serde_json::from_value(input)?)?;
+                Ok(amount::map::serialize(&balances, serde_json::value::Serializer)?)
+            }
+            "get_balance_detailed" => {
+                self.get_balance_detailed(&serde_json::from_value(input)?).to_json()
+            }
+            "get_output_proofs" => {
+                self.get_output_proofs(&serde_json::from_value(input)?).to_json()
+            }
+            "get_transaction_unblinded" => {
+                let txid = input.as_str().ok_or_else(|| {
+                    Error::Generic("get_transaction_unblinded: input is not a string".into())
+                })?;
+                self.get_transaction_unblinded(txid).to_json()
+            }
+            "unblind_transaction" => {
+                self.unblind_transaction(&serde_json::from_value(input)?).to_json()
+            }
+            "get_wallet_summary" => {
+                self.get_wallet_summary(&serde_json::from_value(input)?).to_json()
+            }
+            "get_portfolio_value" => {
+                self.get_portfolio_value(&serde_json::from_value(input)?).to_json()
+            }
+            "convert_amount" => self.convert_amount(&serde_json::from_value(input)?).to_json(),
             "set_transaction_memo" => set_transaction_memo(self, &input),
+            "set_transaction_memos" => {
+                self.set_transaction_memos(&serde_json::from_value(input)?).to_json()
+            }
             "create_transaction" => create_transaction(self, input).map_err(Into::into),
+            "send_funds" => send_funds(self, input).map_err(Into::into),
+            "bump_fee" => self.bump_fee(&serde_json::from_value(input)?).to_json(),
+            "preview_coin_selection" => {
+                self.preview_coin_selection(&serde_json::from_value(input)?).to_json()
+            }
+            "create_cpfp_transaction" => {
+                self.create_cpfp_transaction(&serde_json::from_value(input)?).to_json()
+            }
+            "rescan_subaccount" => {
+                self.rescan_subaccount(&serde_json::from_value(input)?).to_json()
+            }
+            "get_ancestors" => {
+                let txid = input
+                    .as_str()
+                    .ok_or_else(|| Error::Generic("get_ancestors: input is not a string".into()))?;
+                self.get_ancestors(txid).to_json()
+            }
+            "validate_address" => self.validate_address(&serde_json::from_value(input)?).to_json(),
+            "parse_uri" => self.parse_uri(&serde_json::from_value(input)?).to_json(),
             "get_scriptpubkey_data" => self
                 .get_scriptpubkey_data(input.as_str().ok_or_else(|| {
                     Error::Generic("get_scriptpubkey_data: input is not a string".into())
                 })?)
                 .to_json(),
+            "create_sweep_transaction" => {
+                self.create_sweep_transaction(&serde_json::from_value(input)?).to_json()
+            }
+            "create_psbt" => self.create_psbt(&serde_json::from_value(input)?).to_json(),
+            "transaction_to_psbt" => {
+                self.transaction_to_psbt(&serde_json::from_value(input)?).to_json()
+            }
             "sign_transaction" => self.sign_transaction(&serde_json::from_value(input)?).to_json(),
             "send_transaction" => self.send_transaction(&serde_json::from_value(input)?).to_json(),
             "broadcast_transaction" => self
@@ -150,6 +244,10 @@ impl Session for ElectrumSession {
             "get_fee_estimates" => {
                 self.get_fee_estimates().map_err(Into::into).and_then(|x| fee_estimate_values(&x))
             }
+            "get_fee_estimates_by_target" => self.get_fee_estimates_by_target().to_json(),
+            "fee_rate_at_percentile" => {
+                self.fee_rate_at_percentile(&serde_json::from_value(input)?).to_json()
+            }
             "get_min_fee_rate" => self.get_min_fee_rate().to_json(),
 
             "get_settings" => self.get_settings().to_json(),
@@ -159,10 +257,26 @@ impl Session for ElectrumSession {
             "change_settings" => self.change_settings(&serde_json::from_value(input)?).to_json(),
 
             "get_unspent_outputs" => {
-                self.get_unspent_outputs(&serde_json::from_value(input)?).to_json()
+                let opt: GetUnspentOpt = serde_json::from_value(input)?;
+                if opt.is_paged() {
+                    self.get_unspent_outputs_page(&opt).to_json()
+                } else {
+                    self.get_unspent_outputs(&opt).to_json()
+                }
+            }
+            "set_unspent_output_frozen" => {
+                self.set_unspent_output_frozen(&serde_json::from_value(input)?).to_json()
             }
+            "get_frozen_outputs" => self.get_frozen_outputs().to_json(),
+            "get_pending_transactions" => self.get_pending_transactions().to_json(),
+            "discard_pending_transaction" => {
+                self.discard_pending_transaction(&serde_json::from_value(input)?).to_json()
+            }
+            "diff_since" => self.diff_since(&serde_json::from_value(input)?).to_json(),
+            "get_spend_index" => self.get_spend_index(&serde_json::from_value(input)?).to_json(),
             "load_store" => self.load_store(&serde_json::from_value(input)?).to_json(),
             "get_master_blinding_key" => self.get_master_blinding_key().to_json(),
+            "get_master_fingerprint" => self.get_master_fingerprint().to_json(),
             "set_master_blinding_key" => {
                 self.set_master_blinding_key(&serde_json::from_value(input)?).to_json()
             }
@@ -170,6 +284,9 @@ impl Session for ElectrumSession {
             "get_wallet_hash_id" => self.get_wallet_hash_id().to_json(),
             "get_address_data" => self.get_address_data(serde_json::from_value(input)?).to_json(),
 
+            "verify_store" => self.verify_store().to_json(),
+
+            "get_store_info" => self.get_store_info().to_json(),
             "remove_account" => self.remove_account().to_json(),
 
             // "auth_handler_get_status" => Ok(auth_handler.to_json()),
@@ -205,6 +322,28 @@ pub fn determine_electrum_url(network: &NetworkParameters) -> Result<ElectrumUrl
     }
 }
 
+/// Return the primary electrum server followed by the configured backups.
+pub fn determine_electrum_urls(network: &NetworkParameters) -> Result<ElectrumUrls, Error> {
+    let primary = determine_electrum_url(network)?;
+    let backups = network
+        .electrum_backup_urls
+        .iter()
+        .flatten()
+        .map(|url| url.parse())
+        .collect::<Result<_, _>>()?;
+    Ok(ElectrumUrls::new(primary, backups))
+}
+
+/// Return the electrum server used to cross-check the primary one, if any is configured.
+pub fn determine_verification_url(
+    network: &NetworkParameters,
+) -> Result<Option<ElectrumUrl>, Error> {
+    match network.verification_electrum_url.as_deref() {
+        Some(url) if !url.is_empty() => Ok(Some(url.parse()?)),
+        _ => Ok(None),
+    }
+}
+
 impl From<Error> for JsonError {
     fn from(e: Error) -> Self {
         JsonError {
@@ -230,27 +369,49 @@ pub fn get_transaction_hex(session: &ElectrumSession, input: &Value) -> Result<S
     session.get_transaction_hex(txid)
 }
 
-pub fn txs_result_value(txs: &TxsResult) -> Value {
-    json!(txs.0.clone())
-}
-
 pub fn create_transaction(session: &mut ElectrumSession, input: Value) -> Result<Value, Error> {
-    let mut create_tx: CreateTransaction = serde_json::from_value(input.clone())?;
+    let mut create_tx: CreateTransaction = serde_json::from_value(input)?;
 
-    let res = session.create_transaction(&mut create_tx).map(|v| serde_json::to_value(v).unwrap());
+    let res = if create_tx.estimate_only {
+        session.estimate_transaction(&mut create_tx).map(|v| serde_json::to_value(v).unwrap())
+    } else {
+        session.create_transaction(&mut create_tx).map(|v| serde_json::to_value(v).unwrap())
+    };
 
     Ok(match res {
         Err(ref err) => {
             log::warn!("err {:?}", err);
-            let mut input = input;
-            input["error"] = err.to_gdk_code().into();
-            input
+            json!({
+                "error": {
+                    "code": err.to_gdk_code(),
+                    "message": err.to_string(),
+                    "details": err.details(),
+                }
+            })
         }
 
         Ok(v) => v,
     })
 }
 
+pub fn send_funds(session: &mut ElectrumSession, input: Value) -> Result<Value, Error> {
+    let mut create_tx: CreateTransaction = serde_json::from_value(input)?;
+
+    Ok(match session.send_funds(&mut create_tx) {
+        Err(ref err) => {
+            log::warn!("err {:?}", err);
+            json!({
+                "error": {
+                    "code": err.to_gdk_code(),
+                    "message": err.to_string(),
+                    "details": err.details(),
+                }
+            })
+        }
+        Ok(v) => serde_json::to_value(v)?,
+    })
+}
+
 pub fn set_transaction_memo(session: &ElectrumSession, input: &Value) -> Result<Value, JsonError> {
     // TODO: parse txid?.
     let txid = input["txid"]
@@ -1,18 +1,23 @@
-use crate::account::xpubs_equivalent;
+use crate::account::{xpubs_equivalent, Account};
 use crate::spv::CrossValidationResult;
 use crate::{Error, ScriptStatuses};
 use gdk_common::aes::Aes256GcmSiv;
 use gdk_common::be::BETxidConvert;
 use gdk_common::be::{
-    BEBlockHash, BEBlockHeader, BEScript, BETransaction, BETransactionEntry, BETransactions, BETxid,
+    BEBlockHash, BEBlockHeader, BEOutPoint, BEScript, BETransaction, BETransactionEntry,
+    BETransactions, BETxid,
 };
+use gdk_common::bitcoin::hashes::hex::ToHex;
 use gdk_common::bitcoin::hashes::{sha256, Hash};
 use gdk_common::bitcoin::util::bip32::{DerivationPath, ExtendedPubKey};
-use gdk_common::bitcoin::{Transaction, Txid};
+use gdk_common::bitcoin::{OutPoint, Transaction, Txid};
 use gdk_common::elements;
 use gdk_common::elements::TxOutSecrets;
 use gdk_common::log::{info, log, Level};
-use gdk_common::model::{AccountSettings, FeeEstimate, SPVVerifyTxResult, Settings};
+use gdk_common::model::{
+    AccountSettings, Balances, FeeEstimate, PendingTransaction, SPVVerifyTxResult, Settings,
+    StoreInfo,
+};
 use gdk_common::store::{Decryptable, Encryptable, ToCipher};
 use gdk_common::wally::MasterBlindingKey;
 use gdk_common::NetworkId;
@@ -24,10 +29,18 @@ use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
-use std::time::Instant;
+use std::time::{Instant, UNIX_EPOCH};
 
 pub type Store = Arc<RwLock<StoreMeta>>;
 
+/// Outpoints are coerced into a bitcoin::OutPoint to retain database compatibility
+fn frozen_key(outpoint: &BEOutPoint) -> OutPoint {
+    OutPoint {
+        txid: outpoint.txid().into_bitcoin(),
+        vout: outpoint.vout(),
+    }
+}
+
 /// RawCache is a persisted and encrypted cache of wallet data, contains stuff like wallet transactions
 /// It is fully reconstructable from xpub and data from electrum server (plus master blinding for elements)
 #[derive(Default, Serialize, Deserialize)]
@@ -69,6 +82,11 @@ pub struct RawCache {
 
     /// The master blinding key, available only in liquid
     pub master_blinding: Option<MasterBlindingKey>,
+
+    /// previous transactions of wallet transactions inputs, not belonging to the wallet, fetched
+    /// to show the sender addresses
+    #[serde(default)]
+    pub input_prevouts: HashMap<BETxid, BETransaction>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -105,6 +123,11 @@ pub struct RawAccountCache {
     ///
     /// NOTE: is Option to keep cache backwards-compatibility, remove if breaking cache
     pub script_statuses: Option<ScriptStatuses>,
+
+    /// my unconfirmed txs that have been replaced by a conflicting tx, with the replacing txid.
+    /// They are not in `heights` anymore, but still in `all_txs`.
+    #[serde(default)]
+    pub replaced: HashMap<BETxid, BETxid>,
 }
 
 /// RawStore contains data that are not extractable from xpub+blockchain
@@ -120,6 +143,18 @@ pub struct RawStore {
     // additional fields should always be appended at the end as an `Option` to retain db backwards compatibility
     /// account settings
     accounts_settings: Option<HashMap<u32, AccountSettings>>,
+
+    /// non-standard derivation paths of the accounts root, set at account creation
+    ///
+    /// these can't be discovered from the chain, so they are lost if the store is, the export of
+    /// the account includes its path for that reason
+    accounts_base_paths: Option<HashMap<u32, DerivationPath>>,
+
+    /// outputs excluded from automatic coin selection
+    frozen_outputs: Option<HashSet<OutPoint>>,
+
+    /// signed transactions not broadcast yet
+    pending_transactions: Option<HashMap<Txid, PendingTransaction>>,
 }
 
 pub struct StoreMeta {
@@ -130,6 +165,8 @@ pub struct StoreMeta {
     cipher: Aes256GcmSiv,
     last: HashMap<Kind, sha256::Hash>,
     to_remove: bool,
+    /// the outputs spent by the pending transactions, kept in sync with them
+    reserved_outputs: HashSet<BEOutPoint>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
@@ -267,7 +304,7 @@ impl StoreMeta {
 
         store.accounts_settings.get_or_insert_with(|| Default::default());
 
-        let store = StoreMeta {
+        let mut store = StoreMeta {
             cache,
             store,
             id,
@@ -275,7 +312,9 @@ impl StoreMeta {
             path,
             last: HashMap::new(),
             to_remove: false,
+            reserved_outputs: HashSet::new(),
         };
+        store.update_reserved_outputs();
         Ok(store)
     }
 
@@ -437,10 +476,188 @@ impl StoreMeta {
         Ok(())
     }
 
+    /// Whether the transaction belongs to any of the wallet accounts
+    pub fn is_wallet_tx(&self, txid: &BETxid) -> bool {
+        self.cache.accounts.values().any(|a| a.heights.contains_key(txid))
+    }
+
+    /// Insert the memos of the wallet transactions with a single write of the store, returning
+    /// the txids that are not wallet transactions, whose memos are skipped
+    pub fn insert_memos(
+        &mut self,
+        memos: impl IntoIterator<Item = (BETxid, String)>,
+    ) -> Result<Vec<BETxid>, Error> {
+        let mut unknown = vec![];
+        for (txid, memo) in memos {
+            if self.is_wallet_tx(&txid) {
+                self.store.memos.insert(txid.into_bitcoin(), memo);
+            } else {
+                unknown.push(txid);
+            }
+        }
+        self.flush_store()?;
+        Ok(unknown)
+    }
+
+    /// Forget the transactions of the account confirmed at or above `from_height`, or unconfirmed,
+    /// and the script statuses, so that the next sync fetches every script history again
+    pub fn clear_account_history(
+        &mut self,
+        account_num: u32,
+        from_height: u32,
+    ) -> Result<(), Error> {
+        let acc_store = self.account_cache_mut(account_num)?;
+        acc_store.heights.retain(|_, height| matches!(height, Some(h) if *h < from_height));
+        acc_store.script_statuses = None;
+        self.flush_cache()?;
+        Ok(())
+    }
+
+    /// Where the store is persisted, how much space it takes and how many wallet transactions it
+    /// contains
+    pub fn info(&self) -> Result<StoreInfo, Error> {
+        let mut size_bytes = 0;
+        let mut last_modified = 0;
+        for kind in [Kind::Cache, Kind::Store] {
+            let path = self.path.join(kind.to_string());
+            if !path.exists() {
+                continue;
+            }
+            let metadata = std::fs::metadata(&path)?;
+            size_bytes += metadata.len();
+            let modified =
+                metadata.modified()?.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            last_modified = last_modified.max(modified);
+        }
+        let txids: HashSet<_> =
+            self.cache.accounts.values().flat_map(|a| a.heights.keys()).collect();
+        Ok(StoreInfo {
+            path: self.path.display().to_string(),
+            size_bytes,
+            tx_count: txids.len() as u32,
+            last_modified,
+        })
+    }
+
     pub fn get_memo(&self, txid: &BETxid) -> Option<&String> {
         self.store.memos.get(&txid.into_bitcoin())
     }
 
+    pub fn set_output_frozen(&mut self, outpoint: &BEOutPoint, frozen: bool) -> Result<(), Error> {
+        let frozen_outputs = self.store.frozen_outputs.get_or_insert_with(Default::default);
+        let outpoint = frozen_key(outpoint);
+        if frozen {
+            frozen_outputs.insert(outpoint);
+        } else {
+            frozen_outputs.remove(&outpoint);
+        }
+        self.flush_store()?;
+        Ok(())
+    }
+
+    pub fn is_output_frozen(&self, outpoint: &BEOutPoint) -> bool {
+        self.store.frozen_outputs.as_ref().map_or(false, |f| f.contains(&frozen_key(outpoint)))
+    }
+
+    pub fn frozen_outputs(&self) -> Vec<BEOutPoint> {
+        self.store
+            .frozen_outputs
+            .iter()
+            .flatten()
+            .map(|o| BEOutPoint::new(o.txid.into_net(self.id), o.vout))
+            .collect()
+    }
+
+    pub fn insert_pending_transaction(
+        &mut self,
+        txid: BETxid,
+        tx: PendingTransaction,
+    ) -> Result<(), Error> {
+        // Coerced into a bitcoin::Txid to retain database compatibility
+        let pending = self.store.pending_transactions.get_or_insert_with(Default::default);
+        pending.insert(txid.into_bitcoin(), tx);
+        self.update_reserved_outputs();
+        self.flush_store()?;
+        Ok(())
+    }
+
+    /// Returns the removed transaction, if it was pending
+    pub fn remove_pending_transaction(
+        &mut self,
+        txid: &BETxid,
+    ) -> Result<Option<PendingTransaction>, Error> {
+        let removed = self
+            .store
+            .pending_transactions
+            .as_mut()
+            .and_then(|pending| pending.remove(&txid.into_bitcoin()));
+        if removed.is_some() {
+            self.update_reserved_outputs();
+            self.flush_store()?;
+        }
+        Ok(removed)
+    }
+
+    /// Forget the pending transactions that are part of the wallet transactions, because they
+    /// have been broadcast by this or another client
+    pub fn prune_pending_transactions(&mut self) -> Result<(), Error> {
+        let id = self.id;
+        let accounts = &self.cache.accounts;
+        let pending = match self.store.pending_transactions.as_mut() {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+        let len = pending.len();
+        pending.retain(|txid, tx| {
+            let txid = txid.into_net(id);
+            !accounts.get(&tx.subaccount).map_or(false, |a| a.all_txs.contains_key(&txid))
+        });
+        if pending.len() != len {
+            self.update_reserved_outputs();
+            self.flush_store()?;
+        }
+        Ok(())
+    }
+
+    /// The account owning one of the outputs spent by the transaction, if any
+    pub fn spending_account(&self, tx: &BETransaction) -> Option<u32> {
+        let outpoints = tx.previous_outputs();
+        self.cache.accounts.iter().find_map(|(account_num, acc_store)| {
+            let owns = |o: &BEOutPoint| {
+                acc_store.all_txs.get(&o.txid()).map_or(false, |txe| {
+                    o.vout() < txe.tx.output_len() as u32
+                        && acc_store.paths.contains_key(&txe.tx.output_script(o.vout()))
+                })
+            };
+            outpoints.iter().any(owns).then(|| *account_num)
+        })
+    }
+
+    /// The pending transactions, oldest first
+    pub fn pending_transactions(&self) -> Vec<&PendingTransaction> {
+        let mut pending: Vec<_> =
+            self.store.pending_transactions.iter().flatten().map(|(_, tx)| tx).collect();
+        pending.sort_by_key(|tx| tx.created_at_ts);
+        pending
+    }
+
+    /// Whether the output is spent by a pending transaction
+    pub fn is_output_reserved(&self, outpoint: &BEOutPoint) -> bool {
+        self.reserved_outputs.contains(outpoint)
+    }
+
+    fn update_reserved_outputs(&mut self) {
+        let id = self.id;
+        self.reserved_outputs = self
+            .store
+            .pending_transactions
+            .iter()
+            .flatten()
+            .flat_map(|(_, tx)| tx.used_utxos.iter())
+            .filter_map(|u| u.outpoint(id).ok())
+            .collect();
+    }
+
     pub fn insert_settings(&mut self, settings: Option<Settings>) -> Result<(), Error> {
         self.store.settings = settings;
         self.flush_store()?;
@@ -475,6 +692,23 @@ impl StoreMeta {
         Ok(())
     }
 
+    pub fn get_account_base_path(&self, account_num: u32) -> Option<&DerivationPath> {
+        self.store.accounts_base_paths.as_ref()?.get(&account_num)
+    }
+
+    pub fn insert_account_base_path(
+        &mut self,
+        account_num: u32,
+        base_path: DerivationPath,
+    ) -> Result<(), Error> {
+        self.store
+            .accounts_base_paths
+            .get_or_insert_with(Default::default)
+            .insert(account_num, base_path);
+        self.flush_store()?;
+        Ok(())
+    }
+
     pub fn spv_verification_status(&self, account_num: u32, txid: &BETxid) -> SPVVerifyTxResult {
         let acc_store = match self.account_cache(account_num) {
             Ok(store) => store,
@@ -510,6 +744,81 @@ impl StoreMeta {
         Err(Error::TxNotFound(txid.clone()))
     }
 
+    /// Check the internal consistency of the cached data, returning a description of the issues found
+    pub fn consistency_issues(&self) -> Vec<String> {
+        let mut issues = vec![];
+        let mut known_txids = HashSet::new();
+        for (account_num, acc_store) in self.cache.accounts.iter() {
+            for txid in acc_store.heights.keys() {
+                if !acc_store.all_txs.contains_key(txid) {
+                    issues
+                        .push(format!("subaccount {}: missing transaction {}", account_num, txid));
+                }
+            }
+            for (script, path) in acc_store.paths.iter() {
+                if acc_store.scripts.get(path) != Some(script) {
+                    issues.push(format!(
+                        "subaccount {}: missing script for path {}",
+                        account_num, path
+                    ));
+                }
+            }
+            if acc_store.paths.len() != acc_store.scripts.len() {
+                issues.push(format!("subaccount {}: mismatching paths and scripts", account_num));
+            }
+
+            // The balances from the utxo set must match the ones computed from the tx history
+            let mut utxo_balances = Balances::new();
+            for outpoint in Account::unspents_in(acc_store) {
+                let asset_value = match &outpoint {
+                    BEOutPoint::Bitcoin(_) => acc_store
+                        .all_txs
+                        .get_previous_output_value(&outpoint, &acc_store.unblinded)
+                        .map(|value| ("btc".to_string(), value)),
+                    BEOutPoint::Elements(o) => {
+                        acc_store.unblinded.get(o).map(|s| (s.asset.to_hex(), s.value))
+                    }
+                };
+                match asset_value {
+                    Some((asset_id, value)) => {
+                        *utxo_balances.entry(asset_id).or_default() += value as i64
+                    }
+                    None => issues
+                        .push(format!("subaccount {}: invalid utxo {:?}", account_num, outpoint)),
+                }
+            }
+            let mut history_balances = Balances::new();
+            for txid in acc_store.heights.keys() {
+                if let Some(txe) = acc_store.all_txs.get(txid) {
+                    let changes = txe.tx.my_balance_changes(
+                        &acc_store.all_txs,
+                        &acc_store.paths,
+                        &acc_store.unblinded,
+                    );
+                    for (asset_id, value) in changes {
+                        *history_balances.entry(asset_id).or_default() += value;
+                    }
+                }
+            }
+            utxo_balances.retain(|_, v| *v != 0);
+            history_balances.retain(|_, v| *v != 0);
+            if utxo_balances != history_balances {
+                issues.push(format!(
+                    "subaccount {}: utxo balances {:?} do not match transaction history balances {:?}",
+                    account_num, utxo_balances, history_balances
+                ));
+            }
+
+            known_txids.extend(acc_store.all_txs.keys().map(|txid| txid.into_bitcoin()));
+        }
+        for txid in self.store.memos.keys() {
+            if !known_txids.contains(txid) {
+                issues.push(format!("memo for unknown transaction {}", txid));
+            }
+        }
+        issues
+    }
+
     pub fn update_tip(&mut self, new_height: u32, new_header: BEBlockHeader) -> Result<(), Error> {
         self.cache.tip_ = Some((new_height, new_header));
         self.flush_cache()?;
@@ -529,6 +838,7 @@ impl RawAccountCache {
             indexes: Default::default(),
             xpub,
             bip44_discovered,
+            replaced: Default::default(),
         }
     }
     pub fn get_bitcoin_tx(&self, txid: &Txid) -> Result<Transaction, Error> {
@@ -555,10 +865,27 @@ mod tests {
     use super::*;
     use gdk_common::bitcoin::util::bip32::ExtendedPubKey;
     use gdk_common::bitcoin::{Network, Txid};
+    use gdk_common::model::CreateTxUtxo;
     use gdk_common::{be::BETxid, NetworkId};
     use std::str::FromStr;
     use tempfile::TempDir;
 
+    fn test_xpub() -> ExtendedPubKey {
+        // abandon ... M/49'/0'/0'
+        ExtendedPubKey::from_str("tpubD97UxEEcrMpkE8yG3NQveraWveHzTAJx3KwPsUycx9ABfxRjMtiwfm6BtrY5yhF9yF2eyMg2hyDtGDYXx6gVLBox1m2Mq4u8zB2NXFhUZmm").unwrap()
+    }
+
+    fn open_test_store(dir: &Path) -> StoreMeta {
+        StoreMeta::new(dir, &test_xpub(), NetworkId::Bitcoin(Network::Testnet)).unwrap()
+    }
+
+    /// A testnet store in a temporary directory, deleted when the `TempDir` is dropped
+    fn test_store() -> (TempDir, StoreMeta) {
+        let dir = TempDir::new().unwrap();
+        let store = open_test_store(dir.path());
+        (dir, store)
+    }
+
     #[test]
     fn test_db_roundtrip() {
         let id = NetworkId::Bitcoin(Network::Testnet);
@@ -586,6 +913,224 @@ mod tests {
         assert_eq!(store.store.memos.get(txid_btc), Some(&"memo".to_string()));
     }
 
+    #[test]
+    fn test_store_info() {
+        let id = NetworkId::Bitcoin(Network::Testnet);
+        let xpub = test_xpub();
+        let txid = |c: char| BETxid::from_hex(&c.to_string().repeat(64), id).unwrap();
+
+        let (dir, mut store) = test_store();
+        let info = store.info().unwrap();
+        assert_eq!(info.path, dir.path().display().to_string());
+        assert_eq!(info.size_bytes, 0);
+        assert_eq!(info.tx_count, 0);
+
+        store.make_account(0, xpub, true).unwrap();
+        store.make_account(1, xpub, true).unwrap();
+        store.account_cache_mut(0).unwrap().heights.insert(txid('1'), Some(1));
+        store.account_cache_mut(0).unwrap().heights.insert(txid('2'), None);
+        // a transaction between two subaccounts is counted once
+        store.account_cache_mut(1).unwrap().heights.insert(txid('2'), None);
+        store.flush().unwrap();
+        let info = store.info().unwrap();
+        assert!(info.size_bytes > 0);
+        assert!(info.last_modified > 0);
+        assert_eq!(info.tx_count, 2);
+    }
+
+    #[test]
+    fn test_insert_memos() {
+        let id = NetworkId::Bitcoin(Network::Testnet);
+        let xpub = test_xpub();
+        let txid = |c: char| BETxid::from_hex(&c.to_string().repeat(64), id).unwrap();
+        let (known, other, unknown) = (txid('1'), txid('2'), txid('3'));
+
+        let (dir, mut store) = test_store();
+        store.make_account(0, xpub, true).unwrap();
+        store.make_account(1, xpub, true).unwrap();
+        store.account_cache_mut(0).unwrap().heights.insert(known, Some(1));
+        store.account_cache_mut(1).unwrap().heights.insert(other, None);
+        let unknown_txids = store
+            .insert_memos(vec![
+                (known, "a".to_string()),
+                (unknown, "b".to_string()),
+                (other, "c".to_string()),
+            ])
+            .unwrap();
+        assert_eq!(unknown_txids, vec![unknown]);
+        // the store is flushed when dropped
+        drop(store);
+
+        let store = open_test_store(dir.path());
+        assert_eq!(store.get_memo(&known), Some(&"a".to_string()));
+        assert_eq!(store.get_memo(&other), Some(&"c".to_string()));
+        assert_eq!(store.get_memo(&unknown), None);
+    }
+
+    #[test]
+    fn test_clear_account_history() {
+        let id = NetworkId::Bitcoin(Network::Testnet);
+        let xpub = test_xpub();
+        let txid = |c: char| BETxid::from_hex(&c.to_string().repeat(64), id).unwrap();
+
+        let (_dir, mut store) = test_store();
+        store.make_account(0, xpub, true).unwrap();
+        store.make_account(1, xpub, true).unwrap();
+        let acc_store = store.account_cache_mut(0).unwrap();
+        acc_store.heights.insert(txid('1'), Some(9));
+        acc_store.heights.insert(txid('2'), Some(10));
+        acc_store.heights.insert(txid('3'), None);
+        acc_store.script_statuses = Some(HashMap::new());
+        store.account_cache_mut(1).unwrap().heights.insert(txid('4'), Some(10));
+
+        store.clear_account_history(0, 10).unwrap();
+        let acc_store = store.account_cache(0).unwrap();
+        assert_eq!(acc_store.heights.keys().collect::<Vec<_>>(), vec![&txid('1')]);
+        assert!(acc_store.script_statuses.is_none());
+        assert_eq!(store.account_cache(1).unwrap().heights.len(), 1);
+        assert!(store.clear_account_history(2, 10).is_err());
+    }
+
+    #[test]
+    fn test_frozen_outputs() {
+        let id = NetworkId::Bitcoin(Network::Testnet);
+        let txid = BETxid::from_hex(
+            "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+            id,
+        )
+        .unwrap();
+        let (a, b) = (BEOutPoint::new(txid, 0), BEOutPoint::new(txid, 1));
+
+        let (dir, mut store) = test_store();
+        assert!(store.frozen_outputs().is_empty());
+        store.set_output_frozen(&a, true).unwrap();
+        store.set_output_frozen(&b, true).unwrap();
+        store.set_output_frozen(&b, false).unwrap();
+        assert!(store.is_output_frozen(&a));
+        assert!(!store.is_output_frozen(&b));
+        drop(store);
+
+        let store = open_test_store(dir.path());
+        assert_eq!(store.frozen_outputs(), vec![a.clone()]);
+        assert!(store.is_output_frozen(&a));
+    }
+
+    #[test]
+    fn test_pending_transactions() {
+        let id = NetworkId::Bitcoin(Network::Testnet);
+        let prev_txid = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+        let prev = BEOutPoint::new(BETxid::from_hex(prev_txid, id).unwrap(), 1);
+        let pending = |txid: &str, created_at_ts| PendingTransaction {
+            subaccount: 0,
+            txhash: txid.to_string(),
+            hex: String::new(),
+            used_utxos: vec![CreateTxUtxo {
+                txid: prev_txid.to_string(),
+                vout: 1,
+            }],
+            created_at_ts,
+        };
+        let (a, b) = (
+            "0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098",
+            "9b0fc92260312ce44e74ef369f5c66bbb85848f2eddd5a7a1cde251e54ccfdd5",
+        );
+
+        let (dir, mut store) = test_store();
+        assert!(!store.is_output_reserved(&prev));
+        store.insert_pending_transaction(BETxid::from_hex(b, id).unwrap(), pending(b, 2)).unwrap();
+        store.insert_pending_transaction(BETxid::from_hex(a, id).unwrap(), pending(a, 1)).unwrap();
+        assert!(store.is_output_reserved(&prev));
+        assert!(!store.is_output_reserved(&BEOutPoint::new(prev.txid(), 0)));
+        drop(store);
+
+        let mut store = open_test_store(dir.path());
+        assert_eq!(store.pending_transactions(), vec![&pending(a, 1), &pending(b, 2)]);
+        let removed = store.remove_pending_transaction(&BETxid::from_hex(a, id).unwrap()).unwrap();
+        assert_eq!(removed, Some(pending(a, 1)));
+        assert_eq!(
+            store.remove_pending_transaction(&BETxid::from_hex(a, id).unwrap()).unwrap(),
+            None
+        );
+        assert!(store.is_output_reserved(&prev));
+
+        // a pending transaction that reached the wallet history is pruned
+        store.insert_pending_transaction(BETxid::from_hex(a, id).unwrap(), pending(a, 1)).unwrap();
+        let mut acc_store = RawAccountCache::new(test_xpub(), false);
+        acc_store.all_txs.insert(BETxid::from_hex(a, id).unwrap(), BETransaction::new(id).into());
+        store.cache.accounts.insert(0, acc_store);
+        store.prune_pending_transactions().unwrap();
+        assert_eq!(store.pending_transactions(), vec![&pending(b, 2)]);
+        assert!(store.is_output_reserved(&prev));
+        store.remove_pending_transaction(&BETxid::from_hex(b, id).unwrap()).unwrap();
+        assert!(!store.is_output_reserved(&prev));
+    }
+
+    #[test]
+    fn test_consistency_issues() {
+        let id = NetworkId::Bitcoin(Network::Testnet);
+        let txid = BETxid::from_hex(
+            "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16",
+            id,
+        )
+        .unwrap();
+
+        let (_dir, mut store) = test_store();
+        store.make_account(0, test_xpub(), true).unwrap();
+        assert!(store.consistency_issues().is_empty());
+
+        store.account_cache_mut(0).unwrap().heights.insert(txid, Some(1));
+        store.store.memos.insert(*txid.ref_bitcoin().unwrap(), "memo".to_string());
+        let issues = store.consistency_issues();
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].contains("missing transaction"));
+        assert!(issues[1].contains("memo for unknown transaction"));
+    }
+
+    #[test]
+    fn test_consistency_issues_balances() {
+        use gdk_common::bitcoin::{PackedLockTime, Script, TxIn, TxOut};
+
+        let script = Script::from(vec![0x51]);
+        let path = DerivationPath::from_str("m/0/0").unwrap();
+        let funding = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![],
+            output: vec![TxOut {
+                value: 1000,
+                script_pubkey: script.clone(),
+            }],
+        };
+        let spending = Transaction {
+            version: 2,
+            lock_time: PackedLockTime::ZERO,
+            input: vec![TxIn {
+                previous_output: OutPoint::new(funding.txid(), 0),
+                ..Default::default()
+            }],
+            output: vec![],
+        };
+
+        let (_dir, mut store) = test_store();
+        store.make_account(0, test_xpub(), true).unwrap();
+        let acc_store = store.account_cache_mut(0).unwrap();
+        acc_store.paths.insert(BEScript::Bitcoin(script.clone()), path.clone());
+        acc_store.scripts.insert(path, BEScript::Bitcoin(script));
+        for tx in [&funding, &spending] {
+            let tx = BETransaction::Bitcoin(tx.clone());
+            acc_store.heights.insert(tx.txid(), Some(1));
+            acc_store.all_txs.insert(tx.txid(), tx.into());
+        }
+        assert!(store.consistency_issues().is_empty());
+
+        // the history still counts the spend of an output no longer in the utxo set
+        let funding_txid = BETransaction::Bitcoin(funding).txid();
+        store.account_cache_mut(0).unwrap().heights.remove(&funding_txid);
+        let issues = store.consistency_issues();
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].contains("do not match transaction history balances"));
+    }
+
     #[test]
     fn test_db_upgrade() {
         #[derive(Serialize, Deserialize)]
@@ -54,6 +54,40 @@ pub struct AssetEntry {
     pub version: u8,
 }
 
+/// The informations needed to display a single asset, returned by
+/// [`get_asset`](crate::get_asset).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetInfo {
+    /// Ticker of the asset.
+    pub ticker: Option<String>,
+
+    /// Name of the asset.
+    pub name: String,
+
+    /// Precision of the asset as the number of digits after the decimal
+    /// separator.
+    pub precision: u8,
+
+    /// Contains information regarding the internet domain of the asset issuer.
+    pub entity: serde_json::Value,
+
+    /// The asset icon as a Base64 encoded image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+}
+
+impl AssetInfo {
+    pub(crate) fn new(asset: AssetEntry, icon: Option<String>) -> Self {
+        Self {
+            ticker: asset.ticker,
+            name: asset.name,
+            precision: asset.precision,
+            entity: asset.entity,
+            icon,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Prevout {
     txid: Txid,
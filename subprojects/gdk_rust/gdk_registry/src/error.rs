@@ -33,7 +33,7 @@ pub enum Error {
     /// Returned when none of the fields of
     /// [`GetAssetsParams`](crate::GetAssetsParams) are set when calling
     /// [`get_assets`](crate::get_assets).
-    #[error("Specify either `assets_id` or one of more of the following fields when calling `get_assets`: `names`, `tickers`, `category`")]
+    #[error("Specify either `assets_id` or one of more of the following fields when calling `get_assets`: `names`, `tickers`, `category`, `since_version`")]
     GetAssetsNoFields,
 
     /// Wraps hex parsing error
@@ -56,6 +56,16 @@ pub enum Error {
     #[error("{0}")]
     Poison(String),
 
+    /// Returned when a request to the registry didn't complete in time, even
+    /// after retrying.
+    #[error("Request to {url} timed out after {timeout:?}")]
+    Timeout {
+        /// The url of the request.
+        url: String,
+        /// The timeout of each attempt.
+        timeout: std::time::Duration,
+    },
+
     /// Returned when a registry cache file has yet to be created.
     #[error("Registry cache for this wallet has not been created")]
     CacheNotCreated,
@@ -1,8 +1,11 @@
+use std::fmt;
 use std::fs::File;
 use std::io::{self, BufReader, BufWriter, Seek};
+use std::marker::PhantomData;
 
 use gdk_common::log::{info, log_enabled, Level};
-use serde::{de::DeserializeOwned, Serialize};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
 
 use crate::Result;
 
@@ -15,6 +18,75 @@ pub(crate) fn read<V: DeserializeOwned>(file: &mut File) -> Result<V> {
     Ok(serde_cbor::from_reader(buffered)?)
 }
 
+/// Reads the value of `key` from a file containing a map, skipping over the
+/// values of all the other keys instead of deserializing them.
+pub(crate) fn read_entry<K, V>(file: &mut File, key: &K) -> Result<Option<V>>
+where
+    K: DeserializeOwned + PartialEq,
+    V: DeserializeOwned,
+{
+    file.seek(io::SeekFrom::Start(0))?;
+    let mut deserializer = serde_cbor::Deserializer::from_reader(BufReader::new(file));
+    Ok(find_entry(&mut deserializer, key)?)
+}
+
+/// Deserializes the value of `key` from a map, ignoring all the other values.
+pub(crate) fn find_entry<'de, D, K, V>(
+    deserializer: D,
+    key: &K,
+) -> std::result::Result<Option<V>, D::Error>
+where
+    D: de::Deserializer<'de>,
+    K: Deserialize<'de> + PartialEq,
+    V: Deserialize<'de>,
+{
+    FindEntry(key, PhantomData).deserialize(deserializer)
+}
+
+struct FindEntry<'k, K, V>(&'k K, PhantomData<V>);
+
+impl<'de, 'k, K, V> DeserializeSeed<'de> for FindEntry<'k, K, V>
+where
+    K: Deserialize<'de> + PartialEq,
+    V: Deserialize<'de>,
+{
+    type Value = Option<V>;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'k, K, V> Visitor<'de> for FindEntry<'k, K, V>
+where
+    K: Deserialize<'de> + PartialEq,
+    V: Deserialize<'de>,
+{
+    type Value = Option<V>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> std::result::Result<Self::Value, A::Error> {
+        let mut found = None;
+        while let Some(key) = map.next_key::<K>()? {
+            if found.is_none() && key == *self.0 {
+                found = Some(map.next_value()?);
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(found)
+    }
+}
+
 pub(crate) fn write<V: Serialize>(value: &V, file: &mut File) -> Result<()> {
     // Empty the file before writing to avoid having leftover trailing bytes if
     // the new contents are shorter than the old ones (e.g. old file was
@@ -31,6 +103,7 @@ pub(crate) fn write<V: Serialize>(value: &V, file: &mut File) -> Result<()> {
 mod test {
     use super::*;
     use serde_json::Value;
+    use std::collections::HashMap;
 
     #[test]
     fn test_roundtrip() {
@@ -40,4 +113,18 @@ mod test {
         let value = read::<Value>(&mut tempfile).unwrap();
         assert_eq!(content, value, "roundtrip failing");
     }
+
+    #[test]
+    fn test_read_entry() {
+        let mut tempfile = tempfile::tempfile().unwrap();
+        let content: HashMap<u32, Value> =
+            (0..100).map(|i| (i, Value::String(format!("value {}", i)))).collect();
+        write(&content, &mut tempfile).unwrap();
+        let value = read_entry::<u32, Value>(&mut tempfile, &42).unwrap();
+        assert_eq!(value, Some(Value::String("value 42".into())));
+        assert_eq!(read_entry::<u32, Value>(&mut tempfile, &100).unwrap(), None);
+
+        write(&Value::Null, &mut tempfile).unwrap();
+        assert!(read_entry::<u32, Value>(&mut tempfile, &42).is_err());
+    }
 }
@@ -3,6 +3,7 @@ use gdk_common::elements::{hashes::hex::FromHex, AssetId};
 use crate::assets_or_icons::AssetsOrIcons;
 use crate::params::ElementsNetwork;
 use crate::registry_infos::{RegistryAssets, RegistryIcons};
+use crate::AssetEntry;
 
 const ASSETS: [&str; ElementsNetwork::len()] = [
     include_str!("./liquid_assets.json"),
@@ -24,6 +25,20 @@ pub(crate) fn icons(network: ElementsNetwork) -> RegistryIcons {
     serde_json::from_str(to_str(network, AssetsOrIcons::Icons)).expect("checked at test time")
 }
 
+/// Returns a single hard coded asset without deserializing all of them.
+pub(crate) fn asset(network: ElementsNetwork, id: &AssetId) -> Option<AssetEntry> {
+    let json = to_str(network, AssetsOrIcons::Assets);
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    crate::file::find_entry(&mut deserializer, id).expect("checked at test time")
+}
+
+/// Returns a single hard coded icon without deserializing all of them.
+pub(crate) fn icon(network: ElementsNetwork, id: &AssetId) -> Option<String> {
+    let json = to_str(network, AssetsOrIcons::Icons);
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    crate::file::find_entry(&mut deserializer, id).expect("checked at test time")
+}
+
 fn to_str(network: ElementsNetwork, what: AssetsOrIcons) -> &'static str {
     match what {
         AssetsOrIcons::Assets => ASSETS[network as usize],
@@ -56,6 +71,8 @@ pub(crate) mod test {
             let _ = assets(n);
             let _ = icons(n);
             let _ = policy_asset_id(n);
+            assert_eq!(asset(n, &policy_asset_id(n)).as_ref(), assets(n).get(&policy_asset_id(n)));
+            assert_eq!(icon(n, &policy_asset_id(n)).as_ref(), icons(n).get(&policy_asset_id(n)));
         }
     }
 
@@ -1,28 +1,62 @@
 use std::collections::HashMap;
-use std::io::BufReader;
+use std::error::Error as _;
+use std::io::{self, BufReader};
 use std::time::{Duration, Instant};
 
-use gdk_common::log::info;
+use gdk_common::log::{info, warn};
 use gdk_common::ureq;
+use gdk_common::util::Backoff;
 
-use crate::Result;
+use crate::{Error, Result};
 use serde_json::Value;
 
+/// Delay before the first retry of a failed call.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Upper bound of the delay between retries.
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(8);
+
 /// Returns `None` if the response status is `304 Not Modified`.
+///
+/// Each attempt can take up to `timeout`, failed attempts are retried up to
+/// `max_retries` times with an exponential backoff if the error is transient.
 pub(crate) fn call(
     url: &str,
     agent: &ureq::Agent,
     last_modified: &str,
     custom_params: &HashMap<String, String>,
+    timeout: Duration,
+    max_retries: u32,
 ) -> Result<Option<(Value, String)>> {
     let start = Instant::now();
 
-    let mut request =
-        agent.get(url).timeout(Duration::from_secs(30)).set("If-Modified-Since", last_modified);
-    for param in custom_params {
-        request = request.set(param.0, param.1);
-    }
-    let response = request.call()?;
+    let mut attempt = 0;
+    let mut backoff = Backoff::new(RETRY_BASE_DELAY, RETRY_MAX_DELAY);
+    let response = loop {
+        let mut request = agent.get(url).timeout(timeout).set("If-Modified-Since", last_modified);
+        for param in custom_params {
+            request = request.set(param.0, param.1);
+        }
+        match request.call() {
+            Ok(response) => break response,
+            Err(err) if attempt < max_retries && is_transient(&err) => {
+                attempt += 1;
+                let delay = backoff.next_delay();
+                warn!(
+                    "call to {} failed with {}, retrying in {:?} ({}/{})",
+                    url, err, delay, attempt, max_retries
+                );
+                std::thread::sleep(delay);
+            }
+            Err(err) if is_timeout(&err) => {
+                return Err(Error::Timeout {
+                    url: url.to_owned(),
+                    timeout,
+                })
+            }
+            Err(err) => return Err(err.into()),
+        }
+    };
 
     let status = response.status();
 
@@ -48,6 +82,26 @@ pub(crate) fn call(
     Ok(Some((value, last_modified)))
 }
 
+fn is_timeout(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::Transport(transport) => transport
+            .source()
+            .and_then(|source| source.downcast_ref::<io::Error>())
+            .map_or(false, |e| {
+                matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)
+            }),
+        ureq::Error::Status(..) => false,
+    }
+}
+
+/// Whether the request could succeed if retried.
+fn is_transient(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::Transport(_) => true,
+        ureq::Error::Status(status, _) => *status >= 500,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -76,12 +130,53 @@ mod test {
                 ),
             );
 
-            let (_, last_modified) =
-                call(&server.url_str(what.endpoint()), &agent, "", &HashMap::new())
-                    .unwrap()
-                    .unwrap();
+            let (_, last_modified) = call(
+                &server.url_str(what.endpoint()),
+                &agent,
+                "",
+                &HashMap::new(),
+                Duration::from_secs(30),
+                0,
+            )
+            .unwrap()
+            .unwrap();
 
             assert_eq!(expected_last_modified, last_modified);
         }
     }
+
+    #[test]
+    fn test_call_retry() {
+        use httptest::{cycle, matchers::*, responders::*, Expectation, Server};
+
+        let agent = ureq::agent();
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/"))
+                .times(2)
+                .respond_with(cycle![status_code(503), status_code(200).body("{}")]),
+        );
+        let url = server.url_str("/");
+
+        let res = call(&url, &agent, "", &HashMap::new(), Duration::from_secs(30), 1);
+        assert!(res.unwrap().is_some());
+    }
+
+    #[test]
+    fn test_call_timeout() {
+        use httptest::{matchers::*, responders::*, Expectation, Server};
+
+        let agent = ureq::agent();
+        let server = Server::run();
+        server.expect(
+            Expectation::matching(request::method_path("GET", "/"))
+                .times(2)
+                .respond_with(delay_and_then(Duration::from_secs(2), status_code(200).body("{}"))),
+        );
+        let url = server.url_str("/");
+
+        let err =
+            call(&url, &agent, "", &HashMap::new(), Duration::from_millis(200), 1).unwrap_err();
+        assert!(matches!(err, Error::Timeout { .. }), "{:?}", err);
+    }
 }
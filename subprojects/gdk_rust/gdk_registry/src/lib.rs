@@ -34,6 +34,7 @@ mod last_modified;
 mod params;
 mod registry;
 mod registry_infos;
+mod versions;
 
 use std::path::Path;
 use std::sync::Arc;
@@ -46,13 +47,14 @@ use last_modified::LastModified;
 use params::GetAssetsQuery;
 use registry_infos::RegistrySource;
 
-pub use asset_entry::AssetEntry;
+pub use asset_entry::{AssetEntry, AssetInfo};
 pub use error::{Error, Result};
 pub use hard_coded::policy_asset_id;
 pub use params::{
-    AssetCategory, Config, ElementsNetwork, GetAssetsBuilder, GetAssetsParams, RefreshAssetsParams,
+    AssetCategory, Config, ElementsNetwork, GetAssetParams, GetAssetsBuilder, GetAssetsParams,
+    RefreshAssetsParams,
 };
-pub use registry_infos::RegistryInfos;
+pub use registry_infos::{RegistryChanges, RegistryInfos};
 
 /// Initialize the library by specifying the root directory where the cached
 /// data is persisted across sessions.
@@ -66,16 +68,27 @@ pub fn init(dir: impl AsRef<Path>) -> Result<()> {
 /// Unlike [`refresh_assets`], this function will cache the queried assets to
 /// avoid performing a full registry read on every call. The cache file stored
 /// on disk is encrypted via the wallet's xpub key.
+///
+/// When `since_version` is set only the assets added or modified after that
+/// registry version are returned, together with the current version.
 pub fn get_assets(params: GetAssetsParams) -> Result<RegistryInfos> {
     let network = params.config.network;
+    let since_version = params.since_version;
+
+    let delta = |registry: Result<RegistryInfos>| match since_version {
+        Some(version) => registry::changed_since(network, registry?, version),
+        None => registry,
+    };
 
     let (assets_id, xpub) = match params.into_query()? {
         GetAssetsQuery::FromCache(assets_id, xpub) => (assets_id, xpub),
-        GetAssetsQuery::FromRegistry(matcher) => return registry::filter_full(network, &*matcher),
+        GetAssetsQuery::FromRegistry(matcher) => {
+            return delta(registry::filter_full(network, &*matcher))
+        }
         GetAssetsQuery::FromHardCoded(matcher) => {
-            return registry::filter_hard_coded(network, &*matcher)
+            return delta(registry::filter_hard_coded(network, &*matcher))
         }
-        GetAssetsQuery::WholeRegistry => return registry::get_full(network),
+        GetAssetsQuery::WholeRegistry => return delta(registry::get_full(network)),
     };
 
     let mut cache_files = cache::CACHE_FILES.lock()?;
@@ -151,6 +164,16 @@ pub fn get_assets(params: GetAssetsParams) -> Result<RegistryInfos> {
     Ok(cache.to_registry(from_cache))
 }
 
+/// Returns informations about a single asset and its icon, or `None` if the
+/// asset is not in the local registry.
+///
+/// Unlike [`get_assets`], only the requested asset is deserialized from the
+/// local registry, which makes this suitable for frequent lookups.
+pub fn get_asset(params: GetAssetParams) -> Result<Option<AssetInfo>> {
+    let asset = registry::get_asset(params.config.network, &params.asset_id)?;
+    Ok(asset.map(|(asset, icon)| AssetInfo::new(asset, icon)))
+}
+
 /// Returns informations about a set of assets and related icons.
 ///
 /// Results could come from the persisted cached value when `params.refresh`
@@ -159,6 +182,18 @@ pub fn get_assets(params: GetAssetsParams) -> Result<RegistryInfos> {
 /// managed by Blockstream and no proxy is used to access it. This default
 /// configuration can be overridden by providing the `params.config` parameter.
 pub fn refresh_assets(params: RefreshAssetsParams) -> Result<RegistrySource> {
+    refresh(params).map(|(source, _)| source)
+}
+
+/// Fetches the asset registry like [`refresh_assets`] but without updating
+/// the local copy, returning how many assets and icons would be added or
+/// updated.
+pub fn refresh_assets_dry_run(mut params: RefreshAssetsParams) -> Result<RegistryChanges> {
+    params.dry_run = true;
+    refresh(params).map(|(_, changes)| changes)
+}
+
+fn refresh(params: RefreshAssetsParams) -> Result<(RegistrySource, RegistryChanges)> {
     if !params.wants_something() {
         return Err(Error::BothAssetsIconsFalse);
     }
@@ -176,16 +211,19 @@ pub fn refresh_assets(params: RefreshAssetsParams) -> Result<RegistrySource> {
         })
     };
 
-    let icons_source = params
+    let (icons_source, icons_changes) = params
         .wants_icons()
         // forces multiline formatting
         .then(|| registry::refresh_icons(&params))
         .transpose()?
         .unwrap_or_default();
 
-    let assets_source = assets_handle.join().unwrap()?;
+    let (assets_source, assets_changes) = assets_handle.join().unwrap()?;
 
-    Ok(RegistrySource::merge(assets_source, icons_source))
+    Ok((
+        RegistrySource::merge(assets_source, icons_source),
+        RegistryChanges::merge(assets_changes, icons_changes),
+    ))
 }
 
 #[cfg(test)]
@@ -546,6 +584,123 @@ mod tests {
             assert_eq!(res.icons.len(), 1);
         }
 
+        #[test]
+        fn get_assets_delta() {
+            let _ = env_logger::try_init();
+
+            let temp_dir = TempDir::new().unwrap();
+            info!("{:?}", temp_dir);
+            init(&temp_dir).unwrap();
+
+            let xpub = ExtendedPubKey::from_str(DEFAULT_XPUB).unwrap();
+            let delta = |version| {
+                let params = GetAssetsBuilder::new().since_version(version).build();
+                super::get_assets(params).unwrap()
+            };
+
+            // `since_version` can't be combined with `assets_id`.
+            let params = GetAssetsBuilder::new().assets_id([], xpub).since_version(0).build();
+            assert!(matches!(super::get_assets(params), Err(Error::GetAssetsIdNotAlone)));
+
+            // Nothing changed before the first refresh.
+            let res = delta(0);
+            assert_eq!(res.version, Some(0));
+            assert!(res.assets.is_empty());
+            assert!(res.icons.is_empty());
+
+            test_refresh_assets(true, true, false).unwrap();
+            let res = delta(0);
+            assert_eq!(res.version, Some(2));
+            assert!(!res.assets.is_empty());
+            assert!(!res.icons.is_empty());
+
+            // Refreshing without changes on the server doesn't bump the version.
+            test_refresh_assets(true, true, false).unwrap();
+            assert!(delta(2).assets.is_empty());
+            assert_eq!(delta(2).version, Some(2));
+
+            // Only the new assets and icons are returned.
+            assets_or_icons::test::update_liquid_data();
+            test_refresh_assets(true, true, false).unwrap();
+            let res = delta(2);
+            let full = get_full_registry();
+            assert_eq!(res.version, Some(4));
+            assert!(!res.assets.is_empty());
+            assert!(!res.icons.is_empty());
+            assert!(res.assets.len() < full.assets.len());
+            assert!(res.icons.len() < full.icons.len());
+        }
+
+        #[test]
+        fn refresh_assets_dry_run() {
+            let _ = env_logger::try_init();
+
+            let temp_dir = TempDir::new().unwrap();
+            info!("{:?}", temp_dir);
+            init(&temp_dir).unwrap();
+
+            let dry_run = || {
+                let server = Server::run();
+                let config = local_server_config(&server, true, true);
+                let params = RefreshAssetsParams::new(true, true, config, None);
+                super::refresh_assets_dry_run(params).unwrap()
+            };
+
+            let expected = RegistryChanges { added: 4, updated: 0, icons_new: 0 };
+            let before = get_full_registry();
+            assert_eq!(dry_run(), expected);
+
+            // The local registry is left untouched.
+            assert_eq!(get_full_registry(), before);
+            assert_eq!(dry_run(), expected);
+
+            test_refresh_assets(true, true, false).unwrap();
+            assert_eq!(dry_run(), RegistryChanges::default());
+
+            assets_or_icons::test::update_liquid_data();
+            assert_eq!(dry_run(), RegistryChanges { added: 2, updated: 0, icons_new: 2 });
+        }
+
+        #[test]
+        fn get_single_asset() {
+            let _ = env_logger::try_init();
+
+            let temp_dir = TempDir::new().unwrap();
+            info!("{:?}", temp_dir);
+            init(&temp_dir).unwrap();
+
+            let get_asset = |id: &str| {
+                let params = GetAssetParams {
+                    asset_id: AssetId::from_str(id).unwrap(),
+                    config: Config::default(),
+                };
+                super::get_asset(params).unwrap()
+            };
+
+            // Hard coded asset, with its icon.
+            let policy_asset = policy_asset_id(ElementsNetwork::Liquid);
+            let lbtc = get_asset(&policy_asset.to_string()).unwrap();
+            assert_eq!(lbtc.ticker.as_deref(), Some("L-BTC"));
+            assert_eq!(lbtc.precision, 8);
+            assert!(lbtc.icon.is_some());
+
+            // Only in the local registry once it's refreshed, without an icon.
+            const MCT: &str = "4d4354944366ea1e33f27c37fec97504025d6062c551208f68597d1ed40ec53e";
+            assert_eq!(get_asset(MCT), None);
+            test_refresh_assets(true, true, false).unwrap();
+            let mct = get_asset(MCT).unwrap();
+            assert_eq!(mct.ticker.as_deref(), Some("MCT"));
+            assert_eq!(mct.name, "Magical Crypto Token");
+            assert_eq!(mct.icon, None);
+            let full = get_full_registry();
+            assert_eq!(mct, AssetInfo::new(full.assets[&AssetId::from_str(MCT).unwrap()].clone(), None));
+
+            // Corrupted files are treated as empty.
+            registry::tests::corrupt_file(ElementsNetwork::Liquid, AssetsOrIcons::Assets).unwrap();
+            assert_eq!(get_asset(MCT), None);
+            assert!(get_asset(&policy_asset.to_string()).is_some());
+        }
+
         #[test]
         fn get_assets_extended() {
             let _ = env_logger::try_init();
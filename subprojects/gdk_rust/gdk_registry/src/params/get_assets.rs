@@ -23,11 +23,26 @@ pub struct GetAssetsParams {
     #[serde(default)]
     category: Option<AssetCategory>,
 
+    /// Only return the assets added or modified after this registry version.
+    #[serde(default)]
+    pub(crate) since_version: Option<u64>,
+
     /// Options to configure network used and registry connection.
     #[serde(default)]
     pub(crate) config: Config,
 }
 
+/// Parameters passed to [`crate::get_asset`].
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct GetAssetParams {
+    /// The id of the asset to look up.
+    pub asset_id: AssetId,
+
+    /// Options to configure network used and registry connection.
+    #[serde(default)]
+    pub config: Config,
+}
+
 ///
 #[derive(Debug, Copy, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "snake_case")]
@@ -64,6 +79,10 @@ pub(crate) enum GetAssetsQuery {
 
 impl GetAssetsParams {
     pub(crate) fn into_query(self) -> crate::Result<GetAssetsQuery> {
+        if self.assets_id.is_some() && self.since_version.is_some() {
+            return Err(Error::GetAssetsIdNotAlone);
+        }
+
         match (self.assets_id, self.names, self.tickers, self.category) {
             // If both `assets_id` and any other field is set we return an
             // error.
@@ -73,6 +92,10 @@ impl GetAssetsParams {
 
             (None, _, _, Some(AssetCategory::All)) => Ok(GetAssetsQuery::WholeRegistry),
 
+            (None, None, None, None) if self.since_version.is_some() => {
+                Ok(GetAssetsQuery::WholeRegistry)
+            }
+
             (None, None, None, None) => Err(Error::GetAssetsNoFields),
 
             (Some(assets_id), None, None, None) if self.xpub.is_some() => {
@@ -157,6 +180,12 @@ impl GetAssetsBuilder {
         self
     }
 
+    /// Only returns the assets added or modified after `version`.
+    pub fn since_version(mut self, version: u64) -> Self {
+        self.0.since_version = Some(version);
+        self
+    }
+
     ///
     pub fn config(mut self, config: Config) -> Self {
         self.0.config = config;
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::time::Duration;
 
 use gdk_common::bitcoin::util::bip32::ExtendedPubKey;
 use gdk_common::ureq;
@@ -11,6 +12,10 @@ use gdk_common::network;
 
 const BASE_URL: &str = "http://assets.blockstream.info";
 
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+const DEFAULT_MAX_RETRIES: u32 = 2;
+
 /// Parameters passed to [`crate::refresh_assets`].
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct RefreshAssetsParams {
@@ -31,6 +36,20 @@ pub struct RefreshAssetsParams {
     /// `missing` section.
     #[serde(default)]
     pub(crate) xpub: Option<ExtendedPubKey>,
+
+    /// Timeout in seconds of each request to the registry. Defaults to 30.
+    #[serde(default)]
+    timeout_secs: Option<u64>,
+
+    /// How many times a request to the registry is retried after a network
+    /// failure or a server error. Defaults to 2.
+    #[serde(default)]
+    max_retries: Option<u32>,
+
+    /// Fetch the registry and compare it to the local copy without writing
+    /// anything to disk.
+    #[serde(default)]
+    pub(crate) dry_run: bool,
 }
 
 impl RefreshAssetsParams {
@@ -49,9 +68,25 @@ impl RefreshAssetsParams {
             icons,
             config,
             xpub,
+            timeout_secs: None,
+            max_retries: None,
+            dry_run: false,
         }
     }
 
+    /// Whether this refresh should leave the local registry untouched.
+    pub const fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub(crate) fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS))
+    }
+
+    pub(crate) fn max_retries(&self) -> u32 {
+        self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES)
+    }
+
     pub(crate) fn url(&self, what: AssetsOrIcons) -> String {
         format!("{}{}", self.config.url, what.endpoint())
     }
@@ -99,6 +134,16 @@ impl Default for Config {
     }
 }
 
+impl Config {
+    /// The default configuration for another network.
+    pub fn with_network(network: ElementsNetwork) -> Self {
+        Self {
+            network,
+            ..Default::default()
+        }
+    }
+}
+
 /// Discriminate the elements network
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -126,6 +171,16 @@ impl fmt::Display for ElementsNetwork {
     }
 }
 
+impl From<network::ElementsNetwork> for ElementsNetwork {
+    fn from(network: network::ElementsNetwork) -> Self {
+        match network {
+            network::ElementsNetwork::Liquid => Self::Liquid,
+            network::ElementsNetwork::LiquidTestnet => Self::LiquidTestnet,
+            network::ElementsNetwork::ElementsRegtest => Self::ElementsRegtest,
+        }
+    }
+}
+
 impl ElementsNetwork {
     /// Returns the number of possible networks.
     pub(crate) const fn len() -> usize {
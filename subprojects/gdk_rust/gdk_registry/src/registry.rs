@@ -3,20 +3,24 @@ use std::fs::{self, File, OpenOptions};
 use std::path::Path;
 use std::sync::{Mutex, MutexGuard};
 
+use gdk_common::elements::AssetId;
 use gdk_common::log::{debug, warn};
 use gdk_common::once_cell::sync::OnceCell;
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::params::{ElementsNetwork, RefreshAssetsParams};
-use crate::registry_infos::{RegistryAssets, RegistryIcons, RegistrySource};
+use crate::registry_infos::{RegistryAssets, RegistryChanges, RegistryIcons, RegistrySource};
+use crate::versions::Versions;
 use crate::{cache, file, hard_coded, http};
 use crate::{AssetEntry, AssetsOrIcons, Error, LastModified, RegistryInfos, Result};
 
 type LastModifiedFiles = HashMap<ElementsNetwork, Mutex<File>>;
 type RegistryFiles = HashMap<(ElementsNetwork, AssetsOrIcons), Mutex<File>>;
+type VersionsFiles = HashMap<ElementsNetwork, Mutex<File>>;
 
 static LAST_MODIFIED_FILES: OnceCell<LastModifiedFiles> = OnceCell::new();
 static REGISTRY_FILES: OnceCell<RegistryFiles> = OnceCell::new();
+static VERSIONS_FILES: OnceCell<VersionsFiles> = OnceCell::new();
 
 /// Returns the file at `path`, using `initializer` to initialize the file's
 /// contents if it doesn't already exist.
@@ -39,6 +43,8 @@ pub(crate) fn init(registry_dir: impl AsRef<Path>) -> Result<()> {
     let mut registry_files: RegistryFiles =
         HashMap::with_capacity(ElementsNetwork::len() * AssetsOrIcons::len());
 
+    let mut versions_files: VersionsFiles = HashMap::with_capacity(ElementsNetwork::len());
+
     let mut path = registry_dir.as_ref().to_owned();
 
     for network in ElementsNetwork::iter() {
@@ -48,6 +54,9 @@ pub(crate) fn init(registry_dir: impl AsRef<Path>) -> Result<()> {
         let file = get_file(&path.join("last-modified"), LastModified::default)?;
         last_modified_files.insert(network, Mutex::new(file));
 
+        let file = get_file(&path.join("versions"), Versions::default)?;
+        versions_files.insert(network, Mutex::new(file));
+
         {
             let assets = AssetsOrIcons::Assets;
             path.push(assets.to_string());
@@ -71,12 +80,18 @@ pub(crate) fn init(registry_dir: impl AsRef<Path>) -> Result<()> {
 
     REGISTRY_FILES.set(registry_files).map_err(|_err| Error::AlreadyInitialized)?;
 
+    VERSIONS_FILES.set(versions_files).map_err(|_err| Error::AlreadyInitialized)?;
+
     Ok(())
 }
 
-pub(crate) fn refresh_assets(params: &RefreshAssetsParams) -> Result<RegistrySource> {
-    match refresh::<RegistryAssets>(AssetsOrIcons::Assets, params)? {
-        Some(mut assets) => {
+pub(crate) fn refresh_assets(
+    params: &RefreshAssetsParams,
+) -> Result<(RegistrySource, RegistryChanges)> {
+    match refresh::<AssetEntry>(AssetsOrIcons::Assets, params)? {
+        Some((_, changes)) if params.dry_run => Ok((RegistrySource::Downloaded, changes)),
+
+        Some((mut assets, changes)) => {
             let len = assets.len();
             debug!("downloaded {} assets", assets.len());
             assets.retain(|_, entry| entry.verifies().unwrap_or(false));
@@ -86,27 +101,58 @@ pub(crate) fn refresh_assets(params: &RefreshAssetsParams) -> Result<RegistrySou
             if let Some(xpub) = params.xpub {
                 cache::update_missing_assets(xpub, &assets)?;
             }
-            Ok(RegistrySource::Downloaded)
+            Ok((RegistrySource::Downloaded, changes))
         }
 
-        _ => Ok(RegistrySource::NotModified),
+        _ => Ok((RegistrySource::NotModified, RegistryChanges::default())),
     }
 }
 
-pub(crate) fn refresh_icons(params: &RefreshAssetsParams) -> Result<RegistrySource> {
-    match refresh::<RegistryIcons>(AssetsOrIcons::Icons, params)? {
-        Some(icons) => {
+pub(crate) fn refresh_icons(
+    params: &RefreshAssetsParams,
+) -> Result<(RegistrySource, RegistryChanges)> {
+    match refresh::<String>(AssetsOrIcons::Icons, params)? {
+        Some((icons, changes)) => {
             debug!("downloaded {} icons", icons.len());
-            if let Some(xpub) = params.xpub {
+            if let (Some(xpub), false) = (params.xpub, params.dry_run) {
                 cache::update_missing_icons(xpub, &icons)?;
             }
-            Ok(RegistrySource::Downloaded)
+            let changes = RegistryChanges {
+                icons_new: changes.added + changes.updated,
+                ..Default::default()
+            };
+            Ok((RegistrySource::Downloaded, changes))
         }
 
-        _ => Ok(RegistrySource::NotModified),
+        _ => Ok((RegistrySource::NotModified, RegistryChanges::default())),
     }
 }
 
+/// Returns a single asset and its icon, if any, without reading the whole
+/// local registry.
+pub(crate) fn get_asset(
+    network: ElementsNetwork,
+    id: &AssetId,
+) -> Result<Option<(AssetEntry, Option<String>)>> {
+    // Hard coded values take precedence over the local registry, see `get_full`.
+    let asset = match hard_coded::asset(network, id) {
+        Some(asset) => Some(asset),
+        None => fetch_entry(network, AssetsOrIcons::Assets, id)?,
+    };
+
+    let asset = match asset {
+        Some(asset) => asset,
+        None => return Ok(None),
+    };
+
+    let icon = match hard_coded::icon(network, id) {
+        Some(icon) => Some(icon),
+        None => fetch_entry(network, AssetsOrIcons::Icons, id)?,
+    };
+
+    Ok(Some((asset, icon)))
+}
+
 /// Returns all the local assets and icons.
 pub(crate) fn get_full(network: ElementsNetwork) -> Result<RegistryInfos> {
     let assets = {
@@ -139,6 +185,22 @@ pub(crate) fn filter_hard_coded(
     filter(registry, matcher)
 }
 
+/// Retains only the assets and icons added or modified after `version`,
+/// setting the current registry version on the returned infos.
+pub(crate) fn changed_since(
+    network: ElementsNetwork,
+    mut registry: RegistryInfos,
+    version: u64,
+) -> Result<RegistryInfos> {
+    let versions = crate::file::read::<Versions>(&mut *get_versions_file(network)?)?;
+
+    registry.assets.retain(|id, _| versions.changed_since(id, version));
+    registry.icons.retain(|id, _| versions.changed_since(id, version));
+    registry.version = Some(versions.version());
+
+    Ok(registry)
+}
+
 fn filter(
     mut registry: RegistryInfos,
     matcher: &dyn Fn(&AssetEntry, Option<&str>) -> bool,
@@ -176,26 +238,73 @@ fn fetch<T: Default + Serialize + DeserializeOwned>(
     }
 }
 
-fn refresh<T: Serialize + DeserializeOwned>(
+/// Reads the entry `id` of the local registry `what`, if any.
+fn fetch_entry<V: DeserializeOwned>(
+    network: ElementsNetwork,
+    what: AssetsOrIcons,
+    id: &AssetId,
+) -> Result<Option<V>> {
+    let file = &mut *get_registry_file(network, what)?;
+
+    match file::read_entry(file, id) {
+        Ok(value) => Ok(value),
+
+        Err(err) => {
+            // The file gets reset on the next full read, see `fetch`.
+            warn!("couldn't deserialize local {} due to {}", what, err);
+            Ok(None)
+        }
+    }
+}
+
+/// Downloads the registry `what` if it was modified, returning it together
+/// with the number of entries added or updated compared to the local copy.
+///
+/// The local copy is not modified if `params.dry_run` is set.
+fn refresh<V: Serialize + DeserializeOwned + PartialEq>(
     what: AssetsOrIcons,
     params: &RefreshAssetsParams,
-) -> Result<Option<T>> {
+) -> Result<Option<(HashMap<AssetId, V>, RegistryChanges)>> {
     let file = &mut *get_registry_file(params.network(), what)?;
 
-    let last_modified = if file::read::<T>(file).is_ok() {
+    let local = file::read::<HashMap<AssetId, V>>(file).ok();
+    let last_modified = if local.is_some() {
         get_last_modified(params.network(), what)?
     } else {
         String::new()
     };
 
-    match http::call(&params.url(what), &params.agent()?, &last_modified, &params.custom_headers())?
-    {
+    match http::call(
+        &params.url(what),
+        &params.agent()?,
+        &last_modified,
+        &params.custom_headers(),
+        params.timeout(),
+        params.max_retries(),
+    )? {
         Some((value, new_modified)) => {
             debug!("fetched {} were last modified {}", what, new_modified);
-            let downloaded = serde_json::from_value::<T>(value)?;
-            file::write(&downloaded, file)?;
-            set_last_modified(new_modified, params.network(), what)?;
-            Ok(Some(downloaded))
+            let downloaded = serde_json::from_value::<HashMap<AssetId, V>>(value)?;
+            let local = local.unwrap_or_default();
+
+            let mut changes = RegistryChanges::default();
+            let mut changed = Vec::new();
+            for (id, value) in downloaded.iter() {
+                match local.get(id) {
+                    None => changes.added += 1,
+                    Some(old) if old != value => changes.updated += 1,
+                    Some(_) => continue,
+                }
+                changed.push(*id);
+            }
+            debug!("{} changes compared to the local {}: {:?}", what, what, changes);
+
+            if !params.dry_run {
+                file::write(&downloaded, file)?;
+                set_last_modified(new_modified, params.network(), what)?;
+                bump_version(params.network(), changed)?;
+            }
+            Ok(Some((downloaded, changes)))
         }
 
         _ => {
@@ -230,6 +339,24 @@ fn get_last_modified_file(network: ElementsNetwork) -> Result<MutexGuard<'static
         .map_err(Into::into)
 }
 
+fn get_versions_file(network: ElementsNetwork) -> Result<MutexGuard<'static, File>> {
+    VERSIONS_FILES
+        .get()
+        .ok_or(Error::RegistryUninitialized)?
+        .get(&network)
+        .expect("all networks are initialized")
+        .lock()
+        .map_err(Into::into)
+}
+
+fn bump_version<I: IntoIterator<Item = AssetId>>(network: ElementsNetwork, ids: I) -> Result<()> {
+    get_versions_file(network).and_then(|mut file| {
+        let mut versions = crate::file::read::<Versions>(&mut file)?;
+        versions.bump(ids);
+        crate::file::write(&versions, &mut file)
+    })
+}
+
 fn get_last_modified(network: ElementsNetwork, what: AssetsOrIcons) -> Result<String> {
     get_last_modified_file(network)
         //
@@ -18,6 +18,11 @@ pub struct RegistryInfos {
     /// Assets icons: the hashmap value is a Base64 encoded image.
     pub icons: RegistryIcons,
 
+    /// The local registry version, only set when querying the assets changed
+    /// since a given version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+
     #[serde(default, skip_serializing)]
     pub(crate) source: Option<RegistrySource>,
 }
@@ -42,6 +47,7 @@ impl fmt::Debug for RegistryInfos {
         f.debug_struct("RegistryInfos")
             .field("assets", &assets)
             .field("icons", &icons)
+            .field("version", &self.version)
             .field("source", &self.source)
             .finish()
     }
@@ -55,6 +61,30 @@ pub enum RegistrySource {
     NotModified,
 }
 
+/// How many assets and icons a refresh adds or updates, returned by
+/// [`refresh_assets_dry_run`](crate::refresh_assets_dry_run).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RegistryChanges {
+    /// Number of assets not in the local registry.
+    pub added: u32,
+
+    /// Number of assets whose metadata differ from the local registry.
+    pub updated: u32,
+
+    /// Number of icons not in the local registry or differing from it.
+    pub icons_new: u32,
+}
+
+impl RegistryChanges {
+    pub(crate) fn merge(self, other: Self) -> Self {
+        Self {
+            added: self.added + other.added,
+            updated: self.updated + other.updated,
+            icons_new: self.icons_new + other.icons_new,
+        }
+    }
+}
+
 impl Default for RegistrySource {
     fn default() -> Self {
         Self::LocalRegistry
@@ -83,6 +113,7 @@ impl RegistryInfos {
         Self {
             assets,
             icons,
+            version: None,
             source: None,
         }
     }
@@ -95,6 +126,7 @@ impl RegistryInfos {
         Self {
             assets,
             icons,
+            version: None,
             source: Some(source),
         }
     }
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use gdk_common::elements::AssetId;
+use serde::{Deserialize, Serialize};
+
+/// Tracks which registry version last changed each asset.
+///
+/// The version is a local counter bumped every time a refresh adds or
+/// modifies at least one asset or icon.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Versions {
+    version: u64,
+    changed: HashMap<AssetId, u64>,
+}
+
+impl Versions {
+    pub(crate) const fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Bumps the version if any of the `ids` has been added or modified,
+    /// marking them as changed in the new version.
+    pub(crate) fn bump<I: IntoIterator<Item = AssetId>>(&mut self, ids: I) {
+        let mut ids = ids.into_iter().peekable();
+        if ids.peek().is_none() {
+            return;
+        }
+        self.version += 1;
+        for id in ids {
+            self.changed.insert(id, self.version);
+        }
+    }
+
+    /// Whether the asset has been added or modified after `version`.
+    pub(crate) fn changed_since(&self, id: &AssetId, version: u64) -> bool {
+        self.changed.get(id).map_or(false, |&v| v > version)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_versions() {
+        let (a, b) = (AssetId::default(), AssetId::from_slice(&[1; 32]).unwrap());
+
+        let mut versions = Versions::default();
+        versions.bump(vec![]);
+        assert_eq!(versions.version(), 0);
+        assert!(!versions.changed_since(&a, 0));
+
+        versions.bump(vec![a]);
+        versions.bump(vec![b]);
+        assert_eq!(versions.version(), 2);
+        assert!(versions.changed_since(&a, 0));
+        assert!(!versions.changed_since(&a, 1));
+        assert!(versions.changed_since(&b, 1));
+        assert!(!versions.changed_since(&b, 2));
+    }
+}
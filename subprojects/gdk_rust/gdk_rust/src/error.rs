@@ -39,6 +39,15 @@ pub enum Error {
     #[error("The {0} currency pair is not currently supported")]
     UnsupportedCurrencyPair(exchange_rates::Pair),
 
+    #[error("Unknown exchange {0}")]
+    UnknownExchange(String),
+
+    #[error("The {exchange} exchange does not support the {currency} currency")]
+    UnsupportedExchangeCurrency {
+        exchange: String,
+        currency: exchange_rates::Currency,
+    },
+
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
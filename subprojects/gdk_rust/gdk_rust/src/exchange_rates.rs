@@ -1,11 +1,13 @@
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 
 use gdk_common::exchange_rates::{Currency, Pair, Ticker};
-use gdk_common::log::{debug, info};
+use gdk_common::log::{debug, info, warn};
+use gdk_common::model::{ExchangeRateError, ExchangeRateErrorType, ExchangeRateOk};
 use gdk_common::session::Session;
 use gdk_common::ureq;
 use serde::{de::Deserializer, Deserialize};
+use serde_json::Value;
 
 use crate::Error;
 
@@ -13,6 +15,44 @@ use crate::Error;
 pub(crate) fn fetch_cached<S: Session>(
     sess: &mut S,
     params: &ConvertAmountParams,
+) -> Result<Option<Ticker>, Error> {
+    if let Some(exchange) = &params.exchange_override {
+        return fetch_from_exchange(sess, params, exchange);
+    }
+    let providers = self::providers(&params.url, &params.exchange, params.currency);
+    fetch_cached_from(sess, params, providers)
+}
+
+/// Fetches the exchange rate from `exchange` only, bypassing the cache and
+/// the fallback providers.
+///
+/// The fetched rate is not cached since it might differ from the one of the
+/// exchange set in the settings.
+fn fetch_from_exchange<S: Session>(
+    sess: &S,
+    params: &ConvertAmountParams,
+    exchange: &str,
+) -> Result<Option<Ticker>, Error> {
+    let pair = Pair::new(Currency::BTC, params.currency);
+    let provider = self::provider(&params.url, exchange, params.currency)?;
+    let agent = sess.build_request_agent()?;
+    match self::fetch(&agent, params.currency, &[provider]) {
+        ExchangeRateOk::RateOk(rate) => Ok(Some(Ticker::new(pair, rate.rate))),
+        ExchangeRateOk::NoBackends => Err(Error::Rates(ExchangeRateError {
+            message: format!("cannot fetch the {} exchange rate from {}", pair, exchange),
+            error: ExchangeRateErrorType::FetchError,
+        })),
+    }
+}
+
+/// Returns the cached exchange rate if fresh, otherwise fetches it from
+/// `providers`.
+///
+/// If every provider fails the last fetched rate is returned, even if stale.
+fn fetch_cached_from<S: Session>(
+    sess: &mut S,
+    params: &ConvertAmountParams,
+    providers: Vec<Box<dyn RateProvider>>,
 ) -> Result<Option<Ticker>, Error> {
     let pair = Pair::new(Currency::BTC, params.currency);
 
@@ -34,16 +74,29 @@ pub(crate) fn fetch_cached<S: Session>(
 
     info!("missed exchange rate cache");
 
+    let currency = params.currency;
+    if providers.is_empty() {
+        return Err(Error::UnsupportedCurrencyPair(pair));
+    }
+
     let agent = sess.build_request_agent()?;
     let cache = sess.xr_cache();
-    let currency = params.currency;
-    let url = params.url.clone();
-    let exchange = params.exchange.clone();
+    let fetched_at = sess.now();
+    let last_rate = sess.get_last_rate(&pair);
 
     let handle = thread::spawn(move || {
-        let ticker = self::fetch(&agent, currency, &url, &exchange)?;
+        let rate = match self::fetch(&agent, currency, &providers) {
+            ExchangeRateOk::RateOk(rate) => rate.rate,
+            ExchangeRateOk::NoBackends => {
+                if last_rate.is_some() {
+                    warn!("returning stale {} exchange rate", pair);
+                }
+                return Ok(last_rate.map(|rate| Ticker::new(pair, rate)));
+            }
+        };
+        let ticker = Ticker::new(pair, rate);
         let cache = &mut *cache.lock().unwrap();
-        cache.insert(ticker.pair, (SystemTime::now(), ticker.rate));
+        cache.insert(ticker.pair, (fetched_at, ticker.rate));
         Ok::<_, Error>(Some(ticker))
     });
 
@@ -54,37 +107,269 @@ pub(crate) fn fetch_cached<S: Session>(
     Ok(None)
 }
 
+/// Fetches the `BTC-currency` exchange rate trying each provider in order
+/// until one succeeds, returning [`ExchangeRateOk::NoBackends`] if they all
+/// fail.
 pub(crate) fn fetch(
     agent: &ureq::Agent,
     currency: Currency,
+    providers: &[Box<dyn RateProvider>],
+) -> ExchangeRateOk {
+    let pair = Pair::new(Currency::BTC, currency);
+
+    for provider in providers {
+        let endpoint = provider.endpoint(currency);
+        info!("fetching {} price data from {}", pair, endpoint);
+
+        let ticker = agent
+            .get(&endpoint)
+            .call()
+            .map_err(Error::from)
+            .and_then(|response| Ok(response.into_json::<Value>()?))
+            .and_then(|body| provider.parse(currency, &body))
+            .and_then(|tickers| {
+                tickers.into_iter().find(|t| t.pair == pair).ok_or_else(|| {
+                    parse_error(format!("{} response is missing {}", provider.name(), pair))
+                })
+            });
+
+        match ticker {
+            Ok(ticker) => {
+                info!("got exchange rate {:?}", ticker);
+                return ExchangeRateOk::ok(currency.to_string(), ticker.rate);
+            }
+            Err(err) => warn!("{} exchange rate fetch failed: {}", provider.name(), err),
+        }
+    }
+
+    ExchangeRateOk::no_backends()
+}
+
+/// Returns the providers supporting `currency` to query, in order.
+///
+/// If `url` is not empty, the price server is queried first for `exchange`.
+/// The direct providers are only fallbacks, the one selected by `exchange`
+/// first.
+pub(crate) fn providers(
     url: &str,
     exchange: &str,
-) -> Result<Ticker, Error> {
-    #[derive(serde::Deserialize)]
-    struct ExchangeRateResponse {
-        // TODO: this should be returned as a number by the server.
-        // rate: f64,
-        rate: String,
-    }
-
-    let endpoint = format!(
-        "{}/v0/venues/{}/pairs/{}/{}",
-        url,
-        exchange.to_ascii_uppercase(),
-        Currency::BTC.endpoint_name(),
-        currency.endpoint_name()
-    );
+    currency: Currency,
+) -> Vec<Box<dyn RateProvider>> {
+    let mut direct = direct_providers();
+
+    let mut providers: Vec<Box<dyn RateProvider>> = vec![];
+    if !url.is_empty() {
+        providers.push(Box::new(PriceServer {
+            url: url.to_owned(),
+            venue: exchange.to_owned(),
+        }));
+    }
+    if let Some(pos) = direct.iter().position(|p| p.name().eq_ignore_ascii_case(exchange)) {
+        providers.push(direct.remove(pos));
+    }
+    providers.extend(direct);
+    providers.retain(|p| p.supports(currency));
+    providers
+}
 
-    let pair = Pair::new(Currency::BTC, currency);
+/// Returns the provider selected by `exchange`, which must support
+/// `currency`.
+///
+/// Exchanges without a direct provider are queried through the price server,
+/// if `url` is not empty.
+pub(crate) fn provider(
+    url: &str,
+    exchange: &str,
+    currency: Currency,
+) -> Result<Box<dyn RateProvider>, Error> {
+    let provider =
+        match direct_providers().into_iter().find(|p| p.name().eq_ignore_ascii_case(exchange)) {
+            Some(provider) => provider,
+            None if !url.is_empty() => Box::new(PriceServer {
+                url: url.to_owned(),
+                venue: exchange.to_owned(),
+            }),
+            None => return Err(Error::UnknownExchange(exchange.to_owned())),
+        };
+    if !provider.supports(currency) {
+        return Err(Error::UnsupportedExchangeCurrency {
+            exchange: exchange.to_owned(),
+            currency,
+        });
+    }
+    Ok(provider)
+}
+
+fn direct_providers() -> Vec<Box<dyn RateProvider>> {
+    vec![Box::new(Bitfinex), Box::new(Kraken), Box::new(Coinbase)]
+}
+
+fn parse_error(message: String) -> Error {
+    Error::Rates(ExchangeRateError {
+        message,
+        error: ExchangeRateErrorType::ParseError,
+    })
+}
+
+fn parse_rate(value: &Value) -> Option<f64> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        value => value.as_f64(),
+    }
+}
+
+/// A source of exchange rates.
+pub(crate) trait RateProvider: Send {
+    fn name(&self) -> &'static str;
+
+    /// Whether the `BTC-currency` pair is listed by the provider.
+    fn supports(&self, currency: Currency) -> bool;
+
+    /// The url to query for the `BTC-currency` exchange rate.
+    fn endpoint(&self, currency: Currency) -> String;
+
+    /// Parses the tickers contained in the response body.
+    fn parse(&self, currency: Currency, body: &Value) -> Result<Vec<Ticker>, Error>;
+}
+
+/// The Blockstream price server, proxying the rates of the given venue.
+pub(crate) struct PriceServer {
+    url: String,
+    venue: String,
+}
+
+impl RateProvider for PriceServer {
+    fn name(&self) -> &'static str {
+        "price server"
+    }
+
+    fn supports(&self, currency: Currency) -> bool {
+        // the venue might not list the pair, but that can't be known in advance
+        currency.is_fiat()
+    }
+
+    fn endpoint(&self, currency: Currency) -> String {
+        format!(
+            "{}/v0/venues/{}/pairs/{}/{}",
+            self.url,
+            self.venue.to_ascii_uppercase(),
+            Currency::BTC.endpoint_name(),
+            currency.endpoint_name()
+        )
+    }
+
+    fn parse(&self, currency: Currency, body: &Value) -> Result<Vec<Ticker>, Error> {
+        // TODO: the rate should be returned as a number by the server.
+        let rate = body
+            .get("rate")
+            .and_then(parse_rate)
+            .ok_or_else(|| parse_error(format!("invalid price server response: {}", body)))?;
+        Ok(vec![Ticker::new(Pair::new_btc(currency), rate)])
+    }
+}
+
+/// Bitfinex public ticker, the bid price is used as rate.
+pub(crate) struct Bitfinex;
+
+impl RateProvider for Bitfinex {
+    fn name(&self) -> &'static str {
+        "bitfinex"
+    }
+
+    fn supports(&self, currency: Currency) -> bool {
+        matches!(currency, Currency::USD | Currency::EUR | Currency::GBP | Currency::JPY)
+    }
+
+    fn endpoint(&self, currency: Currency) -> String {
+        format!("https://api-pub.bitfinex.com/v2/ticker/tBTC{}", currency)
+    }
+
+    fn parse(&self, currency: Currency, body: &Value) -> Result<Vec<Ticker>, Error> {
+        // [BID, BID_SIZE, ASK, ASK_SIZE, DAILY_CHANGE, ...]
+        let rate = body
+            .as_array()
+            .and_then(|a| a.first())
+            .and_then(parse_rate)
+            .ok_or_else(|| parse_error(format!("invalid bitfinex response: {}", body)))?;
+        Ok(vec![Ticker::new(Pair::new_btc(currency), rate)])
+    }
+}
+
+/// Kraken public ticker, the bid price is used as rate.
+pub(crate) struct Kraken;
+
+impl RateProvider for Kraken {
+    fn name(&self) -> &'static str {
+        "kraken"
+    }
+
+    fn supports(&self, currency: Currency) -> bool {
+        matches!(
+            currency,
+            Currency::USD
+                | Currency::EUR
+                | Currency::GBP
+                | Currency::JPY
+                | Currency::CAD
+                | Currency::CHF
+                | Currency::AUD
+        )
+    }
+
+    fn endpoint(&self, currency: Currency) -> String {
+        format!(
+            "https://api.kraken.com/0/public/Ticker?pair={}{}",
+            Currency::BTC.endpoint_name(),
+            currency.endpoint_name()
+        )
+    }
 
-    info!("fetching {} price data from {}", pair, endpoint);
+    fn parse(&self, currency: Currency, body: &Value) -> Result<Vec<Ticker>, Error> {
+        // {"error":[],"result":{"XXBTZUSD":{"b":["<price>","<lot volume>","<volume>"],...}}}
+        let rate = body
+            .get("result")
+            .and_then(Value::as_object)
+            .and_then(|result| result.values().next())
+            .and_then(|ticker| ticker.get("b"))
+            .and_then(|bid| bid.get(0))
+            .and_then(parse_rate)
+            .ok_or_else(|| parse_error(format!("invalid kraken response: {}", body)))?;
+        Ok(vec![Ticker::new(Pair::new_btc(currency), rate)])
+    }
+}
 
-    let response = agent.get(&endpoint).call()?.into_json::<ExchangeRateResponse>()?;
+/// Coinbase spot price.
+pub(crate) struct Coinbase;
 
-    let ticker = Ticker::new(pair, response.rate.parse::<f64>().unwrap());
+impl RateProvider for Coinbase {
+    fn name(&self) -> &'static str {
+        "coinbase"
+    }
+
+    fn supports(&self, currency: Currency) -> bool {
+        currency.is_fiat() && !matches!(currency, Currency::Other(..))
+    }
 
-    info!("got exchange rate {:?}", ticker);
-    Ok(ticker)
+    fn endpoint(&self, currency: Currency) -> String {
+        format!("https://api.coinbase.com/v2/prices/BTC-{}/spot", currency)
+    }
+
+    fn parse(&self, _currency: Currency, body: &Value) -> Result<Vec<Ticker>, Error> {
+        // {"data":{"base":"BTC","currency":"USD","amount":"<price>"}}
+        let data = body
+            .get("data")
+            .ok_or_else(|| parse_error(format!("invalid coinbase response: {}", body)))?;
+        let currency = data
+            .get("currency")
+            .and_then(Value::as_str)
+            .and_then(|c| c.parse::<Currency>().ok())
+            .ok_or_else(|| parse_error(format!("invalid coinbase currency: {}", data)))?;
+        let rate = data
+            .get("amount")
+            .and_then(parse_rate)
+            .ok_or_else(|| parse_error(format!("invalid coinbase amount: {}", data)))?;
+        Ok(vec![Ticker::new(Pair::new_btc(currency), rate)])
+    }
 }
 
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -103,7 +388,14 @@ pub(crate) struct ConvertAmountParams {
     /// exchange rate.
     exchange: String,
 
-    #[serde(default = "one_minute")]
+    /// The currency exchange to fetch the exchange rate from for this call
+    /// only, in place of `exchange` and without falling back to others.
+    #[serde(default)]
+    exchange_override: Option<String>,
+
+    /// The maximum age in seconds of a cached exchange rate before it is
+    /// fetched again.
+    #[serde(default = "one_minute", rename = "max_age", deserialize_with = "deserialize_secs")]
     cache_limit: Duration,
 }
 
@@ -111,6 +403,13 @@ fn one_minute() -> Duration {
     Duration::from_secs(60)
 }
 
+fn deserialize_secs<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    u64::deserialize(deserializer).map(Duration::from_secs)
+}
+
 fn deserialize_rate<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
 where
     D: Deserializer<'de>,
@@ -128,25 +427,98 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
     use std::sync::Arc;
-    use std::time::Duration;
+    use std::time::{Duration, SystemTime};
 
     use super::*;
     use gdk_common::exchange_rates::{ExchangeRatesCache, ExchangeRatesCacher};
     use gdk_common::network::NetworkParameters;
     use gdk_common::notification::NativeNotif;
-    use serde_json::Value;
 
     #[derive(Default)]
     struct TestSession {
         xr_cache: ExchangeRatesCache,
         network_parameters: NetworkParameters,
+        clock: Option<SystemTime>,
     }
 
     impl ExchangeRatesCacher for TestSession {
         fn xr_cache(&self) -> ExchangeRatesCache {
             Arc::clone(&self.xr_cache)
         }
+
+        fn now(&self) -> SystemTime {
+            self.clock.unwrap_or_else(SystemTime::now)
+        }
+    }
+
+    /// Serves `body` as the json response of a single request, returns the
+    /// url of the server.
+    fn serve_once(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0u8; 1024]);
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        url
+    }
+
+    fn price_server(url: String) -> Vec<Box<dyn RateProvider>> {
+        vec![Box::new(PriceServer {
+            url,
+            venue: "test".into(),
+        })]
+    }
+
+    #[test]
+    fn test_cache_max_age() {
+        let start = SystemTime::now();
+        let pair = Pair::new_btc(Currency::USD);
+        let mut session = TestSession {
+            clock: Some(start),
+            ..Default::default()
+        };
+        let params: ConvertAmountParams = serde_json::from_value(serde_json::json!({
+            "currencies": "USD",
+            "price_url": "",
+            "fallback_rate": "",
+            "exchange": "test",
+            "max_age": 60,
+        }))
+        .unwrap();
+        assert_eq!(params.cache_limit, one_minute());
+
+        session.cache_ticker(Ticker::new(pair, 1.0));
+        session.clock = Some(start + Duration::from_secs(59));
+        assert_eq!(session.get_cached_rate(&pair, params.cache_limit), Some(1.0));
+        let res = fetch_cached_from(&mut session, &params, vec![]).unwrap();
+        assert_eq!(res, Some(Ticker::new(pair, 1.0)));
+
+        // Once stale the rate is fetched again and cached with the current time
+        let now = start + Duration::from_secs(61);
+        session.clock = Some(now);
+        assert_eq!(session.get_cached_rate(&pair, params.cache_limit), None);
+        let providers = price_server(serve_once(r#"{"rate":"2.0"}"#));
+        let res = fetch_cached_from(&mut session, &params, providers).unwrap();
+        assert_eq!(res, Some(Ticker::new(pair, 2.0)));
+        assert_eq!(session.xr_cache.lock().unwrap().get(&pair), Some(&(now, 2.0)));
+
+        // If fetching fails the stale rate is returned
+        session.clock = Some(now + Duration::from_secs(61));
+        let providers = price_server("http://127.0.0.1:1".into());
+        let res = fetch_cached_from(&mut session, &params, providers).unwrap();
+        assert_eq!(res, Some(Ticker::new(pair, 2.0)));
+        assert_eq!(session.get_cached_rate(&pair, params.cache_limit), None);
     }
 
     impl Session for TestSession {
@@ -190,6 +562,7 @@ mod tests {
             url: "https://green-bitcoin-testnet.blockstream.com/prices".into(),
             fallback_rate: Some(1.0),
             exchange: "bitfinex".to_owned(),
+            exchange_override: None,
             cache_limit: one_minute(),
         };
 
@@ -224,6 +597,7 @@ mod tests {
             url: "https://green-bitcoin-testnet.blockstream.com/prices".into(),
             fallback_rate: None,
             exchange: "bitfinex".to_owned(),
+            exchange_override: None,
             cache_limit: one_minute(),
         };
 
@@ -240,6 +614,7 @@ mod tests {
             url: "https://green-bitcoin-testnet.blockstream.com/prices".into(),
             fallback_rate: None,
             exchange: "bitstamp".to_owned(),
+            exchange_override: None,
             cache_limit: one_minute(),
         };
 
@@ -252,10 +627,164 @@ mod tests {
             url: "https://green-bitcoin-testnet.blockstream.com/prices".into(),
             fallback_rate: Some(1.0),
             exchange: "bitstamp".to_owned(),
+            exchange_override: None,
             cache_limit: Duration::from_millis(0),
         };
 
         let res = fetch_cached(&mut session, &params).unwrap();
         assert!(res.is_none());
     }
+
+    #[test]
+    fn test_providers_order() {
+        let names = |providers: Vec<Box<dyn RateProvider>>| {
+            providers.iter().map(|p| p.name()).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(providers("", "KRAKEN", Currency::USD)),
+            vec!["kraken", "bitfinex", "coinbase"]
+        );
+        assert_eq!(
+            names(providers("https://prices", "bitstamp", Currency::USD)),
+            vec!["price server", "bitfinex", "kraken", "coinbase"]
+        );
+        assert_eq!(
+            names(providers("https://prices", "kraken", Currency::USD)),
+            vec!["price server", "kraken", "bitfinex", "coinbase"]
+        );
+        assert_eq!(names(providers("", "bitfinex", Currency::CHF)), vec!["kraken", "coinbase"]);
+    }
+
+    #[test]
+    fn test_fetch_eur() {
+        let mut session = TestSession::default();
+        let eur = Pair::new_btc(Currency::EUR);
+        let usd = Pair::new_btc(Currency::USD);
+        session.cache_ticker(Ticker::new(eur, 18000.0));
+
+        let params = ConvertAmountParams {
+            currency: Currency::EUR,
+            url: "".into(),
+            fallback_rate: Some(1.0),
+            exchange: "bitfinex".to_owned(),
+            exchange_override: None,
+            cache_limit: one_minute(),
+        };
+        let res = fetch_cached(&mut session, &params).unwrap();
+        assert_eq!(res, Some(Ticker::new(eur, 18000.0)));
+        assert!(session.get_cached_rate(&usd, one_minute()).is_none());
+
+        assert_eq!(
+            Bitfinex.endpoint(Currency::EUR),
+            "https://api-pub.bitfinex.com/v2/ticker/tBTCEUR"
+        );
+        let body =
+            serde_json::json!([18000.5, 1.0, 18001.0, 1.0, 0.0, 0.0, 18000.7, 10.0, 0.0, 0.0]);
+        let tickers = Bitfinex.parse(Currency::EUR, &body).unwrap();
+        assert_eq!(tickers, vec![Ticker::new(eur, 18000.5)]);
+    }
+
+    #[test]
+    fn test_fetch_unsupported_currency() {
+        let mut session = TestSession::default();
+        let currency = "ABCD".parse::<Currency>().unwrap();
+
+        let params = ConvertAmountParams {
+            currency,
+            url: "".into(),
+            fallback_rate: None,
+            exchange: "bitfinex".to_owned(),
+            exchange_override: None,
+            cache_limit: one_minute(),
+        };
+        let err = fetch_cached(&mut session, &params).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedCurrencyPair(pair) if pair.second() == currency));
+    }
+
+    #[test]
+    fn test_exchange_override() {
+        let usd = Pair::new_btc(Currency::USD);
+        assert_eq!(provider("", "KRAKEN", Currency::USD).unwrap().name(), "kraken");
+        assert_eq!(
+            provider("https://prices", "bitstamp", Currency::USD).unwrap().name(),
+            "price server"
+        );
+        let err = provider("", "bitstamp", Currency::USD).err().unwrap();
+        assert!(matches!(err, Error::UnknownExchange(ref e) if e == "bitstamp"));
+        let err = provider("", "bitfinex", Currency::CHF).err().unwrap();
+        assert_eq!(err.to_string(), "The bitfinex exchange does not support the CHF currency");
+
+        // The cache is bypassed and left untouched
+        let mut session = TestSession::default();
+        session.cache_ticker(Ticker::new(usd, 1.0));
+        let params: ConvertAmountParams = serde_json::from_value(serde_json::json!({
+            "currencies": "USD",
+            "price_url": serve_once(r#"{"rate":"2.0"}"#),
+            "fallback_rate": "1.0",
+            "exchange": "bitfinex",
+            "exchange_override": "bitstamp",
+        }))
+        .unwrap();
+        let res = fetch_cached(&mut session, &params).unwrap();
+        assert_eq!(res, Some(Ticker::new(usd, 2.0)));
+        assert_eq!(session.get_cached_rate(&usd, one_minute()), Some(1.0));
+
+        // Failures are reported instead of falling back to other exchanges
+        let params = ConvertAmountParams {
+            url: "http://127.0.0.1:1".into(),
+            ..params
+        };
+        assert!(matches!(fetch_cached(&mut session, &params), Err(Error::Rates(_))));
+    }
+
+    #[test]
+    fn test_parse_price_server() {
+        let body = serde_json::json!({ "rate": "20000.5" });
+        let provider = PriceServer {
+            url: "https://prices".into(),
+            venue: "bitstamp".into(),
+        };
+        let tickers = provider.parse(Currency::EUR, &body).unwrap();
+        assert_eq!(tickers, vec![Ticker::new(Pair::new_btc(Currency::EUR), 20000.5)]);
+        assert_eq!(
+            provider.endpoint(Currency::EUR),
+            "https://prices/v0/venues/BITSTAMP/pairs/XBT/EUR"
+        );
+        assert!(provider.parse(Currency::EUR, &serde_json::json!({})).is_err());
+    }
+
+    #[test]
+    fn test_parse_bitfinex() {
+        let body: Value = serde_json::from_str(
+            "[20001.0,10.5,20002.0,8.1,-150.0,-0.0074,20001.5,1234.5,20500.0,19800.0]",
+        )
+        .unwrap();
+        let tickers = Bitfinex.parse(Currency::USD, &body).unwrap();
+        assert_eq!(tickers, vec![Ticker::new(Pair::new_btc(Currency::USD), 20001.0)]);
+        assert!(Bitfinex.parse(Currency::USD, &serde_json::json!([])).is_err());
+        assert!(Bitfinex.parse(Currency::USD, &serde_json::json!(["error", 10020])).is_err());
+    }
+
+    #[test]
+    fn test_parse_kraken() {
+        let body: Value = serde_json::from_str(
+            r#"{"error":[],"result":{"XXBTZUSD":{"a":["20002.10000","1","1.000"],"b":["20001.90000","2","2.000"],"c":["20002.00000","0.01"]}}}"#,
+        )
+        .unwrap();
+        let tickers = Kraken.parse(Currency::USD, &body).unwrap();
+        assert_eq!(tickers, vec![Ticker::new(Pair::new_btc(Currency::USD), 20001.9)]);
+        let body = serde_json::json!({"error":["EQuery:Unknown asset pair"]});
+        assert!(Kraken.parse(Currency::USD, &body).is_err());
+    }
+
+    #[test]
+    fn test_parse_coinbase() {
+        let body: Value =
+            serde_json::from_str(r#"{"data":{"base":"BTC","currency":"GBP","amount":"17000.25"}}"#)
+                .unwrap();
+        let tickers = Coinbase.parse(Currency::GBP, &body).unwrap();
+        assert_eq!(tickers, vec![Ticker::new(Pair::new_btc(Currency::GBP), 17000.25)]);
+        let body = serde_json::json!({"errors":[{"id":"not_found"}]});
+        assert!(Coinbase.parse(Currency::GBP, &body).is_err());
+    }
 }
@@ -11,17 +11,27 @@ use std::ffi::CString;
 use std::io::Write;
 use std::os::raw::c_char;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Once};
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-use gdk_common::model::{InitParam, SPVDownloadHeadersParams, SPVVerifyTxParams};
+use gdk_common::be::{compute_fee_rate, estimate_tx_size};
+use gdk_common::model::{
+    CombinePsetParams, CombinePsetResult, ComputeAccountNumParams, ComputeFeeRateParams,
+    EstimateTxSizeParams, FinalizePsetParams, GenerateMnemonicParams, GenerateMnemonicResult,
+    GetVersionResult, InitParam, LogFormat, SPVDownloadHeadersParams, SPVVerifyTxParams,
+    SetLogLevelParam, ValidateMnemonicParams, ValidateMnemonicResult,
+};
 
 use crate::error::Error;
 use gdk_common::exchange_rates::{ExchangeRatesCache, ExchangeRatesCacher};
 use gdk_common::log::{self, debug, info, LevelFilter, Metadata, Record};
+use gdk_common::mnemonic;
+use gdk_common::pset;
 use gdk_common::session::{JsonError, Session};
 use gdk_common::ureq;
-use gdk_electrum::{headers, ElectrumSession, NativeNotif};
+use gdk_common::util::amount;
+use gdk_electrum::{headers, socksify, ElectrumSession, NativeNotif};
 use serde::Serialize;
 
 pub const GA_OK: i32 = 0;
@@ -30,16 +40,42 @@ pub const GA_NOT_AUTHORIZED: i32 = -5;
 
 pub struct GdkSession {
     pub backend: GdkBackend,
+
+    /// The last error returned by a session call, exposed through `get_last_error`
+    last_error: Option<LastError>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LastError {
+    pub message: String,
+    pub error_code: String,
+    pub method: String,
+    /// Unix timestamp in seconds of when the error occurred
+    pub timestamp: u64,
+}
+
+impl LastError {
+    fn new(method: &str, err: &JsonError) -> Self {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        LastError {
+            message: err.message.clone(),
+            error_code: err.error.clone(),
+            method: method.to_string(),
+            timestamp,
+        }
+    }
 }
 
 pub enum GdkBackend {
     // Rpc(RpcSession),
     Electrum(ElectrumSession),
-    Greenlight(GreenlightSession),
+    Greenlight(Box<GreenlightSession>),
 }
 
-#[derive(Default)]
 pub struct GreenlightSession {
+    network: gdk_common::NetworkParameters,
+    proxy: Option<String>,
+    notify: NativeNotif,
     xr_cache: ExchangeRatesCache,
 }
 
@@ -50,20 +86,28 @@ impl ExchangeRatesCacher for GreenlightSession {
 }
 
 impl Session for GreenlightSession {
-    fn new(_network_parameters: gdk_common::NetworkParameters) -> Result<Self, JsonError> {
-        todo!()
+    fn new(network_parameters: gdk_common::NetworkParameters) -> Result<Self, JsonError> {
+        let mut notify = NativeNotif::new();
+        notify.set_mode(network_parameters.notification_mode);
+
+        Ok(Self {
+            proxy: socksify(network_parameters.proxy.as_deref()),
+            network: network_parameters,
+            notify,
+            xr_cache: ExchangeRatesCache::default(),
+        })
     }
 
     fn native_notification(&mut self) -> &mut NativeNotif {
-        todo!()
+        &mut self.notify
     }
 
     fn network_parameters(&self) -> &gdk_common::NetworkParameters {
-        todo!()
+        &self.network
     }
 
     fn build_request_agent(&self) -> Result<ureq::Agent, ureq::Error> {
-        todo!()
+        gdk_common::network::build_request_agent(self.proxy.as_deref())
     }
 
     fn handle_call(&mut self, method: &str, _input: Value) -> Result<Value, JsonError> {
@@ -138,6 +182,20 @@ fn init_logging(level: LevelFilter) {
     });
 }
 
+/// Change the maximum log level of an already initialized logging framework, to capture more
+/// details on a running session.
+/// On Android the logcat filter set by `init_logging` stays fixed, but the max level still applies.
+fn set_log_level(level: LevelFilter) {
+    log::set_max_level(level);
+    info!("log level set to {}", level);
+}
+
+fn parse_log_level(input: &str) -> Result<LevelFilter, Error> {
+    let param: SetLogLevelParam = serde_json::from_str(input)?;
+    LevelFilter::from_str(&param.log_level)
+        .map_err(|_| Error::Common(gdk_common::Error::InputValidationFailed))
+}
+
 fn create_session(network: &Value) -> Result<GdkSession, Value> {
     info!("create_session {:?}", network);
     if !network.is_object() || !network.as_object().unwrap().contains_key("server_type") {
@@ -155,7 +213,9 @@ fn create_session(network: &Value) -> Result<GdkSession, Value> {
 
     let backend = match network["server_type"].as_str() {
         // Some("rpc") => GDKRUST_session::Rpc( GDKRPC_session::create_session(parsed_network.unwrap()).unwrap() ),
-        Some("greenlight") => GdkBackend::Greenlight(GreenlightSession::default()),
+        Some("greenlight") => {
+            GdkBackend::Greenlight(Box::new(GreenlightSession::new(parsed_network)?))
+        }
         Some("electrum") => {
             let session = ElectrumSession::new(parsed_network)?;
             GdkBackend::Electrum(session)
@@ -164,6 +224,7 @@ fn create_session(network: &Value) -> Result<GdkSession, Value> {
     };
     let gdk_session = GdkSession {
         backend,
+        last_error: None,
     };
     Ok(gdk_session)
 }
@@ -204,6 +265,22 @@ pub extern "C" fn GDKRUST_call_session(
 }
 
 fn call_session(sess: &mut GdkSession, method: &str, input: &str) -> Result<Value, JsonError> {
+    if method == "get_last_error" {
+        return Ok(serde_json::to_value(&sess.last_error)?);
+    }
+
+    let res = dispatch_session_call(sess, method, input);
+    if let Err(err) = &res {
+        sess.last_error = Some(LastError::new(method, err));
+    }
+    res
+}
+
+fn dispatch_session_call(
+    sess: &mut GdkSession,
+    method: &str,
+    input: &str,
+) -> Result<Value, JsonError> {
     let input = serde_json::from_str(input)?;
 
     if method == "exchange_rates" {
@@ -211,7 +288,7 @@ fn call_session(sess: &mut GdkSession, method: &str, input: &str) -> Result<Valu
 
         let ticker = match sess.backend {
             GdkBackend::Electrum(ref mut s) => exchange_rates::fetch_cached(s, &params),
-            GdkBackend::Greenlight(ref mut s) => exchange_rates::fetch_cached(s, &params),
+            GdkBackend::Greenlight(ref mut s) => exchange_rates::fetch_cached(s.as_mut(), &params),
         }?;
 
         let rate = ticker.map(|t| format!("{:.8}", t.rate)).unwrap_or_default();
@@ -242,10 +319,13 @@ fn call_session(sess: &mut GdkSession, method: &str, input: &str) -> Result<Valu
 
     info!("GDKRUST_call_session handle_call {} input {:?}", method, input_redacted);
 
-    let res = match sess.backend {
+    // Satoshi amounts are returned as strings only to the callers asking for it
+    let satoshi_as_string =
+        input.get("satoshi_as_string").and_then(Value::as_bool).unwrap_or(false);
+    let res = amount::with_satoshi_as_string(satoshi_as_string, || match sess.backend {
         GdkBackend::Electrum(ref mut s) => s.handle_call(&method, input),
         GdkBackend::Greenlight(ref mut s) => s.handle_call(&method, input),
-    };
+    });
 
     let methods_to_redact_out =
         vec!["credentials_from_pin_data", "decrypt_with_pin", "get_master_blinding_key"];
@@ -274,7 +354,7 @@ pub extern "C" fn GDKRUST_set_notification_handler(
 
     match backend {
         GdkBackend::Electrum(ref mut s) => s.notify.set_native((handler, self_context)),
-        GdkBackend::Greenlight(ref mut _s) => (), // TODO,
+        GdkBackend::Greenlight(ref mut s) => s.notify.set_native((handler, self_context)),
     };
 
     info!("set notification handler");
@@ -335,17 +415,60 @@ pub extern "C" fn GDKRUST_call(
     error_value
 }
 
+/// The version of this build, which does not require a session nor `init`
+fn get_version() -> GetVersionResult {
+    GetVersionResult {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_commit: env!("GDK_GIT_COMMIT").to_string(),
+        features: compiled_features(),
+    }
+}
+
+/// The cargo features enabled in this build
+fn compiled_features() -> Vec<String> {
+    let features = [("testing", cfg!(feature = "testing"))];
+    features.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| name.to_string()).collect()
+}
+
 fn handle_call(method: &str, input: &str) -> Result<String, Error> {
     let start = Instant::now();
 
     let res = match method {
         "init" => {
             let param: InitParam = serde_json::from_str(input)?;
+            JSON_LOGS.store(param.log_format == LogFormat::Json, Ordering::Relaxed);
             init_logging(LevelFilter::from_str(&param.log_level).unwrap_or(LevelFilter::Off));
             gdk_registry::init(&param.registry_dir)?;
             // TODO: read more initialization params
             to_string(&json!("".to_string()))
         }
+        "set_log_level" => {
+            set_log_level(parse_log_level(input)?);
+            to_string(&json!("".to_string()))
+        }
+        "get_version" => to_string(&get_version()),
+        "generate_mnemonic" => {
+            let param: GenerateMnemonicParams = serde_json::from_str(input)?;
+            to_string(&GenerateMnemonicResult {
+                mnemonic: mnemonic::generate_mnemonic(param.word_count)?,
+            })
+        }
+        "validate_mnemonic" => {
+            let param: ValidateMnemonicParams = serde_json::from_str(input)?;
+            to_string(&ValidateMnemonicResult {
+                valid: mnemonic::validate_mnemonic(&param.mnemonic),
+            })
+        }
+        "combine_pset" => {
+            let param: CombinePsetParams = serde_json::from_str(input)?;
+            to_string(&CombinePsetResult {
+                pset: pset::combine_psets(&param.psets)?,
+            })
+        }
+        "finalize_pset" => {
+            let param: FinalizePsetParams = serde_json::from_str(input)?;
+            to_string(&pset::finalize_pset(&param.pset)?)
+        }
         "spv_verify_tx" => {
             let param: SPVVerifyTxParams = serde_json::from_str(input)?;
             to_string(&headers::spv_verify_tx(&param)?.as_i32())
@@ -354,14 +477,38 @@ fn handle_call(method: &str, input: &str) -> Result<String, Error> {
             let param: SPVDownloadHeadersParams = serde_json::from_str(input)?;
             to_string(&headers::download_headers(&param)?)
         }
+        "compute_account_num" => {
+            let param: ComputeAccountNumParams = serde_json::from_str(input)?;
+            let account_num = param
+                .script_type
+                .account_num(param.bip32_account)
+                .ok_or(Error::Common(gdk_common::Error::InputValidationFailed))?;
+            to_string(&account_num)
+        }
+        "compute_fee_rate" => {
+            let param: ComputeFeeRateParams = serde_json::from_str(input)?;
+            to_string(&compute_fee_rate(&param)?)
+        }
+        "estimate_tx_size" => {
+            let param: EstimateTxSizeParams = serde_json::from_str(input)?;
+            to_string(&estimate_tx_size(&param))
+        }
         "refresh_assets" => {
             let param: gdk_registry::RefreshAssetsParams = serde_json::from_str(input)?;
-            to_string(&gdk_registry::refresh_assets(param)?)
+            if param.dry_run() {
+                to_string(&gdk_registry::refresh_assets_dry_run(param)?)
+            } else {
+                to_string(&gdk_registry::refresh_assets(param)?)
+            }
         }
         "get_assets" => {
             let params: gdk_registry::GetAssetsParams = serde_json::from_str(input)?;
             to_string(&gdk_registry::get_assets(params)?)
         }
+        "get_asset" => {
+            let params: gdk_registry::GetAssetParams = serde_json::from_str(input)?;
+            to_string(&gdk_registry::get_asset(params)?)
+        }
 
         _ => {
             return Err(Error::MethodNotFound {
@@ -379,6 +526,9 @@ fn handle_call(method: &str, input: &str) -> Result<String, Error> {
 #[cfg(not(target_os = "android"))]
 static LOGGER: SimpleLogger = SimpleLogger;
 
+/// Whether `SimpleLogger` emits records as JSON objects, set by `init`
+static JSON_LOGS: AtomicBool = AtomicBool::new(false);
+
 pub struct SimpleLogger;
 
 impl log::Log for SimpleLogger {
@@ -395,17 +545,185 @@ impl log::Log for SimpleLogger {
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            let ts = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards");
-            let _ = writeln!(
-                std::io::stdout(),
+            let _ = self.write_record(
+                &mut std::io::stdout(),
+                record,
+                JSON_LOGS.load(Ordering::Relaxed),
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+impl SimpleLogger {
+    fn write_record<W: Write>(
+        &self,
+        out: &mut W,
+        record: &Record,
+        json: bool,
+    ) -> std::io::Result<()> {
+        let ts = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards");
+        if json {
+            let line = json!({
+                "ts": ts.as_millis() as u64,
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(out, "{}", line)
+        } else {
+            writeln!(
+                out,
                 "{:02}.{:03} {} - {}",
                 ts.as_secs() % 60,
                 ts.subsec_millis(),
                 record.level(),
                 record.args()
-            );
+            )
         }
     }
+}
 
-    fn flush(&self) {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_error() {
+        let network = gdk_common::NetworkParameters::default();
+        let mut sess = GdkSession {
+            backend: GdkBackend::Greenlight(Box::new(GreenlightSession::new(network).unwrap())),
+            last_error: None,
+        };
+
+        let res = call_session(&mut sess, "get_last_error", "{}").unwrap();
+        assert_eq!(res, Value::Null);
+
+        let err = call_session(&mut sess, "foo", "{}").unwrap_err();
+
+        let res = call_session(&mut sess, "get_last_error", "{}").unwrap();
+        assert_eq!(res["method"], "foo");
+        assert_eq!(res["message"], err.message);
+        assert_eq!(res["error_code"], err.error);
+        assert!(res["timestamp"].as_u64().unwrap() > 0);
+
+        // Querying the last error doesn't overwrite it
+        let res = call_session(&mut sess, "get_last_error", "{}").unwrap();
+        assert_eq!(res["method"], "foo");
+    }
+
+    #[test]
+    fn test_greenlight_session() {
+        let network = json!({
+            "server_type": "greenlight",
+            "name": "Greenlight",
+            "network": "greenlight",
+            "development": true,
+            "liquid": false,
+            "mainnet": false,
+            "tx_explorer_url": "",
+            "address_explorer_url": "",
+            "pin_server_url": "",
+            "pin_server_onion_url": "",
+            "pin_server_public_key": "",
+            "proxy": "127.0.0.1:9050",
+            "state_dir": "/tmp",
+        });
+        let mut sess = create_session(&network).unwrap();
+        let s = match &mut sess.backend {
+            GdkBackend::Greenlight(s) => s,
+            _ => panic!("expected a greenlight session"),
+        };
+
+        assert_eq!(s.network_parameters().name, "Greenlight");
+        assert!(s.network_parameters().development);
+        assert_eq!(s.proxy.as_deref(), Some("socks5://127.0.0.1:9050"));
+        assert!(s.build_request_agent().is_ok());
+        assert!(s.native_notification().native.is_none());
+    }
+
+    #[test]
+    fn test_parse_log_level() {
+        // the max level is global, so only the parsing is tested to not affect other tests
+        let parse = |level: &str| parse_log_level(&json!({ "log_level": level }).to_string());
+        assert_eq!(parse("error").unwrap(), LevelFilter::Error);
+        assert_eq!(parse("info").unwrap(), LevelFilter::Info);
+        assert_eq!(parse("OFF").unwrap(), LevelFilter::Off);
+        assert!(parse("loud").is_err());
+        assert!(parse_log_level("{}").is_err());
+    }
+
+    #[test]
+    fn test_get_version() {
+        let value: Value =
+            serde_json::from_str(&handle_call("get_version", "{}").unwrap()).unwrap();
+        assert!(!value["version"].as_str().unwrap().is_empty());
+        assert!(!value["git_commit"].as_str().unwrap().is_empty());
+        assert_eq!(value["features"], json!(compiled_features()));
+    }
+
+    #[test]
+    fn test_mnemonic() {
+        let generate = |word_count: usize| -> Result<String, Error> {
+            let input = json!({ "word_count": word_count }).to_string();
+            let value: Value = serde_json::from_str(&handle_call("generate_mnemonic", &input)?)?;
+            Ok(value["mnemonic"].as_str().unwrap().to_string())
+        };
+        let validate = |mnemonic: &str| -> bool {
+            let input = json!({ "mnemonic": mnemonic }).to_string();
+            let value: Value =
+                serde_json::from_str(&handle_call("validate_mnemonic", &input).unwrap()).unwrap();
+            value["valid"].as_bool().unwrap()
+        };
+
+        for word_count in [12, 15, 18, 21, 24] {
+            let mnemonic = generate(word_count).unwrap();
+            assert_eq!(mnemonic.split(' ').count(), word_count);
+            assert!(validate(&mnemonic));
+            assert_ne!(mnemonic, generate(word_count).unwrap());
+        }
+        for word_count in [0, 11, 13, 27] {
+            assert!(generate(word_count).is_err());
+        }
+
+        let valid = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        assert!(validate(valid));
+        // The last word carries the checksum
+        assert!(!validate(&valid.replace("about", "abandon")));
+        assert!(!validate(&valid.replace("about", "notaword")));
+        assert!(!validate(""));
+    }
+
+    #[test]
+    fn test_json_log_format() {
+        use log::Log;
+
+        let message = "hello \"json\"";
+        let mut out = vec![];
+        // `format_args!` can't outlive the statement, so the record is built in place
+        SimpleLogger
+            .write_record(
+                &mut out,
+                &Record::builder()
+                    .level(log::Level::Warn)
+                    .target("gdk_electrum")
+                    .args(format_args!("{}", message))
+                    .build(),
+                true,
+            )
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 1);
+        let value: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(value["level"], "WARN");
+        assert_eq!(value["target"], "gdk_electrum");
+        assert_eq!(value["message"], message);
+        assert!(value["ts"].as_u64().unwrap() > 0);
+
+        // The filtering of noisy targets still applies
+        let metadata =
+            Metadata::builder().level(log::Level::Debug).target("electrum_client::raw").build();
+        assert!(!SimpleLogger.enabled(&metadata));
+    }
 }
@@ -11,17 +11,18 @@ use gdk_common::log::info;
 use serde_json::Value;
 use tempfile::TempDir;
 
-use gdk_common::be::BETransaction;
+use gdk_common::be::{BETransaction, BETxid};
+use gdk_common::exchange_rates::{Currency, ExchangeRatesCacher, Pair, Ticker};
 use gdk_common::model::*;
 use gdk_common::scripts::ScriptType;
 use gdk_common::session::Session;
 use gdk_common::{NetworkId, NetworkParameters, State};
 use gdk_electrum::error::Error;
 use gdk_electrum::headers::bitcoin::HeadersChain;
-use gdk_electrum::interface::ElectrumUrl;
+use gdk_electrum::interface::{ElectrumUrl, ElectrumUrls};
 use gdk_electrum::{headers, spv, ElectrumSession};
 use gdk_test::utils;
-use gdk_test::{ElectrumSessionExt, TestSession};
+use gdk_test::{ElectrumSessionExt, TestSession, TestSigner};
 
 static MEMO1: &str = "hello memo";
 static MEMO2: &str = "hello memo2";
@@ -177,6 +178,69 @@ fn roundtrip_liquid_2() {
     test_session.stop();
 }
 
+#[test]
+fn multi_asset_liquid() {
+    let mut test_session = TestSession::new(true, |_| ());
+    let node_address = test_session.node_getnewaddress(None);
+    let assets = test_session.fund(100_000_000, Some(1));
+    let (policy_asset, token) = (test_session.asset_id().unwrap(), assets[0].clone());
+    let init_policy = test_session.balance_account(0, None, None);
+    let init_token = test_session.balance_account(0, Some(token.clone()), None);
+
+    // send L-BTC and a token in the same transaction
+    let mut create_opt = test_session.create_opt(
+        &node_address,
+        10_000,
+        Some(policy_asset.clone()),
+        None,
+        0,
+        test_session.utxos(0),
+    );
+    create_opt.addressees.push(AddressAmount {
+        address: node_address.clone(),
+        satoshi: 5_000,
+        asset_id: Some(token.clone()),
+        op_return: None,
+    });
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    // the fee is paid in L-BTC only
+    assert_eq!(tx.satoshi[&policy_asset] as u64, 10_000 + tx.fee);
+    assert_eq!(tx.satoshi[&token], 5_000);
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    let txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &txid, None, Some(TransactionType::Outgoing));
+    assert_eq!(test_session.balance_account(0, None, None), init_policy - 10_000 - tx.fee);
+    assert_eq!(test_session.balance_account(0, Some(token.clone()), None), init_token - 5_000);
+
+    // the error names the asset with insufficient balance
+    let mut create_opt = test_session.create_opt(
+        &node_address,
+        10_000,
+        Some(policy_asset),
+        None,
+        0,
+        test_session.utxos(0),
+    );
+    create_opt.addressees.push(AddressAmount {
+        address: node_address,
+        satoshi: init_token,
+        asset_id: Some(token.clone()),
+        op_return: None,
+    });
+    match test_session.session.create_transaction(&mut create_opt) {
+        Err(
+            e @ Error::InsufficientFunds {
+                ..
+            },
+        ) => {
+            assert_eq!(e.details()["asset_id"], token);
+            assert_eq!(e.details()["shortfall"], 5_000);
+        }
+        r => panic!("unexpected result {:?}", r.map(|tx| tx.txid)),
+    }
+    test_session.stop();
+}
+
 fn check_account_balances(test_session: &TestSession, balances: &HashMap<u32, u64>) {
     for (n, balance) in balances {
         assert_eq!(test_session.balance_account(*n, None, None), *balance);
@@ -242,7 +306,7 @@ fn create_tx_err(is_liquid: bool) {
     assert!(create_opt.utxos.iter().all(|(_, v)| v.len() == 0));
     assert!(matches!(
         test_session.session.create_transaction(&mut create_opt),
-        Err(Error::InsufficientFunds)
+        Err(Error::InsufficientFunds { .. })
     ));
 
     // Not enough to pay the fee
@@ -260,8 +324,15 @@ fn create_tx_err(is_liquid: bool) {
     );
     assert!(matches!(
         test_session.session.create_transaction(&mut create_opt),
-        Err(Error::InsufficientFunds)
+        Err(Error::InsufficientFunds { .. })
     ));
+    let err = test_session
+        .session
+        .handle_call("create_transaction", serde_json::to_value(&create_opt).unwrap())
+        .unwrap();
+    assert_eq!(err["error"]["code"], "id_insufficient_funds");
+    assert_eq!(err["error"]["message"], "insufficient funds");
+    assert!(err["error"]["details"]["shortfall"].as_u64().unwrap() > 0);
 
     // Invalid subaccount
     let mut create_opt =
@@ -284,6 +355,11 @@ fn create_tx_err(is_liquid: bool) {
         test_session.session.create_transaction(&mut create_opt),
         Err(Error::FeeRateBelowMinimum(_))
     ));
+    let err = test_session
+        .session
+        .handle_call("create_transaction", serde_json::to_value(&create_opt).unwrap())
+        .unwrap();
+    assert_eq!(err["error"]["code"], "id_fee_rate_is_below_minimum");
 
     // Not an address
     let mut create_opt = test_session.create_opt(
@@ -298,6 +374,11 @@ fn create_tx_err(is_liquid: bool) {
         test_session.session.create_transaction(&mut create_opt),
         Err(Error::InvalidAddress)
     ));
+    let err = test_session
+        .session
+        .handle_call("create_transaction", serde_json::to_value(&create_opt).unwrap())
+        .unwrap();
+    assert_eq!(err["error"]["code"], "id_invalid_address");
 
     // Wrong networks
     let wrong_net_addr = if is_liquid {
@@ -558,12 +639,13 @@ fn coin_selection(is_liquid: bool) {
         address: node_address.to_string(),
         satoshi: sat8,
         asset_id: test_session.asset_id(),
+        op_return: None,
     });
     create_opt.utxos = CreateTxUtxos::default();
     create_opt.utxo_strategy = UtxoStrategy::Manual;
     assert!(matches!(
         test_session.session.create_transaction(&mut create_opt),
-        Err(Error::InsufficientFunds)
+        Err(Error::InsufficientFunds { .. })
     ));
 
     if is_liquid {
@@ -583,13 +665,14 @@ fn coin_selection(is_liquid: bool) {
             address: node_address.to_string(),
             satoshi: sat2_a,
             asset_id: Some(asset_a.clone()),
+            op_return: None,
         });
         utxos.0.remove_entry(&btc_key);
         create_opt.utxos = utils::convertutxos(&utxos);
         create_opt.utxo_strategy = UtxoStrategy::Manual;
         assert!(matches!(
             test_session.session.create_transaction(&mut create_opt),
-            Err(Error::InsufficientFunds)
+            Err(Error::InsufficientFunds { .. })
         ));
 
         // send_all with asset does not send all l-btc
@@ -678,8 +761,12 @@ fn subaccounts(is_liquid: bool) {
     let mut test_session = TestSession::new(is_liquid, |_| ());
 
     let account0 = test_session.session.get_subaccount(0).unwrap();
-    let n_txs =
-        test_session.session.get_transactions(&GetTransactionsOpt::default()).unwrap().0.len();
+    let n_txs = test_session
+        .session
+        .get_transactions(&GetTransactionsOpt::default())
+        .unwrap()
+        .transactions
+        .len();
     assert_eq!(n_txs, 0);
     assert_eq!(account0.bip44_discovered, false);
     assert!(test_session.session.get_subaccount(1).is_err());
@@ -891,6 +978,7 @@ fn subaccounts(is_liquid: bool) {
         .session
         .get_next_subaccount(GetNextAccountOpt {
             script_type: ScriptType::P2pkh,
+            base_path: None,
         })
         .unwrap();
     assert_eq!(next_p2pkh, 50);
@@ -949,6 +1037,7 @@ fn subaccounts(is_liquid: bool) {
             subaccount: subaccount.account_num,
             num_confs: 0,
             confidential_utxos_only: None,
+            policy_asset_only: false,
         };
         let balance = *new_session.get_balance(&opt).unwrap().get(&btc_key).unwrap_or(&0i64) as u64;
         assert_eq!(
@@ -961,11 +1050,23 @@ fn subaccounts(is_liquid: bool) {
 
     // tx belong to the wallet
     assert!(test_session.session.get_transaction_hex(&txid).is_ok());
-    assert!(test_session.session.get_transaction_details(&txid).is_ok());
+    assert!(test_session
+        .session
+        .get_transaction_details(&GetTransactionDetailsOpt {
+            txhash: txid.to_string(),
+            ..Default::default()
+        })
+        .is_ok());
     // tx does not belong to the wallet
     let fake_txid = "0000000000000000000000000000000000000000000000000000000000000000";
     assert!(test_session.session.get_transaction_hex(&fake_txid).is_err());
-    assert!(test_session.session.get_transaction_details(&fake_txid).is_err());
+    assert!(test_session
+        .session
+        .get_transaction_details(&GetTransactionDetailsOpt {
+            txhash: fake_txid.to_string(),
+            ..Default::default()
+        })
+        .is_err());
 
     // Auth handler login does not have xprv, thus signing is disabled
     let tx = {
@@ -1039,6 +1140,7 @@ fn spend_unsynced(is_liquid: bool) {
         address: address2.to_string(),
         satoshi: sat2,
         asset_id: test_session.asset_id(),
+        op_return: None,
     });
     create_opt.utxos = utils::convertutxos(&utxos);
     let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
@@ -1065,6 +1167,7 @@ fn spend_unsynced(is_liquid: bool) {
         address: address2.to_string(),
         satoshi: sat2,
         asset_id: test_session.asset_id(),
+        op_return: None,
     });
     create_opt.utxos = utils::convertutxos(&utxos);
     let res = test_session.session.create_transaction(&mut create_opt);
@@ -1082,6 +1185,21 @@ fn spend_unsynced(is_liquid: bool) {
     let utxos_btc = utxos.0.get(&btc_key).unwrap();
     assert_eq!(utxos_btc.len(), 1);
     assert!(utxos_btc.iter().all(|u| u.txhash != txid1));
+
+    // The spent utxo is reported in the spend index
+    let spend_index = test_session
+        .session
+        .get_spend_index(&GetSpendIndexOpt {
+            subaccount: 0,
+        })
+        .unwrap();
+    assert_eq!(spend_index.len(), 1);
+    assert!(spend_index[0].outpoint.starts_with(&txid1));
+    assert_eq!(spend_index[0].spent_in, txid2);
+    assert_eq!(spend_index[0].height, 0);
+
+    let verify_store = test_session.session.verify_store().unwrap();
+    assert!(verify_store.ok, "{:?}", verify_store.issues);
 }
 
 #[test]
@@ -1112,12 +1230,13 @@ fn addresses(is_liquid: bool) {
         last_pointer: None,
         is_internal: false,
         count: 10,
+        with_tx_count: true,
     };
 
     let previous_addresses = test_session.session.get_previous_addresses(&opt).unwrap();
     assert_eq!(previous_addresses.list.len(), 10);
     assert_eq!(previous_addresses.list[0].pointer, 11);
-    assert_eq!(previous_addresses.list[0].tx_count, 1);
+    assert_eq!(previous_addresses.list[0].tx_count, Some(1));
     assert_eq!(previous_addresses.list[9].pointer, 2);
     assert_eq!(previous_addresses.last_pointer, Some(2));
     assert!(previous_addresses.list.iter().all(|e| !e.is_internal));
@@ -1126,6 +1245,27 @@ fn addresses(is_liquid: bool) {
         assert!(previous_addresses.list.iter().all(|e| e.is_confidential.unwrap()));
     }
 
+    // Skipping the transaction count does not scan the transactions, no address has a count
+    opt.with_tx_count = false;
+    let start = Instant::now();
+    let fast_addresses = test_session.session.get_previous_addresses(&opt).unwrap();
+    let fast_elapsed = start.elapsed();
+    assert!(fast_addresses.list.iter().all(|e| e.tx_count.is_none()));
+    let without_count = |list: &[PreviousAddress]| {
+        list.iter()
+            .map(|e| PreviousAddress {
+                tx_count: None,
+                ..e.clone()
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(without_count(&fast_addresses.list), without_count(&previous_addresses.list));
+    assert_eq!(fast_addresses.last_pointer, previous_addresses.last_pointer);
+    opt.with_tx_count = true;
+    let start = Instant::now();
+    test_session.session.get_previous_addresses(&opt).unwrap();
+    info!("previous addresses without tx_count {:?}, with {:?}", fast_elapsed, start.elapsed());
+
     opt.last_pointer = Some(100);
     let previous_addresses_100 = test_session.session.get_previous_addresses(&opt).unwrap();
     opt.last_pointer = Some(12);
@@ -1159,6 +1299,7 @@ fn addresses(is_liquid: bool) {
         last_pointer: None,
         is_internal: false,
         count: 10,
+        with_tx_count: true,
     };
 
     let previous_addresses = test_session.session.get_previous_addresses(&opt).unwrap();
@@ -1206,6 +1347,7 @@ fn sighash(is_liquid: bool) {
             address: dest_address,
             satoshi: 5000,
             asset_id: test_session.asset_id(),
+            op_return: None,
         });
         create_opt.utxos = utils::convertutxos(&test_session.utxos(create_opt.subaccount));
         let mut txc = test_session.session.create_transaction(&mut create_opt).unwrap();
@@ -1272,6 +1414,7 @@ fn skip_signing(is_liquid: bool) {
         address: dest_address,
         satoshi: 15000,
         asset_id: test_session.asset_id(),
+        op_return: None,
     });
     create_opt.utxos = utils::convertutxos(&test_session.utxos(create_opt.subaccount));
     let mut txc = test_session.session.create_transaction(&mut create_opt).unwrap();
@@ -1329,6 +1472,188 @@ fn not_unblindable_liquid() {
     assert!(test_session.utxos(0).0.is_empty());
 }
 
+#[test]
+fn unspent_outputs_pages() {
+    let mut test_session = TestSession::new(false, |_| ());
+    for satoshi in [10_000, 20_000, 30_000, 40_000, 50_000] {
+        let ap = test_session.get_receive_address(0);
+        let txid = test_session.node_sendtoaddress(&ap.address, satoshi, None);
+        test_session.wait_tx(vec![0], &txid, Some(satoshi), Some(TransactionType::Incoming));
+        // mix confirmed and unconfirmed outputs
+        if satoshi < 40_000 {
+            test_session.mine_block();
+        }
+    }
+    let outpoints = |utxos: &GetUnspentOutputs| -> Vec<(String, u32, u32)> {
+        utxos.0["btc"].iter().map(|u| (u.txhash.clone(), u.pt_idx, u.block_height)).collect()
+    };
+    let mut all = outpoints(&test_session.utxos(0));
+    assert_eq!(all.len(), 5);
+
+    let mut paged = vec![];
+    let mut opt = GetUnspentOpt {
+        subaccount: 0,
+        first: Some(0),
+        count: Some(2),
+        ..Default::default()
+    };
+    loop {
+        let page = test_session.session.get_unspent_outputs_page(&opt).unwrap();
+        let utxos = outpoints(&page.unspent_outputs);
+        assert!(utxos.len() <= 2);
+        paged.extend(utxos);
+        match page.last_pointer {
+            Some(next) => opt.first = Some(next),
+            None => break,
+        }
+    }
+    // confirmed outputs first, by height
+    let heights: Vec<u32> = paged
+        .iter()
+        .map(|(_, _, h)| {
+            if *h == 0 {
+                u32::MAX
+            } else {
+                *h
+            }
+        })
+        .collect();
+    assert!(heights.windows(2).all(|w| w[0] <= w[1]));
+    paged.sort();
+    all.sort();
+    assert_eq!(paged, all);
+
+    // the same page is returned by the handler, with the cursor
+    let value = test_session
+        .session
+        .handle_call("get_unspent_outputs", serde_json::json!({"subaccount": 0, "count": 2}))
+        .unwrap();
+    assert_eq!(value["last_pointer"], 2);
+    assert_eq!(value["unspent_outputs"]["btc"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn portfolio_value() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let opt = GetPortfolioValueOpt {
+        currency: Currency::USD,
+        include_hidden: false,
+    };
+    let value = test_session.session.get_portfolio_value(&opt).unwrap();
+    assert_eq!(value.total_satoshi, 0);
+    assert_eq!(value.total_fiat, "");
+    assert_eq!(value.rate, "");
+    assert_eq!(value.currency, "USD");
+
+    test_session.fund(150_000_000, None);
+    let pair = Pair::new(Currency::BTC, Currency::USD);
+    test_session.session.cache_ticker(Ticker::new(pair, 20_000.0));
+    let value = test_session.session.get_portfolio_value(&opt).unwrap();
+    assert_eq!(value.total_satoshi, 150_000_000);
+    assert_eq!(value.total_fiat, "30000.00");
+    assert_eq!(value.rate, "20000.00000000");
+    assert!(value.excluded_assets.is_empty());
+
+    let value = test_session
+        .session
+        .handle_call("get_portfolio_value", serde_json::json!({"currency": "USD"}))
+        .unwrap();
+    assert_eq!(value["total_fiat"], "30000.00");
+    test_session.stop();
+}
+
+#[test]
+fn convert_amount() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let convert = |test_session: &TestSession, value: &str, unit: &str, to_unit: &str| {
+        test_session.session.convert_amount(&ConvertAmountOpt {
+            value: value.to_string(),
+            unit: unit.to_string(),
+            to_unit: to_unit.to_string(),
+            currency: Some(Currency::USD),
+        })
+    };
+
+    let result = convert(&test_session, "123456789", "sat", "BTC").unwrap();
+    assert_eq!(result.value, "1.23456789");
+    assert_eq!(result.satoshi, 123_456_789);
+    let result = convert(&test_session, &result.value, "BTC", "sat").unwrap();
+    assert_eq!(result.value, "123456789");
+    assert_eq!(convert(&test_session, "0.5", "mBTC", "bits").unwrap().value, "500");
+
+    // No exchange rate fetched yet
+    assert!(convert(&test_session, "1", "BTC", "fiat").is_err());
+
+    let pair = Pair::new(Currency::BTC, Currency::USD);
+    test_session.session.cache_ticker(Ticker::new(pair, 20_000.0));
+    assert_eq!(convert(&test_session, "1.5", "BTC", "fiat").unwrap().value, "30000.00");
+    let result = convert(&test_session, "10", "fiat", "sat").unwrap();
+    assert_eq!(result.value, "50000");
+
+    let value = test_session
+        .session
+        .handle_call(
+            "convert_amount",
+            serde_json::json!({"value": "1", "unit": "fiat", "to_unit": "BTC", "currency": "USD"}),
+        )
+        .unwrap();
+    assert_eq!(value["value"], "0.00005");
+    test_session.stop();
+}
+
+#[test]
+fn wallet_summary() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let account1 = test_session
+        .session
+        .create_subaccount(CreateAccountOpt {
+            name: "Account 1".into(),
+            subaccount: 1,
+            ..Default::default()
+        })
+        .unwrap();
+    let summary = |test_session: &TestSession, include_hidden| {
+        test_session
+            .session
+            .get_wallet_summary(&GetWalletSummaryOpt {
+                include_hidden,
+            })
+            .unwrap()
+    };
+    let empty = summary(&test_session, false);
+    assert_eq!(empty.account_count, 2);
+    assert_eq!(empty.total["btc"], 0);
+
+    // confirmed funds in account 0, unconfirmed in account 1
+    test_session.fund(100_000, None);
+    test_session.mine_block();
+    let acc1_address = test_session.get_receive_address(account1.account_num);
+    let txid = test_session.node_sendtoaddress(&acc1_address.address, 20_000, None);
+    test_session.wait_tx(
+        vec![account1.account_num],
+        &txid,
+        Some(20_000),
+        Some(TransactionType::Incoming),
+    );
+    let all = summary(&test_session, false);
+    assert_eq!(all.total["btc"], 120_000);
+    assert_eq!(all.confirmed["btc"], 100_000);
+    assert_eq!(all.unconfirmed["btc"], 20_000);
+
+    test_session
+        .session
+        .set_subaccount_hidden(SetAccountHiddenOpt {
+            subaccount: account1.account_num,
+            hidden: true,
+        })
+        .unwrap();
+    let visible = summary(&test_session, false);
+    assert_eq!(visible.account_count, 1);
+    assert_eq!(visible.total["btc"], 100_000);
+    assert_eq!(visible.unconfirmed["btc"], 0);
+    assert_eq!(summary(&test_session, true), all);
+}
+
 #[test]
 fn labels() {
     // Create a session and two accounts
@@ -1369,6 +1694,7 @@ fn labels() {
         address: test_session.get_receive_address(account2.account_num).address,
         satoshi: sat,
         asset_id: None,
+        op_return: None,
     });
     create_opt.utxos = utils::convertutxos(&test_session.utxos(create_opt.subaccount));
     create_opt.memo = Some("Foo, Bar Foo".into());
@@ -1390,6 +1716,21 @@ fn labels() {
     assert_eq!(test_session.get_tx_from_list(account1.account_num, &txid).memo, "Bar, Foo Qux");
     assert_eq!(test_session.get_tx_from_list(account2.account_num, &txid).memo, "Bar, Foo Qux");
 
+    // Memos of unknown transactions are skipped, the others are set
+    let unknown = "11".repeat(32);
+    let opt = SetTransactionMemosOpt {
+        memos: [(txid.clone(), "Qux".to_string()), (unknown.clone(), "Foo".to_string())].into(),
+    };
+    let notified = test_session.session.filter_events("store_updated").len();
+    let result = test_session.session.set_transaction_memos(&opt).unwrap();
+    assert_eq!(result.unknown_txids, vec![unknown.clone()]);
+    assert_eq!(test_session.get_tx_from_list(account1.account_num, &txid).memo, "Qux");
+    let events = test_session.session.filter_events("store_updated");
+    assert_eq!(events.len(), notified + 1);
+    assert_eq!(events[notified]["store_updated"]["memos"], serde_json::json!([txid]));
+    // the same transactions are rejected setting a single memo
+    assert!(test_session.session.set_transaction_memo(&unknown, "Foo").is_err());
+
     // Using the external signer and broadcast_transaction does not the memo
     let test_signer = test_session.test_signer();
     let mut create_opt = CreateTransaction::default();
@@ -1399,6 +1740,7 @@ fn labels() {
         address: test_session.get_receive_address(account2.account_num).address,
         satoshi: sat,
         asset_id: None,
+        op_return: None,
     });
     create_opt.utxos = utils::convertutxos(&test_session.utxos(create_opt.subaccount));
     create_opt.memo = Some("Foo, Bar Foo".into());
@@ -1444,6 +1786,7 @@ fn rbf() {
         address: dest_address,
         satoshi: 50000,
         asset_id: None,
+        op_return: None,
     });
     create_opt.utxos = utils::convertutxos(&test_session.utxos(create_opt.subaccount));
     create_opt.fee_rate = Some(25000);
@@ -1471,16 +1814,22 @@ fn rbf() {
     assert_eq!(txitem.fee_rate / 1000, 43);
     assert_eq!(txitem.memo, "poz qux");
 
-    // The old transaction should be gone (after the next sync with the server)
+    assert_eq!(txitem.replaced_by, None);
+
+    // The old transaction should be flagged as replaced (after the next sync with the server)
     for i in 0..60 {
         std::thread::sleep(std::time::Duration::from_secs(1));
-        if test_session.get_tx_list(1).iter().all(|e| e.txhash != txid1) {
+        let old_txitem = test_session.get_tx_from_list(1, &txid1);
+        if old_txitem.replaced_by.is_some() {
+            assert_eq!(old_txitem.replaced_by, Some(txid2.clone()));
+            assert!(!old_txitem.can_rbf);
             assert!(test_session.utxos(1).0.get("btc").unwrap().iter().all(|e| e.txhash != txid1));
             assert_eq!(test_session.balance_account(1, None, None), sat - txitem.fee);
             break;
         }
-        assert!(i < 59, "timeout waiting for replaced transaction to disappear");
+        assert!(i < 59, "timeout waiting for replaced transaction to be flagged");
     }
+    assert_eq!(test_session.get_tx_list(1).iter().filter(|e| e.replaced_by.is_some()).count(), 1);
 
     // Transactions that are not properly signed should be rejected, to prevent the user from
     // being tricked into fee-bumping them.
@@ -1498,13 +1847,100 @@ fn rbf() {
     test_session.stop();
 }
 
+#[test]
+fn probe_server_capabilities() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let capabilities = test_session.session.probe_server_capabilities().unwrap();
+    assert_eq!(capabilities.genesis_hash, test_session.network.id().genesis_hash().unwrap());
+    assert!(!capabilities.protocol_version.is_empty());
+    let value = test_session.session.handle_call("probe_server_capabilities", Value::Null).unwrap();
+    assert_eq!(value["genesis_hash"], capabilities.genesis_hash);
+
+    // A testnet session connected to the regtest server
+    let tmp_dir = TempDir::new().unwrap();
+    let mut network = test_session.network_parameters().clone();
+    network.state_dir = format!("{}", tmp_dir.path().display());
+    network.development = false;
+    let mut session = ElectrumSession::new(network).unwrap();
+    assert!(matches!(
+        session.probe_server_capabilities(),
+        Err(Error::MismatchingGenesisHash { .. })
+    ));
+
+    // The server is refused on connect too
+    let err = session.connect(&Value::Null).unwrap_err();
+    assert!(matches!(err, Error::MismatchingGenesisHash { .. }));
+    assert_eq!(err.to_gdk_code(), "id_wrong_network_server");
+    test_session.stop();
+}
+
+#[test]
+fn set_electrum_tls() {
+    let mut test_session = TestSession::new(false, |_| ());
+    assert!(test_session.session.ping_server().is_ok());
+    let network_events = test_session.session.filter_events("network").len();
+
+    // The test electrum server does not speak TLS
+    let opt = SetElectrumTlsOpt {
+        tls: true,
+        validate_domain: false,
+    };
+    test_session.session.set_electrum_tls(&opt).unwrap();
+    assert!(test_session.session.ping_server().is_err());
+    assert_eq!(
+        test_session.session.filter_events("network").last(),
+        Some(&utils::ntf_network(State::Disconnected, State::Connected))
+    );
+    assert_eq!(test_session.session.filter_events("network").len(), network_events + 1);
+
+    // Back to plaintext, with the same host and port
+    let value = serde_json::json!({ "tls": false });
+    test_session.session.handle_call("set_electrum_tls", value).unwrap();
+    assert!(test_session.session.ping_server().is_ok());
+    assert_eq!(
+        test_session.session.filter_events("network").last(),
+        Some(&utils::ntf_network(State::Connected, State::Connected))
+    );
+    assert_eq!(test_session.session.filter_events("network").len(), network_events + 2);
+
+    // The wallet keeps syncing
+    test_session.fund(10_000, None);
+    test_session.stop();
+}
+
+#[test]
+fn get_connection_info() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let electrum_url = test_session.network_parameters().electrum_url.clone().unwrap();
+    let info = test_session.session.get_connection_info().unwrap();
+    assert_eq!(info.electrum_url, format!("tcp://{}", electrum_url));
+    assert!(!info.tls);
+    assert!(!info.onion);
+    assert!(!info.backup);
+    assert!(info.last_network_call_succeeded);
+
+    // The test electrum server does not speak TLS
+    let opt = SetElectrumTlsOpt {
+        tls: true,
+        validate_domain: false,
+    };
+    test_session.session.set_electrum_tls(&opt).unwrap();
+    let value = test_session.session.handle_call("get_connection_info", Value::Null).unwrap();
+    assert_eq!(value["electrum_url"], format!("ssl://{}", electrum_url));
+    assert_eq!(value["tls"], true);
+    assert_eq!(value["last_network_call_succeeded"], false);
+    test_session.stop();
+}
+
 #[test]
 fn test_electrum_disconnect() {
     let mut test_session = TestSession::new(false, |_| ());
     assert!(test_session.electrs.client.ping().is_ok());
+    assert!(test_session.session.ping_server().is_ok());
 
     assert_eq!(test_session.session.filter_events("network").len(), 1);
     test_session.electrs.kill().unwrap();
+    assert!(test_session.session.ping_server().is_err());
     for i in 0.. {
         assert!(i < 100);
         if test_session.session.filter_events("network").len() > 1 {
@@ -1883,37 +2319,2219 @@ fn test_utxo_unconfirmed() {
     }
 }
 
-fn setup_forking_sessions(enable_session_cross: bool) -> (TestSession, TestSession) {
-    let test_session2 = TestSession::new(false, |_| ());
+#[test]
+fn get_store_info() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    test_session.session.store().unwrap().write().unwrap().flush().unwrap();
+    let info = test_session.session.get_store_info().unwrap();
+    assert!(info.path.starts_with(&test_session.network_parameters().state_dir));
+    assert!(info.size_bytes > 0);
+    assert!(info.last_modified > 0);
+    assert_eq!(info.tx_count, 1);
+    let value = test_session.session.handle_call("get_store_info", Value::Null).unwrap();
+    assert_eq!(value["path"], info.path);
+
+    // Watch-only stores report their own path
+    let bundle = test_session.session.export_watch_only_bundle().unwrap();
+    let tmp_dir = TempDir::new().unwrap();
+    let mut wo_session = {
+        let mut network = test_session.network_parameters().clone();
+        network.state_dir = format!("{}", tmp_dir.path().display());
+        ElectrumSession::new(network).unwrap()
+    };
+    assert!(matches!(wo_session.get_store_info(), Err(Error::StoreNotLoaded)));
+    wo_session.login_wo(bundle.credentials).unwrap();
+    let wo_info = wo_session.get_store_info().unwrap();
+    assert!(wo_info.path.starts_with(&format!("{}", tmp_dir.path().display())));
+    wo_session.disconnect().unwrap();
+    test_session.stop();
+}
 
-    let test_session1 = TestSession::new(false, |network| {
-        if enable_session_cross {
-            network.spv_multi = Some(true);
-            network.spv_servers = Some(vec![test_session2.electrs.electrum_url.clone()]);
-        }
-    });
+#[test]
+fn export_watch_only_bundle() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session
+        .session
+        .create_subaccount(CreateAccountOpt {
+            subaccount: 1,
+            name: "Account 1".into(),
+            ..Default::default() // p2wpkh
+        })
+        .unwrap();
 
-    // Connect nodes and point both to the same tip
-    test_session2.node_connect(test_session1.p2p_port);
-    test_session1.node_generate(20);
+    let bundle = test_session.session.export_watch_only_bundle().unwrap();
+    match &bundle.credentials {
+        WatchOnlyCredentials::CoreDescriptors(descriptors) => assert_eq!(descriptors.len(), 4),
+        _ => panic!("unexpected credentials"),
+    }
 
-    test_session1.wait_blockheight(121);
-    test_session2.wait_blockheight(121);
+    // Login with a watch-only session using the exported credentials
+    let tmp_dir = TempDir::new().unwrap();
+    let mut wo_session = {
+        let mut network = test_session.network_parameters().clone();
+        network.state_dir = format!("{}", tmp_dir.path().display());
+        ElectrumSession::new(network).unwrap()
+    };
+    wo_session.login_wo(bundle.credentials.clone()).unwrap();
+    for account_num in [0, 1] {
+        let account = test_session.session.get_subaccount(account_num).unwrap();
+        let wo_account = wo_session.get_subaccount(account_num).unwrap();
+        assert_eq!(account.core_descriptors, wo_account.core_descriptors);
+    }
+    let wo_bundle = wo_session.export_watch_only_bundle().unwrap();
+    assert_eq!(bundle.master_fingerprint, wo_bundle.master_fingerprint);
+
+    // The master fingerprint is the one in the descriptors key origin
+    let fingerprint = test_session.session.get_master_fingerprint().unwrap().master_fingerprint;
+    assert_eq!(fingerprint, bundle.master_fingerprint);
+    assert_eq!(wo_session.get_master_fingerprint().unwrap().master_fingerprint, fingerprint);
+    let account = test_session.session.get_subaccount(0).unwrap();
+    assert!(account.core_descriptors[0].contains(&format!("[{}/", fingerprint)));
+    let value = test_session.session.handle_call("get_master_fingerprint", Value::Null).unwrap();
+    assert_eq!(value["master_fingerprint"], fingerprint.to_string());
+    wo_session.disconnect().unwrap();
+}
 
-    // Disconnect so they don't learn about eachother blocks
-    test_session1.node_disconnect_all();
+#[test]
+fn fee_estimates_by_target() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let estimates = test_session.session.get_fee_estimates_by_target().unwrap();
+    let targets: Vec<_> = estimates.targets.keys().cloned().collect();
+    assert_eq!(targets, FEE_ESTIMATE_TARGETS.to_vec());
+    let fees: Vec<_> = estimates.targets.values().cloned().collect();
+    assert!(fees.windows(2).all(|w| w[0] >= w[1]));
+    assert!(fees.iter().all(|f| *f >= estimates.relay_fee));
+
+    // the legacy array is still returned by get_fee_estimates
+    let value = test_session.session.handle_call("get_fee_estimates", Value::Null).unwrap();
+    assert_eq!(value["fees"].as_array().unwrap().len(), 25);
+    let value =
+        test_session.session.handle_call("get_fee_estimates_by_target", Value::Null).unwrap();
+    assert_eq!(value["relay_fee"], estimates.relay_fee);
+    test_session.stop();
+}
 
-    (test_session1, test_session2)
+#[test]
+fn fee_rate_at_percentile() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+
+    for percentile in [0.0, 50.0, 100.0] {
+        let opt = FeeRateAtPercentileOpt {
+            percentile,
+        };
+        let result = test_session.session.fee_rate_at_percentile(&opt).unwrap();
+        assert!(result.fee_rate > 0.0);
+    }
+    for percentile in [-1.0, 100.1, f64::NAN] {
+        let opt = FeeRateAtPercentileOpt {
+            percentile,
+        };
+        assert!(test_session.session.fee_rate_at_percentile(&opt).is_err());
+    }
 }
 
-fn get_chain(test_session: &mut TestSession) -> HeadersChain {
+#[test]
+fn export_account() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session
+        .session
+        .create_subaccount(CreateAccountOpt {
+            subaccount: 1,
+            name: "Account 1".into(),
+            ..Default::default() // p2wpkh
+        })
+        .unwrap();
+
+    let exported = test_session
+        .session
+        .export_account(&GetAccountDescriptorsOpt {
+            subaccount: 1,
+        })
+        .unwrap();
+    assert_eq!(exported.subaccount, 1);
+    // The descriptors are the ones checked against the node in `account_descriptors`
+    let opt = GetAccountDescriptorsOpt {
+        subaccount: 1,
+    };
+    let descriptors = test_session.session.get_account_descriptors(&opt).unwrap();
+    assert_eq!(exported.descriptors.receive, descriptors.receive);
+    assert_eq!(exported.descriptors.change, descriptors.change);
+    let account = test_session.session.get_subaccount(1).unwrap();
+    assert_eq!(account.slip132_extended_pubkey, exported.slip132_extended_pubkey);
+    assert!(exported.slip132_extended_pubkey.as_ref().unwrap().starts_with("vpub"));
+    assert!(test_session
+        .session
+        .export_account(&GetAccountDescriptorsOpt {
+            subaccount: 17,
+        })
+        .is_err());
+
+    // A watch-only session loaded from the exported descriptors has the same account
+    let tmp_dir = TempDir::new().unwrap();
+    let mut wo_session = {
+        let mut network = test_session.network_parameters().clone();
+        network.state_dir = format!("{}", tmp_dir.path().display());
+        ElectrumSession::new(network).unwrap()
+    };
+    let credentials = WatchOnlyCredentials::CoreDescriptors(vec![
+        exported.descriptors.receive.clone(),
+        exported.descriptors.change.clone(),
+    ]);
+    wo_session.login_wo(credentials).unwrap();
+    let wo_exported = wo_session
+        .export_account(&GetAccountDescriptorsOpt {
+            subaccount: 1,
+        })
+        .unwrap();
+    assert_eq!(wo_exported.descriptors.receive, exported.descriptors.receive);
+    assert_eq!(wo_exported.descriptors.change, exported.descriptors.change);
+    assert_eq!(wo_exported.xpub, exported.xpub);
+    assert_eq!(wo_exported.slip132_extended_pubkey, exported.slip132_extended_pubkey);
+    wo_session.disconnect().unwrap();
     test_session.stop();
-    HeadersChain::new(&test_session.session.network.state_dir, bitcoin::Network::Regtest).unwrap()
 }
 
-fn assert_unwrap_invalid(result: spv::CrossValidationResult) -> spv::CrossValidationInvalid {
-    match result {
-        spv::CrossValidationResult::Invalid(inv) => inv,
-        _ => panic!("expected cross-validation to fail"),
+#[test]
+fn input_addresses() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let node_address = test_session.node_getnewaddress(Some("bech32"));
+    let txid = test_session.node_sendtoaddress(&node_address, 200_000, None);
+    test_session.mine_block();
+    let node_tx: Value = test_session
+        .node
+        .client
+        .call("getrawtransaction", &[txid.clone().into(), true.into()])
+        .unwrap();
+    let vout = node_tx["vout"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .position(|o| o["value"].as_f64() == Some(0.002))
+        .unwrap() as u32;
+    let wallet_address = test_session.get_receive_address(0).address;
+    // Spend the node output to the wallet, so that its prevout is not in the wallet
+    let inputs = serde_json::json!([{"txid": txid, "vout": vout}]);
+    let outputs = serde_json::json!([{ wallet_address: 0.0019 }]);
+    let raw: String =
+        test_session.node.client.call("createrawtransaction", &[inputs, outputs]).unwrap();
+    let signed: Value =
+        test_session.node.client.call("signrawtransactionwithwallet", &[raw.into()]).unwrap();
+    let incoming_txid: String =
+        test_session.node.client.call("sendrawtransaction", &[signed["hex"].clone()]).unwrap();
+    test_session.wait_tx(vec![0], &incoming_txid, Some(190_000), Some(TransactionType::Incoming));
+
+    let mut opt = GetTransactionDetailsOpt {
+        txhash: incoming_txid.clone(),
+        fetch_input_addresses: false,
+    };
+    assert!(test_session.session.get_transaction_details(&opt).unwrap().input_addresses.is_none());
+    opt.fetch_input_addresses = true;
+    for _ in 0..2 {
+        // The second time the previous transaction is cached
+        let details = test_session.session.get_transaction_details(&opt).unwrap();
+        assert_eq!(details.input_addresses, Some(vec![node_address.clone()]));
+    }
+    let value = test_session
+        .session
+        .handle_call("get_transaction_details", serde_json::to_value(&opt).unwrap())
+        .unwrap();
+    assert_eq!(value["input_addresses"], serde_json::json!([node_address]));
+    let value = test_session
+        .session
+        .handle_call("get_transaction_details", incoming_txid.clone().into())
+        .unwrap();
+    assert!(value.get("input_addresses").is_none());
+    test_session.stop();
+}
+
+#[test]
+fn receive_address_type() {
+    // The network default applies only to the subaccounts of its type
+    let mut test_session =
+        TestSession::new(false, |n| n.default_address_type = Some("p2wpkh".into()));
+    for (subaccount, name) in [(1, "Segwit"), (2, "Legacy")] {
+        test_session
+            .session
+            .create_subaccount(CreateAccountOpt {
+                subaccount,
+                name: name.into(),
+                ..Default::default()
+            })
+            .unwrap();
     }
+    let mut set_default = |subaccount: u32, address_type: &str| {
+        test_session.session.update_subaccount(UpdateAccountOpt {
+            subaccount,
+            default_address_type: Some(address_type.into()),
+            ..Default::default()
+        })
+    };
+    // The default of a subaccount must be its type
+    let err = set_default(2, "p2wpkh").unwrap_err();
+    assert!(matches!(err, Error::UnsupportedAddressType(_, 2)));
+    set_default(2, "p2pkh").unwrap();
+
+    let get_address = |subaccount: u32, address_type: Option<&str>| {
+        test_session.session.get_receive_address(&GetAddressOpt {
+            subaccount,
+            address_type: address_type.map(String::from),
+            is_internal: None,
+        })
+    };
+    // Without a requested type, the subaccount default, the network one or the type of the
+    // subaccount is used
+    assert_eq!(get_address(0, None).unwrap().address_type, "p2sh-p2wpkh");
+    assert_eq!(get_address(1, None).unwrap().address_type, "p2wpkh");
+    assert_eq!(get_address(2, None).unwrap().address_type, "p2pkh");
+    assert_eq!(get_address(2, Some("")).unwrap().address_type, "p2pkh");
+
+    let address = get_address(2, Some("p2pkh")).unwrap();
+    assert_eq!(address.address_type, "p2pkh");
+    assert!(address.address.starts_with('m') || address.address.starts_with('n'));
+
+    for (subaccount, address_type) in [(0, "p2wpkh"), (2, "p2sh-p2wpkh"), (0, "csv"), (2, "foo")] {
+        let err = get_address(subaccount, Some(address_type)).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedAddressType(_, n) if n == subaccount));
+    }
+
+    // An empty default removes it
+    test_session
+        .session
+        .update_subaccount(UpdateAccountOpt {
+            subaccount: 2,
+            default_address_type: Some("".into()),
+            ..Default::default()
+        })
+        .unwrap();
+    let settings = test_session.session.get_subaccount(2).unwrap().settings;
+    assert_eq!(settings.default_address_type, None);
+    test_session.stop();
+}
+
+#[test]
+fn account_descriptors() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let opt = GetAccountDescriptorsOpt {
+        subaccount: 0,
+    };
+    let descriptors = test_session.session.get_account_descriptors(&opt).unwrap();
+    let account = test_session.session.get_subaccount(0).unwrap();
+    assert_eq!(
+        account.core_descriptors,
+        vec![descriptors.receive.clone(), descriptors.change.clone()]
+    );
+    let fingerprint = test_session.session.get_master_fingerprint().unwrap().master_fingerprint;
+
+    // The node computes the same checksums and can import the descriptors
+    let mut requests = vec![];
+    for (desc, internal) in [(&descriptors.receive, false), (&descriptors.change, true)] {
+        assert!(desc.starts_with(&format!("sh(wpkh([{}/49'/1'/0']", fingerprint)), "{}", desc);
+        let info: Value =
+            test_session.node.client.call("getdescriptorinfo", &[desc.clone().into()]).unwrap();
+        assert_eq!(info["checksum"].as_str(), desc.split('#').nth(1));
+        requests.push(serde_json::json!({
+            "desc": desc,
+            "internal": internal,
+            "active": true,
+            "timestamp": "now",
+        }));
+    }
+    // A blank watch-only descriptor wallet
+    let params = [
+        "account_descriptors".into(),
+        true.into(),
+        true.into(),
+        "".into(),
+        false.into(),
+        true.into(),
+    ];
+    let _: Value = test_session.node.client.call("createwallet", &params).unwrap();
+    let wallet = electrsd::bitcoind::bitcoincore_rpc::Client::new(
+        &test_session.node.rpc_url_with_wallet("account_descriptors"),
+        electrsd::bitcoind::bitcoincore_rpc::Auth::CookieFile(
+            test_session.node.params.cookie_file.clone(),
+        ),
+    )
+    .unwrap();
+    let result: Value = wallet.call("importdescriptors", &[requests.into()]).unwrap();
+    assert!(result.as_array().unwrap().iter().all(|r| r["success"] == true), "{}", result);
+
+    // The receive addresses match the ones of the node
+    let address = test_session.get_receive_address(0);
+    let range = serde_json::json!([address.pointer, address.pointer]);
+    let derived: Vec<String> =
+        wallet.call("deriveaddresses", &[descriptors.receive.clone().into(), range]).unwrap();
+    assert_eq!(derived, vec![address.address]);
+
+    let value = test_session
+        .session
+        .handle_call("get_account_descriptors", serde_json::json!({"subaccount": 0}))
+        .unwrap();
+    assert_eq!(value["receive"], descriptors.receive);
+    assert_eq!(value["change"], descriptors.change);
+    assert!(test_session
+        .session
+        .get_account_descriptors(&GetAccountDescriptorsOpt {
+            subaccount: 1
+        })
+        .is_err());
+}
+
+#[test]
+fn watch_only_multisig() {
+    use gdk_common::bitcoin::util::bip32::{ExtendedPrivKey, ExtendedPubKey};
+
+    let test_session = TestSession::new(false, |_| ());
+
+    // A 2-of-3 account from the BIP48 p2wsh keys of three signers
+    let path: DerivationPath = "m/48'/1'/0'/2'".parse().unwrap();
+    let keys = (1u8..=3)
+        .map(|i| {
+            let master = ExtendedPrivKey::new_master(bitcoin::Network::Testnet, &[i; 32]).unwrap();
+            let fingerprint = master.fingerprint(&gdk_common::EC);
+            let xprv = master.derive_priv(&gdk_common::EC, &path).unwrap();
+            let xpub = ExtendedPubKey::from_priv(&gdk_common::EC, &xprv);
+            format!("[{}/48'/1'/0'/2']{}/0/*", fingerprint, xpub)
+        })
+        .collect::<Vec<_>>();
+    let descriptor = format!("wsh(sortedmulti(2,{}))", keys.join(","));
+
+    let tmp_dir = TempDir::new().unwrap();
+    let mut wo_session = {
+        let mut network = test_session.network_parameters().clone();
+        network.state_dir = format!("{}", tmp_dir.path().display());
+        ElectrumSession::new(network).unwrap()
+    };
+    let credentials = WatchOnlyCredentials::MultisigDescriptors(vec![descriptor.clone()]);
+    wo_session.login_wo(credentials).unwrap();
+
+    let account_num = ScriptType::P2wsh.account_num(0).unwrap();
+    let account = wo_session.get_subaccount(account_num).unwrap();
+    assert_eq!(account.script_type, ScriptType::P2wsh);
+    assert!(account.slip132_extended_pubkey.is_none());
+    assert!(account.core_descriptors[0].starts_with(&descriptor));
+    assert!(account.core_descriptors[1].starts_with(&descriptor.replace("/0/*", "/1/*")));
+
+    // Receive addresses match the ones derived by the node
+    let address = wo_session
+        .get_receive_address(&GetAddressOpt {
+            subaccount: account_num,
+            address_type: None,
+            is_internal: None,
+        })
+        .unwrap();
+    let info: Value = test_session
+        .node
+        .client
+        .call("getdescriptorinfo", &[account.core_descriptors[0].clone().into()])
+        .unwrap();
+    let range = serde_json::json!([address.pointer, address.pointer]);
+    let derived: Vec<String> = test_session
+        .node
+        .client
+        .call("deriveaddresses", &[info["descriptor"].clone(), range])
+        .unwrap();
+    assert_eq!(derived, vec![address.address.clone()]);
+
+    // Incoming funds are tracked
+    test_session.node_sendtoaddress(&address.address, 50_000, None);
+    let utxo_opt = GetUnspentOpt {
+        subaccount: account_num,
+        num_confs: Some(0),
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let utxos = loop {
+        let utxos = wo_session.get_unspent_outputs(&utxo_opt).unwrap();
+        if !utxos.0.is_empty() {
+            break utxos;
+        }
+        assert!(start.elapsed() < Duration::from_secs(60), "watch-only session did not sync");
+        thread::sleep(Duration::from_millis(500));
+    };
+    assert_eq!(utxos.0[&test_session.btc_key()][0].satoshi, 50_000);
+    let balance_opt = GetBalanceOpt {
+        subaccount: account_num,
+        num_confs: 0,
+        confidential_utxos_only: None,
+        policy_asset_only: false,
+    };
+    let balance = wo_session.get_balance(&balance_opt).unwrap();
+    assert_eq!(balance[&test_session.btc_key()], 50_000);
+
+    // Spending is not supported yet
+    let node_address = test_session.node_getnewaddress(None);
+    let mut create_opt =
+        test_session.create_opt(&node_address, 10_000, None, None, account_num, utxos);
+    assert!(wo_session.create_transaction(&mut create_opt).is_err());
+    wo_session.disconnect().unwrap();
+
+    // The exported descriptors can be used to login
+    let bundle = wo_session.export_watch_only_bundle().unwrap();
+    let tmp_dir = TempDir::new().unwrap();
+    let mut wo_session2 = {
+        let mut network = test_session.network_parameters().clone();
+        network.state_dir = format!("{}", tmp_dir.path().display());
+        ElectrumSession::new(network).unwrap()
+    };
+    wo_session2.login_wo(bundle.credentials).unwrap();
+    let account2 = wo_session2.get_subaccount(account_num).unwrap();
+    assert_eq!(account2.core_descriptors, account.core_descriptors);
+    wo_session2.disconnect().unwrap();
+}
+
+#[test]
+fn watch_only_create_psbt() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    let bundle = test_session.session.export_watch_only_bundle().unwrap();
+
+    let tmp_dir = TempDir::new().unwrap();
+    let mut wo_session = {
+        let mut network = test_session.network_parameters().clone();
+        network.state_dir = format!("{}", tmp_dir.path().display());
+        ElectrumSession::new(network).unwrap()
+    };
+    wo_session.login_wo(bundle.credentials.clone()).unwrap();
+    let utxo_opt = GetUnspentOpt {
+        subaccount: 0,
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let utxos = loop {
+        let utxos = wo_session.get_unspent_outputs(&utxo_opt).unwrap();
+        if !utxos.0.is_empty() {
+            break utxos;
+        }
+        assert!(start.elapsed() < Duration::from_secs(60), "watch-only session did not sync");
+        thread::sleep(Duration::from_millis(500));
+    };
+
+    // A watch-only session can create transactions but not sign them
+    let node_address = test_session.node_getnewaddress(None);
+    let mut create_opt = test_session.create_opt(&node_address, 10_000, None, None, 0, utxos);
+    let tx = wo_session.create_transaction(&mut create_opt).unwrap();
+    assert!(wo_session.sign_transaction(&tx).is_err());
+
+    let psbt = wo_session.create_psbt(&tx).unwrap().psbt;
+    let decoded: bitcoin::util::psbt::PartiallySignedTransaction =
+        bitcoin::consensus::deserialize(&base64::decode(&psbt).unwrap()).unwrap();
+    assert_eq!(decoded.unsigned_tx.txid().to_string(), tx.txid);
+    for (input, utxo) in decoded.inputs.iter().zip(tx.used_utxos.iter()) {
+        // account 0 is p2sh-p2wpkh
+        assert!(input.witness_utxo.is_some());
+        assert!(input.non_witness_utxo.is_some());
+        assert!(input.redeem_script.is_some());
+        let (fingerprint, path) = input.bip32_derivation.values().next().unwrap();
+        assert_eq!(fingerprint.to_string(), bundle.master_fingerprint.to_string());
+        assert_eq!(path, &DerivationPath::from(utxo.user_path.clone()));
+    }
+    // the change output is annotated too
+    assert!(decoded.outputs.iter().any(|o| !o.bip32_derivation.is_empty()));
+
+    let analysis: Value =
+        test_session.node.client.call("analyzepsbt", &[psbt.clone().into()]).unwrap();
+    assert_eq!(analysis["next"].as_str(), Some("signer"));
+
+    // The same PSBT is created from the transaction hex and the used utxos only, or from the
+    // whole created transaction
+    let input = serde_json::json!({"transaction": tx.hex, "used_utxos": tx.used_utxos});
+    let value = wo_session.handle_call("transaction_to_psbt", input).unwrap();
+    assert_eq!(value["psbt"], psbt);
+    let value =
+        wo_session.handle_call("transaction_to_psbt", serde_json::to_value(&tx).unwrap()).unwrap();
+    assert_eq!(value["psbt"], psbt);
+    let input = serde_json::json!({"transaction": tx.hex});
+    assert!(wo_session.handle_call("transaction_to_psbt", input).is_err());
+
+    // The full session signs the same transaction
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    assert_eq!(signed_tx.txid, tx.txid);
+    wo_session.disconnect().unwrap();
+}
+
+#[test]
+fn fee_absolute() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(1_000_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+    let utxos = test_session.utxos(0);
+
+    let mut create_opt =
+        test_session.create_opt(&node_address, 10_000, None, Some(1000), 0, utxos.clone());
+    create_opt.fee_absolute = Some(5_000);
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt.clone()),
+        Err(Error::InvalidFeeOptions)
+    ));
+
+    create_opt.fee_rate = None;
+    create_opt.fee_absolute = Some(10);
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt.clone()),
+        Err(Error::FeeRateBelowMinimum(_))
+    ));
+
+    create_opt.fee_absolute = Some(5_000);
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.fee, 5_000);
+    assert!(tx.effective_fee_rate.unwrap() > 1000);
+
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    let txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &txid, Some(15_000), Some(TransactionType::Outgoing));
+}
+
+#[test]
+fn allow_unconfirmed() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let node_address = test_session.node_getnewaddress(None);
+
+    // An unconfirmed incoming utxo is never spent if `num_confs` is not satisfied
+    let sat1 = 100_000;
+    let address = test_session.get_receive_address(0).address;
+    let txid1 = test_session.node_sendtoaddress(&address, sat1, None);
+    test_session.wait_tx(vec![0], &txid1, Some(sat1), Some(TransactionType::Incoming));
+    let mut create_opt =
+        test_session.create_opt(&node_address, 10_000, None, None, 0, test_session.utxos(0));
+    create_opt.num_confs = 1;
+    create_opt.allow_unconfirmed = true;
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt),
+        Err(Error::InsufficientFunds { .. })
+    ));
+
+    // Confirm it and spend it, leaving only an unconfirmed change
+    test_session.mine_block();
+    let txid2 = test_session.send_tx(&node_address, 10_000, None, None, None, None, None);
+    let utxos = test_session.utxos(0);
+    assert!(utxos.0["btc"].iter().all(|u| u.txhash == txid2 && u.is_internal));
+
+    let mut create_opt = test_session.create_opt(&node_address, 10_000, None, None, 0, utxos);
+    create_opt.num_confs = 1;
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt.clone()),
+        Err(Error::InsufficientFunds { .. })
+    ));
+
+    create_opt.allow_unconfirmed = true;
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert!(tx.used_unconfirmed_utxos);
+    assert!(tx.used_utxos.iter().all(|u| u.txhash == txid2));
+}
+
+#[test]
+fn estimate_only() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(1_000_000, None);
+    test_session.fund(500_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+    let utxos = test_session.utxos(0);
+
+    let mut create_opt =
+        test_session.create_opt(&node_address, 1_200_000, None, Some(2000), 0, utxos);
+    create_opt.estimate_only = true;
+    let estimate = test_session.session.estimate_transaction(&mut create_opt.clone()).unwrap();
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(estimate.fee, tx.fee);
+    assert_eq!(estimate.used_utxos.len(), 2);
+    let outpoints = |utxos: &[UnspentOutput]| {
+        let mut outpoints: Vec<_> = utxos.iter().map(|u| (u.txhash.clone(), u.pt_idx)).collect();
+        outpoints.sort();
+        outpoints
+    };
+    assert_eq!(outpoints(&estimate.used_utxos), outpoints(&tx.used_utxos));
+
+    // The estimate assumes max size signatures
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    let signed_tx = BETransaction::from_hex(
+        &signed_tx.hex,
+        NetworkId::Bitcoin(gdk_common::bitcoin::Network::Regtest),
+    )
+    .unwrap();
+    assert!(estimate.weight >= signed_tx.get_weight());
+    assert!(estimate.weight - signed_tx.get_weight() <= 4 * 2);
+    assert_eq!(estimate.vsize, (estimate.weight + 3) / 4);
+}
+
+#[test]
+fn utxo_strategy_branch_and_bound() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    test_session.fund(300_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+    let utxos = test_session.utxos(0);
+    let small = utxos.0["btc"].iter().find(|u| u.satoshi == 100_000).unwrap().clone();
+
+    // The amount spending exactly the small utxo, minus the fee
+    let only_small =
+        GetUnspentOutputs(vec![("btc".to_string(), vec![small.clone()])].into_iter().collect());
+    let mut create_opt = test_session.create_opt(&node_address, 0, None, Some(1000), 0, only_small);
+    create_opt.send_all = true;
+    let estimate = test_session.session.estimate_transaction(&mut create_opt).unwrap();
+    let amount = create_opt.addressees[0].satoshi;
+    assert_eq!(amount + estimate.fee, 100_000);
+
+    // A changeless selection is found, even if the default selection would spend the largest utxo
+    let mut create_opt =
+        test_session.create_opt(&node_address, amount, None, Some(1000), 0, utxos.clone());
+    create_opt.utxo_strategy = UtxoStrategy::BranchAndBound;
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.used_utxos.len(), 1);
+    assert_eq!(tx.used_utxos[0].txhash, small.txhash);
+    assert_eq!(tx.changes_used, Some(0));
+    assert_eq!(tx.fee, 100_000 - amount);
+
+    // Without a changeless selection, fall back to the default selection
+    let mut create_opt =
+        test_session.create_opt(&node_address, 150_000, None, Some(1000), 0, utxos);
+    create_opt.utxo_strategy = UtxoStrategy::BranchAndBound;
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.used_utxos.len(), 1);
+    assert_eq!(tx.used_utxos[0].satoshi, 300_000);
+    assert_eq!(tx.changes_used, Some(1));
+
+    // Inputs and change carry their derivation path for hardware wallets to display
+    let input_path = &tx.used_utxos[0].derivation_path;
+    assert!(input_path.starts_with("m/49'/1'/0'/"), "{}", input_path);
+    assert_eq!(input_path, &DerivationPath::from(tx.used_utxos[0].user_path.clone()).to_string());
+    let change = tx.transaction_outputs.iter().find(|o| o.is_change).unwrap();
+    assert!(change.derivation_path.starts_with("m/49'/1'/0'/1/"), "{}", change.derivation_path);
+    let recipient = tx.transaction_outputs.iter().find(|o| !o.is_relevant).unwrap();
+    assert!(recipient.derivation_path.is_empty());
+}
+
+#[test]
+fn frozen_outputs() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    test_session.fund(300_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+    let utxos = test_session.utxos(0);
+    let large = utxos.0["btc"].iter().find(|u| u.satoshi == 300_000).unwrap().clone();
+
+    let set_frozen = |test_session: &TestSession, txhash: &str, frozen| {
+        let opt = SetUnspentOutputFrozenOpt {
+            subaccount: 0,
+            txhash: txhash.to_string(),
+            pt_idx: large.pt_idx,
+            frozen,
+        };
+        test_session.session.set_unspent_output_frozen(&opt)
+    };
+    let node_txid = test_session.node_sendtoaddress(&node_address, 10_000, None);
+    assert!(matches!(
+        set_frozen(&test_session, &node_txid, true),
+        Err(Error::UnspentOutputNotFound(_, _))
+    ));
+    set_frozen(&test_session, &large.txhash, true).unwrap();
+    let frozen = test_session.session.get_frozen_outputs().unwrap();
+    assert_eq!(frozen.len(), 1);
+    assert_eq!((frozen[0].txid.as_str(), frozen[0].vout), (large.txhash.as_str(), large.pt_idx));
+
+    // Frozen outputs are excluded unless requested, but still part of the balance
+    let unspent = test_session.utxos(0);
+    assert_eq!(unspent.0["btc"].len(), 1);
+    assert_eq!(unspent.0["btc"][0].satoshi, 100_000);
+    let opt = GetUnspentOpt {
+        subaccount: 0,
+        include_frozen: Some(true),
+        ..Default::default()
+    };
+    assert_eq!(test_session.session.get_unspent_outputs(&opt).unwrap().0["btc"].len(), 2);
+    assert_eq!(test_session.balance_account(0, None, None), 400_000);
+
+    // Coin selection skips frozen outputs, even when passed explicitly
+    let mut create_opt =
+        test_session.create_opt(&node_address, 200_000, None, None, 0, utxos.clone());
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt),
+        Err(Error::InsufficientFunds { .. })
+    ));
+    let mut create_opt = test_session.create_opt(&node_address, 50_000, None, None, 0, utxos);
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.used_utxos.len(), 1);
+    assert_eq!(tx.used_utxos[0].satoshi, 100_000);
+
+    // Manually selected frozen outputs can be spent
+    let only_large =
+        GetUnspentOutputs(vec![("btc".to_string(), vec![large.clone()])].into_iter().collect());
+    let mut create_opt = test_session.create_opt(&node_address, 200_000, None, None, 0, only_large);
+    create_opt.utxo_strategy = UtxoStrategy::Manual;
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.used_utxos[0].txhash, large.txhash);
+
+    // Unfreezing makes them available again
+    set_frozen(&test_session, &large.txhash, false).unwrap();
+    assert!(test_session.session.get_frozen_outputs().unwrap().is_empty());
+    assert_eq!(test_session.utxos(0).0["btc"].len(), 2);
+}
+
+#[test]
+fn dust_threshold() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    let node_address = test_session.node_getnewaddress(Some("bech32"));
+
+    let opt = GetDustThresholdOpt {
+        address: Some(node_address.clone()),
+        ..Default::default()
+    };
+    let threshold = test_session.session.get_dust_threshold(&opt).unwrap().dust_threshold;
+    assert_eq!(threshold, 294);
+    // the change of the p2sh-p2wpkh subaccount 0
+    let value = serde_json::json!({ "subaccount": 0 });
+    let value = test_session.session.handle_call("get_dust_threshold", value).unwrap();
+    assert_eq!(value["dust_threshold"], 540);
+    let value = serde_json::json!({ "address": "x" });
+    assert!(test_session.session.handle_call("get_dust_threshold", value).is_err());
+
+    // An addressee amount at the threshold is rejected, just above it is accepted
+    let utxos = test_session.utxos(0);
+    let create_opt =
+        test_session.create_opt(&node_address, threshold, None, Some(1000), 0, utxos.clone());
+    let err = test_session
+        .session
+        .handle_call("create_transaction", serde_json::to_value(&create_opt).unwrap())
+        .unwrap();
+    assert_eq!(err["error"]["code"], "id_invalid_amount");
+    let mut create_opt =
+        test_session.create_opt(&node_address, threshold + 1, None, Some(1000), 0, utxos.clone());
+    assert!(test_session.session.create_transaction(&mut create_opt).is_ok());
+
+    // A change below the threshold is added to the fee
+    let mut create_opt =
+        test_session.create_opt(&node_address, 0, None, Some(1000), 0, utxos.clone());
+    create_opt.send_all = true;
+    let fee_send_all = test_session.session.create_transaction(&mut create_opt).unwrap().fee;
+    let satoshi = 100_000 - fee_send_all - 100;
+    let mut create_opt =
+        test_session.create_opt(&node_address, satoshi, None, Some(1000), 0, utxos);
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.transaction_outputs.len(), 1);
+    assert_eq!(tx.fee, fee_send_all + 100);
+}
+
+#[test]
+fn exclude_utxos() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    test_session.fund(300_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+    let utxos = test_session.utxos(0);
+    let large = utxos.0["btc"].iter().find(|u| u.satoshi == 300_000).unwrap().clone();
+    let exclude_large = vec![ExcludedUtxo {
+        txhash: large.txhash.clone(),
+        pt_idx: large.pt_idx,
+    }];
+
+    // Automatic selection only uses the remaining outputs
+    for utxo_strategy in [UtxoStrategy::Default, UtxoStrategy::BranchAndBound] {
+        let mut create_opt =
+            test_session.create_opt(&node_address, 50_000, None, None, 0, utxos.clone());
+        create_opt.utxo_strategy = utxo_strategy;
+        create_opt.exclude_utxos = exclude_large.clone();
+        let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+        assert!(tx.used_utxos.iter().all(|u| u.txhash != large.txhash));
+    }
+    let mut create_opt =
+        test_session.create_opt(&node_address, 200_000, None, None, 0, utxos.clone());
+    create_opt.exclude_utxos = exclude_large.clone();
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt),
+        Err(Error::InsufficientFunds { .. })
+    ));
+
+    // Passed as json
+    let create_opt = test_session.create_opt(&node_address, 50_000, None, None, 0, utxos);
+    let mut value = serde_json::to_value(&create_opt).unwrap();
+    value["exclude_utxos"] = serde_json::json!([{"txhash": large.txhash, "pt_idx": large.pt_idx}]);
+    let tx = test_session.session.handle_call("create_transaction", value).unwrap();
+    assert!(tx["used_utxos"].as_array().unwrap().iter().all(|u| u["txhash"] != large.txhash));
+    test_session.stop();
+}
+
+#[test]
+fn preview_coin_selection() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    test_session.fund(200_000, None);
+
+    let opt = PreviewCoinSelectionOpt {
+        subaccount: 0,
+        satoshi: 150_000,
+        fee_rate: Some(1000),
+        ..Default::default()
+    };
+    let preview = test_session.session.preview_coin_selection(&opt).unwrap();
+    assert_eq!(preview.used_utxos.len(), 1);
+    assert_eq!(preview.total_in, 200_000);
+    assert!(preview.fee > 0);
+    assert_eq!(preview.total_in, opt.satoshi + preview.fee + preview.change);
+    // Nothing is reserved by the preview
+    assert_eq!(test_session.utxos(0).0["btc"].len(), 2);
+
+    // The previewed utxos can be the starting set of a manual selection
+    let manual = GetUnspentOutputs(
+        vec![("btc".to_string(), preview.used_utxos.clone())].into_iter().collect(),
+    );
+    let node_address = test_session.node_getnewaddress(None);
+    let mut create_opt =
+        test_session.create_opt(&node_address, 150_000, None, Some(1000), 0, manual);
+    create_opt.utxo_strategy = UtxoStrategy::Manual;
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.used_utxos[0].txhash, preview.used_utxos[0].txhash);
+
+    let opt = PreviewCoinSelectionOpt {
+        satoshi: 1_000_000,
+        ..opt
+    };
+    assert!(matches!(
+        test_session.session.preview_coin_selection(&opt),
+        Err(Error::InsufficientFunds { .. })
+    ));
+}
+
+#[test]
+fn preview_coin_selection_liquid() {
+    let mut test_session = TestSession::new(true, |_| ());
+    test_session.fund(100_000, None);
+
+    // Without an asset the policy asset is selected and pays the fee
+    let opt = PreviewCoinSelectionOpt {
+        subaccount: 0,
+        satoshi: 50_000,
+        fee_rate: Some(100),
+        ..Default::default()
+    };
+    let preview = test_session.session.preview_coin_selection(&opt).unwrap();
+    assert_eq!(preview.used_utxos.len(), 1);
+    assert_eq!(preview.total_in, 100_000);
+    assert!(preview.fee > 0);
+    assert_eq!(preview.total_in, opt.satoshi + preview.fee + preview.change);
+
+    test_session.stop();
+}
+
+#[test]
+fn fee_rate_clamp() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let node_address = test_session.node_getnewaddress(None);
+    test_session.fund(100_000, None);
+    let min_fee_rate = test_session.session.get_min_fee_rate().unwrap();
+
+    let mut create_opt = test_session.create_opt(
+        &node_address,
+        10_000,
+        None,
+        Some(min_fee_rate - 1),
+        0,
+        test_session.utxos(0),
+    );
+    let err = test_session
+        .session
+        .handle_call("create_transaction", serde_json::to_value(&create_opt).unwrap())
+        .unwrap();
+    assert_eq!(err["error"]["code"], "id_fee_rate_is_below_minimum");
+    assert_eq!(err["error"]["details"]["min_fee_rate"], min_fee_rate);
+
+    create_opt.clamp_fee_rate = true;
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.create_transaction.unwrap().fee_rate, Some(min_fee_rate));
+    assert!(tx.effective_fee_rate.unwrap() >= min_fee_rate);
+
+    // The clamp applies to replacements too
+    let txid = test_session.send_tx(&node_address, 10_000, None, None, None, None, None);
+    let opt = BumpFeeOpt {
+        subaccount: 0,
+        txid,
+        fee_rate: 1,
+        clamp_fee_rate: true,
+    };
+    // the original transaction already pays the minimum
+    assert!(matches!(
+        test_session.session.bump_fee(&opt),
+        Err(Error::InvalidReplacementFeeRate(_))
+    ));
+
+    // Once the minimum rises above the original fee rate, the clamp raises the requested rate
+    // enough for the replacement to be valid
+    let original = test_session.get_tx_from_list(0, &opt.txid);
+    let raised_min_fee_rate = original.fee_rate * 3;
+    test_session.session.store().unwrap().write().unwrap().cache.fee_estimates =
+        vec![FeeEstimate(raised_min_fee_rate); 25];
+    let tx = test_session.session.bump_fee(&opt).unwrap();
+    assert_eq!(tx.create_transaction.unwrap().fee_rate, Some(raised_min_fee_rate));
+    assert!(tx.effective_fee_rate.unwrap() >= raised_min_fee_rate);
+    assert!(tx.fee > original.fee);
+}
+
+#[test]
+fn bump_fee() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let node_address = test_session.node_getnewaddress(None);
+    test_session.fund(1_000_000, None);
+    test_session.mine_block();
+
+    let bump_fee = |test_session: &mut TestSession, txid: &str, fee_rate| {
+        let opt = BumpFeeOpt {
+            subaccount: 0,
+            txid: txid.to_string(),
+            fee_rate,
+            ..Default::default()
+        };
+        test_session.session.bump_fee(&opt)
+    };
+
+    // The change output pays for the higher fee
+    let funding_txid = test_session.utxos(0).0["btc"][0].txhash.clone();
+    let txid = test_session.send_tx(&node_address, 100_000, None, None, None, None, None);
+    let txitem = test_session.get_tx_from_list(0, &txid);
+    assert!(matches!(
+        bump_fee(&mut test_session, &txid, txitem.fee_rate),
+        Err(Error::InvalidReplacementFeeRate(_))
+    ));
+    let tx = bump_fee(&mut test_session, &txid, 5000).unwrap();
+    assert!(tx.rbf_optin);
+    assert!(tx.fee > txitem.fee);
+    assert_eq!(tx.used_utxos.len(), 1);
+    assert_eq!(tx.used_utxos[0].txhash, funding_txid);
+    assert_eq!(tx.changes_used, Some(1));
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    let txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &txid, None, Some(TransactionType::Outgoing));
+
+    // Confirmed transactions cannot be replaced
+    test_session.mine_block();
+    assert!(matches!(
+        bump_fee(&mut test_session, &txid, 10_000),
+        Err(Error::TxAlreadyConfirmed(_))
+    ));
+
+    // Without change, a new confirmed input is added
+    test_session.fund(500_000, None);
+    test_session.mine_block();
+    let utxos = test_session.utxos(0);
+    let largest = utxos.0["btc"].iter().max_by_key(|u| u.satoshi).unwrap().clone();
+    let only_largest =
+        GetUnspentOutputs(vec![("btc".to_string(), vec![largest.clone()])].into_iter().collect());
+    let (txid, _, fee) = test_session.send_all_from_account(
+        0,
+        &node_address,
+        None,
+        Some(only_largest),
+        Some(UtxoStrategy::Manual),
+    );
+    let tx = bump_fee(&mut test_session, &txid, 5000).unwrap();
+    assert!(tx.fee > fee);
+    assert_eq!(tx.used_utxos.len(), 2);
+    assert!(tx.used_utxos.iter().any(|u| u.txhash == largest.txhash && u.pt_idx == largest.pt_idx));
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    let txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &txid, None, Some(TransactionType::Outgoing));
+}
+
+#[test]
+fn create_cpfp_transaction() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let ap = test_session.get_receive_address(0);
+    let txid = test_session.node_sendtoaddress(&ap.address, 100_000, None);
+    test_session.wait_tx(vec![0], &txid, Some(100_000), Some(TransactionType::Incoming));
+    let parent = test_session.get_tx_from_list(0, &txid);
+    let owned = test_session.utxos(0).0["btc"].iter().find(|u| u.txhash == txid).unwrap().clone();
+
+    let cpfp = |test_session: &mut TestSession, vout, fee_rate| {
+        let opt = CpfpOpt {
+            subaccount: 0,
+            txid: txid.clone(),
+            vout,
+            fee_rate,
+        };
+        test_session.session.create_cpfp_transaction(&opt)
+    };
+
+    assert!(matches!(
+        cpfp(&mut test_session, None, parent.fee_rate),
+        Err(Error::InvalidCpfpFeeRate(_))
+    ));
+    // the other output of the parent is the node change
+    assert!(matches!(
+        cpfp(&mut test_session, Some(1 - owned.pt_idx), 30_000),
+        Err(Error::InvalidCpfpOutput(_, _))
+    ));
+
+    let tx = cpfp(&mut test_session, None, 30_000).unwrap();
+    assert_eq!(tx.used_utxos.len(), 1);
+    assert_eq!(tx.used_utxos[0].txhash, owned.txhash);
+    assert_eq!(tx.used_utxos[0].pt_idx, owned.pt_idx);
+    assert_eq!(tx.transaction_outputs.len(), 1);
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    // the estimated size is an upper bound, the signed package can only pay a higher rate
+    let package_fee_rate =
+        (parent.fee + tx.fee) * 1000 / (parent.transaction_vsize + signed_tx.vsize) as u64;
+    assert!(package_fee_rate >= 30_000);
+    let child_txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &child_txid, None, Some(TransactionType::Redeposit));
+
+    // a confirmed parent does not need a child
+    test_session.mine_block();
+    assert!(matches!(cpfp(&mut test_session, None, 30_000), Err(Error::TxAlreadyConfirmed(_))));
+}
+
+#[test]
+fn get_ancestors() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let ap = test_session.get_receive_address(0);
+    let txid = test_session.node_sendtoaddress(&ap.address, 100_000, None);
+    test_session.wait_tx(vec![0], &txid, Some(100_000), Some(TransactionType::Incoming));
+    let parent = test_session.get_tx_from_list(0, &txid);
+
+    // the parent spends confirmed outputs of the node
+    let ancestors = test_session.session.get_ancestors(&txid).unwrap();
+    assert!(ancestors.ancestors.is_empty());
+    assert_eq!(ancestors.package_fee, parent.fee);
+
+    let opt = CpfpOpt {
+        subaccount: 0,
+        txid: txid.clone(),
+        vout: None,
+        fee_rate: 30_000,
+    };
+    let tx = test_session.session.create_cpfp_transaction(&opt).unwrap();
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    let child_txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &child_txid, None, Some(TransactionType::Redeposit));
+
+    let ancestors = test_session.session.get_ancestors(&child_txid).unwrap();
+    assert_eq!(
+        ancestors.ancestors,
+        vec![AncestorTransaction {
+            txhash: txid.clone(),
+            fee: parent.fee,
+            vsize: parent.transaction_vsize,
+        }]
+    );
+    assert_eq!(ancestors.package_fee, parent.fee + tx.fee);
+    assert_eq!(ancestors.package_vsize, parent.transaction_vsize + signed_tx.vsize);
+    assert!(ancestors.package_fee_rate >= 30_000);
+
+    test_session.mine_block();
+    assert!(matches!(
+        test_session.session.get_ancestors(&child_txid),
+        Err(Error::TxAlreadyConfirmed(_))
+    ));
+}
+
+#[test]
+fn create_sweep_transaction() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let network = bitcoin::Network::Regtest;
+    let private_key = bitcoin::PrivateKey::new(
+        bitcoin::secp256k1::SecretKey::new(&mut gdk_common::rand::thread_rng()),
+        network,
+    );
+    let public_key = private_key.public_key(&gdk_common::EC);
+    let mut opt = CreateSweepOpt {
+        subaccount: 0,
+        private_key: private_key.to_wif(),
+        fee_rate: None,
+    };
+    assert!(matches!(
+        test_session.session.create_sweep_transaction(&opt),
+        Err(Error::NoFundsToSweep)
+    ));
+
+    let p2pkh = bitcoin::Address::p2pkh(&public_key, network).to_string();
+    let p2wpkh = bitcoin::Address::p2wpkh(&public_key, network).unwrap().to_string();
+    test_session.node_sendtoaddress(&p2pkh, 50_000, None);
+    test_session.node_sendtoaddress(&p2wpkh, 70_000, None);
+    test_session.mine_block();
+
+    // wait for electrs to index both outputs
+    let start = Instant::now();
+    let tx = loop {
+        match test_session.session.create_sweep_transaction(&opt) {
+            // both outputs are spent
+            Ok(tx) if tx.satoshi["btc"] as u64 + tx.fee == 120_000 => break tx,
+            _ => {
+                assert!(start.elapsed() < Duration::from_secs(60), "sweep utxos not found");
+                thread::sleep(Duration::from_millis(500));
+            }
+        }
+    };
+    assert!(tx.is_sweep);
+
+    opt.fee_rate = Some(10);
+    assert!(matches!(
+        test_session.session.create_sweep_transaction(&opt),
+        Err(Error::FeeRateBelowMinimum(_))
+    ));
+
+    let txid = test_session.session.broadcast_transaction(&tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &txid, Some(tx.satoshi["btc"] as u64), None);
+}
+
+fn setup_forking_sessions(enable_session_cross: bool) -> (TestSession, TestSession) {
+    let test_session2 = TestSession::new(false, |_| ());
+
+    let test_session1 = TestSession::new(false, |network| {
+        if enable_session_cross {
+            network.spv_multi = Some(true);
+            network.spv_servers = Some(vec![test_session2.electrs.electrum_url.clone()]);
+        }
+    });
+
+    // Connect nodes and point both to the same tip
+    test_session2.node_connect(test_session1.p2p_port);
+    test_session1.node_generate(20);
+
+    test_session1.wait_blockheight(121);
+    test_session2.wait_blockheight(121);
+
+    // Disconnect so they don't learn about eachother blocks
+    test_session1.node_disconnect_all();
+
+    (test_session1, test_session2)
+}
+
+fn get_chain(test_session: &mut TestSession) -> HeadersChain {
+    test_session.stop();
+    HeadersChain::new(&test_session.session.network.state_dir, bitcoin::Network::Regtest).unwrap()
+}
+
+fn assert_unwrap_invalid(result: spv::CrossValidationResult) -> spv::CrossValidationInvalid {
+    match result {
+        spv::CrossValidationResult::Invalid(inv) => inv,
+        _ => panic!("expected cross-validation to fail"),
+    }
+}
+
+#[test]
+fn custom_base_path() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let base_path: DerivationPath = "m/49'/1'/7'".parse().unwrap();
+
+    let next = test_session
+        .session
+        .get_next_subaccount(GetNextAccountOpt {
+            script_type: ScriptType::P2wpkh,
+            base_path: Some(base_path.clone().into()),
+        })
+        .unwrap();
+    assert_eq!(next, 1);
+    let account = test_session
+        .session
+        .create_subaccount(CreateAccountOpt {
+            subaccount: next,
+            base_path: Some(base_path.clone().into()),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(account.user_path, Vec::from(base_path.clone()));
+    let root_path = test_session
+        .session
+        .get_subaccount_root_path(GetAccountPathOpt {
+            subaccount: next,
+        })
+        .unwrap();
+    assert_eq!(root_path.path, Vec::from(base_path.clone()));
+    test_session.check_address_from_descriptor(next);
+
+    // The custom path is not recoverable from the chain, the export includes it
+    let exported = test_session
+        .session
+        .export_account(&GetAccountDescriptorsOpt {
+            subaccount: next,
+        })
+        .unwrap();
+    assert_eq!(exported.base_path, Vec::from(base_path.clone()));
+
+    // The path of account 0 and the custom one can't be reused
+    let account0_path = test_session.session.get_subaccount(0).unwrap().user_path;
+    for path in [account0_path, base_path.into()] {
+        let err = test_session
+            .session
+            .create_subaccount(CreateAccountOpt {
+                subaccount: 2,
+                base_path: Some(path),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(matches!(err, Error::AccountPathCollision(_, _)));
+    }
+}
+
+#[test]
+fn broadcast_fallback() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+    let mut create_opt = CreateTransaction::default();
+    create_opt.addressees.push(AddressAmount {
+        address: node_address.to_string(),
+        satoshi: 10_000,
+        asset_id: test_session.asset_id(),
+        op_return: None,
+    });
+    create_opt.utxos = utils::convertutxos(&test_session.utxos(0));
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+
+    // Point the session to an electrum server which is not listening
+    let closed = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let electrum_urls = test_session.session.urls.clone();
+    test_session.session.urls =
+        ElectrumUrls::new(ElectrumUrl::Plaintext(closed.local_addr().unwrap().to_string()), vec![]);
+    drop(closed);
+    assert!(test_session.session.broadcast_transaction(&signed_tx.hex).is_err());
+
+    // A minimal HTTP endpoint capturing the posted transaction
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let url = format!("http://{}/api/tx", listener.local_addr().unwrap());
+    test_session.session.network.broadcast_fallback_url = Some(url);
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0u8; content_length];
+        reader.read_exact(&mut body).unwrap();
+        stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n").unwrap();
+        String::from_utf8(body).unwrap()
+    });
+
+    let txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    assert_eq!(handle.join().unwrap(), signed_tx.hex);
+    assert_eq!(txid, signed_tx.txid);
+
+    // Relay it ourselves, as the endpoint is a mock
+    test_session.session.urls = electrum_urls;
+    test_session.node.client.send_raw_transaction(signed_tx.hex.as_str()).unwrap();
+    test_session.wait_tx(vec![0], &txid, None, None);
+}
+
+#[test]
+fn op_return_output() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+    let op_return = |data: &str| AddressAmount {
+        op_return: Some(data.to_string()),
+        ..Default::default()
+    };
+
+    let mut create_opt = CreateTransaction::default();
+    create_opt.addressees.push(AddressAmount {
+        address: node_address.to_string(),
+        satoshi: 10_000,
+        asset_id: test_session.asset_id(),
+        op_return: None,
+    });
+    create_opt.addressees.push(op_return(&"ab".repeat(80)));
+    create_opt.utxos = utils::convertutxos(&test_session.utxos(0));
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    let txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &txid, None, Some(TransactionType::Outgoing));
+    let tx = BETransaction::from_hex(
+        &signed_tx.hex,
+        NetworkId::Bitcoin(gdk_common::bitcoin::Network::Regtest),
+    )
+    .unwrap();
+    let op_return_outputs = match tx {
+        BETransaction::Bitcoin(tx) => {
+            tx.output.iter().filter(|o| o.script_pubkey.is_op_return() && o.value == 0).count()
+        }
+        BETransaction::Elements(_) => unreachable!(),
+    };
+    assert_eq!(op_return_outputs, 1);
+
+    create_opt.addressees[1] = op_return(&"ab".repeat(81));
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt),
+        Err(Error::OpReturnTooLarge(81))
+    ));
+
+    create_opt.addressees[1] = op_return("ab");
+    create_opt.addressees.push(op_return("cd"));
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt),
+        Err(Error::MultipleOpReturn)
+    ));
+}
+
+#[test]
+fn anti_fee_sniping_locktime() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+    let tip = test_session.session.export_cache().unwrap().tip_height();
+    assert!(tip > 100);
+
+    let mut create_opt = test_session.create_opt(
+        &node_address,
+        10_000,
+        test_session.asset_id(),
+        None,
+        0,
+        test_session.utxos(0),
+    );
+    assert!(create_opt.set_locktime_to_tip);
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert!(tx.lock_time <= tip && tx.lock_time + 100 > tip, "{} {}", tx.lock_time, tip);
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    assert_eq!(signed_tx.lock_time, tx.lock_time);
+
+    create_opt.set_locktime_to_tip = false;
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.lock_time, 0);
+}
+
+#[test]
+fn locktime_datetime() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+
+    let mut create_opt = test_session.create_opt(
+        &node_address,
+        10_000,
+        test_session.asset_id(),
+        None,
+        0,
+        test_session.utxos(0),
+    );
+    // In the past, so that the transaction can be mined
+    let time = 1_600_000_000;
+    create_opt.locktime_datetime = Some(time);
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.lock_time, time as u32);
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    assert_eq!(signed_tx.lock_time, time as u32);
+    let txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &txid, None, Some(TransactionType::Outgoing));
+    let details = test_session
+        .session
+        .get_transaction_details(&GetTransactionDetailsOpt {
+            txhash: txid,
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(details.transaction_locktime, time as u32);
+
+    // Heights are not accepted
+    create_opt.locktime_datetime = Some(800_000);
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt),
+        Err(Error::InvalidLockTimeDatetime(800_000))
+    ));
+}
+
+#[test]
+fn p2tr_subaccount() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+
+    let account = test_session
+        .session
+        .create_subaccount(CreateAccountOpt {
+            subaccount: 3,
+            name: "Taproot".into(),
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(account.script_type, ScriptType::P2tr);
+    assert_eq!(DerivationPath::from(account.user_path).to_string(), "m/86'/1'/0'");
+    assert!(account.slip132_extended_pubkey.is_none());
+    assert!(account.core_descriptors[0].starts_with("tr("));
+    test_session.check_address_from_descriptor(3);
+
+    // receive to and spend from the taproot subaccount
+    let address = test_session.get_receive_address(3).address;
+    assert!(address.starts_with("bcrt1p"));
+    let sat = 20_000;
+    let (txid, fee) = test_session.send_tx_from(0, &address, sat, None);
+    test_session.wait_tx(vec![0, 3], &txid, Some(sat + fee), Some(TransactionType::Outgoing));
+    test_session.mine_block();
+
+    let node_address = test_session.node_getnewaddress(None);
+    let (txid, fee) = test_session.send_tx_from(3, &node_address, 5_000, None);
+    test_session.wait_tx(vec![3], &txid, Some(5_000 + fee), Some(TransactionType::Outgoing));
+    test_session.stop();
+
+    let mut test_session = TestSession::new(true, |_| ());
+    let err = test_session
+        .session
+        .create_subaccount(CreateAccountOpt {
+            subaccount: 3,
+            ..Default::default()
+        })
+        .unwrap_err();
+    assert!(matches!(err, Error::UnsupportedLiquidTaproot));
+    test_session.stop();
+}
+
+#[test]
+fn pending_transactions() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+    let mut create_opt = CreateTransaction::default();
+    create_opt.addressees.push(AddressAmount {
+        address: node_address.to_string(),
+        satoshi: 10_000,
+        asset_id: test_session.asset_id(),
+        op_return: None,
+    });
+    create_opt.utxos = utils::convertutxos(&test_session.utxos(0));
+    let tx = test_session.session.create_transaction(&mut create_opt.clone()).unwrap();
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+
+    // Signing alone does not make a transaction pending
+    assert!(test_session.session.get_pending_transactions().unwrap().is_empty());
+
+    // Simulate a broadcast interrupted before the transaction reached the wallet history
+    let betx = BETransaction::from_hex(&signed_tx.hex, test_session.network.id()).unwrap();
+    let pending = PendingTransaction {
+        subaccount: 0,
+        txhash: signed_tx.txid.clone(),
+        hex: signed_tx.hex.clone(),
+        used_utxos: betx
+            .previous_outputs()
+            .iter()
+            .map(|o| CreateTxUtxo {
+                txid: o.txid().to_string(),
+                vout: o.vout(),
+            })
+            .collect(),
+        created_at_ts: 0,
+    };
+    let store = test_session.session.store().unwrap();
+    store.write().unwrap().insert_pending_transaction(betx.txid(), pending).unwrap();
+    let pending = test_session.session.get_pending_transactions().unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].txhash, signed_tx.txid);
+
+    // The only utxo is reserved by the pending transaction
+    assert!(matches!(
+        test_session.session.create_transaction(&mut create_opt.clone()),
+        Err(Error::InsufficientFunds { .. })
+    ));
+
+    let discard = |txhash: &str| DiscardPendingTransactionOpt {
+        txhash: txhash.to_string(),
+    };
+    test_session.session.discard_pending_transaction(&discard(&signed_tx.txid)).unwrap();
+    assert!(test_session.session.get_pending_transactions().unwrap().is_empty());
+    assert!(matches!(
+        test_session.session.discard_pending_transaction(&discard(&signed_tx.txid)),
+        Err(Error::TxNotFound(_))
+    ));
+
+    // A broadcast transaction is pending until the syncer finds it in the wallet history
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    let txid = test_session.session.broadcast_transaction(&signed_tx.hex).unwrap();
+    test_session.wait_tx(vec![0], &txid, None, Some(TransactionType::Outgoing));
+    assert!(test_session.session.get_pending_transactions().unwrap().is_empty());
+    test_session.stop();
+}
+
+#[test]
+fn send_funds() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+    let mut create_opt = CreateTransaction::default();
+    create_opt.addressees.push(AddressAmount {
+        address: node_address.to_string(),
+        satoshi: 10_000,
+        asset_id: test_session.asset_id(),
+        op_return: None,
+    });
+    create_opt.utxos = utils::convertutxos(&test_session.utxos(0));
+
+    // Fail at creation
+    let mut opt = create_opt.clone();
+    opt.addressees[0].satoshi = 200_000_000;
+    let err = test_session.session.send_funds(&mut opt).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::SendFundsFailed {
+            stage: "create",
+            ..
+        }
+    ));
+    assert_eq!(err.to_gdk_code(), "id_insufficient_funds");
+    assert_eq!(err.details()["stage"], "create");
+
+    // Fail at broadcast, the server rejects the absurd fee, the utxo is not left reserved
+    let mut opt = create_opt.clone();
+    opt.fee_rate = Some(50_000_000);
+    let err = test_session.session.send_funds(&mut opt).unwrap_err();
+    assert!(matches!(
+        err,
+        Error::SendFundsFailed {
+            stage: "broadcast",
+            ..
+        }
+    ));
+    assert!(test_session.session.get_pending_transactions().unwrap().is_empty());
+
+    let value = test_session
+        .session
+        .handle_call("send_funds", serde_json::to_value(&create_opt).unwrap())
+        .unwrap();
+    let txid = value["txid"].as_str().unwrap().to_string();
+    let hex = value["transaction"].as_str().unwrap();
+    let tx = BETransaction::from_hex(hex, test_session.network.id()).unwrap();
+    assert_eq!(tx.txid().to_string(), txid);
+    test_session.wait_tx(vec![0], &txid, None, Some(TransactionType::Outgoing));
+    assert!(test_session.session.get_pending_transactions().unwrap().is_empty());
+
+    let mut opt = create_opt.clone();
+    opt.addressees[0].satoshi = 200_000_000;
+    let value = test_session
+        .session
+        .handle_call("send_funds", serde_json::to_value(&opt).unwrap())
+        .unwrap();
+    assert_eq!(value["error"]["code"], "id_insufficient_funds");
+    assert_eq!(value["error"]["details"]["stage"], "create");
+    test_session.stop();
+}
+
+#[test]
+fn get_balance_at_height() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let initial_height = test_session.session.block_status().unwrap().0;
+    test_session.fund(100_000, None);
+    test_session.mine_block();
+    let first_height = initial_height + 1;
+    test_session.fund(50_000, None);
+    test_session.mine_block();
+    let second_height = first_height + 1;
+    // An unconfirmed transaction is never counted
+    test_session.fund(20_000, None);
+
+    let balance_at = |test_session: &TestSession, height| {
+        let opt = GetBalanceAtHeightOpt {
+            subaccount: 0,
+            height,
+        };
+        test_session.session.get_balance_at_height(&opt).unwrap()["btc"]
+    };
+    // Wait for the confirmations to be synced
+    for _ in 0..60 {
+        if balance_at(&test_session, second_height) == 150_000 {
+            break;
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+    assert_eq!(balance_at(&test_session, initial_height), 0);
+    assert_eq!(balance_at(&test_session, first_height), 100_000);
+    assert_eq!(balance_at(&test_session, second_height), 150_000);
+    assert_eq!(balance_at(&test_session, u32::MAX), 150_000);
+    assert_eq!(test_session.balance_account(0, None, None), 170_000);
+
+    let value = serde_json::json!({"subaccount": 0, "height": first_height});
+    let value = test_session.session.handle_call("get_balance_at_height", value).unwrap();
+    assert_eq!(value["btc"], 100_000);
+    assert!(test_session
+        .session
+        .handle_call("get_balance_at_height", serde_json::json!({"subaccount": 9, "height": 0}))
+        .is_err());
+    test_session.stop();
+}
+
+#[test]
+fn rescan_subaccount() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let ap = test_session.get_receive_address(0);
+    let txid = test_session.node_sendtoaddress(&ap.address, 100_000, None);
+    test_session.wait_tx(vec![0], &txid, Some(100_000), Some(TransactionType::Incoming));
+    test_session.mine_block();
+    let height = test_session.session.block_status().unwrap().0;
+    let txid = BETxid::from_hex(&txid, test_session.network.id()).unwrap();
+
+    let heights = |test_session: &TestSession| {
+        let store = test_session.session.store().unwrap();
+        let store = store.read().unwrap();
+        store.account_cache(0).unwrap().heights.clone()
+    };
+    // Wait for the confirmation to be synced, then seed stale state: the wallet transaction
+    // looks unconfirmed and a transaction that is not of the wallet looks confirmed
+    for _ in 0..60 {
+        if heights(&test_session).get(&txid) == Some(&Some(height)) {
+            break;
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+    let store = test_session.session.store().unwrap();
+    let foreign_txid = {
+        let mut store = store.write().unwrap();
+        let acc_store = store.account_cache_mut(0).unwrap();
+        let foreign_txid =
+            *acc_store.all_txs.keys().find(|t| !acc_store.heights.contains_key(t)).unwrap();
+        acc_store.heights.insert(txid, None);
+        acc_store.heights.insert(foreign_txid, Some(height));
+        foreign_txid
+    };
+
+    // Rescanning above the stale heights does not touch them
+    let opt = RescanSubaccountOpt {
+        subaccount: 0,
+        from_height: height + 1,
+    };
+    test_session.session.rescan_subaccount(&opt).unwrap();
+    assert_eq!(heights(&test_session).get(&foreign_txid), Some(&Some(height)));
+
+    let opt = RescanSubaccountOpt {
+        subaccount: 0,
+        from_height: height,
+    };
+    test_session.session.rescan_subaccount(&opt).unwrap();
+    let heights = heights(&test_session);
+    assert_eq!(heights.get(&txid), Some(&Some(height)));
+    assert!(!heights.contains_key(&foreign_txid));
+
+    let events = test_session.session.filter_events("rescan");
+    let statuses: Vec<_> = events.iter().map(|e| e["rescan"]["status"].clone()).collect();
+    assert_eq!(statuses, vec!["started", "completed", "started", "completed"]);
+
+    let opt = RescanSubaccountOpt {
+        subaccount: 5,
+        from_height: 0,
+    };
+    assert!(test_session.session.rescan_subaccount(&opt).is_err());
+    test_session.stop();
+}
+
+#[test]
+fn discover_subaccount_gap_limit() {
+    let test_session = TestSession::new(false, |network| network.gap_limit = Some(30));
+
+    // Fund an address beyond the default gap limit
+    let address = loop {
+        let address = test_session.get_receive_address(0);
+        if address.pointer == 25 {
+            break address;
+        }
+    };
+    let txid = test_session.node_sendtoaddress(&address.address, 10_000, None);
+    test_session.wait_tx(vec![0], &txid, Some(10_000), Some(TransactionType::Incoming));
+
+    let signer = TestSigner::new(
+        &test_session.credentials,
+        test_session.network_parameters().bip32_network(),
+        false,
+    );
+    let account = test_session.session.get_subaccount(0).unwrap();
+    let discover = |gap_limit| DiscoverAccountOpt {
+        script_type: account.script_type,
+        xpub: signer.account_xpub(&account.user_path.clone().into()),
+        gap_limit,
+    };
+    assert!(!test_session.session.discover_subaccount(discover(Some(20))).unwrap());
+    assert!(test_session.session.discover_subaccount(discover(Some(26))).unwrap());
+    // Defaults to the session gap limit
+    assert!(test_session.session.discover_subaccount(discover(None)).unwrap());
+}
+
+#[test]
+fn get_transactions_bounds() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    test_session.fund(100_000, None);
+
+    let get_txs = |first, count| {
+        let opt = GetTransactionsOpt {
+            first,
+            count,
+            ..Default::default()
+        };
+        test_session.session.get_transactions(&opt).unwrap()
+    };
+    let txs = get_txs(0, usize::MAX);
+    assert_eq!(txs.transactions.len(), 2);
+    assert!(!txs.truncated);
+
+    let txs = get_txs(1, 1);
+    assert_eq!(txs.transactions.len(), 1);
+    assert!(!txs.truncated);
+
+    // An offset past the last transaction is not an error
+    let txs = get_txs(usize::MAX, 10);
+    assert!(txs.transactions.is_empty());
+    assert!(!txs.truncated);
+}
+
+#[test]
+fn get_transactions_filters() {
+    let mut test_session = TestSession::new(false, |_| ());
+    let node_address = test_session.node_getnewaddress(None);
+    let mut outgoing = vec![];
+    for satoshi in [100_000, 200_000] {
+        let address = test_session.get_receive_address(0).address;
+        let txid = test_session.node_sendtoaddress(&address, satoshi, None);
+        test_session.wait_tx(vec![0], &txid, Some(satoshi), Some(TransactionType::Incoming));
+        outgoing.push(test_session.send_tx(&node_address, 10_000, None, None, None, None, None));
+        test_session.mine_block();
+    }
+    let get_txs = |opt: GetTransactionsOpt| {
+        let opt = GetTransactionsOpt {
+            count: 10,
+            ..opt
+        };
+        test_session.session.get_transactions(&opt).unwrap().transactions
+    };
+    let all = get_txs(Default::default());
+    assert_eq!(all.len(), 4);
+
+    // Outgoing only, paged on the matching transactions
+    let opt = GetTransactionsOpt {
+        tx_type: Some(TransactionType::Outgoing),
+        ..Default::default()
+    };
+    let txids: Vec<_> = get_txs(opt.clone()).into_iter().map(|tx| tx.txhash).collect();
+    assert_eq!(txids, vec![outgoing[1].clone(), outgoing[0].clone()]);
+    let page = get_txs(GetTransactionsOpt {
+        first: 1,
+        ..opt
+    });
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].txhash, outgoing[0]);
+
+    // A time window containing the first block only
+    let start_ts = all.iter().find(|tx| tx.txhash == outgoing[0]).unwrap().created_at_ts;
+    let end_ts = start_ts + 1;
+    let window = get_txs(GetTransactionsOpt {
+        start_ts: Some(start_ts),
+        end_ts: Some(end_ts),
+        ..Default::default()
+    });
+    let expected: Vec<_> = all
+        .iter()
+        .filter(|tx| tx.created_at_ts >= start_ts && tx.created_at_ts < end_ts)
+        .map(|tx| tx.txhash.clone())
+        .collect();
+    assert!(expected.contains(&outgoing[0]));
+    assert_eq!(window.into_iter().map(|tx| tx.txhash).collect::<Vec<_>>(), expected);
+
+    // The running balance would miss the filtered out transactions
+    let opt = GetTransactionsOpt {
+        sort: TxSort::Oldest,
+        running_balance: true,
+        tx_type: Some(TransactionType::Outgoing),
+        ..Default::default()
+    };
+    assert!(test_session.session.get_transactions(&opt).is_err());
+}
+
+#[test]
+fn get_transactions_oldest_first() {
+    let mut test_session = TestSession::new(false, |_| ());
+    // Confirm the funding transactions in different blocks, so that their order is defined
+    let mut fund = |satoshi| {
+        let address = test_session.get_receive_address(0).address;
+        let txid = test_session.node_sendtoaddress(&address, satoshi, None);
+        test_session.wait_tx(vec![0], &txid, Some(satoshi), Some(TransactionType::Incoming));
+        test_session.mine_block();
+        txid
+    };
+    let txid1 = fund(100_000);
+    let txid2 = fund(200_000);
+    let node_address = test_session.node_getnewaddress(None);
+    let txid3 = test_session.send_tx(&node_address, 50_000, None, None, None, None, None);
+
+    let opt = GetTransactionsOpt {
+        count: 10,
+        sort: TxSort::Oldest,
+        running_balance: true,
+        ..Default::default()
+    };
+    let txs = test_session.session.get_transactions(&opt).unwrap().transactions;
+    let txids: Vec<_> = txs.iter().map(|tx| tx.txhash.clone()).collect();
+    assert_eq!(txids, vec![txid1, txid2, txid3.clone()]);
+    let balances: Vec<_> = txs.iter().map(|tx| tx.running_balance["btc"]).collect();
+    assert_eq!(&balances[..2], &[100_000, 300_000]);
+    assert_eq!(balances[2], test_session.balance_account(0, None, None) as i64);
+
+    // The running balance accounts for the transactions before the page
+    let page = test_session
+        .session
+        .get_transactions(&GetTransactionsOpt {
+            first: 2,
+            ..opt.clone()
+        })
+        .unwrap()
+        .transactions;
+    assert_eq!(page.len(), 1);
+    assert_eq!(
+        (page[0].txhash.as_str(), page[0].running_balance["btc"]),
+        (txid3.as_str(), balances[2])
+    );
+
+    // Newest first by default, without running balance
+    let opt = GetTransactionsOpt {
+        count: 10,
+        ..Default::default()
+    };
+    let txs = test_session.session.get_transactions(&opt).unwrap().transactions;
+    assert_eq!(txs[0].txhash, txid3);
+    assert!(txs[0].running_balance.is_empty());
+    let opt = GetTransactionsOpt {
+        running_balance: true,
+        ..opt
+    };
+    assert!(test_session.session.get_transactions(&opt).is_err());
+}
+
+#[test]
+fn verification_electrum_url() {
+    // The verification server is a proxy of the primary one hiding every unspent output
+    let mut test_session = TestSession::new(false, |n| {
+        n.verification_electrum_url =
+            Some(listunspent_hiding_proxy(n.electrum_url.as_ref().unwrap()))
+    });
+    test_session.fund(100_000, None);
+    test_session.mine_block();
+
+    let start = Instant::now();
+    let mismatch = loop {
+        let events = test_session.session.filter_events("verification_mismatch");
+        if let Some(event) = events.into_iter().next() {
+            break event;
+        }
+        assert!(start.elapsed() < Duration::from_secs(30), "no verification mismatch");
+        thread::sleep(Duration::from_millis(200));
+    };
+    let ntf = &mismatch["verification_mismatch"];
+    assert_eq!(ntf["subaccount"], 0);
+    assert_eq!(ntf["missing"], serde_json::json!([]));
+    assert_eq!(ntf["unexpected"].as_array().unwrap().len(), 1);
+
+    test_session.stop();
+}
+
+/// Spawn an electrum server proxying `target` that returns no unspent outputs, and return its url
+fn listunspent_hiding_proxy(target: &str) -> String {
+    use std::collections::HashSet;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpStream;
+    use std::sync::{Arc, Mutex};
+
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let url = listener.local_addr().unwrap().to_string();
+    let target = target.to_string();
+    thread::spawn(move || {
+        for client in listener.incoming() {
+            let client = client.unwrap();
+            let server = TcpStream::connect(&target).unwrap();
+            let hidden_ids = Arc::new(Mutex::new(HashSet::new()));
+
+            let (mut server_writer, ids) = (server.try_clone().unwrap(), hidden_ids.clone());
+            let client_reader = BufReader::new(client.try_clone().unwrap());
+            thread::spawn(move || {
+                for line in client_reader.lines() {
+                    let line = line.unwrap();
+                    let request: Value = serde_json::from_str(&line).unwrap();
+                    if request["method"] == "blockchain.scripthash.listunspent" {
+                        ids.lock().unwrap().insert(request["id"].as_u64());
+                    }
+                    writeln!(server_writer, "{}", line).unwrap();
+                }
+            });
+
+            let mut client_writer = client;
+            thread::spawn(move || {
+                for line in BufReader::new(server).lines() {
+                    let mut response: Value = serde_json::from_str(&line.unwrap()).unwrap();
+                    if hidden_ids.lock().unwrap().remove(&response["id"].as_u64()) {
+                        response["result"] = serde_json::json!([]);
+                    }
+                    writeln!(client_writer, "{}", response).unwrap();
+                }
+            });
+        }
+    });
+    url
+}
+
+#[test]
+fn balance_detailed() {
+    let mut test_session = TestSession::new(true, |_| ());
+    let policy_asset = test_session.network.policy_asset.clone().unwrap();
+    test_session.fund(100_000, None);
+
+    let ap = test_session.get_receive_address(0);
+    let unconf_address = utils::to_unconfidential(&ap.address);
+    let txid = test_session.node_sendtoaddress(&unconf_address, 10_000, None);
+    test_session.wait_tx(vec![0], &txid, Some(10_000), Some(TransactionType::Incoming));
+
+    let opt = GetBalanceOpt {
+        subaccount: 0,
+        num_confs: 0,
+        confidential_utxos_only: None,
+        policy_asset_only: false,
+    };
+    let detailed = test_session.session.get_balance_detailed(&opt).unwrap();
+    assert_eq!(
+        detailed[&policy_asset],
+        BalanceDetail {
+            confidential_satoshi: 100_000,
+            unconfidential_satoshi: 10_000,
+        }
+    );
+    // The plain balance is unchanged and matches the sum
+    let balance = test_session.session.get_balance(&opt).unwrap();
+    assert_eq!(balance[&policy_asset], 110_000);
+}
+
+#[test]
+fn output_proofs() {
+    let mut test_session = TestSession::new(true, |_| ());
+    let policy_asset = test_session.network.policy_asset.clone().unwrap();
+    test_session.fund(100_000, None);
+
+    let utxos = test_session.utxos(0);
+    let utxo = &utxos.0[&policy_asset][0];
+    let opt = GetOutputProofsOpt {
+        txid: utxo.txhash.clone(),
+        vout: utxo.pt_idx,
+    };
+    let proofs = test_session.session.get_output_proofs(&opt).unwrap();
+    assert!(!proofs.rangeproof.is_empty());
+    assert!(!proofs.surjectionproof.is_empty());
+
+    // The proofs are the ones in the transaction
+    let tx_hex = test_session.session.get_transaction_hex(&utxo.txhash).unwrap();
+    assert!(tx_hex.contains(&proofs.rangeproof));
+    assert!(tx_hex.contains(&proofs.surjectionproof));
+
+    let opt = GetOutputProofsOpt {
+        vout: 100,
+        ..opt
+    };
+    assert!(test_session.session.get_output_proofs(&opt).is_err());
+}
+
+#[test]
+fn transaction_unblinded() {
+    let mut test_session = TestSession::new(true, |_| ());
+    let policy_asset = test_session.network.policy_asset.clone().unwrap();
+    test_session.fund(100_000, None);
+
+    let utxos = test_session.utxos(0);
+    let utxo = &utxos.0[&policy_asset][0];
+    let unblinded = test_session.session.get_transaction_unblinded(&utxo.txhash).unwrap();
+    assert_eq!(unblinded.txhash, utxo.txhash);
+    // Only the wallet output is returned
+    assert_eq!(unblinded.outputs.len(), 1);
+    let output = &unblinded.outputs[0];
+    assert_eq!(output.pt_idx, utxo.pt_idx);
+    assert_eq!(output.satoshi, Some(100_000));
+    assert_eq!(output.asset_id.as_ref(), Some(&policy_asset));
+    assert_eq!(output.asset_blinder, utxo.asset_blinder);
+    assert_eq!(output.amount_blinder, utxo.amount_blinder);
+}
+
+#[test]
+fn force_legacy() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session
+        .session
+        .create_subaccount(CreateAccountOpt {
+            subaccount: 2,
+            name: "Legacy".into(),
+            ..Default::default() // p2pkh
+        })
+        .unwrap();
+    test_session.fund(100_000, None);
+    let address = test_session.get_receive_address(2).address;
+    let (txid, fee) = test_session.send_tx_from(0, &address, 50_000, None);
+    test_session.wait_tx(vec![0, 2], &txid, Some(50_000 + fee), Some(TransactionType::Outgoing));
+
+    let node_address = test_session.node_getnewaddress(None);
+
+    // Account 0 is p2sh-p2wpkh
+    let mut create_opt =
+        test_session.create_opt(&node_address, 10_000, None, None, 0, test_session.utxos(0));
+    create_opt.force_legacy = true;
+    assert!(test_session.session.create_transaction(&mut create_opt).is_err());
+
+    let mut create_opt =
+        test_session.create_opt(&node_address, 10_000, None, None, 2, test_session.utxos(2));
+    create_opt.force_legacy = true;
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert!(!tx.has_witness);
+    assert!(tx.used_utxos.iter().all(|u| u.address_type == "p2pkh"));
+    let change = tx.transaction_outputs.iter().find(|o| o.is_change).unwrap();
+    assert_eq!(change.address_type, "p2pkh");
+
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    assert!(!signed_tx.has_witness);
+    let signed_tx = BETransaction::from_hex(&signed_tx.hex, test_session.network.id()).unwrap();
+    assert!(!signed_tx.has_witness());
+}
+
+#[test]
+fn electrum_backup_urls() {
+    // The primary server is not listening, the backup is the working one
+    let closed = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let closed_url = closed.local_addr().unwrap().to_string();
+    drop(closed);
+    let mut test_session = TestSession::new(false, |n| {
+        n.electrum_backup_urls = Some(vec![n.electrum_url.clone().unwrap()]);
+        n.electrum_url = Some(closed_url);
+    });
+    let backup_url = test_session.network.electrum_backup_urls.clone().unwrap().remove(0);
+    assert_eq!(test_session.session.urls.url(), backup_url);
+    let events = test_session.session.filter_events("electrum_server");
+    assert_eq!(events.last().unwrap()["electrum_server"]["url"], backup_url.as_str());
+
+    // The wallet syncs through the backup
+    test_session.fund(100_000, None);
+    test_session.mine_block();
+}
+
+#[test]
+fn diff_since() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    test_session.mine_block();
+
+    // Take the snapshot
+    let snapshot = test_session.session.diff_since(&DiffSinceOpt::default()).unwrap();
+    let tx_count_per_subaccount =
+        snapshot.subaccounts.iter().map(|s| (s.subaccount, s.tx_count)).collect();
+    let opt = DiffSinceOpt {
+        tip_height: snapshot.tip_height,
+        tx_count_per_subaccount,
+        ..Default::default()
+    };
+    let diff = test_session.session.diff_since(&opt).unwrap();
+    assert_eq!(diff.tip_height, snapshot.tip_height);
+    assert!(diff.subaccounts[0].transactions.is_empty());
+
+    // A new incoming transaction shows up in the diff
+    let ap = test_session.get_receive_address(0);
+    let txid = test_session.node_sendtoaddress(&ap.address, 50_000, None);
+    test_session.wait_tx(vec![0], &txid, Some(50_000), Some(TransactionType::Incoming));
+    let diff = test_session.session.diff_since(&opt).unwrap();
+    let account_diff = &diff.subaccounts[0];
+    assert_eq!(account_diff.tx_count, snapshot.subaccounts[0].tx_count + 1);
+    assert!(!account_diff.reload_required);
+    assert_eq!(account_diff.transactions.len(), 1);
+    assert_eq!(account_diff.transactions[0].txhash, txid);
+    assert_eq!(account_diff.balance_delta["btc"], 50_000);
+    assert!(account_diff.spent_utxos.is_empty());
+    assert_eq!(account_diff.unconfirmed_txids, vec![txid.clone()]);
+
+    // A snapshot taken while the transaction is unconfirmed does not count it again
+    let unconfirmed_opt = DiffSinceOpt {
+        tip_height: diff.tip_height,
+        tx_count_per_subaccount: vec![(0, account_diff.tx_count)].into_iter().collect(),
+        unconfirmed_txids_per_subaccount: vec![(0, account_diff.unconfirmed_txids.clone())]
+            .into_iter()
+            .collect(),
+    };
+    let diff = test_session.session.diff_since(&unconfirmed_opt).unwrap();
+    assert!(diff.subaccounts[0].transactions.is_empty());
+    assert!(!diff.subaccounts[0].reload_required);
+
+    // It is still part of the diff once confirmed
+    test_session.mine_block();
+    let diff = test_session.session.diff_since(&opt).unwrap();
+    assert_eq!(diff.tip_height, snapshot.tip_height + 1);
+    assert_eq!(diff.subaccounts[0].transactions.len(), 1);
+    assert!(diff.subaccounts[0].unconfirmed_txids.is_empty());
+
+    // but without changing the balance again for the snapshot that had it unconfirmed
+    let diff = test_session.session.diff_since(&unconfirmed_opt).unwrap();
+    let account_diff = &diff.subaccounts[0];
+    assert_eq!(account_diff.transactions.len(), 1);
+    assert_eq!(account_diff.balance_delta.get("btc").copied().unwrap_or(0), 0);
+    assert!(!account_diff.reload_required);
+
+    // A snapshot referring to a transaction that is gone requires a reload, even if the
+    // number of transactions did not go down
+    let mut gone_opt = unconfirmed_opt.clone();
+    gone_opt.unconfirmed_txids_per_subaccount.insert(0, vec!["0".repeat(64)]);
+    let diff = test_session.session.diff_since(&gone_opt).unwrap();
+    assert!(diff.subaccounts[0].reload_required);
+
+    // Subaccounts missing from the snapshot return all their transactions
+    let diff = test_session
+        .session
+        .diff_since(&DiffSinceOpt {
+            tip_height: diff.tip_height,
+            ..Default::default()
+        })
+        .unwrap();
+    assert_eq!(diff.subaccounts[0].transactions.len(), 2);
+    assert_eq!(diff.subaccounts[0].balance_delta["btc"], 150_000);
+}
+
+#[test]
+fn policy_asset_balance() {
+    let mut test_session = TestSession::new(true, |_| ());
+    test_session.fund(100_000, Some(1));
+
+    let opt = GetBalanceOpt {
+        subaccount: 0,
+        num_confs: 0,
+        confidential_utxos_only: None,
+        policy_asset_only: true,
+    };
+    let balance = test_session.session.get_policy_asset_balance(&opt).unwrap();
+    assert_eq!(balance.satoshi, 100_000);
+
+    // Through the json api the issued asset is left out
+    let value = serde_json::to_value(&opt).unwrap();
+    let balance = test_session.session.handle_call("get_balance", value).unwrap();
+    assert_eq!(balance, serde_json::json!({ "satoshi": 100_000 }));
+}
+
+#[test]
+fn change_index() {
+    let mut test_session = TestSession::new(false, |_| ());
+    test_session.fund(100_000, None);
+    let node_address = test_session.node_getnewaddress(None);
+
+    let mut create_opt =
+        test_session.create_opt(&node_address, 10_000, None, None, 0, test_session.utxos(0));
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.changes_used, Some(1));
+    let change_index = tx.change_index.unwrap();
+    let change = &tx.transaction_outputs[change_index as usize];
+    assert!(change.is_change);
+    assert_eq!(change.satoshi, 100_000 - 10_000 - tx.fee);
+
+    // Kept once signed
+    let signed_tx = test_session.session.sign_transaction(&tx).unwrap();
+    assert_eq!(signed_tx.change_index, Some(change_index));
+
+    // Without change there is no index
+    let mut create_opt =
+        test_session.create_opt(&node_address, 0, None, None, 0, test_session.utxos(0));
+    create_opt.send_all = true;
+    let tx = test_session.session.create_transaction(&mut create_opt).unwrap();
+    assert_eq!(tx.changes_used, Some(0));
+    assert!(tx.change_index.is_none());
 }
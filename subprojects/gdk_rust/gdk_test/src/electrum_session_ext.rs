@@ -61,6 +61,8 @@ impl ElectrumSessionExt for ElectrumSession {
                 discovered: false,
                 is_already_created: true,
                 allow_gaps: false,
+                base_path: None,
+                multisig: None,
             };
             self.create_subaccount(opt).unwrap();
         }
@@ -74,9 +76,13 @@ impl ElectrumSessionExt for ElectrumSession {
             TestSigner::new(credentials, self.network.bip32_network(), self.network.liquid);
 
         for script_type in ScriptType::types() {
+            if self.network.liquid && *script_type == ScriptType::P2tr {
+                continue;
+            }
             loop {
                 let opt = GetNextAccountOpt {
                     script_type: *script_type,
+                    base_path: None,
                 };
                 let account_num = self.get_next_subaccount(opt).unwrap();
                 let opt = GetAccountPathOpt {
@@ -87,6 +93,7 @@ impl ElectrumSessionExt for ElectrumSession {
                 let opt = DiscoverAccountOpt {
                     script_type: *script_type,
                     xpub,
+                    gap_limit: None,
                 };
                 if self.discover_subaccount(opt).unwrap() {
                     let opt = CreateAccountOpt {
@@ -109,7 +116,7 @@ impl ElectrumSessionExt for ElectrumSession {
         opt.subaccount = subaccount;
         opt.count = n;
         for _ in 0..10 {
-            if self.get_transactions(&opt).unwrap().0.len() >= n {
+            if self.get_transactions(&opt).unwrap().transactions.len() >= n {
                 return;
             }
             thread::sleep(Duration::from_secs(1));
@@ -311,6 +311,7 @@ impl TestSession {
             address: address.to_string(),
             satoshi: 0,
             asset_id: asset_id.clone().or(self.asset_id()),
+            op_return: None,
         });
         create_opt.send_all = true;
         let tx = self.session.create_transaction(&mut create_opt).unwrap();
@@ -354,6 +355,7 @@ impl TestSession {
             address: address.to_string(),
             satoshi,
             asset_id: asset.clone().or(self.asset_id()),
+            op_return: None,
         });
         create_opt.memo = memo;
         create_opt.utxos = utils::convertutxos(&unspent_outputs.unwrap_or_else(|| self.utxos(0)));
@@ -370,7 +372,9 @@ impl TestSession {
         let num_used_utxos = tx.used_utxos.len();
         match create_opt.utxo_strategy {
             UtxoStrategy::Manual => assert_eq!(num_used_utxos, num_utxos),
-            UtxoStrategy::Default => assert!(num_used_utxos > 0 && num_used_utxos <= num_utxos),
+            UtxoStrategy::Default | UtxoStrategy::BranchAndBound => {
+                assert!(num_used_utxos > 0 && num_used_utxos <= num_utxos)
+            }
         }
         let signed_tx = self.session.sign_transaction(&tx).unwrap();
         self.check_fee_rate(fee_rate, &signed_tx, MAX_FEE_PERCENT_DIFF);
@@ -434,6 +438,7 @@ impl TestSession {
             address: address.to_string(),
             satoshi,
             asset_id: asset.clone().or(self.asset_id()),
+            op_return: None,
         });
         create_opt.utxos = utils::convertutxos(&self.utxos(create_opt.subaccount));
         let tx = self.session.create_transaction(&mut create_opt).unwrap();
@@ -481,7 +486,7 @@ impl TestSession {
         let mut opt = GetTransactionsOpt::default();
         opt.subaccount = subaccount;
         opt.count = 100;
-        self.session.get_transactions(&opt).unwrap().0
+        self.session.get_transactions(&opt).unwrap().transactions
     }
 
     pub fn get_tx_from_list(&self, subaccount: u32, txid: &str) -> TxListItem {
@@ -525,6 +530,7 @@ impl TestSession {
                 address: address.to_string(),
                 satoshi: amount,
                 asset_id,
+                op_return: None,
             });
         }
         create_opt.utxos = utils::convertutxos(&self.utxos(create_opt.subaccount));
@@ -594,12 +600,13 @@ impl TestSession {
             address: node_address.clone(),
             satoshi: init_sat, // not enough to pay the fee with confidential utxos only
             asset_id: self.asset_id(),
+            op_return: None,
         });
         create_opt.utxos = utils::convertutxos(&self.utxos(create_opt.subaccount));
         create_opt.confidential_utxos_only = true;
         assert!(matches!(
             self.session.create_transaction(&mut create_opt),
-            Err(Error::InsufficientFunds)
+            Err(Error::InsufficientFunds { .. })
         ));
 
         let balance_node_before = self.balance_node(None);
@@ -646,6 +653,7 @@ impl TestSession {
             address: address.to_string(),
             satoshi,
             asset_id: self.asset_id(),
+            op_return: None,
         });
         create_opt.utxos = utils::convertutxos(&self.utxos(create_opt.subaccount));
         let tx = self.session.create_transaction(&mut create_opt).unwrap();
@@ -681,6 +689,7 @@ impl TestSession {
             address: address.to_string(),
             satoshi,
             asset_id,
+            op_return: None,
         });
         create_opt
     }
@@ -927,6 +936,7 @@ impl TestSession {
             subaccount: 0,
             num_confs: 0,
             confidential_utxos_only: None,
+            policy_asset_only: false,
         };
         self.session.get_balance(&opt).unwrap()
     }
@@ -946,6 +956,7 @@ impl TestSession {
             subaccount: account_num,
             num_confs: 0,
             confidential_utxos_only,
+            policy_asset_only: false,
         };
         let balance = self.session.get_balance(&opt).unwrap();
         match self.network_id {
@@ -970,6 +981,8 @@ impl TestSession {
             num_confs: None,
             confidential_utxos_only: None,
             all_coins: None,
+            include_frozen: None,
+            ..Default::default()
         };
         self.session.get_unspent_outputs(&utxo_opt).unwrap()
     }